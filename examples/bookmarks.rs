@@ -19,15 +19,15 @@ fn main() {
         let mut page = Page::new(pagesize::A6, Some(Margins::all(In(0.5).into())));
 
         let start = layout::baseline_start(&page, &doc.fonts[fira_mono], Pt(24.0));
-        page.add_span(SpanLayout {
-            text: pagename.to_string(),
-            font: SpanFont {
+        page.add_span(SpanLayout::new(
+            pagename.to_string(),
+            SpanFont {
                 id: fira_mono,
                 size: Pt(24.0),
             },
-            colour: colours::BLACK,
-            coords: start,
-        });
+            colours::BLACK,
+            start,
+        ));
 
         let start = (
             start.0,
@@ -43,19 +43,22 @@ fn main() {
             },
             1 - pi,
         );
-        page.add_span(SpanLayout {
-            text: link_label,
-            font: SpanFont {
+        page.add_span(SpanLayout::new(
+            link_label,
+            SpanFont {
                 id: fira_mono,
                 size: Pt(24.0),
             },
-            colour: colours::BLACK,
-            coords: start,
-        });
+            colours::BLACK,
+            start,
+        ));
 
         doc.add_page(page);
     }
     let page_a_bookmark = doc.add_bookmark(None, pagenames[0], 0);
+    doc.outline.entries[page_a_bookmark]
+        .expanded()
+        .coloured(colours::BLUE);
     doc.add_bookmark(Some(page_a_bookmark), pagenames[1], 1);
 
     // we're going to save the contents to a file on disk, but anywhere where we can write would do
@@ -23,19 +23,19 @@ fn main() {
     let start = layout::baseline_start(&page, &doc.fonts[fira_mono], Pt(16.0));
 
     // add a span of text to the page
-    page.add_span(SpanLayout {
+    page.add_span(SpanLayout::new(
         // that will say "Hello world!"
-        text: "Hello world!".to_string(),
+        "Hello world!",
         // that will be presented in size 16pt Fira Mono font
-        font: SpanFont {
+        SpanFont {
             id: fira_mono,
             size: Pt(16.0),
         },
         // that will be black
-        colour: colours::BLACK,
+        colours::BLACK,
         // and start where we calculated it should go before
-        coords: start,
-    });
+        start,
+    ));
 
     // don't forget to add the page to the document (or it won't be rendered!)
     doc.add_page(page);
@@ -9,10 +9,9 @@ fn main() {
     let mut doc = Document::default();
     doc.set_info(
         Info::new()
-            .title("Image Test")
-            .author("Kenton Hamaluik")
-            .subject("Development Test / Example")
-            .clone(),
+            .with_title("Image Test")
+            .with_author("Kenton Hamaluik")
+            .with_subject("Development Test / Example"),
     );
 
     let pagesize = pdf_gen::pagesize::LETTER;
@@ -31,6 +30,7 @@ fn main() {
             x2: x + w,
             y2: y + h,
         },
+        artifact: None,
     });
     doc.add_page(page);
 
@@ -0,0 +1,64 @@
+use pdf_gen::pagesize;
+use pdf_gen::report::Report;
+use pdf_gen::Document;
+use pdf_gen::Font;
+use pdf_gen::Table;
+use pdf_gen::{layout::Margins, SpanFont};
+use pdf_gen::{In, Pt};
+
+fn main() {
+    let fira_mono = include_bytes!("../assets/FiraMono-Regular.ttf");
+    let fira_mono = Font::load(fira_mono.to_vec()).expect("can load font");
+
+    let mut doc = Document::default();
+    let fira_mono = doc.add_font(fira_mono);
+
+    let body = SpanFont {
+        id: fira_mono,
+        size: Pt(10.0),
+    };
+    let heading = SpanFont {
+        id: fira_mono,
+        size: Pt(20.0),
+    };
+
+    let mut items = Table::new(body);
+    items
+        .column("Item", In(4.5).into())
+        .column("Qty", In(1.0).into())
+        .column("Amount", In(2.0).into());
+    for i in 0..120 {
+        items.row([
+            format!("Widget #{}", i + 1),
+            "1".to_string(),
+            "$1.00".to_string(),
+        ]);
+    }
+
+    let mut report = Report::new(
+        pagesize::LETTER,
+        Margins::all(In(0.75).into()),
+        body,
+        heading,
+    );
+    report
+        .header("Invoice #1234", Some("ACME Corporation"))
+        .key_values([
+            ("Invoice Date", "2022-06-01"),
+            ("Due Date", "2022-07-01"),
+            ("Billed To", "Wile E. Coyote, 1 Desert Road, Somewhere, AZ"),
+        ])
+        .table(
+            Some("Line Items"),
+            items,
+            vec![vec![
+                "Total".to_string(),
+                "120".to_string(),
+                "$120.00".to_string(),
+            ]],
+        );
+    report.render(&mut doc).expect("can render report");
+
+    let mut out = std::fs::File::create("invoice.pdf").unwrap();
+    doc.write(&mut out).unwrap();
+}
@@ -14,10 +14,9 @@ fn main() {
     let fira_mono = doc.add_font(fira_mono);
     doc.set_info(
         Info::new()
-            .title("Lorem Ipsum Test")
-            .author("Kenton Hamaluik")
-            .subject("Development Test / Example")
-            .clone(),
+            .with_title("Lorem Ipsum Test")
+            .with_author("Kenton Hamaluik")
+            .with_subject("Development Test / Example"),
     );
 
     let mut text: Vec<(String, Colour, SpanFont)> = vec![
@@ -56,7 +55,8 @@ fn main() {
         let mut page = Page::new(page_size, Some(margins));
         let start = layout::baseline_start(&page, &doc.fonts[fira_mono], Pt(16.0));
         let bbox = page.content_box.clone();
-        layout::layout_text(&doc, &mut page, start, &mut text, In(0.0).into(), bbox);
+        layout::layout_text(&doc, &mut page, start, &mut text, In(0.0).into(), bbox)
+            .expect("can lay out text");
 
         // add a page number!
         let page_number_text = format!("Page {}", page_index + 1);
@@ -66,15 +66,15 @@ fn main() {
         } else {
             page.content_box.x1
         };
-        page.add_span(SpanLayout {
-            text: page_number_text,
-            font: SpanFont {
+        page.add_span(SpanLayout::new(
+            page_number_text,
+            SpanFont {
                 id: fira_mono,
                 size: Pt(10.0),
             },
-            colour: Colour::Grey { g: 0.5 },
-            coords: (px, In(0.25).into()),
-        });
+            Colour::Grey { g: 0.5 },
+            (px, In(0.25).into()),
+        ));
 
         doc.add_page(page);
         page_index += 1;
@@ -0,0 +1,66 @@
+use pdf_gen::Document;
+use pdf_gen::Font;
+use pdf_gen::Template;
+use pdf_gen::TemplateData;
+
+const TEMPLATE: &str = r#"{
+    "styles": {
+        "title": { "font": "Fira Mono", "size": 24.0, "colour": [0.2, 0.2, 0.6] },
+        "body": { "font": "Fira Mono", "size": 10.0 }
+    },
+    "pages": [{
+        "size": [612.0, 792.0],
+        "margins": [36.0, 36.0, 36.0, 36.0],
+        "frames": [
+            {
+                "rect": [36.0, 700.0, 576.0, 756.0],
+                "type": "text",
+                "text": "Invoice #{{number}}",
+                "style": "title"
+            },
+            {
+                "rect": [36.0, 640.0, 576.0, 690.0],
+                "type": "text",
+                "text": "Billed to: {{customer}}",
+                "style": "body"
+            },
+            {
+                "rect": [36.0, 36.0, 576.0, 630.0],
+                "type": "table",
+                "table": "items",
+                "style": "body",
+                "columns": [
+                    { "header": "Item", "width": 360.0 },
+                    { "header": "Qty", "width": 60.0 },
+                    { "header": "Price", "width": 120.0 }
+                ]
+            }
+        ]
+    }]
+}"#;
+
+fn main() {
+    let fira_mono = include_bytes!("../assets/FiraMono-Regular.ttf");
+    let fira_mono = Font::load(fira_mono.to_vec()).expect("can load font");
+
+    let mut doc = Document::default();
+    doc.add_font(fira_mono);
+
+    let template = Template::from_json(TEMPLATE).expect("can parse template");
+    let mut data = TemplateData::new();
+    data.string("number", 1234)
+        .string("customer", "ACME Corporation")
+        .table(
+            "items",
+            vec![
+                vec!["Widgets".to_string(), "4".to_string(), "$10.00".to_string()],
+                vec!["Gadgets".to_string(), "2".to_string(), "$25.00".to_string()],
+            ],
+        );
+    template
+        .render(&mut doc, &data)
+        .expect("can render template");
+
+    let mut out = std::fs::File::create("template.pdf").unwrap();
+    doc.write(&mut out).unwrap();
+}
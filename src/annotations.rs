@@ -0,0 +1,278 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::page::{
+    DestinationView, ExternalLink, ExternalLinkTarget, Highlight, IntraDocumentLink,
+    PageLinkReference,
+};
+use crate::rect::Rect;
+use crate::units::Pt;
+use crate::PDFError;
+
+/// The links and highlights of a document, separated from its content so that they can
+/// be saved and applied to another document with the same page geometry, such as a
+/// later revision of a document under review (see [Document::export_annotations]).
+///
+/// With the `serde` feature (enabled by the `templates` and `records` features), the
+/// layer can be serialized. Rects are given as `[x1, y1, x2, y2]` in points, and
+/// colours as RGB components from 0.0 to 1.0.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnotationLayer {
+    /// The annotations of each page that has any
+    pub pages: Vec<PageAnnotations>,
+}
+
+/// The annotations of a single page in an [AnnotationLayer]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageAnnotations {
+    /// The index of the page, in page order
+    pub page: usize,
+    /// The page's media box, which the page that the annotations are applied to must
+    /// match
+    pub media_box: [f32; 4],
+    /// The page's links, both within the document and to other documents
+    pub links: Vec<AnnotatedLink>,
+    /// The page's highlights
+    pub highlights: Vec<AnnotatedHighlight>,
+}
+
+/// A link in an [AnnotationLayer]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnotatedLink {
+    /// The bounding box of the link
+    pub position: [f32; 4],
+    /// The clickable regions within the bounding box, if not all of it
+    pub regions: Vec<[f32; 4]>,
+    /// What the link does when it is clicked
+    pub target: AnnotatedLinkTarget,
+}
+
+/// What an [AnnotatedLink] does when it is clicked
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnnotatedLinkTarget {
+    /// Navigate to the whole of a page of the document, by its index in page order
+    Page { page: usize },
+    /// Navigate to a page of the document, scrolled so that the given vertical position
+    /// is at the top of the view
+    PageTop { page: usize, top: f32 },
+    /// Navigate to a page of the document, zoomed into a region of it
+    PageRect { page: usize, rect: [f32; 4] },
+    /// Open another PDF document at the given page index
+    RemoteDocument { file: String, page: usize },
+    /// Launch an application, or open a file
+    Launch { file: String },
+    /// Open a URI
+    Uri { uri: String },
+}
+
+/// A highlight in an [AnnotationLayer]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnotatedHighlight {
+    /// The highlighted regions
+    pub regions: Vec<[f32; 4]>,
+    /// The colour of the highlight
+    pub colour: [f32; 3],
+    /// The note attached to the highlight, if any
+    pub note: Option<String>,
+}
+
+fn to_array(rect: &Rect) -> [f32; 4] {
+    [*rect.x1, *rect.y1, *rect.x2, *rect.y2]
+}
+
+fn to_rect([x1, y1, x2, y2]: [f32; 4]) -> Rect {
+    Rect {
+        x1: Pt(x1),
+        y1: Pt(y1),
+        x2: Pt(x2),
+        y2: Pt(y2),
+    }
+}
+
+impl AnnotationLayer {
+    /// Serialize the layer as JSON
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> Result<String, PDFError> {
+        serde_json::to_string_pretty(self).map_err(Into::into)
+    }
+
+    /// Parse a layer from JSON
+    #[cfg(feature = "serde_json")]
+    pub fn from_json(json: &str) -> Result<AnnotationLayer, PDFError> {
+        serde_json::from_str(json).map_err(Into::into)
+    }
+}
+
+impl Document {
+    /// Collect the links and highlights of every page into an [AnnotationLayer]. Links
+    /// to pages which aren't in the document are left out, and links to pages refer to
+    /// them by their index in page order
+    pub fn export_annotations(&self) -> AnnotationLayer {
+        let mut layer = AnnotationLayer::default();
+        for (page_index, id) in self.page_order.iter().enumerate() {
+            let page = match self.pages.get(*id) {
+                Some(page) => page,
+                None => continue,
+            };
+
+            let mut links: Vec<AnnotatedLink> = Vec::new();
+            for link in page.links.iter() {
+                let target_page = match link.page {
+                    PageLinkReference::ById(id) => match self.index_of_page(id) {
+                        Some(index) => index,
+                        None => continue,
+                    },
+                    PageLinkReference::ByIndex(index) => index,
+                };
+                links.push(AnnotatedLink {
+                    position: to_array(&link.position),
+                    regions: link.regions.iter().map(to_array).collect(),
+                    target: match link.view {
+                        DestinationView::Fit => AnnotatedLinkTarget::Page { page: target_page },
+                        DestinationView::Top(top) => AnnotatedLinkTarget::PageTop {
+                            page: target_page,
+                            top: *top,
+                        },
+                        DestinationView::Rect(rect) => AnnotatedLinkTarget::PageRect {
+                            page: target_page,
+                            rect: to_array(&rect),
+                        },
+                    },
+                });
+            }
+            links.extend(page.external_links.iter().map(|link| AnnotatedLink {
+                position: to_array(&link.position),
+                regions: link.regions.iter().map(to_array).collect(),
+                target: match &link.target {
+                    ExternalLinkTarget::RemoteDocument { file, page } => {
+                        AnnotatedLinkTarget::RemoteDocument {
+                            file: file.clone(),
+                            page: *page,
+                        }
+                    }
+                    ExternalLinkTarget::Launch { file } => {
+                        AnnotatedLinkTarget::Launch { file: file.clone() }
+                    }
+                    ExternalLinkTarget::Uri { uri } => {
+                        AnnotatedLinkTarget::Uri { uri: uri.clone() }
+                    }
+                },
+            }));
+
+            let highlights: Vec<AnnotatedHighlight> = page
+                .highlights
+                .iter()
+                .map(|highlight| {
+                    let (r, g, b) = highlight.colour.to_rgb();
+                    AnnotatedHighlight {
+                        regions: highlight.regions.iter().map(to_array).collect(),
+                        colour: [r, g, b],
+                        note: highlight.note.clone(),
+                    }
+                })
+                .collect();
+
+            if !links.is_empty() || !highlights.is_empty() {
+                layer.pages.push(PageAnnotations {
+                    page: page_index,
+                    media_box: to_array(&page.media_box),
+                    links,
+                    highlights,
+                });
+            }
+        }
+        layer
+    }
+
+    /// Add the links and highlights of an [AnnotationLayer] to the document's pages,
+    /// alongside any annotations that they already have. Returns an error without
+    /// changing the document if a page of the layer isn't in the document, or is a
+    /// different size to the page that the annotations were exported from
+    pub fn import_annotations(&mut self, layer: &AnnotationLayer) -> Result<(), PDFError> {
+        for annotations in layer.pages.iter() {
+            let matches = self
+                .page_order
+                .get(annotations.page)
+                .and_then(|&id| self.pages.get(id))
+                .map(|page| {
+                    to_array(&page.media_box)
+                        .iter()
+                        .zip(annotations.media_box.iter())
+                        .all(|(a, b)| (a - b).abs() < 0.01)
+                })
+                .unwrap_or(false);
+            if !matches {
+                return Err(PDFError::AnnotationPageMismatch {
+                    page: annotations.page,
+                });
+            }
+        }
+
+        for annotations in layer.pages.iter() {
+            let id = match self.page_order.get(annotations.page) {
+                Some(&id) => id,
+                None => continue,
+            };
+            let page = &mut self.pages[id];
+            for link in annotations.links.iter() {
+                let position = to_rect(link.position);
+                let regions: Vec<Rect> = link.regions.iter().copied().map(to_rect).collect();
+                let (target_page, view) = match &link.target {
+                    AnnotatedLinkTarget::Page { page } => (*page, DestinationView::Fit),
+                    AnnotatedLinkTarget::PageTop { page, top } => {
+                        (*page, DestinationView::Top(Pt(*top)))
+                    }
+                    AnnotatedLinkTarget::PageRect { page, rect } => {
+                        (*page, DestinationView::Rect(to_rect(*rect)))
+                    }
+                    AnnotatedLinkTarget::RemoteDocument { file, page: remote } => {
+                        page.external_links.push(ExternalLink {
+                            position,
+                            target: ExternalLinkTarget::RemoteDocument {
+                                file: file.clone(),
+                                page: *remote,
+                            },
+                            regions,
+                        });
+                        continue;
+                    }
+                    AnnotatedLinkTarget::Launch { file } => {
+                        page.external_links.push(ExternalLink {
+                            position,
+                            target: ExternalLinkTarget::Launch { file: file.clone() },
+                            regions,
+                        });
+                        continue;
+                    }
+                    AnnotatedLinkTarget::Uri { uri } => {
+                        page.external_links.push(ExternalLink {
+                            position,
+                            target: ExternalLinkTarget::Uri { uri: uri.clone() },
+                            regions,
+                        });
+                        continue;
+                    }
+                };
+                page.links.push(IntraDocumentLink {
+                    position,
+                    page: PageLinkReference::ByIndex(target_page),
+                    view,
+                    regions,
+                });
+            }
+            for highlight in annotations.highlights.iter() {
+                let [r, g, b] = highlight.colour;
+                page.highlights.push(Highlight {
+                    regions: highlight.regions.iter().copied().map(to_rect).collect(),
+                    colour: Colour::new_rgb(r, g, b),
+                    note: highlight.note.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
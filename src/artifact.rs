@@ -0,0 +1,42 @@
+/// Marks content as an artifact: something drawn on the page which isn't part of the
+/// document's real content, such as running headers and footers, page numbers,
+/// watermarks, and background decorations. Artifacts are written as `/Artifact` marked
+/// content, so that screen readers skip them and text extraction leaves them out.
+///
+/// Text is marked with [crate::SpanMetadata::artifact], images and forms with their
+/// `artifact` fields, and raw content with [crate::Page::add_artifact_content].
+/// [crate::layout::number_pages] marks the page numbers it adds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Artifact {
+    /// A running header at the top of the page
+    Header,
+    /// A running footer at the bottom of the page
+    Footer,
+    /// A page number
+    PageNumber,
+    /// A line number in the margin
+    LineNumber,
+    /// A watermark drawn over or under the page's content
+    Watermark,
+    /// Typographic or design elements such as rules and borders which separate content
+    Layout,
+    /// Backgrounds and other decorations behind the page's content
+    Background,
+}
+
+impl Artifact {
+    /// The operator starting the marked content sequence for the artifact
+    pub(crate) fn begin(&self) -> &'static str {
+        match self {
+            Artifact::Header => "/Artifact <</Type /Pagination /Subtype /Header>> BDC\n",
+            Artifact::Footer => "/Artifact <</Type /Pagination /Subtype /Footer>> BDC\n",
+            // the PageNum subtype was only added in PDF 2.0; older readers ignore it
+            Artifact::PageNumber => "/Artifact <</Type /Pagination /Subtype /PageNum>> BDC\n",
+            // as is the LineNum subtype
+            Artifact::LineNumber => "/Artifact <</Type /Pagination /Subtype /LineNum>> BDC\n",
+            Artifact::Watermark => "/Artifact <</Type /Pagination /Subtype /Watermark>> BDC\n",
+            Artifact::Layout => "/Artifact <</Type /Layout>> BDC\n",
+            Artifact::Background => "/Artifact <</Type /Background>> BDC\n",
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::image::Image;
+use crate::PDFError;
+use id_arena::Arena;
+
+/// A way of reducing the size of a document, used by [SizeBudget]. The strategies only
+/// reduce the size of images: fonts aren't subset (see [crate::Font]), so there is no
+/// strategy for them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SizeStrategy {
+    /// Downsample raster images so that neither their width nor their height is larger
+    /// than `max_dimension` pixels (see [Image::downsample])
+    DownsampleImages { max_dimension: u32 },
+    /// Re-encode opaque raster images as JPEGs with the given quality, from 1 to 100
+    /// (see [Image::compress_jpeg])
+    CompressImages { quality: u8 },
+}
+
+impl SizeStrategy {
+    /// Apply the strategy to the images of a document, returning how many of the
+    /// images were changed
+    pub(crate) fn apply(&self, images: &mut Arena<Image>) -> Result<usize, PDFError> {
+        let mut changed = 0;
+        for (_, image) in images.iter_mut() {
+            let image_changed = match *self {
+                SizeStrategy::DownsampleImages { max_dimension } => {
+                    image.downsample(max_dimension)?
+                }
+                SizeStrategy::CompressImages { quality } => image.compress_jpeg(quality)?,
+            };
+            if image_changed {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// A target maximum size for a written document, along with the strategies that may
+/// be used to reach it (see [crate::Document::write_with_budget]). Strategies are
+/// applied in the order they are given, stopping as soon as the document is small
+/// enough, so the least destructive strategies should come first. Fonts are always
+/// embedded in their entirety, so large fonts may put a budget out of reach.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBudget {
+    /// The maximum size of the written document, in bytes
+    pub max_bytes: usize,
+    /// The strategies to try, in order
+    pub strategies: Vec<SizeStrategy>,
+}
+
+impl SizeBudget {
+    /// Create a budget with no strategies
+    pub fn new(max_bytes: usize) -> SizeBudget {
+        SizeBudget {
+            max_bytes,
+            strategies: Vec::default(),
+        }
+    }
+
+    /// Add a strategy to try after all the current ones
+    pub fn strategy(&mut self, strategy: SizeStrategy) -> &mut Self {
+        self.strategies.push(strategy);
+        self
+    }
+}
+
+/// A strategy which was applied while writing a document with a [SizeBudget]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AppliedStrategy {
+    /// The strategy that was applied
+    pub strategy: SizeStrategy,
+    /// How many images were changed by the strategy
+    pub images_changed: usize,
+    /// The size of the document before the strategy was applied, in bytes
+    pub size_before: usize,
+    /// The size of the document after the strategy was applied, in bytes
+    pub size_after: usize,
+}
+
+/// What happened while writing a document with a [SizeBudget]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeReport {
+    /// The size of the written document, in bytes
+    pub size: usize,
+    /// Whether the written document fits within the budget
+    pub within_budget: bool,
+    /// The strategies which were applied, in order. Strategies which didn't change
+    /// anything are not included
+    pub applied: Vec<AppliedStrategy>,
+}
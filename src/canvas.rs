@@ -0,0 +1,123 @@
+use crate::colour::Colour;
+use crate::drawing::{DrawStyle, Shape, Stroke};
+use crate::form::FormLayout;
+use crate::page::{Destination, ImageLayout, Page, SpanLayout};
+use crate::rect::Rect;
+use crate::units::Pt;
+use pdf_writer::Content;
+
+/// A view of a page which uses a top-left origin, with y coordinates increasing down
+/// the page, as is common in other graphics systems (PDF itself uses a bottom-left
+/// origin with y increasing up the page). Everything added through the canvas is
+/// converted into page coordinates, so the two can be mixed freely:
+///
+/// ```
+/// use pdf_gen::{pagesize, Canvas, Page, Pt};
+///
+/// let mut page = Page::new(pagesize::LETTER, None);
+/// let canvas = Canvas::new(&mut page);
+/// // one inch down from the top of the page
+/// assert_eq!(canvas.point((Pt(0.0), Pt(72.0))), (Pt(0.0), Pt(720.0)));
+/// ```
+///
+/// Rects given to the canvas may list their top and bottom edges in either order.
+/// Destinations of links (such as [Destination::top]) are on other pages, so are still
+/// given in page coordinates.
+pub struct Canvas<'p> {
+    page: &'p mut Page,
+}
+
+impl<'p> Canvas<'p> {
+    /// Draw onto the given page using top-left coordinates
+    pub fn new(page: &'p mut Page) -> Canvas<'p> {
+        Canvas { page }
+    }
+
+    /// The underlying page, for anything that should be added in page coordinates
+    pub fn page(&mut self) -> &mut Page {
+        self.page
+    }
+
+    /// Convert a point from top-left coordinates into page coordinates
+    pub fn point(&self, point: (Pt, Pt)) -> (Pt, Pt) {
+        (point.0, self.page.media_box.y2 - point.1)
+    }
+
+    /// Convert a rect from top-left coordinates into page coordinates
+    pub fn rect(&self, rect: &Rect) -> Rect {
+        let top = self.page.media_box.y2 - rect.y1;
+        let bottom = self.page.media_box.y2 - rect.y2;
+        Rect {
+            x1: Pt(rect.x1.min(*rect.x2)),
+            y1: Pt(top.min(*bottom)),
+            x2: Pt(rect.x1.max(*rect.x2)),
+            y2: Pt(top.max(*bottom)),
+        }
+    }
+
+    /// The page's content box in top-left coordinates, where `y1` is the top edge
+    pub fn content_box(&self) -> Rect {
+        let content_box = &self.page.content_box;
+        Rect {
+            x1: content_box.x1,
+            y1: self.page.media_box.y2 - content_box.y2,
+            x2: content_box.x2,
+            y2: self.page.media_box.y2 - content_box.y1,
+        }
+    }
+
+    /// Add a span of text, whose coordinates give the start of its baseline measured
+    /// from the top-left of the page
+    pub fn add_span(&mut self, mut span: SpanLayout) {
+        span.coords = self.point(span.coords);
+        self.page.add_span(span);
+    }
+
+    /// Add an image filling the given position
+    pub fn add_image(&mut self, mut image: ImageLayout) {
+        image.position = self.rect(&image.position);
+        self.page.add_image(image);
+    }
+
+    /// Draw a form filling the given position
+    pub fn add_form(&mut self, mut form: FormLayout) {
+        form.position = self.rect(&form.position);
+        self.page.add_form(form);
+    }
+
+    /// Draw a shape whose coordinates are all measured from the top-left of the page.
+    /// The shape is flipped vertically as it is drawn, so arcs sweep clockwise rather
+    /// than anticlockwise
+    pub fn add_shape<S: Shape + ?Sized>(&mut self, shape: &S, style: &DrawStyle) {
+        let mut content = Content::new();
+        content.save_state();
+        content.transform([1.0, 0.0, 0.0, -1.0, 0.0, *self.page.media_box.y2]);
+        crate::drawing::draw(&mut content, shape, style);
+        content.restore_state();
+        self.page.add_content(content);
+    }
+
+    /// Draw a straight line between two points
+    pub fn add_rule(&mut self, from: (Pt, Pt), to: (Pt, Pt), stroke: Stroke) {
+        let (from, to) = (self.point(from), self.point(to));
+        self.page.add_rule(from, to, stroke);
+    }
+
+    /// Add a link that when clicked will navigate to the given destination
+    pub fn add_intradocument_link(&mut self, position: Rect, destination: Destination) {
+        let position = self.rect(&position);
+        self.page.add_intradocument_link(position, destination);
+    }
+
+    /// Add a link that when clicked will open the given URI
+    pub fn add_uri_link<S: ToString>(&mut self, position: Rect, uri: S) {
+        let position = self.rect(&position);
+        self.page.add_uri_link(position, uri);
+    }
+
+    /// Highlight regions of the page
+    pub fn add_highlight(&mut self, regions: Vec<Rect>, colour: Colour, note: Option<String>) {
+        let regions = regions.iter().map(|region| self.rect(region)).collect();
+        self.page.add_highlight(regions, colour, note);
+    }
+}
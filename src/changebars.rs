@@ -0,0 +1,73 @@
+use crate::artifact::Artifact;
+use crate::document::Document;
+use crate::drawing::{DrawStyle, Line, Stroke};
+use crate::page::Page;
+use crate::units::Pt;
+use pdf_writer::Content;
+
+impl Page {
+    /// Draw change bars: vertical lines at `x` (typically in the margin) beside every
+    /// line of text on the page which has been marked as changed (see
+    /// [crate::SpanMetadata::changed]). Consecutive changed lines share a single bar.
+    ///
+    /// Layout functions copy metadata onto every span they generate, so marking the text
+    /// of a paragraph as changed before laying it out marks each of its lines, and
+    /// calling this on each page once its text has been laid out draws bars beside just
+    /// the part of the paragraph on that page. The bars are marked as artifacts (see
+    /// [Artifact]).
+    pub fn add_change_bars(&mut self, document: &Document, x: Pt, stroke: Stroke) {
+        // the vertical extent of each changed line, by line
+        let mut lines: Vec<(usize, Pt, Pt)> = Vec::new();
+        for placed in self
+            .placed_spans(document)
+            .filter(|placed| placed.span.metadata.changed)
+        {
+            match lines.iter_mut().find(|(line, _, _)| *line == placed.line) {
+                Some((_, bottom, top)) => {
+                    if placed.rect.y1 < *bottom {
+                        *bottom = placed.rect.y1;
+                    }
+                    if placed.rect.y2 > *top {
+                        *top = placed.rect.y2;
+                    }
+                }
+                None => lines.push((placed.line, placed.rect.y1, placed.rect.y2)),
+            }
+        }
+        if lines.is_empty() {
+            return;
+        }
+        lines.sort_by_key(|&(line, _, _)| line);
+
+        // join consecutive lines into bars
+        let mut bars: Vec<(usize, Pt, Pt)> = Vec::new();
+        for (line, bottom, top) in lines.into_iter() {
+            match bars.last_mut() {
+                Some((last, bar_bottom, _)) if *last + 1 == line => {
+                    *last = line;
+                    if bottom < *bar_bottom {
+                        *bar_bottom = bottom;
+                    }
+                }
+                _ => bars.push((line, bottom, top)),
+            }
+        }
+
+        let style = DrawStyle {
+            fill: None,
+            stroke: Some(stroke),
+        };
+        let mut content = Content::new();
+        for (_, bottom, top) in bars.into_iter() {
+            crate::drawing::draw(
+                &mut content,
+                &Line {
+                    from: (x, bottom),
+                    to: (x, top),
+                },
+                &style,
+            );
+        }
+        self.add_artifact_content(Artifact::Layout, content);
+    }
+}
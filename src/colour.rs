@@ -50,6 +50,65 @@ impl Colour {
             g: g as f32 / 255.0,
         }
     }
+
+    /// Convert the colour to an RGB triple, with each component ranging from 0.0 to 1.0.
+    /// CMYK colours are converted naively (without any colour profile), so the result
+    /// is only an approximation of the printed colour
+    pub fn to_rgb(&self) -> (f32, f32, f32) {
+        match *self {
+            Colour::RGB { r, g, b } => (r, g, b),
+            Colour::CMYK { c, m, y, k } => (
+                (1.0 - c) * (1.0 - k),
+                (1.0 - m) * (1.0 - k),
+                (1.0 - y) * (1.0 - k),
+            ),
+            Colour::Grey { g } => (g, g, g),
+        }
+    }
+
+    /// Blend between this colour (when `t` is 0.0) and another (when `t` is 1.0). Colours
+    /// in the same colour space are blended in that space; otherwise both are converted
+    /// to RGB first (see [Colour::to_rgb])
+    pub fn mix(&self, other: Colour, t: f32) -> Colour {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        match (*self, other) {
+            (
+                Colour::RGB { r, g, b },
+                Colour::RGB {
+                    r: r2,
+                    g: g2,
+                    b: b2,
+                },
+            ) => Colour::RGB {
+                r: lerp(r, r2),
+                g: lerp(g, g2),
+                b: lerp(b, b2),
+            },
+            (
+                Colour::CMYK { c, m, y, k },
+                Colour::CMYK {
+                    c: c2,
+                    m: m2,
+                    y: y2,
+                    k: k2,
+                },
+            ) => Colour::CMYK {
+                c: lerp(c, c2),
+                m: lerp(m, m2),
+                y: lerp(y, y2),
+                k: lerp(k, k2),
+            },
+            (Colour::Grey { g }, Colour::Grey { g: g2 }) => Colour::Grey { g: lerp(g, g2) },
+            _ => {
+                let ((r, g, b), (r2, g2, b2)) = (self.to_rgb(), other.to_rgb());
+                Colour::RGB {
+                    r: lerp(r, r2),
+                    g: lerp(g, g2),
+                    b: lerp(b, b2),
+                }
+            }
+        }
+    }
 }
 
 impl<T: Into<f32>> From<(T, T, T)> for Colour {
@@ -96,6 +155,60 @@ impl<T: Into<f32>> From<[T; 4]> for Colour {
     }
 }
 
+/// A conversion applied to every colour in a document when it is written (see
+/// [crate::Document::colour_conversion]), i.e. to produce a print-economical edition
+/// of a document without rebuilding its content
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ColourConversion {
+    /// Convert every colour to a shade of grey with the same luminance
+    Greyscale,
+    /// Lighten every colour towards white (reducing the amount of ink used) by the
+    /// given amount, from 0.0 (no change) to 1.0 (white)
+    Lighten(f32),
+    /// Convert every colour to grey, then lighten it by the given amount
+    GreyscaleLighten(f32),
+}
+
+impl ColourConversion {
+    /// Convert a colour
+    pub fn convert(&self, colour: Colour) -> Colour {
+        let grey = |colour: Colour| match colour {
+            Colour::Grey { .. } => colour,
+            _ => {
+                let (r, g, b) = colour.to_rgb();
+                Colour::Grey {
+                    g: 0.299 * r + 0.587 * g + 0.114 * b,
+                }
+            }
+        };
+        let lighten = |colour: Colour, amount: f32| {
+            let towards_white = |v: f32| v + (1.0 - v) * amount;
+            match colour {
+                Colour::RGB { r, g, b } => Colour::RGB {
+                    r: towards_white(r),
+                    g: towards_white(g),
+                    b: towards_white(b),
+                },
+                // less of each ink is lighter
+                Colour::CMYK { c, m, y, k } => Colour::CMYK {
+                    c: c * (1.0 - amount),
+                    m: m * (1.0 - amount),
+                    y: y * (1.0 - amount),
+                    k: k * (1.0 - amount),
+                },
+                Colour::Grey { g } => Colour::Grey {
+                    g: towards_white(g),
+                },
+            }
+        };
+        match *self {
+            ColourConversion::Greyscale => grey(colour),
+            ColourConversion::Lighten(amount) => lighten(colour, amount),
+            ColourConversion::GreyscaleLighten(amount) => lighten(grey(colour), amount),
+        }
+    }
+}
+
 /// A list of pre-defined colour constants
 pub mod colours {
     use super::*;
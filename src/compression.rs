@@ -0,0 +1,45 @@
+use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use pdf_writer::Filter;
+use std::borrow::Cow;
+
+/// How the streams of a document (page and form content, embedded fonts and their
+/// character maps, raster images, and attachments) are compressed when it is written.
+/// See [crate::Document::compression]. JPEG images are always written as they are, and
+/// XMP metadata is never compressed
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Write streams uncompressed, such as to read a document's content while debugging
+    /// it. Documents are usually several times larger without compression
+    None,
+    /// Compress streams as quickly as possible, at the expense of size
+    Fast,
+    /// A balance between speed and size
+    #[default]
+    Default,
+    /// Compress streams as small as possible, at the expense of speed
+    Best,
+}
+
+impl Compression {
+    /// The zlib compression level, or [None] if streams aren't compressed
+    fn level(&self) -> Option<u8> {
+        match self {
+            Compression::None => None,
+            Compression::Fast => Some(CompressionLevel::BestSpeed as u8),
+            Compression::Default => Some(CompressionLevel::DefaultLevel as u8),
+            Compression::Best => Some(CompressionLevel::BestCompression as u8),
+        }
+    }
+
+    /// Compress a stream's data, giving the data to write along with the filter that
+    /// decodes it (if it was compressed)
+    pub(crate) fn compress<'a>(&self, data: &'a [u8]) -> (Cow<'a, [u8]>, Option<Filter>) {
+        match self.level() {
+            Some(level) => (
+                Cow::Owned(compress_to_vec_zlib(data, level)),
+                Some(Filter::FlateDecode),
+            ),
+            None => (Cow::Borrowed(data), None),
+        }
+    }
+}
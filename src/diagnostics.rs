@@ -0,0 +1,325 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::layout::width_of_text;
+use crate::page::{render_contents, PageContents, SpanLayout};
+use crate::rect::Rect;
+#[cfg(feature = "shaping")]
+use crate::shaping::text_advances;
+use crate::units::Pt;
+use crate::PDFError;
+
+/// A line of text whose words are spread much further apart than usual, such as a
+/// justified line with only a few long words on it. See [Document::loose_lines]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LooseLine {
+    /// The index of the page that the line is on, in page order
+    pub page: usize,
+    /// The region of the page covered by the line
+    pub rect: Rect,
+    /// The widest gap between two words on the line, as a multiple of the width of a
+    /// space in the line's font
+    pub spacing: f32,
+}
+
+/// The width of a line of text as it was laid out, and the width it would have if its
+/// text were shaped. See [Document::shaping_differences]
+#[cfg(feature = "shaping")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapingDifference {
+    /// The index of the page that the line is on, in page order
+    pub page: usize,
+    /// The baseline of the line
+    pub baseline: Pt,
+    /// The text on the line
+    pub text: String,
+    /// The width of the line's text, as it was laid out
+    pub layout_width: Pt,
+    /// The width of the line's text when shaped
+    pub shaped_width: Pt,
+}
+
+#[cfg(feature = "shaping")]
+impl ShapingDifference {
+    /// How much wider (or narrower, if negative) the line would be if it were shaped, in
+    /// points
+    pub fn difference(&self) -> f32 {
+        *self.shaped_width - *self.layout_width
+    }
+}
+
+impl Document {
+    /// Find the lines of text on the document's pages (not including text within forms)
+    /// where the gap between two words is more than `threshold` times the width of a
+    /// space, so that the document's typography can be tuned (i.e. by changing the
+    /// wording, the size of the bounding box, or the line breaker). Between 1.5 and 2
+    /// is a reasonable threshold for justified text.
+    ///
+    /// Lines are the spans of text which share a baseline, so this works with text
+    /// from any layout function or placed by hand.
+    pub fn loose_lines(&self, threshold: f32) -> Vec<LooseLine> {
+        let mut loose = Vec::new();
+        for page_index in 0..self.page_order.len() {
+            let lines = self.page_lines(page_index);
+            loose.extend(lines.iter().filter_map(|(baseline, spans)| {
+                let (rect, spacing) = self.line_spacing(*baseline, spans)?;
+                if spacing > threshold {
+                    Some(LooseLine {
+                        page: page_index,
+                        rect,
+                        spacing,
+                    })
+                } else {
+                    None
+                }
+            }));
+        }
+        loose
+    }
+
+    /// The same as [Document::loose_lines], except that each loose line is also marked
+    /// with a highlight annotation noting how loose it is. This is intended as a
+    /// debugging overlay while tuning a document, rather than something to publish
+    pub fn highlight_loose_lines(&mut self, threshold: f32, colour: Colour) -> Vec<LooseLine> {
+        let loose = self.loose_lines(threshold);
+        for line in loose.iter() {
+            let id = self.page_order[line.page];
+            self.pages[id].add_highlight(
+                vec![line.rect],
+                colour,
+                Some(format!(
+                    "Loose line: spaces up to {:.1} times their usual width",
+                    line.spacing
+                )),
+            );
+        }
+        loose
+    }
+
+    /// The spans of text on a page (not including text within forms), grouped into lines
+    /// by their baselines
+    fn page_lines(&self, page_index: usize) -> Vec<(Pt, Vec<&SpanLayout>)> {
+        let mut lines: Vec<(Pt, Vec<&SpanLayout>)> = Vec::new();
+        for span in self.pages[self.page_order[page_index]]
+            .contents
+            .iter()
+            .filter_map(|content| match content {
+                PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+                    Some(spans.iter())
+                }
+                _ => None,
+            })
+            .flatten()
+        {
+            match lines
+                .iter_mut()
+                .find(|(baseline, _)| (*baseline - span.coords.1).abs() < 0.01)
+            {
+                Some((_, spans)) => spans.push(span),
+                None => lines.push((span.coords.1, vec![span])),
+            }
+        }
+        lines
+    }
+
+    /// Compare the width of each line of text on the document's pages (not including
+    /// text within forms) as it was laid out with the width it would have if its text
+    /// were shaped (see [crate::Font::shape]), which accounts for the ligatures and
+    /// kerning of the fonts' layout tables. This shows how much laying a document out
+    /// again with shaping would change it, such as while migrating documents which were
+    /// laid out without the `shaping` feature. Lines which were already shaped have no
+    /// difference.
+    ///
+    /// Only lines whose widths differ by more than `threshold` are reported, so a
+    /// threshold of `Pt(0.0)` reports every line that would change.
+    #[cfg(feature = "shaping")]
+    pub fn shaping_differences(&self, threshold: Pt) -> Vec<ShapingDifference> {
+        let mut differences = Vec::new();
+        for page_index in 0..self.page_order.len() {
+            for (baseline, spans) in self.page_lines(page_index) {
+                let mut difference = ShapingDifference {
+                    page: page_index,
+                    baseline,
+                    text: String::new(),
+                    layout_width: Pt(0.0),
+                    shaped_width: Pt(0.0),
+                };
+                for span in spans.iter() {
+                    let font = &self.fonts[span.font.id];
+                    difference.text.push_str(&span.text);
+                    difference.layout_width += span.char_advances(font).into_iter().sum::<Pt>();
+                    difference.shaped_width += text_advances(
+                        font,
+                        &span.text,
+                        span.font.size,
+                        span.effects.letter_spacing,
+                        span.effects.small_caps,
+                    )
+                    .into_iter()
+                    .sum::<Pt>();
+                }
+                if difference.difference().abs() > *threshold {
+                    differences.push(difference);
+                }
+            }
+        }
+        differences
+    }
+
+    /// The region covered by a line of spans and the widest gap between its words (as
+    /// a multiple of the width of a space), or [None] if it has fewer than two words
+    fn line_spacing(&self, baseline: Pt, spans: &[&SpanLayout]) -> Option<(Rect, f32)> {
+        // the horizontal extent of each word, with the width of a space after it
+        let mut words: Vec<(Pt, Pt, Pt)> = Vec::new();
+        let mut rect = Rect {
+            x1: Pt(f32::MAX),
+            y1: baseline,
+            x2: Pt(f32::MIN),
+            y2: baseline,
+        };
+        for span in spans.iter() {
+            let font = &self.fonts[span.font.id];
+            let space = span.font.size * font.space_advance() / font.units_per_em() as f32;
+            let (bottom, top) = (
+                baseline + font.descent(span.font.size),
+                baseline + font.ascent(span.font.size),
+            );
+            if bottom < rect.y1 {
+                rect.y1 = bottom;
+            }
+            if top > rect.y2 {
+                rect.y2 = top;
+            }
+
+            let mut x = span.coords.0;
+            let mut in_word = false;
+            for ch in span.text.chars() {
+                let advance = width_of_text(ch.encode_utf8(&mut [0; 4]), font, span.font.size)
+                    + span.effects.letter_spacing;
+                if ch.is_whitespace() {
+                    in_word = false;
+                } else {
+                    match words.last_mut() {
+                        Some((_, end, _)) if in_word => *end = x + advance,
+                        _ => words.push((x, x + advance, space)),
+                    }
+                    in_word = true;
+                }
+                x += advance;
+            }
+        }
+        if words.len() < 2 {
+            return None;
+        }
+
+        words.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let spacing = words
+            .windows(2)
+            .map(|pair| *((pair[1].0 - pair[0].1) / pair[0].2))
+            .fold(0.0, f32::max);
+        for &(start, end, _) in words.iter() {
+            if start < rect.x1 {
+                rect.x1 = start;
+            }
+            if end > rect.x2 {
+                rect.x2 = end;
+            }
+        }
+        Some((rect, spacing))
+    }
+}
+
+/// The kinds of content on a page, as counted by [Document::content_sizes]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContentKind {
+    /// Text spans, clipped or not
+    Text,
+    /// Images
+    Image,
+    /// Forms drawn on the page
+    Form,
+    /// Raw content
+    Raw,
+    /// Content generated when the document is written
+    Deferred,
+}
+
+impl ContentKind {
+    fn of(content: &PageContents) -> ContentKind {
+        match content {
+            PageContents::Text(_) | PageContents::ClippedText(_, _) => ContentKind::Text,
+            PageContents::Image(_) => ContentKind::Image,
+            PageContents::Form(_) => ContentKind::Form,
+            PageContents::RawContent(_) => ContentKind::Raw,
+            PageContents::Deferred(_) => ContentKind::Deferred,
+        }
+    }
+}
+
+/// The size of a page's content stream, and how much of it each kind of content takes
+/// up. See [Document::content_sizes]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageContentSize {
+    /// The index of the page, in page order
+    pub page: usize,
+    /// The size of the page's content stream before it is compressed, in bytes
+    pub uncompressed: usize,
+    /// The size of the page's content stream once it is compressed (see
+    /// [Document::compression]), in bytes
+    pub compressed: usize,
+    /// How many bytes of the uncompressed content stream each kind of content on the
+    /// page takes up, largest first. Kinds which aren't on the page are left out
+    pub kinds: Vec<(ContentKind, usize)>,
+}
+
+impl Document {
+    /// Render each page's content stream as it would be written, and report how large
+    /// it is and which kinds of content it is made of, so that the pages (and content)
+    /// which bloat a document can be found. Images and forms are written once each
+    /// outside of the pages, so they only contribute the operators which draw them;
+    /// content shared between pages (see [Document::deduplicate_content]) is counted on
+    /// every page it is on.
+    pub fn content_sizes(&self) -> Result<Vec<PageContentSize>, PDFError> {
+        let mut sizes = Vec::with_capacity(self.page_order.len());
+        for (page_index, id) in self.page_order.iter().enumerate() {
+            let page = self.pages.get(*id).ok_or(PDFError::PageMissing)?;
+            let area = (page.media_box, page.content_box);
+
+            let mut content = page.defaults.render();
+            if !content.is_empty() {
+                content.push(b'\n');
+            }
+            let mut kinds: Vec<(ContentKind, usize)> = Vec::new();
+            for item in page.contents.iter() {
+                let rendered: usize = render_contents(
+                    std::slice::from_ref(item),
+                    area,
+                    page.word_spacing,
+                    self,
+                    page_index,
+                )?
+                .into_iter()
+                .map(|rendered| {
+                    content.extend_from_slice(&rendered);
+                    rendered.len()
+                })
+                .sum();
+                let kind = ContentKind::of(item);
+                match kinds.iter_mut().find(|(k, _)| *k == kind) {
+                    Some((_, size)) => *size += rendered,
+                    None if rendered > 0 => kinds.push((kind, rendered)),
+                    None => {}
+                }
+            }
+            kinds.sort_by(|a, b| b.1.cmp(&a.1));
+
+            let (compressed, _) = self.compression.compress(&content);
+            sizes.push(PageContentSize {
+                page: page_index,
+                uncompressed: content.len(),
+                compressed: compressed.len(),
+                kinds,
+            });
+        }
+        Ok(sizes)
+    }
+}
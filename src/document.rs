@@ -1,15 +1,25 @@
 use crate::{
+    colour::ColourConversion,
+    compression::Compression,
+    einvoice::add_file_id,
     font::Font,
-    image::Image,
+    form::FormXObject,
+    image::{Image, ImageSpill},
     info::Info,
+    navigation::OutlineNavigation,
     outline::Outline,
     page::Page,
     refs::{ObjectReferences, RefType},
-    OutlineEntry, PDFError,
+    resources::{check_form_cycles, UsedResources},
+    shared::SharedContent,
+    theme::map_colours,
+    AppliedStrategy, EInvoice, EmbeddingPolicy, FontStack, Heading, LineBreaker, OutlineId,
+    PDFError, Pt, SizeBudget, SizeReport, StyleSheet, Tabs, Theme,
 };
 use id_arena::{Arena, Id};
 use pdf_writer::{Finish, PdfWriter, Ref};
-use std::{cell::RefCell, io::Write, rc::Rc};
+use std::collections::HashMap;
+use std::io::Write;
 
 #[derive(Default)]
 /// A document is the main object that stores all the contents of the PDF
@@ -20,7 +30,74 @@ pub struct Document {
     pub page_order: Vec<Id<Page>>,
     pub fonts: Arena<Font>,
     pub images: Arena<Image>,
+    pub forms: Arena<FormXObject>,
     pub outline: Outline,
+    pub headings: Vec<Heading>,
+    /// Named text styles, used by [crate::layout::layout_styled_text]
+    pub styles: StyleSheet,
+    /// Alternate sets of colours for the document, by name
+    pub themes: HashMap<String, Theme>,
+    /// The name of the theme to apply when the document is written, if any
+    pub theme: Option<String>,
+    /// A conversion applied to every colour in the document's content when it is written
+    /// (after any theme), such as converting to greyscale to produce a print-economical
+    /// edition
+    pub colour_conversion: Option<ColourConversion>,
+    /// Whether [Document::colour_conversion] is also applied to raster images. The images
+    /// in the document are left as they are; only the written copies are converted
+    pub convert_images: bool,
+    /// When enabled, content which is repeated across pages (such as page decorations
+    /// and headers) is written once as a shared Form XObject which each page then draws,
+    /// rather than being written into every page's content stream. Only identical
+    /// content (i.e. spans with the same text, fonts, colours, and positions) is shared.
+    pub deduplicate_content: bool,
+    /// When enabled, adding a font which is the same as one already in the document
+    /// (i.e. loaded from the same file by different parts of an application) gives the
+    /// Id of the existing font rather than adding it again, so that it is only embedded
+    /// once
+    pub deduplicate_fonts: bool,
+    /// How the document's streams are compressed when it is written. Defaults to
+    /// [Compression::Default]
+    pub compression: Compression,
+    /// When enabled, raw content added to pages and forms (i.e. with
+    /// [crate::Page::add_raw_content]) is checked when the document is written, so that
+    /// mistakes such as unbalanced `q` / `Q` or `BT` / `ET` operators, unknown operators,
+    /// or malformed names are reported as errors rather than producing a corrupt PDF
+    pub validate_content: bool,
+    /// When enabled, the bookmarks, headings, and links between pages in the document
+    /// are checked when the document is written (see [Document::broken_references]), and
+    /// any which refer to pages or positions which don't exist are reported as a
+    /// [PDFError::BrokenReferences] error
+    pub validate_references: bool,
+    /// Navigation links to the sections of the document, generated from its outline and
+    /// drawn on every page when it is written (see [crate::OutlineNavigation])
+    pub outline_navigation: Option<OutlineNavigation>,
+    /// What to do when the document is written with a font whose licence doesn't allow
+    /// it to be embedded (see [crate::Font::embedding_permissions]). Defaults to
+    /// [EmbeddingPolicy::Warn], which writes the document regardless; use
+    /// [Document::restricted_fonts] to find the fonts to warn about
+    pub font_embedding: EmbeddingPolicy,
+    /// Decides where the layout functions may wrap text onto a new line. Defaults to
+    /// [crate::NaiveBreaker], which breaks wherever a line overflows
+    pub line_breaker: Box<dyn LineBreaker>,
+    /// How the layout functions expand tabs into spaces. Defaults to four spaces per tab
+    pub tabs: Tabs,
+    /// Whether the layout functions trim whitespace from the ends of the lines that they
+    /// lay out, dropping any spans which are left without any width, so that the lines'
+    /// widths (as used for alignment, and as given to `on_line` callbacks) only cover
+    /// their visible text. Off by default
+    pub trim_line_ends: bool,
+    /// An electronic invoice to attach, which also makes the document be written as a
+    /// PDF/A-3 file. See [crate::EInvoice]
+    pub e_invoice: Option<EInvoice>,
+    /// Fonts to fall back on for characters which a font doesn't have glyphs for, by the
+    /// first font of each stack. See [Document::set_font_stack]
+    pub font_stacks: HashMap<Id<Font>, FontStack>,
+    /// When set, raster images added once the document holds the spill's limit of image
+    /// data in memory are encoded into temporary files instead (see [Image::spill]).
+    /// Spilling is only an optimisation, so [Document::add_image] keeps an image which
+    /// can't be spilled in memory, while [Document::try_add_image] returns the error
+    pub image_spill: Option<ImageSpill>,
 }
 
 impl Document {
@@ -30,6 +107,13 @@ impl Document {
         self.info = Some(info);
     }
 
+    /// Attach an electronic invoice (Factur-X or ZUGFeRD) to the document, writing it as
+    /// a PDF/A-3 file along with the XMP metadata that e-invoicing requires. See
+    /// [EInvoice] for what must be supplied
+    pub fn set_e_invoice(&mut self, e_invoice: EInvoice) {
+        self.e_invoice = Some(e_invoice);
+    }
+
     /// Add a page to the document, returning the index of that page within the document.
     /// This index can be used to refer to the page if needed, provided that you don't
     /// remove or reorder the pages in the document. The page will be added to the end
@@ -40,6 +124,13 @@ impl Document {
         id
     }
 
+    /// Add several pages to the end of the document, in order, returning their Ids. As
+    /// pages can be sent between threads, they can be built up on worker threads and
+    /// then added to the document all at once.
+    pub fn add_pages<I: IntoIterator<Item = Page>>(&mut self, pages: I) -> Vec<Id<Page>> {
+        pages.into_iter().map(|page| self.add_page(page)).collect()
+    }
+
     /// Add a page to the document, inserting it before the page identified by `next`.
     /// If there is no page identified by `next`, the page will be added to the end of
     /// the document.
@@ -95,7 +186,19 @@ impl Document {
     /// the document, such that any page can access it by referring to it by its index /
     /// reference. The returned value is the index of the font, which is valid so long as
     /// you don't ever remove or reorder fonts from / in the document.
+    ///
+    /// Fonts are cheap to clone, so a font that is used in many documents only needs to
+    /// be loaded once and can then be cloned into each document.
     pub fn add_font(&mut self, font: Font) -> Id<Font> {
+        if self.deduplicate_fonts {
+            if let Some((id, _)) = self
+                .fonts
+                .iter()
+                .find(|(_, existing)| existing.same_face(&font))
+            {
+                return id;
+            }
+        }
         self.fonts.alloc(font)
     }
 
@@ -103,22 +206,103 @@ impl Document {
     /// within the document, such that any page can access and re-use images by referring
     /// to it by its its / reference. The returned value is the index of the image, which
     /// is valid so long as you don't ever remove or reorder images from / in the document.
-    pub fn add_image(&mut self, image: Image) -> Id<Image> {
+    ///
+    /// Images are cheap to clone, so an image that is used in many documents only needs
+    /// to be loaded once and can then be cloned into each document.
+    ///
+    /// If the image should be spilled (see [Document::image_spill]) but can't be, it is
+    /// kept in memory instead; use [Document::try_add_image] to find out why.
+    pub fn add_image(&mut self, mut image: Image) -> Id<Image> {
+        // spilling is only an optimisation, so the image can still be used without it
+        let _ = self.spill_image(&mut image);
         self.images.alloc(image)
     }
 
+    /// Add an image to the document structure, as with [Document::add_image], except
+    /// that if the image should be spilled (see [Document::image_spill]) but can't be,
+    /// the error is returned and the image isn't added.
+    pub fn try_add_image(&mut self, mut image: Image) -> Result<Id<Image>, PDFError> {
+        self.spill_image(&mut image)?;
+        Ok(self.images.alloc(image))
+    }
+
+    /// Spill an image which is about to be added, if the document already holds as much
+    /// image data in memory as its spill allows
+    fn spill_image(&self, image: &mut Image) -> Result<(), PDFError> {
+        if let Some(spill) = &self.image_spill {
+            let held: usize = self
+                .images
+                .iter()
+                .map(|(_, image)| image.memory_size())
+                .sum();
+            if held + image.memory_size() > spill.max_in_memory {
+                image.spill(&spill.directory)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a form to the document structure, so that it can be drawn on pages (see
+    /// [crate::Page::add_form]). As with images, the returned value is the index of the
+    /// form, which is valid so long as you don't ever remove or reorder forms from / in
+    /// the document. Forms are written once no matter how many times they are drawn.
+    pub fn add_form(&mut self, form: FormXObject) -> Id<FormXObject> {
+        self.forms.alloc(form)
+    }
+
+    /// Make a form which draws the contents of a page that has already been added to the
+    /// document, so that the page can also be drawn (at any size) onto other pages, i.e.
+    /// for thumbnails or imposition. The page stays in the document, and any changes to
+    /// it before the document is written are reflected in the form. Returns [None] if
+    /// the page isn't in the document.
+    pub fn page_to_xobject(&mut self, page: Id<Page>) -> Option<Id<FormXObject>> {
+        let mut form = FormXObject::new(self.pages.get(page)?.media_box);
+        form.source_page = Some(page);
+        Some(self.add_form(form))
+    }
+
+    /// Add (or replace) a named theme, which can then be selected with
+    /// [Document::set_theme]
+    pub fn add_theme<S: ToString>(&mut self, name: S, theme: Theme) {
+        self.themes.insert(name.to_string(), theme);
+    }
+
+    /// Select the theme to apply when the document is written, or [None] to write the
+    /// document with its colours as they are. The theme must have been added with
+    /// [Document::add_theme] by the time the document is written
+    pub fn set_theme<S: ToString>(&mut self, name: Option<S>) {
+        self.theme = name.map(|name| name.to_string());
+    }
+
     /// Add a bookmark in the document outline pointing to a page with a given index. For now,
     /// this will always fit the entire page into view when navigating to the bookmark.
     pub fn add_bookmark<S: ToString>(
         &mut self,
-        parent: Option<Rc<RefCell<OutlineEntry>>>,
+        parent: Option<OutlineId>,
         title: S,
         page_index: usize,
-    ) -> Rc<RefCell<OutlineEntry>> {
+    ) -> OutlineId {
         self.outline
             .add_bookmark(parent, page_index, title.to_string())
     }
 
+    /// Register a heading in the document. When the document is written, all registered
+    /// headings are converted into bookmarks in the document outline, nested according to
+    /// their levels (a level 2 heading is nested under the previous level 1 heading, etc).
+    /// `y` is the position of the top of the heading on the page, which is where the
+    /// viewer will scroll to when navigating to the bookmark.
+    ///
+    /// Headings are kept in the order they were registered, so they can also be used to
+    /// generate a table of contents.
+    pub fn add_heading<S: ToString>(&mut self, level: usize, title: S, page: Id<Page>, y: Pt) {
+        self.headings.push(Heading {
+            level,
+            title: title.to_string(),
+            page,
+            y,
+        });
+    }
+
     /// Write the entire document to the writer. Note: although this can write to arbitrary
     /// streams, the entire document is "rendered" in memory first. If you have a very large
     /// document, this could allocate a significant amount of memory. This limitation is due
@@ -130,24 +314,134 @@ impl Document {
     /// change the order of them before writing, then you should update all font_index
     /// references on all pages to reflect the change). Calling `write` will automatically
     /// generate PDF objects and corresponding references to those objects.
-    pub fn write<W: Write>(self, mut w: W) -> Result<(), PDFError> {
+    pub fn write<W: Write>(mut self, mut w: W) -> Result<(), PDFError> {
+        self.check_references()?;
+        self.check_font_embedding()?;
+        self.apply_headings()?;
+        self.apply_outline_navigation();
+        w.write_all(self.render()?.as_slice()).map_err(Into::into)
+    }
+
+    /// Write the document, first reducing its size with the budget's strategies (in
+    /// order) until it fits within the budget. The document is written even if it is
+    /// still too large once all of the strategies have been applied; check
+    /// [SizeReport::within_budget] to find out whether it fit.
+    pub fn write_with_budget<W: Write>(
+        mut self,
+        mut w: W,
+        budget: &SizeBudget,
+    ) -> Result<SizeReport, PDFError> {
+        self.check_references()?;
+        self.check_font_embedding()?;
+        self.apply_headings()?;
+        self.apply_outline_navigation();
+
+        let mut bytes = self.render()?;
+        let mut applied = Vec::new();
+        for strategy in budget.strategies.iter() {
+            if bytes.len() <= budget.max_bytes {
+                break;
+            }
+            let images_changed = strategy.apply(&mut self.images)?;
+            if images_changed > 0 {
+                let size_before = bytes.len();
+                bytes = self.render()?;
+                applied.push(AppliedStrategy {
+                    strategy: *strategy,
+                    images_changed,
+                    size_before,
+                    size_after: bytes.len(),
+                });
+            }
+        }
+
+        w.write_all(bytes.as_slice())?;
+        Ok(SizeReport {
+            size: bytes.len(),
+            within_budget: bytes.len() <= budget.max_bytes,
+            applied,
+        })
+    }
+
+    /// Report broken references if [Document::validate_references] is enabled
+    fn check_references(&self) -> Result<(), PDFError> {
+        if !self.validate_references {
+            return Ok(());
+        }
+        let broken = self.broken_references();
+        if broken.is_empty() {
+            Ok(())
+        } else {
+            Err(PDFError::BrokenReferences(broken))
+        }
+    }
+
+    /// Convert registered headings into bookmarks in the outline
+    fn apply_headings(&mut self) -> Result<(), PDFError> {
+        let headings = std::mem::take(&mut self.headings);
+        self.outline.add_headings(&headings, &self.page_order)
+    }
+
+    fn render(&self) -> Result<Vec<u8>, PDFError> {
         let Document {
             info,
             pages,
             page_order,
             fonts,
             images,
+            forms,
             outline,
+            headings: _,
+            styles: _,
+            themes,
+            theme,
+            colour_conversion,
+            convert_images,
+            deduplicate_content,
+            deduplicate_fonts: _,
+            compression,
+            validate_content: _,
+            validate_references: _,
+            outline_navigation: _,
+            font_embedding: _,
+            line_breaker: _,
+            tabs: _,
+            trim_line_ends: _,
+            e_invoice,
+            font_stacks: _,
+            image_spill: _,
         } = self;
 
+        let theme: Option<&Theme> = match theme {
+            Some(name) => Some(
+                themes
+                    .get(name)
+                    .ok_or_else(|| PDFError::ThemeMissing(name.clone()))?,
+            ),
+            None => None,
+        };
+        let apply_colours = |content: Vec<u8>| match (theme, colour_conversion) {
+            (None, None) => content,
+            _ => map_colours(&content, |colour| {
+                let colour = theme.map(|theme| theme.map(colour)).unwrap_or(colour);
+                colour_conversion
+                    .map(|conversion| conversion.convert(colour))
+                    .unwrap_or(colour)
+            }),
+        };
+
         let mut refs = ObjectReferences::new();
 
         let catalog_id = refs.gen(RefType::Catalog);
         let page_tree_id = refs.gen(RefType::PageTree);
 
+        let now = chrono::Local::now();
         let mut writer = PdfWriter::new();
         if let Some(info) = info {
-            info.write(&mut refs, &mut writer);
+            info.write(&mut refs, &mut writer, &now);
+        }
+        if let Some(e_invoice) = e_invoice {
+            e_invoice.write(info.as_ref(), &now, &mut refs, *compression, &mut writer);
         }
 
         // let page_refs: Vec<Ref> = pages
@@ -166,32 +460,106 @@ impl Document {
             .kids(page_refs);
 
         for (i, font) in fonts.iter() {
-            font.write(&mut refs, i, &mut writer);
+            font.write(&mut refs, i, *compression, &mut writer);
         }
 
         for (i, image) in images.iter() {
-            image.write(&mut refs, i.index(), &mut writer)?;
+            match colour_conversion {
+                Some(conversion) if *convert_images => {
+                    let mut converted = image.clone();
+                    converted.convert_colours(conversion)?;
+                    converted.write(&mut refs, i.index(), *compression, &mut writer)?;
+                }
+                _ => image.write(&mut refs, i.index(), *compression, &mut writer)?,
+            }
         }
 
-        for id in page_order.iter() {
+        // forms can draw each other, so all of their references are needed up front
+        for (i, _) in forms.iter() {
+            refs.gen(RefType::Form(i.index()));
+        }
+        let rendered_forms: Vec<Vec<u8>> = forms
+            .iter()
+            .map(|(_, form)| form.render(self).map(apply_colours))
+            .collect::<Result<_, _>>()?;
+        let used: Vec<UsedResources> = rendered_forms
+            .iter()
+            .map(|content| UsedResources::scan(content, self))
+            .collect();
+        check_form_cycles(&used)?;
+        for ((i, form), (content, used)) in forms.iter().zip(rendered_forms.iter().zip(used.iter()))
+        {
+            form.write(
+                &mut refs,
+                i.index(),
+                content,
+                used,
+                *compression,
+                &mut writer,
+            )?;
+        }
+
+        let page_error = |page_index: usize| {
+            move |e: PDFError| PDFError::Page {
+                page_index,
+                source: Box::new(e),
+            }
+        };
+
+        let mut rendered: Vec<(&Page, Vec<Vec<u8>>)> = Vec::with_capacity(page_order.len());
+        for (page_index, id) in page_order.iter().enumerate() {
             let page = pages.get(*id).ok_or(PDFError::PageMissing)?;
+            let items: Vec<Vec<u8>> = page
+                .render_items(self, page_index)
+                .map_err(page_error(page_index))?
+                .into_iter()
+                .map(apply_colours)
+                .collect();
+            rendered.push((page, items));
+        }
+
+        let shared = if *deduplicate_content {
+            let pages: Vec<(&Page, &Vec<Vec<u8>>)> = rendered
+                .iter()
+                .map(|(page, items)| (*page, items))
+                .collect();
+            SharedContent::find(&pages)
+        } else {
+            SharedContent::default()
+        };
+        shared.write(&mut refs, fonts, images, forms, *compression, &mut writer)?;
+
+        for (page_index, (id, (page, items))) in page_order.iter().zip(rendered.iter()).enumerate()
+        {
             page.write(
                 &mut refs,
                 id.index(),
-                &page_order,
-                &fonts,
-                &images,
+                page_order,
+                fonts,
+                images,
+                forms,
+                items,
+                &shared,
+                *compression,
                 &mut writer,
-            )?;
+            )
+            .map_err(page_error(page_index))?;
         }
 
-        outline.write(&mut refs, &mut writer);
+        outline.write(page_order, &mut refs, &mut writer)?;
 
         let mut catalog = writer.catalog(catalog_id);
         catalog.pages(page_tree_id);
-        catalog.outlines(refs.get(RefType::Outlines).unwrap());
+        catalog.outlines(refs.require(RefType::Outlines)?);
+        if let Some(e_invoice) = e_invoice {
+            e_invoice.write_catalog(&refs, &mut catalog)?;
+        }
         catalog.finish();
 
-        w.write_all(writer.finish().as_slice()).map_err(Into::into)
+        let mut pdf = writer.finish();
+        if e_invoice.is_some() {
+            add_file_id(&mut pdf);
+        }
+        Ok(pdf)
     }
 }
@@ -0,0 +1,967 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::layout;
+use crate::page::{ContentTarget, Page, SpanFont, SpanLayout};
+use crate::rect::Rect;
+use crate::units::Pt;
+use pdf_writer::types::{LineCapStyle, LineJoinStyle};
+use pdf_writer::Content;
+
+/// The distance of the control points of a cubic Bézier approximating a quarter circle
+/// from its end points, as a fraction of the radius
+const KAPPA: f32 = 0.552_284_8;
+
+/// How the outline of a shape is drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stroke {
+    /// The colour of the line
+    pub colour: Colour,
+    /// The width of the line
+    pub width: Pt,
+    /// Whether the line is solid, dashed, or dotted
+    pub dash: Dash,
+}
+
+impl Stroke {
+    /// Create a solid stroke
+    pub fn new(colour: Colour, width: Pt) -> Stroke {
+        Stroke {
+            colour,
+            width,
+            dash: Dash::Solid,
+        }
+    }
+
+    /// Create a dashed stroke
+    pub fn dashed(colour: Colour, width: Pt) -> Stroke {
+        Stroke {
+            dash: Dash::Dashed,
+            ..Stroke::new(colour, width)
+        }
+    }
+
+    /// Create a dotted stroke
+    pub fn dotted(colour: Colour, width: Pt) -> Stroke {
+        Stroke {
+            dash: Dash::Dotted,
+            ..Stroke::new(colour, width)
+        }
+    }
+}
+
+/// The pattern of a [Stroke]. Dashes and dots are sized relative to the width of the
+/// stroke
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dash {
+    /// A continuous line
+    Solid,
+    /// Dashes three times as long as the line is wide, with gaps twice as long
+    Dashed,
+    /// Round dots as wide as the line, with gaps twice as long
+    Dotted,
+    /// A custom pattern of alternating dash and gap lengths
+    Custom(Pt, Pt),
+}
+
+/// How a [Shape] is painted: filled, stroked, or both. If neither is set, nothing is
+/// drawn
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct DrawStyle {
+    /// The colour to fill the inside of the shape with
+    pub fill: Option<Colour>,
+    /// How to draw the outline of the shape
+    pub stroke: Option<Stroke>,
+}
+
+impl DrawStyle {
+    /// Fill the shape without drawing its outline
+    pub fn filled(colour: Colour) -> DrawStyle {
+        DrawStyle {
+            fill: Some(colour),
+            stroke: None,
+        }
+    }
+
+    /// Draw the outline of the shape without filling it
+    pub fn stroked(colour: Colour, width: Pt) -> DrawStyle {
+        DrawStyle {
+            fill: None,
+            stroke: Some(Stroke::new(colour, width)),
+        }
+    }
+
+    /// Fill the shape, then draw its outline on top
+    pub fn filled_and_stroked(fill: Colour, stroke: Stroke) -> DrawStyle {
+        DrawStyle {
+            fill: Some(fill),
+            stroke: Some(stroke),
+        }
+    }
+}
+
+/// Something with an outline that can be drawn on a page (see [Page::add_shape])
+pub trait Shape {
+    /// Add the path of the shape to the content, without painting it
+    fn path(&self, content: &mut Content);
+}
+
+/// A straight line between two points. Lines have no inside, so are only ever stroked
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Line {
+    /// The start of the line
+    pub from: (Pt, Pt),
+    /// The end of the line
+    pub to: (Pt, Pt),
+}
+
+impl Shape for Line {
+    fn path(&self, content: &mut Content) {
+        content.move_to(*self.from.0, *self.from.1);
+        content.line_to(*self.to.0, *self.to.1);
+    }
+}
+
+/// A series of connected straight lines, optionally closed back to the first point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    /// The points of the line, in order
+    pub points: Vec<(Pt, Pt)>,
+    /// Whether to join the last point back to the first, making a polygon
+    pub closed: bool,
+}
+
+impl Shape for Polyline {
+    fn path(&self, content: &mut Content) {
+        let mut points = self.points.iter();
+        if let Some((x, y)) = points.next() {
+            content.move_to(**x, **y);
+            for (x, y) in points {
+                content.line_to(**x, **y);
+            }
+            if self.closed {
+                content.close_path();
+            }
+        }
+    }
+}
+
+/// A part of a [Path]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathSegment {
+    /// Start a new subpath at a point
+    MoveTo((Pt, Pt)),
+    /// A straight line to a point
+    LineTo((Pt, Pt)),
+    /// A cubic Bézier curve to the last point, with the first two as control points
+    CubicTo((Pt, Pt), (Pt, Pt), (Pt, Pt)),
+    /// A straight line back to the start of the subpath, closing it
+    Close,
+}
+
+/// An arbitrary outline made of lines and curves, such as the outline of text (see
+/// [crate::Font::text_outline]). Paths are filled with the non-zero winding rule, so
+/// that glyph outlines come out as they do when drawn as text
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    /// The parts of the path, in order
+    pub segments: Vec<PathSegment>,
+}
+
+impl Path {
+    /// Create an empty path
+    pub fn new() -> Path {
+        Path::default()
+    }
+
+    /// Start a new subpath at a point
+    pub fn move_to(&mut self, to: (Pt, Pt)) -> &mut Self {
+        self.segments.push(PathSegment::MoveTo(to));
+        self
+    }
+
+    /// Add a straight line from the current point
+    pub fn line_to(&mut self, to: (Pt, Pt)) -> &mut Self {
+        self.segments.push(PathSegment::LineTo(to));
+        self
+    }
+
+    /// Add a quadratic Bézier curve from the current point, which is stored as the
+    /// equivalent cubic curve
+    pub fn quad_to(&mut self, control: (Pt, Pt), to: (Pt, Pt)) -> &mut Self {
+        let from = self.current_point();
+        let third = |a: Pt, b: Pt| a + (b - a) * (2.0 / 3.0);
+        self.segments.push(PathSegment::CubicTo(
+            (third(from.0, control.0), third(from.1, control.1)),
+            (third(to.0, control.0), third(to.1, control.1)),
+            to,
+        ));
+        self
+    }
+
+    /// Add a cubic Bézier curve from the current point
+    pub fn cubic_to(&mut self, control1: (Pt, Pt), control2: (Pt, Pt), to: (Pt, Pt)) -> &mut Self {
+        self.segments
+            .push(PathSegment::CubicTo(control1, control2, to));
+        self
+    }
+
+    /// Close the current subpath with a straight line back to its start
+    pub fn close(&mut self) -> &mut Self {
+        self.segments.push(PathSegment::Close);
+        self
+    }
+
+    /// Append another path to this one
+    pub fn extend(&mut self, other: &Path) -> &mut Self {
+        self.segments.extend(other.segments.iter().copied());
+        self
+    }
+
+    /// A copy of the path moved by the given distances
+    pub fn translated(&self, dx: Pt, dy: Pt) -> Path {
+        let mv = |(x, y): (Pt, Pt)| (x + dx, y + dy);
+        Path {
+            segments: self
+                .segments
+                .iter()
+                .map(|segment| match *segment {
+                    PathSegment::MoveTo(to) => PathSegment::MoveTo(mv(to)),
+                    PathSegment::LineTo(to) => PathSegment::LineTo(mv(to)),
+                    PathSegment::CubicTo(c1, c2, to) => {
+                        PathSegment::CubicTo(mv(c1), mv(c2), mv(to))
+                    }
+                    PathSegment::Close => PathSegment::Close,
+                })
+                .collect(),
+        }
+    }
+
+    /// Where the next segment of the path starts
+    fn current_point(&self) -> (Pt, Pt) {
+        let mut start = (Pt(0.0), Pt(0.0));
+        let mut current = start;
+        for segment in self.segments.iter() {
+            match *segment {
+                PathSegment::MoveTo(to) => {
+                    start = to;
+                    current = to;
+                }
+                PathSegment::LineTo(to) | PathSegment::CubicTo(_, _, to) => current = to,
+                PathSegment::Close => current = start,
+            }
+        }
+        current
+    }
+}
+
+impl Shape for Path {
+    fn path(&self, content: &mut Content) {
+        for segment in self.segments.iter() {
+            match *segment {
+                PathSegment::MoveTo((x, y)) => {
+                    content.move_to(*x, *y);
+                }
+                PathSegment::LineTo((x, y)) => {
+                    content.line_to(*x, *y);
+                }
+                PathSegment::CubicTo((x1, y1), (x2, y2), (x, y)) => {
+                    content.cubic_to(*x1, *y1, *x2, *y2, *x, *y);
+                }
+                PathSegment::Close => {
+                    content.close_path();
+                }
+            }
+        }
+    }
+}
+
+impl Shape for Rect {
+    fn path(&self, content: &mut Content) {
+        content.rect(
+            *self.x1,
+            *self.y1,
+            *(self.x2 - self.x1),
+            *(self.y2 - self.y1),
+        );
+    }
+}
+
+/// The radius of each corner of a [RoundedRect]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct CornerRadii {
+    /// The radius of the top-left corner
+    pub top_left: Pt,
+    /// The radius of the top-right corner
+    pub top_right: Pt,
+    /// The radius of the bottom-right corner
+    pub bottom_right: Pt,
+    /// The radius of the bottom-left corner
+    pub bottom_left: Pt,
+}
+
+impl CornerRadii {
+    /// The same radius for every corner
+    pub fn all(radius: Pt) -> CornerRadii {
+        CornerRadii {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    /// Round the top corners, leaving the bottom corners square (i.e. for tabs)
+    pub fn top(radius: Pt) -> CornerRadii {
+        CornerRadii {
+            top_left: radius,
+            top_right: radius,
+            ..CornerRadii::default()
+        }
+    }
+
+    /// Round the bottom corners, leaving the top corners square
+    pub fn bottom(radius: Pt) -> CornerRadii {
+        CornerRadii {
+            bottom_right: radius,
+            bottom_left: radius,
+            ..CornerRadii::default()
+        }
+    }
+}
+
+/// A rectangle with rounded corners. Radii which are too large for the rectangle are
+/// reduced so that adjacent corners don't overlap
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RoundedRect {
+    /// The bounds of the rectangle
+    pub rect: Rect,
+    /// The radius of each corner
+    pub radii: CornerRadii,
+}
+
+impl RoundedRect {
+    /// Create a rectangle with the same radius on every corner
+    pub fn new(rect: Rect, radius: Pt) -> RoundedRect {
+        RoundedRect {
+            rect,
+            radii: CornerRadii::all(radius),
+        }
+    }
+
+    /// Create a capsule (or "pill") which fills the rectangle, with its shorter sides
+    /// completely rounded
+    pub fn pill(rect: Rect) -> RoundedRect {
+        let width = (*rect.x2 - *rect.x1).abs();
+        let height = (*rect.y2 - *rect.y1).abs();
+        RoundedRect::new(rect, Pt(width.min(height) / 2.0))
+    }
+}
+
+impl Shape for RoundedRect {
+    fn path(&self, content: &mut Content) {
+        let (x1, x2) = (
+            self.rect.x1.min(*self.rect.x2),
+            self.rect.x1.max(*self.rect.x2),
+        );
+        let (y1, y2) = (
+            self.rect.y1.min(*self.rect.y2),
+            self.rect.y1.max(*self.rect.y2),
+        );
+        let limit = ((x2 - x1) / 2.0).min((y2 - y1) / 2.0);
+        let radius = |r: Pt| r.max(0.0).min(limit);
+        let tl = radius(self.radii.top_left);
+        let tr = radius(self.radii.top_right);
+        let br = radius(self.radii.bottom_right);
+        let bl = radius(self.radii.bottom_left);
+
+        // go anti-clockwise from the bottom edge, with a quarter circle at each corner
+        content.move_to(x1 + bl, y1);
+        content.line_to(x2 - br, y1);
+        content.cubic_to(
+            x2 - br * (1.0 - KAPPA),
+            y1,
+            x2,
+            y1 + br * (1.0 - KAPPA),
+            x2,
+            y1 + br,
+        );
+        content.line_to(x2, y2 - tr);
+        content.cubic_to(
+            x2,
+            y2 - tr * (1.0 - KAPPA),
+            x2 - tr * (1.0 - KAPPA),
+            y2,
+            x2 - tr,
+            y2,
+        );
+        content.line_to(x1 + tl, y2);
+        content.cubic_to(
+            x1 + tl * (1.0 - KAPPA),
+            y2,
+            x1,
+            y2 - tl * (1.0 - KAPPA),
+            x1,
+            y2 - tl,
+        );
+        content.line_to(x1, y1 + bl);
+        content.cubic_to(
+            x1,
+            y1 + bl * (1.0 - KAPPA),
+            x1 + bl * (1.0 - KAPPA),
+            y1,
+            x1 + bl,
+            y1,
+        );
+        content.close_path();
+    }
+}
+
+/// A polygon with equal sides and angles, such as a triangle, hexagon, or octagon. Unless
+/// rotated, the polygon has a vertex pointing straight up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegularPolygon {
+    /// The centre of the polygon
+    pub centre: (Pt, Pt),
+    /// The distance from the centre to each vertex
+    pub radius: Pt,
+    /// The number of sides, which must be at least 3 for anything to be drawn
+    pub sides: usize,
+    /// How far to rotate the polygon anti-clockwise, in degrees
+    pub rotation: f32,
+}
+
+impl Shape for RegularPolygon {
+    fn path(&self, content: &mut Content) {
+        if self.sides < 3 {
+            return;
+        }
+        let step = 360.0 / self.sides as f32;
+        polygon_path(
+            content,
+            (0..self.sides).map(|i| {
+                point_on_circle(
+                    self.centre,
+                    *self.radius,
+                    90.0 + self.rotation + step * i as f32,
+                )
+            }),
+        );
+    }
+}
+
+/// A star with any number of points, which alternate between the outer radius and the
+/// inner radius. Unless rotated, the star has a point pointing straight up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Star {
+    /// The centre of the star
+    pub centre: (Pt, Pt),
+    /// The distance from the centre to the tip of each point
+    pub outer_radius: Pt,
+    /// The distance from the centre to the inside corners between the points
+    pub inner_radius: Pt,
+    /// The number of points, which must be at least 2 for anything to be drawn
+    pub points: usize,
+    /// How far to rotate the star anti-clockwise, in degrees
+    pub rotation: f32,
+}
+
+impl Star {
+    /// Create a classic five-pointed star, with its inside corners in line with the
+    /// lines between its outer points
+    pub fn five_pointed(centre: (Pt, Pt), outer_radius: Pt) -> Star {
+        Star {
+            centre,
+            outer_radius,
+            inner_radius: outer_radius * 0.381_966,
+            points: 5,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Shape for Star {
+    fn path(&self, content: &mut Content) {
+        if self.points < 2 {
+            return;
+        }
+        let step = 180.0 / self.points as f32;
+        polygon_path(
+            content,
+            (0..self.points * 2).map(|i| {
+                let radius = if i % 2 == 0 {
+                    self.outer_radius
+                } else {
+                    self.inner_radius
+                };
+                point_on_circle(self.centre, *radius, 90.0 + self.rotation + step * i as f32)
+            }),
+        );
+    }
+}
+
+/// Part of the outline of a circle. Angles are in degrees, measured anti-clockwise from
+/// the positive x-axis (i.e. 3 o'clock). Arcs are not closed, so are usually only
+/// stroked
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Arc {
+    /// The centre of the circle
+    pub centre: (Pt, Pt),
+    /// The radius of the circle
+    pub radius: Pt,
+    /// The angle the arc starts at
+    pub start_angle: f32,
+    /// The angle the arc ends at. If this is less than `start_angle`, the arc is drawn
+    /// clockwise
+    pub end_angle: f32,
+}
+
+impl Shape for Arc {
+    fn path(&self, content: &mut Content) {
+        let (x, y) = point_on_circle(self.centre, *self.radius, self.start_angle);
+        content.move_to(x, y);
+        arc_path(
+            content,
+            self.centre,
+            *self.radius,
+            self.start_angle,
+            self.end_angle,
+        );
+    }
+}
+
+/// A slice of a circle, as in a pie chart: an [Arc] closed with straight lines to the
+/// centre of the circle. Angles are the same as for [Arc]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wedge {
+    /// The centre of the circle
+    pub centre: (Pt, Pt),
+    /// The radius of the circle
+    pub radius: Pt,
+    /// The angle the wedge starts at
+    pub start_angle: f32,
+    /// The angle the wedge ends at
+    pub end_angle: f32,
+}
+
+impl Shape for Wedge {
+    fn path(&self, content: &mut Content) {
+        content.move_to(*self.centre.0, *self.centre.1);
+        let (x, y) = point_on_circle(self.centre, *self.radius, self.start_angle);
+        content.line_to(x, y);
+        arc_path(
+            content,
+            self.centre,
+            *self.radius,
+            self.start_angle,
+            self.end_angle,
+        );
+        content.close_path();
+    }
+}
+
+/// A circle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Circle {
+    /// The centre of the circle
+    pub centre: (Pt, Pt),
+    /// The radius of the circle
+    pub radius: Pt,
+}
+
+impl Shape for Circle {
+    fn path(&self, content: &mut Content) {
+        circle_path(content, (*self.centre.0, *self.centre.1), *self.radius);
+    }
+}
+
+/// The point on a circle at the given angle, in degrees anti-clockwise from the
+/// positive x-axis
+fn point_on_circle(centre: (Pt, Pt), radius: f32, degrees: f32) -> (f32, f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    (*centre.0 + radius * cos, *centre.1 + radius * sin)
+}
+
+/// Add a closed polygon through the points to the path of some content
+fn polygon_path<I: Iterator<Item = (f32, f32)>>(content: &mut Content, mut points: I) {
+    if let Some((x, y)) = points.next() {
+        content.move_to(x, y);
+        for (x, y) in points {
+            content.line_to(x, y);
+        }
+        content.close_path();
+    }
+}
+
+/// Continue the path of some content (which must already be at the start of the arc)
+/// along an arc, using one Bézier curve for every quarter circle or part thereof
+fn arc_path(content: &mut Content, centre: (Pt, Pt), radius: f32, start: f32, end: f32) {
+    let sweep = end - start;
+    let segments = (sweep.abs() / 90.0).ceil().max(1.0) as usize;
+    let step = sweep / segments as f32;
+    // the distance of the control points along the tangents at either end of a segment
+    let k = radius * 4.0 / 3.0 * (step.to_radians() / 4.0).tan();
+    for i in 0..segments {
+        let (d1, d2) = (start + step * i as f32, start + step * (i + 1) as f32);
+        let (p1, p2) = (
+            point_on_circle(centre, radius, d1),
+            point_on_circle(centre, radius, d2),
+        );
+        let (a1, a2) = (d1.to_radians(), d2.to_radians());
+        content.cubic_to(
+            p1.0 - k * a1.sin(),
+            p1.1 + k * a1.cos(),
+            p2.0 + k * a2.sin(),
+            p2.1 - k * a2.cos(),
+            p2.0,
+            p2.1,
+        );
+    }
+}
+
+/// Draw a shape into some content, wrapped in `q` / `Q` so the style doesn't leak
+/// into anything drawn after it
+pub fn draw<S: Shape + ?Sized>(content: &mut Content, shape: &S, style: &DrawStyle) {
+    if style.fill.is_none() && style.stroke.is_none() {
+        return;
+    }
+    content.save_state();
+    if let Some(fill) = style.fill {
+        set_fill_colour(content, fill);
+    }
+    if let Some(stroke) = style.stroke {
+        set_stroke(content, &stroke);
+    }
+    shape.path(content);
+    match (style.fill.is_some(), style.stroke.is_some()) {
+        (true, true) => content.fill_nonzero_and_stroke(),
+        (true, false) => content.fill_nonzero(),
+        _ => content.stroke(),
+    };
+    content.restore_state();
+}
+
+/// Set the colour used by fill operations
+pub fn set_fill_colour(content: &mut Content, colour: Colour) {
+    match colour {
+        Colour::RGB { r, g, b } => content.set_fill_rgb(r, g, b),
+        Colour::CMYK { c, m, y, k } => content.set_fill_cmyk(c, m, y, k),
+        Colour::Grey { g } => content.set_fill_gray(g),
+    };
+}
+
+/// Set the colour used by stroke operations
+pub fn set_stroke_colour(content: &mut Content, colour: Colour) {
+    match colour {
+        Colour::RGB { r, g, b } => content.set_stroke_rgb(r, g, b),
+        Colour::CMYK { c, m, y, k } => content.set_stroke_cmyk(c, m, y, k),
+        Colour::Grey { g } => content.set_stroke_gray(g),
+    };
+}
+
+/// Set the colour, width, and dash pattern used by stroke operations
+pub fn set_stroke(content: &mut Content, stroke: &Stroke) {
+    set_stroke_colour(content, stroke.colour);
+    content.set_line_width(*stroke.width);
+    let width = *stroke.width;
+    match stroke.dash {
+        Dash::Solid => {}
+        Dash::Dashed => {
+            content.set_line_cap(LineCapStyle::ButtCap);
+            content.set_dash_pattern([width * 3.0, width * 2.0], 0.0);
+        }
+        Dash::Dotted => {
+            // zero-length dashes with round caps are drawn as dots
+            content.set_line_cap(LineCapStyle::RoundCap);
+            content.set_dash_pattern([0.0, width * 3.0], 0.0);
+        }
+        Dash::Custom(dash, gap) => {
+            content.set_dash_pattern([*dash, *gap], 0.0);
+        }
+    }
+}
+
+/// Add a circle to the path of some content, approximated with four Bézier curves
+fn circle_path(content: &mut Content, (cx, cy): (f32, f32), r: f32) {
+    let k = r * KAPPA;
+    content.move_to(cx + r, cy);
+    content.cubic_to(cx + r, cy + k, cx + k, cy + r, cx, cy + r);
+    content.cubic_to(cx - k, cy + r, cx - r, cy + k, cx - r, cy);
+    content.cubic_to(cx - r, cy - k, cx - k, cy - r, cx, cy - r);
+    content.cubic_to(cx + k, cy - r, cx + r, cy - k, cx + r, cy);
+    content.close_path();
+}
+
+/// The decoration at the end of an [Arrow]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArrowHead {
+    /// A plain line end
+    None,
+    /// Two lines angled back from the point, like `->`
+    Open,
+    /// A solid triangle
+    Filled,
+    /// A line across the end, perpendicular to the arrow, like `-|`
+    Bar,
+    /// A solid circle centred on the end, as is common for leader lines
+    Dot,
+}
+
+/// A line through any number of points, with a head at either end. With two points this
+/// is a simple arrow; with more it is a leader line or connector with bends in it (see
+/// [Arrow::elbow])
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arrow {
+    /// The points the arrow passes through, from its tail to its tip
+    pub points: Vec<(Pt, Pt)>,
+    /// The head at the tail (first point) of the arrow
+    pub start: ArrowHead,
+    /// The head at the tip (last point) of the arrow
+    pub end: ArrowHead,
+    /// The length of the arrow heads, along the line
+    pub head_size: Pt,
+    /// How the line (and arrow heads) are drawn
+    pub stroke: Stroke,
+}
+
+impl Arrow {
+    /// Create an arrow from one point to another, with a filled head at `to`
+    pub fn new(from: (Pt, Pt), to: (Pt, Pt), stroke: Stroke) -> Arrow {
+        Arrow {
+            points: vec![from, to],
+            start: ArrowHead::None,
+            end: ArrowHead::Filled,
+            head_size: stroke.width * 4.0 + Pt(2.0),
+            stroke,
+        }
+    }
+
+    /// Create a connector from one point to another which travels horizontally, then
+    /// vertically, then horizontally again (turning halfway between the points), as is
+    /// common in wiring diagrams and flow charts
+    pub fn elbow(from: (Pt, Pt), to: (Pt, Pt), stroke: Stroke) -> Arrow {
+        let mid = from.0 + (to.0 - from.0) / 2.0;
+        Arrow {
+            points: vec![from, (mid, from.1), (mid, to.1), to],
+            ..Arrow::new(from, to, stroke)
+        }
+    }
+
+    /// Set the heads at the tail and tip of the arrow
+    pub fn heads(&mut self, start: ArrowHead, end: ArrowHead) -> &mut Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Set the length of the arrow heads
+    pub fn head_size(&mut self, head_size: Pt) -> &mut Self {
+        self.head_size = head_size;
+        self
+    }
+
+    /// Draw the arrow into some content
+    pub fn draw(&self, content: &mut Content) {
+        let mut points: Vec<(f32, f32)> = self.points.iter().map(|(x, y)| (**x, **y)).collect();
+        // ignore repeated points, which have no direction
+        points.dedup();
+        if points.len() < 2 {
+            return;
+        }
+
+        content.save_state();
+        set_stroke(content, &self.stroke);
+        set_fill_colour(content, self.stroke.colour);
+        content.set_line_join(LineJoinStyle::MiterJoin);
+
+        let n = points.len();
+        let heads = [
+            (self.start, points[0], points[1]),
+            (self.end, points[n - 1], points[n - 2]),
+        ];
+
+        // pull the shaft back from filled heads so that the line's end doesn't poke
+        // out past the point of the head
+        let mut shaft = points.clone();
+        if self.start == ArrowHead::Filled {
+            shaft[0] = back_from(points[0], points[1], *self.head_size * 0.5);
+        }
+        if self.end == ArrowHead::Filled {
+            shaft[n - 1] = back_from(points[n - 1], points[n - 2], *self.head_size * 0.5);
+        }
+        content.move_to(shaft[0].0, shaft[0].1);
+        for (x, y) in shaft.iter().skip(1) {
+            content.line_to(*x, *y);
+        }
+        content.stroke();
+
+        // heads are always drawn solid, even on dashed arrows
+        content.set_dash_pattern([], 0.0);
+        for (head, tip, towards) in heads {
+            self.draw_head(content, head, tip, towards);
+        }
+        content.restore_state();
+    }
+
+    fn draw_head(
+        &self,
+        content: &mut Content,
+        head: ArrowHead,
+        tip: (f32, f32),
+        towards: (f32, f32),
+    ) {
+        let length = *self.head_size;
+        let half_width = length * 0.4;
+        let (dx, dy) = direction(towards, tip);
+        let base = (tip.0 - dx * length, tip.1 - dy * length);
+        let (nx, ny) = (-dy * half_width, dx * half_width);
+        match head {
+            ArrowHead::None => {}
+            ArrowHead::Open => {
+                content.move_to(base.0 + nx, base.1 + ny);
+                content.line_to(tip.0, tip.1);
+                content.line_to(base.0 - nx, base.1 - ny);
+                content.stroke();
+            }
+            ArrowHead::Filled => {
+                content.move_to(tip.0, tip.1);
+                content.line_to(base.0 + nx, base.1 + ny);
+                content.line_to(base.0 - nx, base.1 - ny);
+                content.close_path();
+                content.fill_nonzero();
+            }
+            ArrowHead::Bar => {
+                content.move_to(tip.0 + nx, tip.1 + ny);
+                content.line_to(tip.0 - nx, tip.1 - ny);
+                content.stroke();
+            }
+            ArrowHead::Dot => {
+                circle_path(content, tip, length / 3.0);
+                content.fill_nonzero();
+            }
+        }
+    }
+}
+
+/// The unit vector pointing from `from` to `to`
+fn direction(from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    (dx / length, dy / length)
+}
+
+/// The point `distance` back from `tip` towards `towards`
+fn back_from(tip: (f32, f32), towards: (f32, f32), distance: f32) -> (f32, f32) {
+    let (dx, dy) = direction(tip, towards);
+    (tip.0 + dx * distance, tip.1 + dy * distance)
+}
+
+/// A box of text with a leader line pointing from the box to a point on the page, for
+/// labelling parts of diagrams and figures
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callout {
+    /// The text in the box. Lines are separated by `\n`
+    pub text: String,
+    /// The font of the text
+    pub font: SpanFont,
+    /// The colour of the text
+    pub text_colour: Colour,
+    /// The bottom-left corner of the box
+    pub position: (Pt, Pt),
+    /// The point that the leader line points to
+    pub target: (Pt, Pt),
+    /// Space between the edges of the box and its text
+    pub padding: Pt,
+    /// How the box and leader line are drawn
+    pub stroke: Stroke,
+    /// The colour to fill the box with, if any
+    pub background: Option<Colour>,
+    /// The head at the target end of the leader line
+    pub head: ArrowHead,
+}
+
+impl Callout {
+    /// Create a callout with black text and a thin black outline and leader line which
+    /// ends in a dot
+    pub fn new<S: ToString>(
+        text: S,
+        font: SpanFont,
+        position: (Pt, Pt),
+        target: (Pt, Pt),
+    ) -> Callout {
+        Callout {
+            text: text.to_string(),
+            font,
+            text_colour: crate::colours::BLACK,
+            position,
+            target,
+            padding: font.size / 2.0,
+            stroke: Stroke::new(crate::colours::BLACK, Pt(0.5)),
+            background: None,
+            head: ArrowHead::Dot,
+        }
+    }
+
+    /// Draw the callout onto the page. The leader line starts at the point on the edge
+    /// of the box closest to the target; if the target is inside the box, no leader line
+    /// is drawn
+    pub fn layout<T: ContentTarget + ?Sized>(&self, document: &Document, page: &mut T) {
+        let font = &document.fonts[self.font.id];
+        let line_height = font.line_height(self.font.size);
+        let lines: Vec<&str> = self.text.lines().collect();
+        let text_width = lines
+            .iter()
+            .map(|line| layout::width_of_text(line, font, self.font.size))
+            .fold(Pt(0.0), |a, b| if b > a { b } else { a });
+
+        let (x1, y1) = self.position;
+        let x2 = x1 + text_width + self.padding * 2.0;
+        let y2 = y1 + line_height * lines.len() as f32 + self.padding * 2.0;
+
+        let mut content = Content::new();
+        let style = DrawStyle {
+            fill: self.background,
+            stroke: Some(self.stroke),
+        };
+        draw(
+            &mut content,
+            &Polyline {
+                points: vec![(x1, y1), (x2, y1), (x2, y2), (x1, y2)],
+                closed: true,
+            },
+            &style,
+        );
+
+        let (tx, ty) = self.target;
+        let from = (clamp(tx, x1, x2), clamp(ty, y1, y2));
+        if from != self.target {
+            let mut leader = Arrow::new(from, self.target, self.stroke);
+            leader.heads(ArrowHead::None, self.head);
+            leader.draw(&mut content);
+        }
+        page.add_content(content);
+
+        let mut y = y2 - self.padding - font.ascent(self.font.size);
+        for line in lines {
+            page.add_span(SpanLayout {
+                text: line.to_string(),
+                font: self.font,
+                colour: self.text_colour,
+                coords: (x1 + self.padding, y),
+                effects: Default::default(),
+                metadata: Default::default(),
+                glyphs: Vec::new(),
+            });
+            y -= line_height;
+        }
+    }
+}
+
+fn clamp(value: Pt, min: Pt, max: Pt) -> Pt {
+    if value < min {
+        min
+    } else if value > max {
+        max
+    } else {
+        value
+    }
+}
@@ -0,0 +1,210 @@
+use crate::document::Document;
+use crate::image::ImageType;
+use crate::page::{Page, PageContents, PageLinkReference};
+use crate::rect::Rect;
+use crate::resources::UsedResources;
+use std::fmt::Write;
+
+impl Document {
+    /// Describe the structure of the document as a readable tree: its fonts, images, and
+    /// forms, then each page (in order) with its contents, the positions of its spans
+    /// of text, its links, and the resources that it uses. This is intended to help
+    /// debug layout issues without having to open the written PDF in an inspector; the
+    /// format is not stable, so shouldn't be parsed.
+    ///
+    /// Deferred content isn't generated until the document is written, so it is only
+    /// listed, not described.
+    pub fn dump_structure(&self) -> String {
+        let mut out = String::new();
+        // writing to a String can't fail
+        let _ = self.dump(&mut out);
+        out
+    }
+
+    fn dump(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(
+            out,
+            "Document: {} pages, {} fonts, {} images, {} forms",
+            self.page_order.len(),
+            self.fonts.len(),
+            self.images.len(),
+            self.forms.len()
+        )?;
+
+        for (i, (_, font)) in self.fonts.iter().enumerate() {
+            writeln!(out, "  Font F{i}: {}", font.name())?;
+        }
+        for (i, (_, image)) in self.images.iter().enumerate() {
+            let kind = match image.image.as_ref() {
+                ImageType::Raster(_) => "raster",
+                ImageType::SVG(_) => "SVG",
+            };
+            writeln!(
+                out,
+                "  Image I{i}: {kind}, {} x {}",
+                image.width, image.height
+            )?;
+        }
+        for (i, (_, form)) in self.forms.iter().enumerate() {
+            write!(out, "  Form X{i}: bbox {}", rect(&form.bbox))?;
+            if let Some(page) = form.source_page {
+                match self.index_of_page(page) {
+                    Some(index) => write!(out, ", draws page {index}")?,
+                    None => write!(out, ", draws a page which isn't in the document")?,
+                }
+            }
+            writeln!(out)?;
+            dump_contents(out, &form.contents, "    ")?;
+        }
+
+        for (page_index, id) in self.page_order.iter().enumerate() {
+            let page = match self.pages.get(*id) {
+                Some(page) => page,
+                None => {
+                    writeln!(out, "Page {page_index}: missing from the document")?;
+                    continue;
+                }
+            };
+            self.dump_page(out, page_index, page)?;
+        }
+        Ok(())
+    }
+
+    fn dump_page(&self, out: &mut String, page_index: usize, page: &Page) -> std::fmt::Result {
+        writeln!(
+            out,
+            "Page {page_index}: media box {}, content box {}",
+            rect(&page.media_box),
+            rect(&page.content_box)
+        )?;
+        dump_contents(out, &page.contents, "  ")?;
+
+        for link in page.links.iter() {
+            let target = match link.page {
+                PageLinkReference::ById(id) => match self.index_of_page(id) {
+                    Some(index) => format!("page {index}"),
+                    None => "a page which isn't in the document".to_string(),
+                },
+                PageLinkReference::ByIndex(index) => format!("page {index}"),
+            };
+            writeln!(out, "  Link at {} to {target}", rect(&link.position))?;
+        }
+        for link in page.external_links.iter() {
+            writeln!(
+                out,
+                "  External link at {} to {:?}",
+                rect(&link.position),
+                link.target
+            )?;
+        }
+        for highlight in page.highlights.iter() {
+            writeln!(out, "  Highlight of {} regions", highlight.regions.len())?;
+        }
+        for stamp in page.stamps.iter() {
+            writeln!(
+                out,
+                "  Stamp at {} drawn with X{}",
+                rect(&stamp.position),
+                stamp.form_index
+            )?;
+        }
+
+        let used = used_resources(&page.contents, self);
+        let names = |prefix: &str, indices: &std::collections::BTreeSet<usize>| {
+            indices
+                .iter()
+                .map(|i| format!("{prefix}{i}"))
+                .collect::<Vec<String>>()
+        };
+        let mut resources = names("F", &used.fonts);
+        resources.extend(names("I", &used.images));
+        resources.extend(names("X", &used.forms));
+        if !resources.is_empty() {
+            writeln!(out, "  Resources used: {}", resources.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn dump_contents(out: &mut String, contents: &[PageContents], indent: &str) -> std::fmt::Result {
+    for content in contents.iter() {
+        match content {
+            PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+                match content {
+                    PageContents::ClippedText(_, clip) => writeln!(
+                        out,
+                        "{indent}Text ({} spans, clipped to {})",
+                        spans.len(),
+                        rect(clip)
+                    )?,
+                    _ => writeln!(out, "{indent}Text ({} spans)", spans.len())?,
+                }
+                for span in spans.iter() {
+                    writeln!(
+                        out,
+                        "{indent}  {:?} in F{} at {}pt, at ({}, {})",
+                        span.text,
+                        span.font.id.index(),
+                        *span.font.size,
+                        *span.coords.0,
+                        *span.coords.1
+                    )?;
+                }
+            }
+            PageContents::Image(image) => {
+                writeln!(
+                    out,
+                    "{indent}Image I{} at {}",
+                    image.image_index,
+                    rect(&image.position)
+                )?;
+            }
+            PageContents::Form(form) => {
+                writeln!(
+                    out,
+                    "{indent}Form X{} at {}",
+                    form.form_index,
+                    rect(&form.position)
+                )?;
+            }
+            PageContents::RawContent(content) => {
+                writeln!(out, "{indent}Raw content ({} bytes)", content.len())?;
+            }
+            PageContents::Deferred(_) => {
+                writeln!(out, "{indent}Deferred content")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The resources used by some contents, excluding deferred content
+fn used_resources(contents: &[PageContents], document: &Document) -> UsedResources {
+    let mut used = UsedResources::default();
+    for content in contents.iter() {
+        match content {
+            PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+                used.fonts
+                    .extend(spans.iter().map(|span| span.font.id.index()));
+            }
+            PageContents::Image(image) => {
+                used.images.insert(image.image_index);
+            }
+            PageContents::Form(form) => {
+                used.forms.insert(form.form_index);
+            }
+            PageContents::RawContent(content) => {
+                let raw = UsedResources::scan(content, document);
+                used.fonts.extend(raw.fonts);
+                used.images.extend(raw.images);
+                used.forms.extend(raw.forms);
+            }
+            PageContents::Deferred(_) => {}
+        }
+    }
+    used
+}
+
+fn rect(rect: &Rect) -> String {
+    format!("[{} {} {} {}]", *rect.x1, *rect.y1, *rect.x2, *rect.y2)
+}
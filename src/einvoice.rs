@@ -0,0 +1,378 @@
+use crate::compression::Compression;
+use crate::info::{pdf_date, Info, CREATOR};
+use crate::refs::{ObjectReferences, RefType};
+use chrono::prelude::*;
+use pdf_writer::{Finish, Name, PdfWriter, Str, TextStr};
+
+/// The Factur-X / ZUGFeRD profile that an invoice's XML conforms to, which determines
+/// how much of the invoice is described by the XML
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EInvoiceProfile {
+    /// Only the information needed for booking the invoice
+    Minimum,
+    /// [EInvoiceProfile::Minimum] plus document-level information, without lines
+    BasicWL,
+    /// A simple invoice, including its lines
+    Basic,
+    /// The European standard for electronic invoices (EN 16931)
+    #[default]
+    EN16931,
+    /// EN 16931 plus the extensions commonly needed between businesses
+    Extended,
+    /// The German XRechnung profile of EN 16931
+    XRechnung,
+}
+
+impl EInvoiceProfile {
+    /// The name of the profile as recorded in the document's metadata
+    pub fn conformance_level(&self) -> &'static str {
+        match self {
+            EInvoiceProfile::Minimum => "MINIMUM",
+            EInvoiceProfile::BasicWL => "BASIC WL",
+            EInvoiceProfile::Basic => "BASIC",
+            EInvoiceProfile::EN16931 => "EN 16931",
+            EInvoiceProfile::Extended => "EXTENDED",
+            EInvoiceProfile::XRechnung => "XRECHNUNG",
+        }
+    }
+
+    /// The file name that the profile requires the XML to be attached as
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            EInvoiceProfile::XRechnung => "xrechnung.xml",
+            _ => "factur-x.xml",
+        }
+    }
+}
+
+/// How an attached file relates to the document's contents
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AFRelationship {
+    /// The file is the original source of the document's contents
+    Source,
+    /// The file holds the data that the document's contents present
+    Data,
+    /// The file is an alternative representation of the document's contents
+    Alternative,
+    /// The file supplements the document's contents
+    Supplement,
+    /// The relationship isn't known or doesn't fit the others
+    #[default]
+    Unspecified,
+}
+
+impl AFRelationship {
+    fn name(&self) -> Name<'static> {
+        Name(match self {
+            AFRelationship::Source => b"Source",
+            AFRelationship::Data => b"Data",
+            AFRelationship::Alternative => b"Alternative",
+            AFRelationship::Supplement => b"Supplement",
+            AFRelationship::Unspecified => b"Unspecified",
+        })
+    }
+}
+
+/// An electronic invoice (Factur-X or ZUGFeRD) to produce along with the document. When
+/// set with [crate::Document::set_e_invoice], the document is written as a PDF/A-3b file
+/// with the invoice's XML attached and described by the document's XMP metadata, as
+/// the Factur-X and ZUGFeRD standards require:
+///
+/// ```
+/// # use pdf_gen::{Document, EInvoice, EInvoiceProfile};
+/// # let xml = b"<rsm:CrossIndustryInvoice/>".to_vec();
+/// # let srgb_icc_profile = Vec::new();
+/// let mut doc = Document::default();
+/// doc.set_e_invoice(EInvoice::new(xml, EInvoiceProfile::EN16931, srgb_icc_profile));
+/// ```
+///
+/// PDF/A requires an output intent describing the document's colours, so the bytes of
+/// an RGB ICC profile (typically sRGB IEC61966-2.1, which is freely available from the
+/// ICC) must be supplied; none is bundled with this crate. The XML isn't checked
+/// against the profile. PDF/A also restricts the document's contents: link annotations
+/// are written hidden from print, which PDF/A doesn't allow, so leave links out of
+/// e-invoices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EInvoice {
+    /// The invoice's XML, in the UN/CEFACT Cross Industry Invoice syntax
+    pub xml: Vec<u8>,
+    /// The profile that the XML conforms to
+    pub profile: EInvoiceProfile,
+    /// How the XML relates to the document's contents. Defaults to
+    /// [AFRelationship::Alternative], as ZUGFeRD requires; Factur-X also allows
+    /// [AFRelationship::Data] and [AFRelationship::Source]
+    pub relationship: AFRelationship,
+    /// The bytes of the ICC profile used as the document's output intent
+    pub icc_profile: Vec<u8>,
+    /// The number of colour components in the ICC profile, i.e. 3 for RGB
+    pub icc_components: i32,
+    /// The name of the output condition that the ICC profile describes
+    pub output_condition: String,
+    /// The version of the Factur-X / ZUGFeRD XMP schema being used
+    pub version: String,
+}
+
+impl EInvoice {
+    /// Describe an invoice with the given XML and profile, using an RGB ICC profile for
+    /// the sRGB IEC61966-2.1 output condition
+    pub fn new(xml: Vec<u8>, profile: EInvoiceProfile, icc_profile: Vec<u8>) -> EInvoice {
+        EInvoice {
+            xml,
+            profile,
+            relationship: AFRelationship::Alternative,
+            icc_profile,
+            icc_components: 3,
+            output_condition: "sRGB IEC61966-2.1".to_string(),
+            version: "1.0".to_string(),
+        }
+    }
+
+    /// Set how the XML relates to the document's contents
+    pub fn relationship(&mut self, relationship: AFRelationship) -> &mut Self {
+        self.relationship = relationship;
+        self
+    }
+
+    /// Set the ICC profile used as the output intent, along with the name of the output
+    /// condition it describes and its number of colour components
+    pub fn output_intent<S: ToString>(
+        &mut self,
+        icc_profile: Vec<u8>,
+        components: i32,
+        condition: S,
+    ) -> &mut Self {
+        self.icc_profile = icc_profile;
+        self.icc_components = components;
+        self.output_condition = condition.to_string();
+        self
+    }
+
+    /// Set the version of the Factur-X / ZUGFeRD XMP schema being used
+    pub fn version<S: ToString>(&mut self, version: S) -> &mut Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Write the attached XML, the output intent's profile, and the XMP metadata,
+    /// generating the references that [EInvoice::write_catalog] then uses
+    pub(crate) fn write(
+        &self,
+        info: Option<&Info>,
+        now: &DateTime<Local>,
+        refs: &mut ObjectReferences,
+        compression: Compression,
+        writer: &mut PdfWriter,
+    ) {
+        let file_name = self.profile.file_name();
+
+        let embedded_id = refs.gen(RefType::EmbeddedFile);
+        let (xml, filter) = compression.compress(&self.xml);
+        let mut embedded = writer.stream(embedded_id, &xml);
+        if let Some(filter) = filter {
+            embedded.filter(filter);
+        }
+        embedded.pair(Name(b"Type"), Name(b"EmbeddedFile"));
+        embedded.pair(Name(b"Subtype"), Name(b"text/xml"));
+        let mut params = embedded.insert(Name(b"Params")).dict();
+        params.pair(Name(b"Size"), self.xml.len() as i32);
+        params.pair(Name(b"ModDate"), pdf_date(now));
+        params.finish();
+        embedded.finish();
+
+        let file_spec_id = refs.gen(RefType::FileSpec);
+        let mut file_spec = writer.indirect(file_spec_id).dict();
+        file_spec.pair(Name(b"Type"), Name(b"Filespec"));
+        file_spec.pair(Name(b"F"), Str(file_name.as_bytes()));
+        file_spec.pair(Name(b"UF"), TextStr(file_name));
+        file_spec.pair(Name(b"Desc"), TextStr("Factur-X / ZUGFeRD invoice"));
+        file_spec.pair(Name(b"AFRelationship"), self.relationship.name());
+        let mut files = file_spec.insert(Name(b"EF")).dict();
+        files.pair(Name(b"F"), embedded_id);
+        files.pair(Name(b"UF"), embedded_id);
+        files.finish();
+        file_spec.finish();
+
+        let icc_id = refs.gen(RefType::OutputProfile);
+        let (icc_profile, filter) = compression.compress(&self.icc_profile);
+        let mut icc = writer.stream(icc_id, &icc_profile);
+        if let Some(filter) = filter {
+            icc.filter(filter);
+        }
+        icc.pair(Name(b"N"), self.icc_components);
+        icc.finish();
+
+        // PDF/A doesn't allow the metadata to be compressed, so that it can always be
+        // read without understanding the rest of the file
+        let xmp = self.xmp(info, now);
+        let metadata_id = refs.gen(RefType::Metadata);
+        let mut metadata = writer.stream(metadata_id, xmp.as_bytes());
+        metadata.pair(Name(b"Type"), Name(b"Metadata"));
+        metadata.pair(Name(b"Subtype"), Name(b"XML"));
+        metadata.finish();
+    }
+
+    /// Add the attachment, output intent, and metadata to the document catalog
+    pub(crate) fn write_catalog(
+        &self,
+        refs: &ObjectReferences,
+        catalog: &mut pdf_writer::writers::Catalog,
+    ) -> Result<(), crate::PDFError> {
+        let file_spec_id = refs.require(RefType::FileSpec)?;
+
+        catalog
+            .insert(Name(b"Names"))
+            .dict()
+            .insert(Name(b"EmbeddedFiles"))
+            .dict()
+            .insert(Name(b"Names"))
+            .array()
+            .item(TextStr(self.profile.file_name()))
+            .item(file_spec_id);
+        catalog.insert(Name(b"AF")).array().item(file_spec_id);
+        catalog.pair(Name(b"Metadata"), refs.require(RefType::Metadata)?);
+
+        let mut intents = catalog.insert(Name(b"OutputIntents")).array();
+        let mut intent = intents.push().dict();
+        intent.pair(Name(b"Type"), Name(b"OutputIntent"));
+        intent.pair(Name(b"S"), Name(b"GTS_PDFA1"));
+        intent.pair(
+            Name(b"OutputConditionIdentifier"),
+            TextStr(&self.output_condition),
+        );
+        intent.pair(
+            Name(b"DestOutputProfile"),
+            refs.require(RefType::OutputProfile)?,
+        );
+        intent.finish();
+        intents.finish();
+        Ok(())
+    }
+
+    /// The XMP metadata packet, which must agree with the document information
+    /// dictionary and declares both the PDF/A conformance and the Factur-X extension
+    /// schema
+    fn xmp(&self, info: Option<&Info>, now: &DateTime<Local>) -> String {
+        let date = now.to_rfc3339_opts(SecondsFormat::Secs, false);
+        let mut described = String::new();
+        if let Some(info) = info {
+            if let Some(title) = &info.title {
+                described += &format!(
+                    "      <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>\n",
+                    escape(title)
+                );
+            }
+            if let Some(author) = &info.author {
+                described += &format!(
+                    "      <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n",
+                    escape(author)
+                );
+            }
+            if let Some(subject) = &info.subject {
+                described += &format!(
+                    "      <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>\n",
+                    escape(subject)
+                );
+            }
+            if let Some(keywords) = &info.keywords {
+                described += &format!("      <pdf:Keywords>{}</pdf:Keywords>\n", escape(keywords));
+            }
+            described += &format!("      <xmp:CreatorTool>{}</xmp:CreatorTool>\n", CREATOR);
+            described += &format!("      <xmp:CreateDate>{date}</xmp:CreateDate>\n");
+        }
+
+        format!(
+            r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:dc="http://purl.org/dc/elements/1.1/"
+        xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+        xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+        xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#">
+      <pdfaid:part>3</pdfaid:part>
+      <pdfaid:conformance>B</pdfaid:conformance>
+{described}      <xmp:MetadataDate>{date}</xmp:MetadataDate>
+      <fx:DocumentType>INVOICE</fx:DocumentType>
+      <fx:DocumentFileName>{file_name}</fx:DocumentFileName>
+      <fx:Version>{version}</fx:Version>
+      <fx:ConformanceLevel>{level}</fx:ConformanceLevel>
+    </rdf:Description>
+    <rdf:Description rdf:about=""
+        xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/"
+        xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#"
+        xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+      <pdfaExtension:schemas>
+        <rdf:Bag>
+          <rdf:li rdf:parseType="Resource">
+            <pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
+            <pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
+            <pdfaSchema:prefix>fx</pdfaSchema:prefix>
+            <pdfaSchema:property>
+              <rdf:Seq>
+{properties}              </rdf:Seq>
+            </pdfaSchema:property>
+          </rdf:li>
+        </rdf:Bag>
+      </pdfaExtension:schemas>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+            bom = '\u{feff}',
+            file_name = self.profile.file_name(),
+            version = escape(&self.version),
+            level = self.profile.conformance_level(),
+            properties = [
+                ("DocumentFileName", "The name of the embedded XML invoice file"),
+                (
+                    "DocumentType",
+                    "The type of the hybrid document in capital letters, e.g. INVOICE or ORDER",
+                ),
+                ("Version", "The version of the XML invoice schema"),
+                ("ConformanceLevel", "The conformance level of the XML invoice"),
+            ]
+            .iter()
+            .map(|(name, description)| format!(
+                "                <rdf:li rdf:parseType=\"Resource\">\n\
+                 \x20                 <pdfaProperty:name>{name}</pdfaProperty:name>\n\
+                 \x20                 <pdfaProperty:valueType>Text</pdfaProperty:valueType>\n\
+                 \x20                 <pdfaProperty:category>external</pdfaProperty:category>\n\
+                 \x20                 <pdfaProperty:description>{description}</pdfaProperty:description>\n\
+                 \x20               </rdf:li>\n"
+            ))
+            .collect::<String>(),
+        )
+    }
+}
+
+/// Escape text for use in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Add a file identifier to the trailer of a written document, as PDF/A requires. The
+/// identifier is derived from the contents of the file, and both of its parts are the
+/// same as the file hasn't been updated since it was created
+pub(crate) fn add_file_id(pdf: &mut Vec<u8>) {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let end = match crate::incremental::rfind(pdf, b">>\nstartxref") {
+        Some(end) => end,
+        None => return,
+    };
+    let id: String = (0..2u8)
+        .map(|part| {
+            let mut hasher = DefaultHasher::new();
+            part.hash(&mut hasher);
+            pdf.hash(&mut hasher);
+            format!("{:016X}", hasher.finish())
+        })
+        .collect();
+    let entry = format!("  /ID [<{id}> <{id}>]\n");
+    pdf.splice(end..end, entry.into_bytes());
+}
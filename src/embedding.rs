@@ -0,0 +1,116 @@
+use crate::document::Document;
+use crate::font::Font;
+use crate::PDFError;
+use id_arena::Id;
+use owned_ttf_parser::{AsFaceRef, OwnedFace, Tag};
+
+/// How a font's licence allows it to be embedded in a document, as given by the `fsType`
+/// field of its OS/2 table. See [Font::embedding_permissions]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EmbeddingLevel {
+    /// The font may be embedded and permanently installed by whoever receives the
+    /// document
+    #[default]
+    Installable,
+    /// The font must not be embedded without permission from its owner
+    Restricted,
+    /// The font may be embedded, but documents using it may only be viewed and printed
+    PreviewAndPrint,
+    /// The font may be embedded, and documents using it may be edited
+    Editable,
+}
+
+/// The restrictions a font's licence places on embedding it (see
+/// [Font::embedding_permissions]). Fonts without an OS/2 table, and the standard 14
+/// fonts (which are never embedded), have no restrictions
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddingPermissions {
+    /// Whether, and for what purposes, the font may be embedded
+    pub level: EmbeddingLevel,
+    /// The font must be embedded in its entirety, rather than as a subset of its glyphs
+    pub no_subsetting: bool,
+    /// Only the font's bitmaps may be embedded, not its outlines
+    pub bitmap_only: bool,
+}
+
+impl EmbeddingPermissions {
+    /// Read the permissions from the `fsType` field of the face's OS/2 table
+    pub(crate) fn from_face(face: &OwnedFace) -> EmbeddingPermissions {
+        let fs_type = match face
+            .as_face_ref()
+            .table_data(Tag::from_bytes(b"OS/2"))
+            .and_then(|os2| os2.get(8..10))
+        {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+            None => return EmbeddingPermissions::default(),
+        };
+
+        // older fonts may set more than one of the usage bits, in which case the least
+        // restrictive one applies
+        let level = if fs_type & 0x0008 != 0 {
+            EmbeddingLevel::Editable
+        } else if fs_type & 0x0004 != 0 {
+            EmbeddingLevel::PreviewAndPrint
+        } else if fs_type & 0x0002 != 0 {
+            EmbeddingLevel::Restricted
+        } else {
+            EmbeddingLevel::Installable
+        };
+        EmbeddingPermissions {
+            level,
+            no_subsetting: fs_type & 0x0100 != 0,
+            bitmap_only: fs_type & 0x0200 != 0,
+        }
+    }
+
+    /// Whether the font's outlines may be embedded in a document. Fonts are embedded
+    /// with their outlines, so a font which doesn't allow this shouldn't be used in a
+    /// document without permission from its owner
+    pub fn allows_embedding(&self) -> bool {
+        self.level != EmbeddingLevel::Restricted && !self.bitmap_only
+    }
+}
+
+/// What to do when a document is written with a font whose licence doesn't allow it to be
+/// embedded (see [EmbeddingPermissions::allows_embedding] and
+/// [Document::font_embedding])
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EmbeddingPolicy {
+    /// Embed the font without checking its permissions
+    Allow,
+    /// Embed the font, leaving it to the caller to warn about it. The fonts which this
+    /// applies to are listed by [Document::restricted_fonts], which can be checked before
+    /// the document is written
+    #[default]
+    Warn,
+    /// Refuse to write the document, returning a [PDFError::FontEmbeddingRestricted]
+    /// error
+    Deny,
+}
+
+impl Document {
+    /// The fonts in the document whose licences don't allow them to be embedded (see
+    /// [EmbeddingPermissions::allows_embedding]). Every font added to the document is
+    /// embedded when it is written, whether or not it is used
+    pub fn restricted_fonts(&self) -> Vec<Id<Font>> {
+        self.fonts
+            .iter()
+            .filter(|(_, font)| !font.embedding_permissions().allows_embedding())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Apply [Document::font_embedding] to the fonts which can't be embedded. Only
+    /// [EmbeddingPolicy::Deny] stops the document from being written
+    pub(crate) fn check_font_embedding(&self) -> Result<(), PDFError> {
+        if self.font_embedding != EmbeddingPolicy::Deny {
+            return Ok(());
+        }
+        match self.restricted_fonts().first() {
+            Some(&id) => Err(PDFError::FontEmbeddingRestricted {
+                font: self.fonts[id].name(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
@@ -21,4 +21,146 @@ pub enum PDFError {
 
     #[error("The page has not been allocated to the document page arena (the referenced page is missing)")]
     PageMissing,
+
+    #[error("Internal error: no object reference was generated for {0}")]
+    ReferenceMissing(String),
+
+    #[error("Failed to write page {page_index}: {source}")]
+    /// Writing a page failed; `page_index` is the 0-based index of the page in the document
+    Page {
+        page_index: usize,
+        #[source]
+        source: Box<PDFError>,
+    },
+
+    #[error("The font \"{font}\" has no glyph for {ch:?} and no replacement glyph (in the text \"{excerpt}\")")]
+    MissingGlyph {
+        font: String,
+        ch: char,
+        excerpt: String,
+    },
+
+    #[error("The font \"{font}\" does not have a character map (cmap) table")]
+    MissingCharacterMap { font: String },
+
+    #[error("The licence of the font \"{font}\" doesn't allow it to be embedded")]
+    /// See [crate::Document::font_embedding]
+    FontEmbeddingRestricted { font: String },
+
+    #[error("The font does not have a {0} in its naming table")]
+    MissingFontName(&'static str),
+
+    #[error("The bookmark \"{title}\" refers to a page which does not exist in the document")]
+    BookmarkPageMissing { title: String },
+
+    #[error("A link refers to page index {page_index}, which does not exist in the document")]
+    LinkPageMissing { page_index: usize },
+
+    #[error("Annotations for page index {page}, which is missing from the document or a different size to the page they were exported from")]
+    AnnotationPageMismatch { page: usize },
+
+    #[error("{} broken references{}", .0.len(), including(.0))]
+    /// Bookmarks, headings, or links refer to pages or positions which don't exist (see
+    /// [crate::Document::validate_references])
+    BrokenReferences(Vec<crate::BrokenReference>),
+
+    #[error("Content refers to form index {form_index}, which does not exist in the document")]
+    FormMissing { form_index: usize },
+
+    #[error("Form {form_index} draws itself, either directly or through other forms")]
+    FormCycle { form_index: usize },
+
+    #[error("Runs with ruby can't be laid out with alignment or optical margins")]
+    /// See [crate::layout::layout_runs_aligned]
+    AlignedRuby,
+
+    #[error("The stylesheet does not define the style \"{0}\"")]
+    StyleMissing(String),
+
+    #[error("A style refers to the font \"{0}\", which has not been added to the document")]
+    StyleFontMissing(String),
+
+    #[error("The document has no theme named \"{0}\"")]
+    ThemeMissing(String),
+
+    #[error("Invalid content stream at byte {offset}: {reason}")]
+    /// Raw content failed validation (see [crate::Document::validate_content]); `offset`
+    /// is the position of the problem within the raw content
+    InvalidContent { offset: usize, reason: String },
+
+    #[error("Unable to read the existing PDF: {0}")]
+    /// An existing PDF couldn't be read, i.e. when starting an incremental update
+    MalformedPdf(&'static str),
+
+    #[error("Signing failed: {0}")]
+    /// The signer given to [crate::IncrementalUpdate::sign] returned an error
+    Signer(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("The signature is {size} bytes, but only {reserved} bytes were reserved for it")]
+    /// The signature was larger than the space reserved for it (see
+    /// [crate::Signature::reserve])
+    SignatureTooLarge { size: usize, reserved: usize },
+
+    #[error("Can't parse the formula \"{formula}\": {reason}")]
+    /// A formula given to [crate::MathNode::parse] isn't valid
+    MalformedFormula {
+        formula: String,
+        reason: &'static str,
+    },
+
+    #[cfg(feature = "system-fonts")]
+    #[error("No installed font was found in the family \"{0}\"")]
+    /// [crate::SystemFonts] couldn't find or read a font in the requested family
+    SystemFontMissing(String),
+
+    #[cfg(feature = "hyphenation")]
+    #[error("Failed to load hyphenation patterns: {0}")]
+    /// The patterns for [crate::HyphenatingBreaker] couldn't be loaded
+    Hyphenation(#[from] hyphenation::load::Error),
+
+    #[cfg(feature = "serde_json")]
+    #[error(transparent)]
+    /// [serde_json] failed to serialize or deserialize a value
+    Json(#[from] serde_json::Error),
+
+    #[cfg(feature = "csv")]
+    #[error(transparent)]
+    /// [csv] failed to read a record
+    Csv(#[from] csv::Error),
+
+    #[cfg(feature = "templates")]
+    #[error("The template refers to the style \"{0}\", which it does not define")]
+    TemplateStyleMissing(String),
+
+    #[cfg(feature = "templates")]
+    #[error("The template refers to the font \"{0}\", which has not been added to the document")]
+    TemplateFontMissing(String),
+
+    #[cfg(feature = "templates")]
+    #[error("No data was provided for the template placeholder \"{0}\"")]
+    TemplateDataMissing(String),
+
+    #[cfg(feature = "templates")]
+    #[error("The generator has no template named \"{0}\"")]
+    TemplateMissing(String),
+}
+
+/// Shorten text for use as context in an error message
+pub(crate) fn excerpt(text: &str) -> String {
+    const MAX_CHARS: usize = 32;
+    if text.chars().count() > MAX_CHARS {
+        let mut excerpt: String = text.chars().take(MAX_CHARS).collect();
+        excerpt.push('…');
+        excerpt
+    } else {
+        text.to_string()
+    }
+}
+
+/// Describe the first of some broken references, for [PDFError::BrokenReferences]
+fn including(broken: &[crate::BrokenReference]) -> String {
+    broken
+        .first()
+        .map(|broken| format!(", including: {broken}"))
+        .unwrap_or_default()
 }
@@ -0,0 +1,80 @@
+use crate::document::Document;
+use crate::font::Font;
+use id_arena::Id;
+
+/// An ordered list of fonts to draw text with, where each character is drawn in the
+/// first font which has a glyph for it. This allows text which mixes scripts (such as
+/// Latin and CJK text, or text with emoji) to be laid out even though no single font
+/// covers all of it.
+///
+/// Stacks are registered with the document using [Document::set_font_stack], after
+/// which the layout functions (such as [crate::layout::layout_text]) split any text in
+/// the stack's first font into spans which switch to the other fonts where needed:
+///
+/// ```
+/// # use pdf_gen::{Document, Font, FontStack};
+/// # let mut doc = Document::default();
+/// # let latin = doc.add_font(Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap());
+/// # let cjk = doc.add_font(Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap());
+/// doc.set_font_stack(FontStack::new(vec![latin, cjk]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontStack {
+    /// The fonts, in order of preference
+    pub fonts: Vec<Id<Font>>,
+}
+
+impl FontStack {
+    /// Create a stack from fonts in order of preference
+    pub fn new(fonts: Vec<Id<Font>>) -> FontStack {
+        FontStack { fonts }
+    }
+
+    /// Add a font to the end of the stack
+    pub fn fallback(&mut self, font: Id<Font>) -> &mut Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// The first font in the stack which has a glyph for the character, if any do
+    pub fn font_for(&self, document: &Document, ch: char) -> Option<Id<Font>> {
+        self.fonts
+            .iter()
+            .copied()
+            .find(|&id| document.fonts[id].glyph_id(ch).is_some())
+    }
+
+    /// Split text into the pieces which should be drawn in each font of the stack. Text
+    /// that none of the fonts have glyphs for is left in the first font (where it will be
+    /// drawn with its replacement glyph), and whitespace and control characters stay in
+    /// the font of the text before them, so that text is only split where it has to be
+    pub fn split(&self, document: &Document, text: &str) -> Vec<(String, Id<Font>)> {
+        let primary = match self.fonts.first() {
+            Some(&primary) => primary,
+            None => return Vec::new(),
+        };
+        let mut pieces: Vec<(String, Id<Font>)> = Vec::new();
+        for ch in text.chars() {
+            let font = match pieces.last() {
+                Some(&(_, current)) if ch.is_whitespace() || ch.is_control() => current,
+                _ => self.font_for(document, ch).unwrap_or(primary),
+            };
+            match pieces.last_mut() {
+                Some((piece, current)) if *current == font => piece.push(ch),
+                _ => pieces.push((ch.to_string(), font)),
+            }
+        }
+        pieces
+    }
+}
+
+impl Document {
+    /// Register a font stack, so that text laid out in the stack's first font falls back
+    /// to the stack's other fonts for characters which the first font has no glyph for.
+    /// Replaces any stack previously registered for the same first font
+    pub fn set_font_stack(&mut self, stack: FontStack) {
+        if let Some(&primary) = stack.fonts.first() {
+            self.font_stacks.insert(primary, stack);
+        }
+    }
+}
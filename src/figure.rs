@@ -0,0 +1,171 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::form::FormXObject;
+use crate::image::Image;
+use crate::layout;
+use crate::page::{ContentTarget, ImageLayout, SpanFont};
+use crate::rect::Rect;
+use crate::units::Pt;
+use crate::PDFError;
+use id_arena::Id;
+
+/// Where a [Figure] in a [crate::Report] goes when it doesn't fit in the space left on
+/// the current page
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FigureFloat {
+    /// The figure starts the next page, and the blocks after it follow it
+    #[default]
+    Inline,
+    /// The figure is placed at the top of the next page, and the blocks after it
+    /// continue to fill the current page
+    Top,
+    /// The figure is placed at the bottom of the next page, and the blocks after it
+    /// continue to fill the current page
+    Bottom,
+}
+
+/// An image with an optional caption beneath it, which are measured and placed as a
+/// unit so that the caption is never separated from its image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    /// The image to show
+    pub image: Id<Image>,
+    /// How wide to show the image (its height follows from its aspect ratio). If [None],
+    /// or if it is wider than the space it is placed in, the image fills the width of
+    /// the space
+    pub width: Option<Pt>,
+    /// Text shown beneath the image, wrapped to the width of the image
+    pub caption: Option<String>,
+    /// The space between the image and its caption
+    pub caption_gap: Pt,
+    /// What to do when the figure doesn't fit on the current page of a report
+    pub float: FigureFloat,
+}
+
+impl Figure {
+    /// Create a figure showing the given image, with no caption
+    pub fn new(image: Id<Image>) -> Figure {
+        Figure {
+            image,
+            width: None,
+            caption: None,
+            caption_gap: Pt(6.0),
+            float: FigureFloat::default(),
+        }
+    }
+
+    /// Set the width that the image is shown at
+    pub fn width(&mut self, width: Pt) -> &mut Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Set the caption shown beneath the image
+    pub fn caption<S: ToString>(&mut self, caption: S) -> &mut Self {
+        self.caption = Some(caption.to_string());
+        self
+    }
+
+    /// Set what happens when the figure doesn't fit on the current page of a report
+    pub fn float(&mut self, float: FigureFloat) -> &mut Self {
+        self.float = float;
+        self
+    }
+
+    /// The size that the image is shown at within the given width
+    fn image_size(&self, document: &Document, available_width: Pt) -> (Pt, Pt) {
+        let width = match self.width {
+            Some(width) if width < available_width => width,
+            _ => available_width,
+        };
+        let aspect_ratio = document.images[self.image].aspect_ratio();
+        let height = if aspect_ratio.is_finite() && aspect_ratio > 0.0 {
+            width / aspect_ratio
+        } else {
+            Pt(0.0)
+        };
+        (width, height)
+    }
+
+    /// Measure the total height of the figure (its image, and its caption in the given
+    /// font) when placed in a space of the given width
+    pub fn height(
+        &self,
+        document: &Document,
+        font: SpanFont,
+        available_width: Pt,
+    ) -> Result<Pt, PDFError> {
+        let (width, height) = self.image_size(document, available_width);
+        let area = Rect {
+            x1: Pt(0.0),
+            y1: Pt(f32::MIN),
+            x2: width,
+            y2: Pt(0.0),
+        };
+        let mut scratch = FormXObject::new(area);
+        let caption =
+            self.layout_caption(document, &mut scratch, area, font, crate::colours::BLACK)?;
+        Ok(height - caption)
+    }
+
+    /// Place the figure at the top of `area`, centred horizontally, with its caption in
+    /// the given font and colour. Returns the vertical position of the bottom of the
+    /// figure. The figure is placed in full even if it overflows the bottom of the area
+    pub fn layout<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        font: SpanFont,
+        colour: Colour,
+        area: Rect,
+    ) -> Result<Pt, PDFError> {
+        let (width, height) = self.image_size(document, area.x2 - area.x1);
+        let x1 = area.x1 + (area.x2 - area.x1 - width) / 2.0;
+        let position = Rect {
+            x1,
+            y1: area.y2 - height,
+            x2: x1 + width,
+            y2: area.y2,
+        };
+        page.add_image(ImageLayout {
+            image_index: self.image.index(),
+            position,
+            artifact: None,
+        });
+
+        let caption_area = Rect {
+            y1: Pt(f32::MIN),
+            y2: position.y1,
+            ..position
+        };
+        self.layout_caption(document, page, caption_area, font, colour)
+    }
+
+    /// Lay out the caption at the top of the area, returning the vertical position of the
+    /// bottom of the caption (or the top of the area, if there is no caption)
+    fn layout_caption<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        area: Rect,
+        font: SpanFont,
+        colour: Colour,
+    ) -> Result<Pt, PDFError> {
+        let caption = match &self.caption {
+            Some(caption) if !caption.is_empty() => caption,
+            _ => return Ok(area.y2),
+        };
+        let font_ref = &document.fonts[font.id];
+        let top = area.y2 - self.caption_gap;
+        let mut text = vec![(caption.clone(), colour, font)];
+        let (_, baseline) = layout::layout_text(
+            document,
+            page,
+            (area.x1, top - font_ref.ascent(font.size)),
+            &mut text,
+            Pt(0.0),
+            Rect { y2: top, ..area },
+        )?;
+        Ok(baseline + font_ref.descent(font.size))
+    }
+}
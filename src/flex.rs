@@ -0,0 +1,246 @@
+use crate::rect::Rect;
+use crate::units::Pt;
+
+/// Which way a [FlexContainer] lays out its items
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FlexDirection {
+    /// Left to right
+    #[default]
+    Row,
+    /// Top to bottom
+    Column,
+}
+
+/// How a [FlexContainer] distributes any space left over along its direction once its
+/// items have grown as much as they can
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FlexJustify {
+    /// Items are packed at the left (in a row) or top (in a column)
+    #[default]
+    Start,
+    /// Items are packed in the middle
+    Centre,
+    /// Items are packed at the right (in a row) or bottom (in a column)
+    End,
+    /// The space is divided evenly between the items, with the first and last items
+    /// against the edges of the container
+    SpaceBetween,
+}
+
+/// How a [FlexContainer] positions its items across its direction (vertically in a
+/// row, horizontally in a column)
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FlexAlign {
+    /// Items fill the container
+    #[default]
+    Stretch,
+    /// Items are aligned to the top (in a row) or left (in a column)
+    Start,
+    /// Items are aligned to the middle
+    Centre,
+    /// Items are aligned to the bottom (in a row) or right (in a column)
+    End,
+}
+
+/// An item in a [FlexContainer], which is sized along the container's direction from
+/// its basis, growing into any spare space and shrinking when there isn't enough
+/// space. Items are only sizes, so they can stand for anything (text blocks, images,
+/// or empty space); use [crate::layout::width_of_text] or an image's aspect ratio to
+/// work out what their basis should be.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FlexItem {
+    /// The size of the item along the container's direction, before growing or
+    /// shrinking
+    pub basis: Pt,
+    /// How much of the container's spare space the item takes, relative to the other
+    /// items. Items with a grow of 0 don't grow
+    pub grow: f32,
+    /// How much the item shrinks when there isn't enough space, relative to the other
+    /// items (weighted by their bases). Items with a shrink of 0 don't shrink
+    pub shrink: f32,
+    /// The size of the item across the container's direction. If [None], or if the
+    /// container stretches its items, the item fills the container
+    pub cross: Option<Pt>,
+}
+
+impl FlexItem {
+    /// An item which grows and shrinks from the given basis
+    pub fn new(basis: Pt, grow: f32, shrink: f32) -> FlexItem {
+        FlexItem {
+            basis,
+            grow,
+            shrink,
+            cross: None,
+        }
+    }
+
+    /// An item which is always exactly the given size
+    pub fn fixed(size: Pt) -> FlexItem {
+        FlexItem::new(size, 0.0, 0.0)
+    }
+
+    /// An item which has no size of its own, and takes up a share (by `weight`) of the
+    /// spare space
+    pub fn grow(weight: f32) -> FlexItem {
+        FlexItem::new(Pt(0.0), weight, 0.0)
+    }
+
+    /// Empty space which takes up an equal share of the spare space, i.e. to push the
+    /// items after it to the end of the container
+    pub fn spacer() -> FlexItem {
+        FlexItem::grow(1.0)
+    }
+
+    /// Set the size of the item across the container's direction
+    pub fn cross(&mut self, cross: Pt) -> &mut Self {
+        self.cross = Some(cross);
+        self
+    }
+}
+
+/// A simple container which lays its items out in a row or column within a rect,
+/// somewhere between positioning everything by hand and using a [crate::Table]:
+///
+/// ```
+/// use pdf_gen::{FlexContainer, FlexItem, Pt, Rect};
+///
+/// // a header with a logo on the left and a title filling the rest of the space
+/// let header = Rect { x1: Pt(36.0), y1: Pt(700.0), x2: Pt(576.0), y2: Pt(756.0) };
+/// let rects = FlexContainer::row()
+///     .gap(Pt(12.0))
+///     .item(FlexItem::fixed(Pt(56.0)))
+///     .item(FlexItem::grow(1.0))
+///     .layout(&header);
+/// assert_eq!(rects[1].x1, Pt(104.0));
+/// assert_eq!(rects[1].x2, Pt(576.0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlexContainer {
+    /// Which way the items are laid out
+    pub direction: FlexDirection,
+    /// The space between consecutive items
+    pub gap: Pt,
+    /// How spare space is distributed along the direction
+    pub justify: FlexJustify,
+    /// How items are positioned across the direction
+    pub align: FlexAlign,
+    /// The items, in order
+    pub items: Vec<FlexItem>,
+}
+
+impl FlexContainer {
+    /// An empty container laying its items out left to right
+    pub fn row() -> FlexContainer {
+        FlexContainer::default()
+    }
+
+    /// An empty container laying its items out top to bottom
+    pub fn column() -> FlexContainer {
+        FlexContainer {
+            direction: FlexDirection::Column,
+            ..FlexContainer::default()
+        }
+    }
+
+    /// Set the space between consecutive items
+    pub fn gap(&mut self, gap: Pt) -> &mut Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Set how spare space is distributed along the direction
+    pub fn justify(&mut self, justify: FlexJustify) -> &mut Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Set how items are positioned across the direction
+    pub fn align(&mut self, align: FlexAlign) -> &mut Self {
+        self.align = align;
+        self
+    }
+
+    /// Add an item to the end of the container
+    pub fn item(&mut self, item: FlexItem) -> &mut Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Resolve the rect of each item (in the same order as the items) when the container
+    /// fills `rect`. Items may overflow the rect if they can't shrink enough to fit
+    pub fn layout(&self, rect: &Rect) -> Vec<Rect> {
+        let (main, cross) = match self.direction {
+            FlexDirection::Row => (*(rect.x2 - rect.x1), *(rect.y2 - rect.y1)),
+            FlexDirection::Column => (*(rect.y2 - rect.y1), *(rect.x2 - rect.x1)),
+        };
+        let gaps = *self.gap * self.items.len().saturating_sub(1) as f32;
+        let free = main - gaps - self.items.iter().map(|item| *item.basis).sum::<f32>();
+
+        let total_grow: f32 = self.items.iter().map(|item| item.grow).sum();
+        let total_shrink: f32 = self
+            .items
+            .iter()
+            .map(|item| item.shrink * *item.basis)
+            .sum();
+        let sizes: Vec<f32> = self
+            .items
+            .iter()
+            .map(|item| {
+                if free > 0.0 && total_grow > 0.0 {
+                    *item.basis + free * item.grow / total_grow
+                } else if free < 0.0 && total_shrink > 0.0 {
+                    (*item.basis + free * item.shrink * *item.basis / total_shrink).max(0.0)
+                } else {
+                    *item.basis
+                }
+            })
+            .collect();
+
+        // whatever space is left once the items have grown is used to justify them
+        let remaining = (main - gaps - sizes.iter().sum::<f32>()).max(0.0);
+        let (mut position, spacing) = match self.justify {
+            FlexJustify::Start => (0.0, 0.0),
+            FlexJustify::Centre => (remaining / 2.0, 0.0),
+            FlexJustify::End => (remaining, 0.0),
+            FlexJustify::SpaceBetween if sizes.len() > 1 => {
+                (0.0, remaining / (sizes.len() - 1) as f32)
+            }
+            FlexJustify::SpaceBetween => (0.0, 0.0),
+        };
+
+        self.items
+            .iter()
+            .zip(sizes)
+            .map(|(item, size)| {
+                let item_cross = match (self.align, item.cross) {
+                    (FlexAlign::Stretch, _) | (_, None) => cross,
+                    (_, Some(item_cross)) => item_cross.min(cross),
+                };
+                let cross_start = match self.align {
+                    FlexAlign::Stretch | FlexAlign::Start => 0.0,
+                    FlexAlign::Centre => (cross - item_cross) / 2.0,
+                    FlexAlign::End => cross - item_cross,
+                };
+                let start = position;
+                position += size + *self.gap + spacing;
+
+                // the main axis of a row runs left to right and its cross axis top to
+                // bottom; a column is the other way around
+                match self.direction {
+                    FlexDirection::Row => Rect {
+                        x1: rect.x1 + Pt(start),
+                        y1: rect.y2 - Pt(cross_start + item_cross),
+                        x2: rect.x1 + Pt(start + size),
+                        y2: rect.y2 - Pt(cross_start),
+                    },
+                    FlexDirection::Column => Rect {
+                        x1: rect.x1 + Pt(cross_start),
+                        y1: rect.y2 - Pt(start + size),
+                        x2: rect.x1 + Pt(cross_start + item_cross),
+                        y2: rect.y2 - Pt(start),
+                    },
+                }
+            })
+            .collect()
+    }
+}
@@ -1,88 +1,296 @@
 use crate::{
+    compression::Compression,
+    drawing::Path,
+    embedding::EmbeddingPermissions,
+    rect::Rect,
     refs::{ObjectReferences, RefType},
-    PDFError, Pt,
+    PDFError, Pt, Standard14,
 };
 use id_arena::Id;
-use owned_ttf_parser::{AsFaceRef, OwnedFace};
+use owned_ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use owned_ttf_parser::{AsFaceRef, GlyphId, LineMetrics, OutlineBuilder, OwnedFace, Tag};
 use pdf_writer::{
     types::{FontFlags, SystemInfo},
     Finish, Name, PdfWriter, Ref, Str,
 };
 use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, RwLock};
 
 /// A parsed font object. Fonts can be TTF or OTF fonts, and will be embedded in their
 /// entirety in the generated PDF, so large fonts may dramatically increase the size of
-/// the generated PDF. Future versions will explore subsetting the fonts.
+/// the generated PDF. Future versions will explore subsetting the fonts. Alternatively,
+/// one of the standard 14 fonts that every PDF reader provides can be used without
+/// embedding anything (see [Font::standard]).
 ///
 /// Currently, font lifetimes _must_ exceed document lifetimes in order to be properly
 /// embedded. This may change in the future.
 ///
 /// Typically, fonts are referred to throughout user applications by their _index_ within
 /// the document itself, and not by any typed references
+///
+/// The parsed face is reference counted, so fonts are cheap to clone. To use the same
+/// font in many documents, load it once and add a clone of it to each document.
+#[derive(Clone)]
 pub struct Font {
-    pub face: OwnedFace,
+    source: FontSource,
+    /// The glyphs that characters have been looked up as, shared between clones
+    glyphs: Arc<GlyphCache>,
+    /// The restrictions on embedding the font, read when it is loaded
+    permissions: EmbeddingPermissions,
+}
+
+/// The glyph and advance (in font units) that each character looked up in a font maps
+/// to, or [None] if the font has no glyph for it. Layout and rendering look up every
+/// character, so caching them saves searching the font's character map each time
+#[derive(Default)]
+struct GlyphCache(RwLock<HashMap<char, Option<(u16, u16)>>>);
+
+/// Where a font's glyphs and metrics come from
+#[derive(Clone)]
+enum FontSource {
+    /// A parsed font file, which is embedded in the PDF
+    Embedded(Arc<OwnedFace>),
+    /// One of the standard 14 fonts, which isn't embedded
+    Standard(Standard14),
 }
 
 impl Font {
     /// Load a font from raw bytes, parsing the font and returning an error if the font
     /// could not be parsed
     pub fn load(bytes: Vec<u8>) -> Result<Font, PDFError> {
-        let face = OwnedFace::from_vec(bytes, 0)?;
+        Font::load_face(bytes, 0)
+    }
+
+    /// Load the font at `index` in a font file, which may be a collection of fonts
+    pub(crate) fn load_face(bytes: Vec<u8>, index: u32) -> Result<Font, PDFError> {
+        let face = OwnedFace::from_vec(bytes, index)?;
+
+        // validate everything we need to embed the font up front, so that we can report
+        // problems with the font here rather than when the document is being written
+        let name = find_name(&face, owned_ttf_parser::name_id::FULL_NAME)
+            .ok_or(PDFError::MissingFontName("full name"))?;
+        find_name(&face, owned_ttf_parser::name_id::FAMILY)
+            .ok_or(PDFError::MissingFontName("family name"))?;
+        if face.as_face_ref().tables().cmap.is_none() {
+            return Err(PDFError::MissingCharacterMap { font: name });
+        }
+
+        let permissions = EmbeddingPermissions::from_face(&face);
+        Ok(Font {
+            source: FontSource::Embedded(Arc::new(face)),
+            glyphs: Arc::default(),
+            permissions,
+        })
+    }
+
+    /// Use one of the standard 14 fonts, which every PDF reader provides. The font isn't
+    /// embedded, which saves the size of a font file for every font used, but it can
+    /// only draw the characters that its encoding covers (see [Standard14]):
+    ///
+    /// ```
+    /// # use pdf_gen::{Document, Font, Standard14};
+    /// let mut doc = Document::default();
+    /// let helvetica = doc.add_font(Font::standard(Standard14::Helvetica));
+    /// ```
+    pub fn standard(font: Standard14) -> Font {
+        Font {
+            source: FontSource::Standard(font),
+            glyphs: Arc::default(),
+            permissions: EmbeddingPermissions::default(),
+        }
+    }
+
+    /// The parsed font file, or [None] if the font is one of the standard 14 fonts
+    pub fn face(&self) -> Option<&OwnedFace> {
+        match &self.source {
+            FontSource::Embedded(face) => Some(face),
+            FontSource::Standard(_) => None,
+        }
+    }
+
+    /// What the font's licence allows when embedding it in a document, as declared by the
+    /// font (see [crate::Document::font_embedding] for how documents act on this)
+    pub fn embedding_permissions(&self) -> EmbeddingPermissions {
+        self.permissions
+    }
 
-        Ok(Font { face })
+    /// Whether two fonts are the same face: the same standard font, or fonts loaded from
+    /// identical bytes with the same name (which tells apart the faces of a collection)
+    pub(crate) fn same_face(&self, other: &Font) -> bool {
+        match (&self.source, &other.source) {
+            (FontSource::Standard(a), FontSource::Standard(b)) => a == b,
+            (FontSource::Embedded(a), FontSource::Embedded(b)) => {
+                Arc::ptr_eq(a, b) || (a.as_slice() == b.as_slice() && self.name() == other.name())
+            }
+            _ => false,
+        }
     }
 
-    /// Obtain the full name of the font. Panics if the font does not have a name
+    /// Obtain the full name of the font. The presence of the name is validated when the
+    /// font is loaded, so this will only panic if the face was swapped out afterwards
     pub fn name(&self) -> String {
-        self.face
-            .as_face_ref()
-            .names()
-            .into_iter()
-            .find(|name| name.name_id == owned_ttf_parser::name_id::FULL_NAME && name.is_unicode())
-            .and_then(|name| name.to_string())
-            .expect("font face has a name")
+        match &self.source {
+            FontSource::Embedded(face) => {
+                find_name(face, owned_ttf_parser::name_id::FULL_NAME).expect("font face has a name")
+            }
+            FontSource::Standard(font) => font.name().to_string(),
+        }
     }
 
-    /// Obtain the family name of the font. Panics if the font does not have a font family
+    /// Obtain the family name of the font. The presence of the family is validated when
+    /// the font is loaded, so this will only panic if the face was swapped out afterwards
     pub fn family(&self) -> String {
-        self.face
-            .as_face_ref()
-            .names()
-            .into_iter()
-            .find(|name| name.name_id == owned_ttf_parser::name_id::FAMILY && name.is_unicode())
-            .and_then(|name| name.to_string())
-            .expect("font face has a family")
+        match &self.source {
+            FontSource::Embedded(face) => {
+                find_name(face, owned_ttf_parser::name_id::FAMILY).expect("font face has a family")
+            }
+            FontSource::Standard(font) => font.family().to_string(),
+        }
+    }
+
+    /// The number of font units per em, which glyph metrics are given in
+    pub fn units_per_em(&self) -> u16 {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().units_per_em(),
+            FontSource::Standard(_) => 1000,
+        }
+    }
+
+    /// The distance from the baseline to the top of the font, in font units
+    pub fn ascender(&self) -> i16 {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().ascender(),
+            FontSource::Standard(font) => font.ascender(),
+        }
+    }
+
+    /// The distance from the baseline to the bottom of the font (usually negative), in
+    /// font units
+    pub fn descender(&self) -> i16 {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().descender(),
+            FontSource::Standard(font) => font.descender(),
+        }
+    }
+
+    /// The extra space between lines, in font units
+    pub fn line_gap(&self) -> i16 {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().line_gap(),
+            FontSource::Standard(font) => font.line_gap(),
+        }
+    }
+
+    /// The position and thickness of underlines, if the font gives them
+    pub fn underline_metrics(&self) -> Option<LineMetrics> {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().underline_metrics(),
+            FontSource::Standard(font) => Some(font.underline_metrics()),
+        }
+    }
+
+    /// The position and thickness of strikethroughs, if the font gives them
+    pub fn strikeout_metrics(&self) -> Option<LineMetrics> {
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().strikeout_metrics(),
+            FontSource::Standard(_) => None,
+        }
     }
 
     /// Calculate the ascent (distance from the baseline to the top of the font) for the given font size
     pub fn ascent(&self, size: Pt) -> Pt {
-        let scaling: Pt = size / self.face.as_face_ref().units_per_em() as f32;
-        scaling * self.face.as_face_ref().ascender() as f32
+        let scaling: Pt = size / self.units_per_em() as f32;
+        scaling * self.ascender() as f32
     }
 
     /// Calculate the descent (distance from the baseline to the bottom of the font) for the given font size.
     /// Note: this is usually negative
     pub fn descent(&self, size: Pt) -> Pt {
-        let scaling: Pt = size / self.face.as_face_ref().units_per_em() as f32;
-        scaling * self.face.as_face_ref().descender() as f32
+        let scaling: Pt = size / self.units_per_em() as f32;
+        scaling * self.descender() as f32
     }
 
     /// Calculate the leading (extra space between lines) for the given font size
     pub fn leading(&self, size: Pt) -> Pt {
-        let scaling: Pt = size / self.face.as_face_ref().units_per_em() as f32;
-        scaling * self.face.as_face_ref().line_gap() as f32
+        let scaling: Pt = size / self.units_per_em() as f32;
+        scaling * self.line_gap() as f32
     }
 
     /// Calculate the default line height of the font for the given size. The returned value is
     /// how much to vertically offset a second row of text below a first row of text.
     pub fn line_height(&self, size: Pt) -> Pt {
-        let scaling: Pt = size / self.face.as_face_ref().units_per_em() as f32;
-        let leading: Pt = scaling * self.face.as_face_ref().line_gap() as f32;
-        let ascent: Pt = scaling * self.face.as_face_ref().ascender() as f32;
-        let descent: Pt = scaling * self.face.as_face_ref().descender() as f32;
+        let scaling: Pt = size / self.units_per_em() as f32;
+        let leading: Pt = scaling * self.line_gap() as f32;
+        let ascent: Pt = scaling * self.ascender() as f32;
+        let descent: Pt = scaling * self.descender() as f32;
         leading + ascent - descent
     }
 
+    /// The height of capital letters above the baseline for the given font size. Fonts
+    /// which don't give it are assumed to have capitals as tall as their ascent
+    pub fn cap_height(&self, size: Pt) -> Pt {
+        let scaling: Pt = size / self.units_per_em() as f32;
+        let cap_height = match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().capital_height(),
+            FontSource::Standard(font) => font.cap_height(),
+        };
+        scaling * cap_height.unwrap_or_else(|| self.ascender()) as f32
+    }
+
+    /// The height of lowercase letters such as "x" above the baseline for the given font
+    /// size. Fonts which don't give it are assumed to have lowercase letters as tall as
+    /// their capitals
+    pub fn x_height(&self, size: Pt) -> Pt {
+        let scaling: Pt = size / self.units_per_em() as f32;
+        let x_height = match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().x_height(),
+            FontSource::Standard(font) => font.x_height(),
+        };
+        match x_height {
+            Some(x_height) => scaling * x_height as f32,
+            None => self.cap_height(size),
+        }
+    }
+
+    /// How far a character advances along the line at the given font size, without any
+    /// kerning or letter spacing. Characters without a glyph advance as far as the
+    /// replacement glyph that they are drawn with (see [Font::glyph_id_or_replacement])
+    pub fn advance(&self, ch: char, size: Pt) -> Pt {
+        let scaling: Pt = size / self.units_per_em() as f32;
+        let advance = self
+            .glyph(ch)
+            .or_else(|| self.glyph('\u{FFFD}'))
+            .or_else(|| self.glyph('?'))
+            .map(|(_, advance)| advance)
+            .unwrap_or_default();
+        scaling * advance as f32
+    }
+
+    /// The region covered by the ink of a character's glyph at the given font size,
+    /// relative to the start of the glyph's baseline. Returns [None] for characters
+    /// without a glyph, glyphs without an outline (such as spaces), and the standard 14
+    /// fonts, whose outlines aren't available
+    pub fn glyph_bounding_box(&self, ch: char, size: Pt) -> Option<Rect> {
+        let face = self.face()?.as_face_ref();
+        let bbox = face.glyph_bounding_box(face.glyph_index(ch)?)?;
+        let scaling: Pt = size / face.units_per_em() as f32;
+        Some(Rect {
+            x1: scaling * bbox.x_min as f32,
+            y1: scaling * bbox.y_min as f32,
+            x2: scaling * bbox.x_max as f32,
+            y2: scaling * bbox.y_max as f32,
+        })
+    }
+
+    /// The horizontal advance of the space character, in font units. Fonts without a
+    /// space glyph are assumed to have spaces a quarter of an em wide
+    pub fn space_advance(&self) -> f32 {
+        self.glyph_id(' ')
+            .map(|id| self.glyph_advance(id) as f32)
+            .unwrap_or(self.units_per_em() as f32 / 4.0)
+    }
+
     /// Obtain the weight of the font. Numerical values generally map as follows:
     ///
     /// * 100: Thin (Hairline)
@@ -95,21 +303,31 @@ impl Font {
     /// * 800: Extra Bold (Ultra Bold)
     /// * 900: Black (Heavy)
     pub fn weight(&self) -> u16 {
-        self.face.as_face_ref().weight().to_number()
+        match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().weight().to_number(),
+            FontSource::Standard(font) => font.weight(),
+        }
     }
 
     fn write_cid(
         &self,
+        face: &OwnedFace,
         refs: &mut ObjectReferences,
         font_index: usize,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Ref {
-        let font_descriptor_id = self.write_descriptor(refs, font_index, writer);
+        let font_descriptor_id = self.write_descriptor(face, refs, font_index, compression, writer);
 
         let id = refs.gen(RefType::CidFont(font_index));
 
         let mut cid_font = writer.cid_font(id);
-        cid_font.subtype(pdf_writer::types::CidFontType::Type2);
+        let cff = Font::has_cff_outlines(face);
+        cid_font.subtype(if cff {
+            pdf_writer::types::CidFontType::Type0
+        } else {
+            pdf_writer::types::CidFontType::Type2
+        });
         cid_font.base_font(Name(format!("F{font_index}").as_bytes()));
         cid_font.system_info(SystemInfo {
             registry: Str(b"Adobe"),
@@ -118,10 +336,10 @@ impl Font {
         });
         cid_font.font_descriptor(font_descriptor_id);
 
-        let ids = self.glyph_ids();
-        let ids_augmented = self.glyphs_sizing(&ids);
+        let ids = Font::glyph_ids(face);
+        let ids_augmented = Font::glyphs_sizing(face, &ids);
 
-        let scaling = 1000.0 / self.face.as_face_ref().units_per_em() as f32;
+        let scaling = 1000.0 / face.as_face_ref().units_per_em() as f32;
 
         // find the most popular width to use as the default
         // <width, count>
@@ -138,75 +356,95 @@ impl Font {
         let mut widths = cid_font.widths();
         widths.consecutive(0, [1000.0]);
 
-        let mut id_widths: Vec<(u16, f32)> = ids_augmented
-            .iter()
-            .map(|(&cid, &(_, (width, _)))| (cid, width as f32 * scaling))
+        // shaped text (see [Font::shape]) can be drawn with glyphs that no character maps
+        // to, such as ligatures and contextual forms, so every glyph needs its width
+        let id_widths: Vec<(u16, f32)> = (1..face.as_face_ref().number_of_glyphs())
+            .map(|cid| (cid, self.glyph_advance(cid) as f32 * scaling))
             .collect();
-        id_widths.sort_by_key(|(id, _)| *id);
 
         // TODO: compress with ranges as well
-        let first = id_widths.first().expect("font has at least 1 glyph in it");
-        let mut start_cid: u16 = (*first).0;
-        let mut current_widths: Vec<f32> = vec![(*first).1];
-        for (cid, width) in id_widths.into_iter().skip(1) {
-            if (cid - start_cid) as usize > current_widths.len() {
-                // we need a new block!
-                widths.consecutive(start_cid, current_widths.clone());
-                start_cid = cid;
-                current_widths.clear();
-            }
+        if let Some(&(first_cid, first_width)) = id_widths.first() {
+            let mut start_cid: u16 = first_cid;
+            let mut current_widths: Vec<f32> = vec![first_width];
+            for (cid, width) in id_widths.into_iter().skip(1) {
+                if (cid - start_cid) as usize > current_widths.len() {
+                    // we need a new block!
+                    widths.consecutive(start_cid, current_widths.clone());
+                    start_cid = cid;
+                    current_widths.clear();
+                }
 
-            current_widths.push(width);
-        }
+                current_widths.push(width);
+            }
 
-        if !current_widths.is_empty() {
-            widths.consecutive(start_cid, current_widths);
+            if !current_widths.is_empty() {
+                widths.consecutive(start_cid, current_widths);
+            }
         }
 
         widths.finish();
 
         cid_font.default_width(most_common_width);
-        cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+        // CFF fonts select glyphs with CIDs directly, which are the glyph ids for fonts
+        // that aren't CID-keyed
+        if !cff {
+            cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+        }
 
         id
     }
 
     fn write_font_data(
         &self,
+        face: &OwnedFace,
         refs: &mut ObjectReferences,
         font_index: usize,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Ref {
         let id = refs.gen(RefType::FontData(font_index));
 
-        writer
-            .stream(id, self.face.as_slice())
-            .pair(Name(b"Length1"), self.face.as_slice().len() as i32);
+        let (data, filter) = compression.compress(face.as_slice());
+        let mut stream = writer.stream(id, &data);
+        if let Some(filter) = filter {
+            stream.filter(filter);
+        }
+        // Length1 is the length of the font program once it is decoded
+        if Font::has_cff_outlines(face) {
+            stream.pair(Name(b"Subtype"), Name(b"OpenType"));
+        } else {
+            stream.pair(Name(b"Length1"), face.as_slice().len() as i32);
+        }
 
         id
     }
 
+    /// Whether the font's glyphs are CFF (PostScript) outlines rather than TrueType
+    /// outlines, as in most `.otf` fonts. These fonts are embedded whole as OpenType
+    /// fonts (`FontFile3`), since viewers expect `FontFile2` to hold TrueType outlines
+    fn has_cff_outlines(face: &OwnedFace) -> bool {
+        let tables = face.as_face_ref().tables();
+        tables.cff.is_some() || tables.cff2.is_some()
+    }
+
     fn write_descriptor(
         &self,
+        face: &OwnedFace,
         refs: &mut ObjectReferences,
         font_index: usize,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Ref {
-        let font_data_stream_id = self.write_font_data(refs, font_index, writer);
+        let font_data_stream_id = self.write_font_data(face, refs, font_index, compression, writer);
 
-        let gids = self.glyph_ids();
-        let gids_augmented = self.glyphs_sizing(&gids);
+        let gids = Font::glyph_ids(face);
+        let gids_augmented = Font::glyphs_sizing(face, &gids);
 
         let max_width = gids_augmented
             .values()
             .map(|&(_, (w, _))| w)
             .max()
             .unwrap_or_default();
-        let max_height = gids_augmented
-            .values()
-            .map(|&(_, (_, h))| h)
-            .max()
-            .unwrap_or_default();
         let sum_width: usize = gids_augmented.values().map(|&(_, (w, _))| w as usize).sum();
         let avg_width = sum_width as f32 / gids_augmented.len() as f32;
 
@@ -215,68 +453,69 @@ impl Font {
         let mut descriptor = writer.font_descriptor(id);
         descriptor.name(Name(self.name().as_bytes()));
         descriptor.family(Str(self.family().as_bytes()));
-        descriptor.weight(self.face.as_face_ref().weight().to_number());
+        descriptor.weight(face.as_face_ref().weight().to_number());
 
         let mut flags: FontFlags = FontFlags::empty();
-        if self.face.as_face_ref().is_monospaced() {
+        if face.as_face_ref().is_monospaced() {
             flags.set(FontFlags::FIXED_PITCH, true);
         }
-        if self.face.as_face_ref().is_italic() {
+        if face.as_face_ref().is_italic() {
             flags.set(FontFlags::ITALIC, true);
         }
         descriptor.flags(flags);
 
-        let scaling = 1000.0 / self.face.as_face_ref().units_per_em() as f32;
+        let scaling = 1000.0 / face.as_face_ref().units_per_em() as f32;
+        // the bounding box of all the font's glyphs, from the `head` table
+        let bbox = face.as_face_ref().global_bounding_box();
         descriptor.bbox(pdf_writer::Rect {
-            x1: 0.0,
-            y1: 0.0,
-            x2: sum_width as f32 * scaling,
-            y2: max_height as f32 * scaling,
+            x1: bbox.x_min as f32 * scaling,
+            y1: bbox.y_min as f32 * scaling,
+            x2: bbox.x_max as f32 * scaling,
+            y2: bbox.y_max as f32 * scaling,
         });
-        descriptor.italic_angle(self.face.as_face_ref().italic_angle().unwrap_or_default());
-        descriptor.ascent(self.face.as_face_ref().ascender() as f32 * scaling);
-        descriptor.descent(self.face.as_face_ref().descender() as f32 * scaling);
-        descriptor.leading(self.face.as_face_ref().line_gap() as f32 * scaling);
+        descriptor.italic_angle(face.as_face_ref().italic_angle().unwrap_or_default());
+        descriptor.ascent(self.ascender() as f32 * scaling);
+        descriptor.descent(self.descender() as f32 * scaling);
+        descriptor.leading(self.line_gap() as f32 * scaling);
         descriptor.cap_height(
-            self.face
-                .as_face_ref()
+            face.as_face_ref()
                 .capital_height()
                 .map(|h| h as f32 * scaling)
                 .unwrap_or(1000.0),
         );
         descriptor.x_height(
-            self.face
-                .as_face_ref()
+            face.as_face_ref()
                 .x_height()
-                .unwrap_or_else(|| self.face.as_face_ref().capital_height().unwrap_or_default())
+                .unwrap_or_else(|| face.as_face_ref().capital_height().unwrap_or_default())
                 as f32
                 * scaling,
         );
-        //descriptor.stem_v(todo!());
-        // TODO: how to get this?
-        descriptor.stem_v(80.0);
+        descriptor.stem_v(stem_v(face.as_face_ref().weight().to_number()));
         //descriptor.stem_h(todo!());
         descriptor.avg_width(avg_width * scaling);
         descriptor.max_width(max_width as f32 * scaling);
         descriptor.missing_width(max_width as f32 * scaling);
 
-        descriptor.font_file2(font_data_stream_id);
+        if Font::has_cff_outlines(face) {
+            descriptor.font_file3(font_data_stream_id);
+        } else {
+            descriptor.font_file2(font_data_stream_id);
+        }
 
         id
     }
 
-    fn glyph_ids(&self) -> HashMap<u16, char> {
+    fn glyph_ids(face: &OwnedFace) -> HashMap<u16, char> {
         // Adapted from printpdf
         let mut map: HashMap<u16, char> = HashMap::new();
 
-        for subtable in self
-            .face
+        // the presence of the cmap table is validated when the font is loaded
+        for subtable in face
             .as_face_ref()
             .tables()
             .cmap
-            .expect("font has cmap table")
-            .subtables
             .into_iter()
+            .flat_map(|cmap| cmap.subtables.into_iter())
             .filter(|table| table.is_unicode())
         {
             subtable.codepoints(|codepoint: u32| {
@@ -292,16 +531,18 @@ impl Font {
         map
     }
 
-    fn glyphs_sizing(&self, ids: &HashMap<u16, char>) -> HashMap<u16, (char, (u16, i16))> {
+    fn glyphs_sizing(
+        face: &OwnedFace,
+        ids: &HashMap<u16, char>,
+    ) -> HashMap<u16, (char, (u16, i16))> {
         let mut ids_augmented: HashMap<u16, (char, (u16, i16))> = HashMap::new();
         for (&id, &ch) in ids.iter() {
-            if let Some(gid) = self.face.as_face_ref().glyph_index(ch) {
-                if let Some(h_advance) = self.face.as_face_ref().glyph_hor_advance(gid) {
-                    let height = self
-                        .face
+            if let Some(gid) = face.as_face_ref().glyph_index(ch) {
+                if let Some(h_advance) = face.as_face_ref().glyph_hor_advance(gid) {
+                    let height = face
                         .as_face_ref()
                         .glyph_bounding_box(gid)
-                        .map(|bbox| bbox.y_max - bbox.y_min - self.face.as_face_ref().descender())
+                        .map(|bbox| bbox.y_max - bbox.y_min - face.as_face_ref().descender())
                         .unwrap_or(1000);
                     ids_augmented.insert(id, (ch, (h_advance, height)));
                 }
@@ -312,8 +553,10 @@ impl Font {
 
     fn write_to_unicode(
         &self,
+        face: &OwnedFace,
         refs: &mut ObjectReferences,
         font_index: usize,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Ref {
         let id = refs.gen(RefType::ToUnicode(font_index));
@@ -332,7 +575,7 @@ endcodespacerange
 "#
         .replace("\r\n", "\n");
 
-        let ids = self.glyph_ids();
+        let ids = Font::glyph_ids(face);
         let mut ids: Vec<(u16, char)> = ids.into_iter().collect();
         ids.sort_by_key(|&(id, _)| id);
 
@@ -366,21 +609,30 @@ endcodespacerange
 
         map.push_str("endcmap CMapName currentdict /CMap defineresource pop end end\n");
 
-        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(
-            map.as_bytes(),
-            miniz_oxide::deflate::CompressionLevel::DefaultCompression as u8,
-        );
-        let mut stream = writer.stream(id, compressed.as_slice());
-        stream.filter(pdf_writer::Filter::FlateDecode);
+        let (data, filter) = compression.compress(map.as_bytes());
+        let mut stream = writer.stream(id, &data);
+        if let Some(filter) = filter {
+            stream.filter(filter);
+        }
 
         id
     }
 
-    pub(crate) fn write(&self, refs: &mut ObjectReferences, id: Id<Font>, writer: &mut PdfWriter) {
+    pub(crate) fn write(
+        &self,
+        refs: &mut ObjectReferences,
+        id: Id<Font>,
+        compression: Compression,
+        writer: &mut PdfWriter,
+    ) {
         let font_index = id.index();
+        let face = match &self.source {
+            FontSource::Embedded(face) => face,
+            FontSource::Standard(font) => return font.write(refs, font_index, writer),
+        };
         let font_id = refs.gen(RefType::Font(font_index));
-        let cid_font_id = self.write_cid(refs, font_index, writer);
-        let to_unicode_id = self.write_to_unicode(refs, font_index, writer);
+        let cid_font_id = self.write_cid(face, refs, font_index, compression, writer);
+        let to_unicode_id = self.write_to_unicode(face, refs, font_index, compression, writer);
 
         let mut font = writer.type0_font(font_id);
         font.base_font(Name(format!("F{font_index}").as_bytes()));
@@ -389,11 +641,240 @@ endcodespacerange
         font.to_unicode(to_unicode_id);
     }
 
+    /// The outline of the glyph for a character at the given size, with its origin (the
+    /// start of its baseline) at (0, 0), such as to fill display text with effects that
+    /// text can't have or to produce cut files. The standard 14 fonts aren't embedded,
+    /// so their outlines aren't available; nor are the outlines of characters without a
+    /// glyph. Glyphs without any outline (such as spaces) give an empty path
+    pub fn glyph_outline(&self, ch: char, size: Pt) -> Option<Path> {
+        let face = self.face()?.as_face_ref();
+        let id = face.glyph_index(ch)?;
+        let mut builder = OutlineScaler {
+            path: Path::new(),
+            scaling: size / face.units_per_em() as f32,
+        };
+        face.outline_glyph(id, &mut builder);
+        Some(builder.path)
+    }
+
+    /// The outlines of the glyphs of some text at the given size, with the start of its
+    /// baseline at `origin`, spaced as [crate::layout::width_of_text] measures them.
+    /// Characters without an outline (see [Font::glyph_outline]) are skipped over.
+    ///
+    /// ```
+    /// # use pdf_gen::{Colour, Font, Page, Pt};
+    /// # use pdf_gen::drawing::DrawStyle;
+    /// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+    /// # let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+    /// let title = font.text_outline("Annual Report", Pt(48.0), (Pt(72.0), Pt(700.0)));
+    /// page.add_shape(&title, &DrawStyle::filled(Colour::new_rgb_bytes(0x33, 0x66, 0x99)));
+    /// ```
+    pub fn text_outline(&self, text: &str, size: Pt, origin: (Pt, Pt)) -> Path {
+        let scaling = size / self.units_per_em() as f32;
+        let mut path = Path::new();
+        let mut x = origin.0;
+        let mut previous: Option<u16> = None;
+        for ch in text.chars() {
+            let id = match self.glyph_id(ch) {
+                Some(id) => id,
+                None => continue,
+            };
+            if let Some(previous) = previous {
+                x += scaling * self.kerning(previous, id) as f32;
+            }
+            if let Some(outline) = self.glyph_outline(ch, size) {
+                path.extend(&outline.translated(x, origin.1));
+            }
+            x += scaling * self.glyph_advance(id) as f32;
+            previous = Some(id);
+        }
+        path
+    }
+
+    /// Look up the glyph for a character. For the standard 14 fonts, glyphs are
+    /// identified by their character codes
     pub fn glyph_id(&self, ch: char) -> Option<u16> {
-        self.face.as_face_ref().glyph_index(ch).map(|i| i.0)
+        self.glyph(ch).map(|(id, _)| id)
+    }
+
+    /// Look up the glyph for a character along with its horizontal advance in font
+    /// units, remembering the result so that the font's character map only has to be
+    /// searched the first time each character is looked up
+    pub(crate) fn glyph(&self, ch: char) -> Option<(u16, u16)> {
+        if let Some(&glyph) = self
+            .glyphs
+            .0
+            .read()
+            .ok()
+            .as_ref()
+            .and_then(|glyphs| glyphs.get(&ch))
+        {
+            return glyph;
+        }
+
+        let glyph = match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref().glyph_index(ch).map(|i| i.0),
+            FontSource::Standard(font) => font.code(ch).map(u16::from),
+        }
+        .map(|id| (id, self.glyph_advance(id)));
+        if let Ok(mut glyphs) = self.glyphs.0.write() {
+            glyphs.insert(ch, glyph);
+        }
+        glyph
     }
 
     pub fn replacement_glyph_id(&self) -> Option<u16> {
-        self.face.as_face_ref().glyph_index('\u{FFFD}').map(|i| i.0)
+        self.glyph_id('\u{FFFD}')
+    }
+
+    /// The horizontal advance of a glyph, in font units
+    pub fn glyph_advance(&self, id: u16) -> u16 {
+        match &self.source {
+            FontSource::Embedded(face) => face
+                .as_face_ref()
+                .glyph_hor_advance(GlyphId(id))
+                .unwrap_or_default(),
+            FontSource::Standard(font) => font.width(id),
+        }
+    }
+
+    /// Write a glyph as a string in a content stream: two bytes for embedded fonts,
+    /// which are written with the `Identity-H` encoding, and one byte for the standard
+    /// fonts' character codes
+    pub(crate) fn write_glyph(&self, content: &mut Vec<u8>, id: u16) -> std::io::Result<()> {
+        match &self.source {
+            FontSource::Embedded(_) => write!(content, "{id:04x}"),
+            FontSource::Standard(_) => write!(content, "{id:02x}"),
+        }
+    }
+
+    /// Whether the font has its own small capitals: the `smcp` feature of its `GSUB`
+    /// table (see [crate::TextEffects::small_caps])
+    pub fn has_small_caps(&self) -> bool {
+        self.face()
+            .and_then(|face| face.as_face_ref().tables().gsub)
+            .and_then(|gsub| gsub.features.find(Tag::from_bytes(b"smcp")))
+            .is_some()
+    }
+
+    /// Look up the glyph for a character, falling back to the replacement character
+    /// (`U+FFFD`) and then to `?` if the font doesn't have a glyph for the character.
+    /// Returns [None] if none of those glyphs are in the font.
+    pub fn glyph_id_or_replacement(&self, ch: char) -> Option<u16> {
+        self.glyph_id(ch)
+            .or_else(|| self.replacement_glyph_id())
+            .or_else(|| self.glyph_id('?'))
+    }
+
+    /// The kerning between a pair of glyphs: how much further (or, if negative, less
+    /// far) to move after drawing the `left` glyph when it is followed by the `right`
+    /// glyph, in font units. Pair adjustments of the `GPOS` table's `kern` feature are
+    /// used if the font has them, and the `kern` table otherwise. Returns 0 if the font
+    /// doesn't kern the pair, and for the standard 14 fonts
+    pub fn kerning(&self, left: u16, right: u16) -> i16 {
+        let face = match &self.source {
+            FontSource::Embedded(face) => face.as_face_ref(),
+            FontSource::Standard(_) => return 0,
+        };
+        let (left, right) = (GlyphId(left), GlyphId(right));
+
+        let gpos = face.tables().gpos.and_then(|gpos| {
+            let feature = gpos.features.find(Tag::from_bytes(b"kern"))?;
+            Some((gpos, feature))
+        });
+        if let Some((gpos, feature)) = gpos {
+            for lookup in feature
+                .lookup_indices
+                .into_iter()
+                .filter_map(|index| gpos.lookups.get(index))
+            {
+                for index in 0..lookup.subtables.len() {
+                    let kerning = match lookup.subtables.get::<PositioningSubtable>(index) {
+                        Some(PositioningSubtable::Pair(PairAdjustment::Format1 {
+                            coverage,
+                            sets,
+                        })) => coverage
+                            .get(left)
+                            .and_then(|index| sets.get(index))
+                            .and_then(|set| set.get(right))
+                            .map(|(first, _)| first.x_advance),
+                        Some(PositioningSubtable::Pair(PairAdjustment::Format2 {
+                            coverage,
+                            classes,
+                            matrix,
+                        })) if coverage.contains(left) => matrix
+                            .get((classes.0.get(left), classes.1.get(right)))
+                            .map(|(first, _)| first.x_advance),
+                        _ => None,
+                    };
+                    if let Some(kerning) = kerning {
+                        return kerning;
+                    }
+                }
+            }
+            return 0;
+        }
+
+        face.tables()
+            .kern
+            .into_iter()
+            .flat_map(|kern| kern.subtables.into_iter())
+            .filter(|subtable| subtable.horizontal && !subtable.variable)
+            .find_map(|subtable| subtable.glyphs_kerning(left, right))
+            .unwrap_or_default()
+    }
+}
+
+/// Find a name in a font's naming table
+fn find_name(face: &OwnedFace, name_id: u16) -> Option<String> {
+    face.as_face_ref()
+        .names()
+        .into_iter()
+        .find(|name| name.name_id == name_id && name.is_unicode())
+        .and_then(|name| name.to_string())
+}
+
+/// Estimate the thickness of a font's vertical stems (in thousandths of an em) from its
+/// weight class, as fonts don't record it anywhere that is easy to read. This gives
+/// about 95 for regular weights and 170 for bold ones
+fn stem_v(weight: u16) -> f32 {
+    10.0 + 0.244 * (weight as f32 - 50.0)
+}
+
+/// Collects a glyph's outline into a [Path], scaling it from font units
+struct OutlineScaler {
+    path: Path,
+    scaling: Pt,
+}
+
+impl OutlineScaler {
+    fn point(&self, x: f32, y: f32) -> (Pt, Pt) {
+        (self.scaling * x, self.scaling * y)
+    }
+}
+
+impl OutlineBuilder for OutlineScaler {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.path.move_to(to);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.path.line_to(to);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (control, to) = (self.point(x1, y1), self.point(x, y));
+        self.path.quad_to(control, to);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (control1, control2, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.path.cubic_to(control1, control2, to);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
     }
 }
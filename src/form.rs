@@ -0,0 +1,279 @@
+use crate::artifact::Artifact;
+use crate::compression::Compression;
+use crate::document::Document;
+use crate::page::{render_contents, ImageLayout, Page, PageContents, SpanLayout, WordSpacing};
+use crate::rect::Rect;
+use crate::refs::{ObjectReferences, RefType};
+use crate::resources::UsedResources;
+use crate::PDFError;
+use id_arena::Id;
+use pdf_writer::{Content, Finish, Name, PdfWriter};
+
+/// Content which is written to the document once, and can then be drawn any number of
+/// times on any number of pages (see [crate::Page::add_form]), at any size. Forms are
+/// useful for things like logos made of many drawing operations, stamps, and
+/// watermarks.
+///
+/// Forms have their own coordinate space, within their bounding box; content is
+/// positioned within a form the same way that it is positioned on a page.
+///
+/// Note that fonts are referred to by name as `/Fi` where `i` is the font index, image
+/// xobjects are referred to by name as `/Ii` where `i` is the image index, and other
+/// forms are referred to by name as `/Xi` where `i` is the form index.
+pub struct FormXObject {
+    /// The region of the form's coordinate space which it covers
+    pub bbox: Rect,
+    /// The content of the form
+    pub contents: Vec<PageContents>,
+    /// How the spaces between words in text spans are written
+    pub word_spacing: WordSpacing,
+    /// A page of the document whose contents are drawn into the form, beneath the form's
+    /// own contents (see [Document::page_to_xobject]). The page's links and other
+    /// annotations are not included
+    pub source_page: Option<Id<Page>>,
+    /// Whether to explicitly clip the form's content to its bounding box. Viewers are
+    /// supposed to clip forms to their bounding boxes anyway, but not all of them do so
+    /// consistently; enable this if content may overflow the form
+    pub clip: bool,
+    /// Makes the form a transparency group, which is composited as a whole with what
+    /// is beneath it (rather than each of its elements being composited separately)
+    pub group: Option<TransparencyGroup>,
+}
+
+/// The colour space that a [TransparencyGroup] is composited in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GroupColourSpace {
+    /// DeviceRGB
+    RGB,
+    /// DeviceCMYK
+    CMYK,
+    /// DeviceGray
+    Grey,
+}
+
+/// The attributes of a form which is a transparency group. Groups only make a visible
+/// difference when their contents (or the form as a whole) are partially transparent
+/// or blended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TransparencyGroup {
+    /// The colour space that the group is composited in. If [None], the colour space
+    /// of whatever the form is drawn onto is used
+    pub colour_space: Option<GroupColourSpace>,
+    /// If true, the group is composited onto a fully transparent backdrop rather than
+    /// whatever is beneath the form
+    pub isolated: bool,
+    /// If true, elements of the group are composited onto the group's initial backdrop
+    /// rather than on top of each other, so they "knock out" earlier elements
+    pub knockout: bool,
+}
+
+impl TransparencyGroup {
+    /// An isolated group composited in the RGB colour space, which is the most common
+    /// choice for groups containing transparent content
+    pub fn isolated_rgb() -> TransparencyGroup {
+        TransparencyGroup {
+            colour_space: Some(GroupColourSpace::RGB),
+            isolated: true,
+            knockout: false,
+        }
+    }
+}
+
+/// A form to be drawn onto a page
+#[derive(Clone, PartialEq, Debug)]
+pub struct FormLayout {
+    /// Which form within the document to draw
+    pub form_index: usize,
+    /// Where the form should be drawn on the page, relative to the bottom-left corner of
+    /// the page. The form's bounding box is scaled to fill this
+    pub position: Rect,
+    /// Marks the form as an artifact (such as a watermark or a letterhead) rather than
+    /// part of the document's content
+    pub artifact: Option<Artifact>,
+}
+
+impl FormXObject {
+    /// Create an empty form covering the given region of its coordinate space
+    pub fn new(bbox: Rect) -> FormXObject {
+        FormXObject {
+            bbox,
+            contents: Vec::default(),
+            word_spacing: WordSpacing::default(),
+            source_page: None,
+            clip: false,
+            group: None,
+        }
+    }
+
+    /// Turn a page into a form with the same size and contents, so that it can be drawn
+    /// onto other pages (i.e. for imposing several pages onto one sheet). The page's
+    /// links and other annotations are discarded. To make a form out of a page which
+    /// has already been added to the document, use [Document::page_to_xobject] instead.
+    pub fn from_page(page: Page) -> FormXObject {
+        FormXObject {
+            bbox: page.media_box,
+            contents: page.contents,
+            word_spacing: page.word_spacing,
+            source_page: None,
+            clip: false,
+            group: None,
+        }
+    }
+
+    /// Clip the form's content to its bounding box
+    pub fn clip(&mut self, clip: bool) -> &mut Self {
+        self.clip = clip;
+        self
+    }
+
+    /// Make the form a transparency group
+    pub fn group(&mut self, group: TransparencyGroup) -> &mut Self {
+        self.group = Some(group);
+        self
+    }
+
+    /// Add a span of text to the form, in the layering order that it was added
+    pub fn add_span(&mut self, span: SpanLayout) {
+        self.contents.push(PageContents::Text(vec![span]));
+    }
+
+    /// Add several spans of text to the form as a single block, in the layering order
+    /// that they were added
+    pub fn add_spans(&mut self, spans: Vec<SpanLayout>) {
+        self.contents.push(PageContents::Text(spans));
+    }
+
+    /// Add a block of text to the form, in the layering order that it was added, clipped
+    /// to the given region (see [crate::Page::add_clipped_spans])
+    pub fn add_clipped_spans(&mut self, spans: Vec<SpanLayout>, clip: Rect) {
+        self.contents.push(PageContents::ClippedText(spans, clip));
+    }
+
+    /// Add an image to the form, in the layering order that it was added
+    pub fn add_image(&mut self, image: ImageLayout) {
+        self.contents.push(PageContents::Image(image));
+    }
+
+    /// Draw another form within this form, in the layering order that it was added
+    pub fn add_form(&mut self, form: FormLayout) {
+        self.contents.push(PageContents::Form(form));
+    }
+
+    /// Add arbitrary `pdf_writer::Content` to the form, in the layering order that it was
+    /// added
+    pub fn add_content(&mut self, content: Content) {
+        self.contents
+            .push(PageContents::RawContent(content.finish()));
+    }
+
+    /// Render the form's content stream
+    pub(crate) fn render(&self, document: &Document) -> Result<Vec<u8>, PDFError> {
+        // forms aren't on any one page, so deferred content within them is given the
+        // index of the first page
+        let mut rendered = match self.source_page {
+            Some(id) => {
+                let page = document.pages.get(id).ok_or(PDFError::PageMissing)?;
+                let page_index = document.index_of_page(id).unwrap_or_default();
+                page.render_items(document, page_index)?
+            }
+            None => Vec::default(),
+        };
+        rendered.extend(render_contents(
+            &self.contents,
+            (self.bbox, self.bbox),
+            self.word_spacing,
+            document,
+            0,
+        )?);
+        let mut content: Vec<u8> = Vec::default();
+        if self.clip {
+            let mut clip = Content::new();
+            clip.save_state();
+            clip.rect(
+                *self.bbox.x1,
+                *self.bbox.y1,
+                *(self.bbox.x2 - self.bbox.x1),
+                *(self.bbox.y2 - self.bbox.y1),
+            );
+            clip.clip_nonzero();
+            clip.end_path();
+            content.extend(clip.finish());
+            content.push(b'\n');
+        }
+        for item in rendered {
+            content.extend(item);
+        }
+        if self.clip {
+            content.extend(b"Q\n");
+        }
+        Ok(content)
+    }
+
+    /// Write the form, given its rendered content and the resources that the content
+    /// uses (only those resources are listed in the form's resource dictionary)
+    pub(crate) fn write(
+        &self,
+        refs: &mut ObjectReferences,
+        form_index: usize,
+        content: &[u8],
+        used: &UsedResources,
+        compression: Compression,
+        writer: &mut PdfWriter,
+    ) -> Result<(), PDFError> {
+        let (data, filter) = compression.compress(content);
+
+        let id = refs.require(RefType::Form(form_index))?;
+        let mut form = writer.form_xobject(id, &data);
+        if let Some(filter) = filter {
+            form.filter(filter);
+        }
+        form.bbox(self.bbox.into());
+
+        if let Some(group) = &self.group {
+            let mut dict = form.insert(Name(b"Group")).dict();
+            dict.pair(Name(b"Type"), Name(b"Group"));
+            dict.pair(Name(b"S"), Name(b"Transparency"));
+            if let Some(colour_space) = group.colour_space {
+                dict.pair(
+                    Name(b"CS"),
+                    match colour_space {
+                        GroupColourSpace::RGB => Name(b"DeviceRGB"),
+                        GroupColourSpace::CMYK => Name(b"DeviceCMYK"),
+                        GroupColourSpace::Grey => Name(b"DeviceGray"),
+                    },
+                );
+            }
+            dict.pair(Name(b"I"), group.isolated);
+            dict.pair(Name(b"K"), group.knockout);
+            dict.finish();
+        }
+
+        let mut resources = form.resources();
+        let mut resource_fonts = resources.fonts();
+        for &i in used.fonts.iter() {
+            resource_fonts.pair(
+                Name(format!("F{i}").as_bytes()),
+                refs.require(RefType::Font(i))?,
+            );
+        }
+        resource_fonts.finish();
+        let mut resource_xobjects = resources.x_objects();
+        for &i in used.images.iter() {
+            resource_xobjects.pair(
+                Name(format!("I{i}").as_bytes()),
+                refs.require(RefType::Image(i))?,
+            );
+        }
+        for &i in used.forms.iter() {
+            resource_xobjects.pair(
+                Name(format!("X{i}").as_bytes()),
+                refs.require(RefType::Form(i))?,
+            );
+        }
+        resource_xobjects.finish();
+        resources.finish();
+        form.finish();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use crate::document::Document;
+use crate::fallback::FontStack;
+use crate::font::Font;
+use crate::image::Image;
+use crate::info::Info;
+use crate::style::StyleSheet;
+use crate::theme::Theme;
+#[cfg(feature = "templates")]
+use crate::{PDFError, Template, TemplateData};
+use id_arena::{Arena, Id};
+use std::collections::HashMap;
+
+/// Generates many documents from the same fonts, images, styles, and templates, such as
+/// when generating thousands of invoices in one run. Fonts and images are parsed and
+/// decoded once when they are added to the generator, and each document it creates
+/// shares them rather than loading its own copies.
+///
+/// The Ids of the generator's fonts and images are valid in every document that it
+/// creates, so they can be looked up once and used for every document:
+///
+/// ```
+/// # use pdf_gen::{layout, Font, Generator, Page, Pt, SpanFont, TextRun};
+/// let mut generator = Generator::new();
+/// let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// let font = generator.add_font(font);
+///
+/// for customer in ["Alice", "Bob"] {
+///     let mut doc = generator.document();
+///     let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+///     let start = layout::baseline_start(&page, &doc.fonts[font], Pt(10.0));
+///     let bounding_box = page.content_box;
+///     let body = TextRun::new(SpanFont { id: font, size: Pt(10.0) });
+///     let mut text = vec![body.text(format!("Invoice for {customer}"))];
+///     layout::layout_runs(&doc, &mut page, start, &mut text, Pt(0.0), bounding_box, &[])
+///         .unwrap();
+///     doc.add_page(page);
+///
+///     let mut out = Vec::new();
+///     doc.write(&mut out).unwrap();
+/// }
+/// ```
+///
+/// Anything else about a document (its pages, forms, line breaker, and so on), and any
+/// per-document overrides of the shared settings, such as its info or theme, are set on
+/// the document after it has been created.
+#[derive(Default)]
+pub struct Generator {
+    /// The fonts shared by every document
+    pub fonts: Arena<Font>,
+    /// The images shared by every document
+    pub images: Arena<Image>,
+    /// The named text styles that every document starts with
+    pub styles: StyleSheet,
+    /// The themes that every document starts with
+    pub themes: HashMap<String, Theme>,
+    /// The theme that every document starts with, if any
+    pub theme: Option<String>,
+    /// The font stacks that every document starts with (see [Document::set_font_stack])
+    pub font_stacks: HashMap<Id<Font>, FontStack>,
+    /// The info that every document starts with, if any
+    pub info: Option<Info>,
+    /// Templates which documents can be rendered from, by name
+    #[cfg(feature = "templates")]
+    pub templates: HashMap<String, Template>,
+}
+
+impl Generator {
+    /// Create a generator without any shared resources
+    pub fn new() -> Generator {
+        Generator::default()
+    }
+
+    /// Add a font to share with every document. The returned Id refers to the font in
+    /// every document that the generator creates
+    pub fn add_font(&mut self, font: Font) -> Id<Font> {
+        self.fonts.alloc(font)
+    }
+
+    /// Add an image to share with every document. The returned Id refers to the image in
+    /// every document that the generator creates
+    pub fn add_image(&mut self, image: Image) -> Id<Image> {
+        self.images.alloc(image)
+    }
+
+    /// Register a font stack for every document (see [Document::set_font_stack])
+    pub fn set_font_stack(&mut self, stack: FontStack) {
+        if let Some(&primary) = stack.fonts.first() {
+            self.font_stacks.insert(primary, stack);
+        }
+    }
+
+    /// Add a theme that every document starts with
+    pub fn add_theme<S: ToString>(&mut self, name: S, theme: Theme) {
+        self.themes.insert(name.to_string(), theme);
+    }
+
+    /// Add a template which documents can be rendered from (see [Generator::render]),
+    /// replacing any template previously added with the same name
+    #[cfg(feature = "templates")]
+    pub fn add_template<S: ToString>(&mut self, name: S, template: Template) {
+        self.templates.insert(name.to_string(), template);
+    }
+
+    /// Create an empty document which shares the generator's fonts and images, and
+    /// starts with its styles, themes, font stacks, and info. Fonts and images are
+    /// reference counted, so this doesn't copy their data
+    pub fn document(&self) -> Document {
+        Document {
+            info: self.info.clone(),
+            fonts: self.fonts.clone(),
+            images: self.images.clone(),
+            styles: self.styles.clone(),
+            themes: self.themes.clone(),
+            theme: self.theme.clone(),
+            font_stacks: self.font_stacks.clone(),
+            ..Document::default()
+        }
+    }
+
+    /// Create a document (see [Generator::document]) and fill in the named template with
+    /// the given data (see [Template::render])
+    #[cfg(feature = "templates")]
+    pub fn render(&self, template: &str, data: &TemplateData) -> Result<Document, PDFError> {
+        let template = self
+            .templates
+            .get(template)
+            .ok_or_else(|| PDFError::TemplateMissing(template.to_string()))?;
+        let mut document = self.document();
+        template.render(&mut document, data)?;
+        Ok(document)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::page::ContentTarget;
+use crate::rect::Rect;
+use crate::units::Pt;
+use std::ops::Range;
+
+/// Divides a region (usually a page's content box) into equal columns separated by
+/// gutters, so that blocks in dashboard-style layouts line up predictably. Columns are
+/// numbered from 0, and blocks span a range of them:
+///
+/// ```
+/// use pdf_gen::{Grid, Pt, Rect};
+///
+/// let area = Rect { x1: Pt(0.0), y1: Pt(0.0), x2: Pt(560.0), y2: Pt(720.0) };
+/// let grid = Grid::new(area, 12, Pt(10.0));
+/// assert_eq!(grid.column_width(), Pt(37.5));
+///
+/// // the third through eighth columns, 100pt tall with its top 50pt down the area
+/// let block = grid.span(2..8, Pt(670.0), Pt(100.0));
+/// assert_eq!(block, Rect { x1: Pt(95.0), y1: Pt(570.0), x2: Pt(370.0), y2: Pt(670.0) });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// The region divided into columns
+    pub area: Rect,
+    /// How many columns the region is divided into
+    pub columns: usize,
+    /// The space between neighbouring columns
+    pub gutter: Pt,
+}
+
+impl Grid {
+    /// Divide a region into the given number of columns
+    pub fn new(area: Rect, columns: usize, gutter: Pt) -> Grid {
+        Grid {
+            area,
+            columns: columns.max(1),
+            gutter,
+        }
+    }
+
+    /// Divide the content box of a page (or form) into the given number of columns
+    pub fn for_page<T: ContentTarget + ?Sized>(page: &T, columns: usize, gutter: Pt) -> Grid {
+        Grid::new(page.content_box(), columns, gutter)
+    }
+
+    /// The width of a single column
+    pub fn column_width(&self) -> Pt {
+        let gutters = self.gutter * (self.columns - 1) as f32;
+        (self.area.x2 - self.area.x1 - gutters) / self.columns as f32
+    }
+
+    /// The left and right edges of a range of columns, including the gutters between
+    /// them. Columns beyond the end of the grid are ignored, and an empty range gives a
+    /// span with no width
+    pub fn x_span(&self, columns: Range<usize>) -> (Pt, Pt) {
+        let end = columns.end.min(self.columns);
+        let start = columns.start.min(end);
+        let pitch = self.column_width() + self.gutter;
+        let x1 = self.area.x1 + pitch * start as f32;
+        if start == end {
+            return (x1, x1);
+        }
+        let x2 = x1 + pitch * (end - start) as f32 - self.gutter;
+        (x1, x2)
+    }
+
+    /// The rect covering a range of columns, with its top edge at `top`
+    pub fn span(&self, columns: Range<usize>, top: Pt, height: Pt) -> Rect {
+        let (x1, x2) = self.x_span(columns);
+        Rect {
+            x1,
+            y1: top - height,
+            x2,
+            y2: top,
+        }
+    }
+
+    /// The rect covering a range of columns, spanning the full height of the area
+    pub fn full_height(&self, columns: Range<usize>) -> Rect {
+        self.span(columns, self.area.y2, self.area.y2 - self.area.y1)
+    }
+}
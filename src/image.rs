@@ -1,11 +1,17 @@
 use crate::{
+    colour::{Colour, ColourConversion},
+    compression::Compression,
     refs::{ObjectReferences, RefType},
     PDFError,
 };
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
 use image::{ColorType, DynamicImage};
-use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use miniz_oxide::inflate::decompress_to_vec_zlib;
 use pdf_writer::{Filter, Finish, PdfWriter};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use usvg::Tree;
 
 /// A raster image. 24-bit JPEG images may be embedded directly, whereas
@@ -16,6 +22,97 @@ pub enum RasterImageType {
     DirectlyEmbeddableJpeg(PathBuf),
     /// A generic image which will be rendered as a PNG when writing the PDF
     Image(DynamicImage),
+    /// A 24-bit JPEG which may be embedded directly in the file, from memory
+    Jpeg(Vec<u8>),
+    /// An image which has been encoded into temporary files (see [Image::spill])
+    Spilled(SpilledImage),
+}
+
+/// A raster image which has been encoded and written to temporary files, so that it
+/// doesn't take up memory until the document is written (see [Image::spill]). The
+/// files are deleted when the image is dropped
+pub struct SpilledImage {
+    /// The encoded image
+    path: PathBuf,
+    /// The encoded transparency mask, if the image has one
+    mask: Option<PathBuf>,
+    /// Whether the image is encoded as a JPEG, rather than as zlib-compressed pixels
+    jpeg: bool,
+}
+
+impl SpilledImage {
+    /// Read the image back from its files and decode it
+    fn decode(&self, width: u32, height: u32) -> Result<DynamicImage, PDFError> {
+        if self.jpeg {
+            return Ok(image::open(&self.path)?);
+        }
+        let inflate = |path: &Path| -> Result<Vec<u8>, PDFError> {
+            decompress_to_vec_zlib(&std::fs::read(path)?).map_err(|status| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("can't decompress the spilled image: {status:?}"),
+                )
+                .into()
+            })
+        };
+        let invalid = || {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the spilled image doesn't match its size",
+            )
+        };
+        let rgb =
+            image::RgbImage::from_raw(width, height, inflate(&self.path)?).ok_or_else(invalid)?;
+        match &self.mask {
+            Some(mask) => {
+                let alphas = inflate(mask)?;
+                if alphas.len() != width as usize * height as usize {
+                    return Err(invalid().into());
+                }
+                let mut rgba = DynamicImage::ImageRgb8(rgb).to_rgba8();
+                for (pixel, alpha) in rgba.pixels_mut().zip(alphas.into_iter()) {
+                    pixel.0[3] = alpha;
+                }
+                Ok(DynamicImage::ImageRgba8(rgba))
+            }
+            None => Ok(DynamicImage::ImageRgb8(rgb)),
+        }
+    }
+}
+
+impl Drop for SpilledImage {
+    fn drop(&mut self) {
+        // the files are only temporary, so there's nothing to be done if they're gone
+        let _ = std::fs::remove_file(&self.path);
+        if let Some(mask) = &self.mask {
+            let _ = std::fs::remove_file(mask);
+        }
+    }
+}
+
+/// When raster images are spilled to temporary files as they are added to a document
+/// (see [crate::Document::image_spill]), capping how much memory an image-heavy
+/// document holds while it is being built. This doesn't cap the memory used to write
+/// the document: the whole file is rendered in memory before it is written, so every
+/// spilled image is read back into memory then
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSpill {
+    /// The directory to write the temporary files to
+    pub directory: PathBuf,
+    /// How many bytes of image data the document may hold in memory; images added
+    /// beyond this are spilled
+    pub max_in_memory: usize,
+}
+
+impl ImageSpill {
+    /// Spill images to the system's temporary directory once the document holds more
+    /// than `max_in_memory` bytes of image data
+    pub fn new(max_in_memory: usize) -> ImageSpill {
+        ImageSpill {
+            directory: std::env::temp_dir(),
+            max_in_memory,
+        }
+    }
 }
 
 /// Images may be raster images (see [RasterImageType]), or vector images
@@ -23,17 +120,22 @@ pub enum RasterImageType {
 pub enum ImageType {
     /// A raster image
     Raster(RasterImageType),
-    /// A parsed SVG
-    SVG(Tree),
+    /// The source of an SVG, which has been validated by parsing it. The SVG is parsed
+    /// again when the document is written, as parsed SVGs can't be sent between threads
+    SVG(Vec<u8>),
 }
 
 /// An image with a corresponding width and height. Images may be raster images
 /// or vector SVGs. Each image has a corresponding size, which is generally the
 /// pixel size of the image. When an image is embedded within the document, the
 /// [crate::Page] contents determine the displayed size of the image (in Pt).
+///
+/// The image contents are reference counted, so images are cheap to clone. To use the
+/// same image in many documents, load it once and add a clone of it to each document.
+#[derive(Clone)]
 pub struct Image {
     /// The image type and cointents
-    pub image: ImageType,
+    pub image: Arc<ImageType>,
     /// The width of the image, nominally in pixels
     pub width: f32,
     /// The height of the image, nominally in pixels
@@ -52,7 +154,7 @@ impl Image {
 }
 
 struct EncodeOutput {
-    filter: Filter,
+    filter: Option<Filter>,
     bytes: Vec<u8>,
     mask: Option<Vec<u8>>,
 }
@@ -92,16 +194,13 @@ impl Image {
     /// Creates a vector file from raw bytes, assuming the bytes represent
     /// an `SVG`
     pub fn new_svg(data: &[u8]) -> Result<Image, PDFError> {
-        let opts = usvg::Options {
-            ..Default::default()
-        };
-        let tree = Tree::from_data(data, &opts.to_ref())?;
+        let tree = parse_svg(data)?;
         let size = tree.svg_node().size;
         let width = size.width() as f32;
         let height = size.height() as f32;
 
         Ok(Image {
-            image: ImageType::SVG(tree),
+            image: Arc::new(ImageType::SVG(data.to_vec())),
             width,
             height,
         })
@@ -134,7 +233,9 @@ impl Image {
                 let height = image.height() as f32;
 
                 Ok(Image {
-                    image: ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(path)),
+                    image: Arc::new(ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(
+                        path,
+                    ))),
                     width,
                     height,
                 })
@@ -151,36 +252,228 @@ impl Image {
         let width = image.width() as f32;
         let height = image.height() as f32;
         Ok(Image {
-            image: ImageType::Raster(RasterImageType::Image(image)),
+            image: Arc::new(ImageType::Raster(RasterImageType::Image(image))),
             width,
             height,
         })
     }
 
-    fn encode_raster(&self) -> Result<EncodeOutput, PDFError> {
-        match &self.image {
+    /// Downsample a raster image so that neither its width nor its height is larger
+    /// than `max_dimension` pixels, maintaining its aspect ratio. JPEGs remain JPEGs
+    /// (re-encoded at a high quality). Returns whether the image was changed; SVGs and
+    /// images which are already small enough are left as they are.
+    pub fn downsample(&mut self, max_dimension: u32) -> Result<bool, PDFError> {
+        const JPEG_QUALITY: u8 = 90;
+
+        if self.width <= max_dimension as f32 && self.height <= max_dimension as f32 {
+            return Ok(false);
+        }
+        let resize =
+            |image: &DynamicImage| image.resize(max_dimension, max_dimension, FilterType::Triangle);
+        let (image, jpeg) = match self.image.as_ref() {
+            ImageType::Raster(RasterImageType::Image(image)) => (resize(image), false),
+            ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(path)) => {
+                (resize(&image::open(path)?), true)
+            }
+            ImageType::Raster(RasterImageType::Jpeg(bytes)) => (
+                resize(&image::load_from_memory_with_format(
+                    bytes,
+                    image::ImageFormat::Jpeg,
+                )?),
+                true,
+            ),
+            ImageType::Raster(RasterImageType::Spilled(spilled)) => (
+                resize(&spilled.decode(self.width as u32, self.height as u32)?),
+                spilled.jpeg,
+            ),
+            ImageType::SVG(_) => return Ok(false),
+        };
+
+        self.width = image.width() as f32;
+        self.height = image.height() as f32;
+        self.image = Arc::new(ImageType::Raster(if jpeg {
+            RasterImageType::Jpeg(encode_jpeg(&image, JPEG_QUALITY)?)
+        } else {
+            RasterImageType::Image(image)
+        }));
+        Ok(true)
+    }
+
+    /// Re-encode a raster image as a JPEG with the given quality (from 1 to 100),
+    /// which is usually much smaller than the default lossless encoding at the cost of
+    /// some fidelity. Images with transparency are left as they are, as are images
+    /// whose JPEG encoding wouldn't be any smaller. Returns whether the image was
+    /// changed.
+    pub fn compress_jpeg(&mut self, quality: u8) -> Result<bool, PDFError> {
+        let (image, current_size) = match self.image.as_ref() {
+            ImageType::Raster(RasterImageType::Image(image)) => {
+                if image.color().has_alpha() {
+                    return Ok(false);
+                }
+                (
+                    image.clone(),
+                    self.encode_raster(Compression::default())?.bytes.len(),
+                )
+            }
+            ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(path)) => {
+                (image::open(path)?, std::fs::metadata(path)?.len() as usize)
+            }
+            ImageType::Raster(RasterImageType::Jpeg(bytes)) => (
+                image::load_from_memory_with_format(bytes, image::ImageFormat::Jpeg)?,
+                bytes.len(),
+            ),
+            ImageType::Raster(RasterImageType::Spilled(spilled)) => {
+                if spilled.mask.is_some() {
+                    return Ok(false);
+                }
+                (
+                    spilled.decode(self.width as u32, self.height as u32)?,
+                    std::fs::metadata(&spilled.path)?.len() as usize,
+                )
+            }
+            ImageType::SVG(_) => return Ok(false),
+        };
+
+        let bytes = encode_jpeg(&image, quality)?;
+        if bytes.len() >= current_size {
+            return Ok(false);
+        }
+        self.image = Arc::new(ImageType::Raster(RasterImageType::Jpeg(bytes)));
+        Ok(true)
+    }
+
+    /// Convert the colour of every pixel of a raster image (see [crate::ColourConversion]).
+    /// JPEGs remain JPEGs (re-encoded at a high quality). Returns whether the image was
+    /// changed; SVGs are left as they are.
+    pub fn convert_colours(&mut self, conversion: &ColourConversion) -> Result<bool, PDFError> {
+        const JPEG_QUALITY: u8 = 90;
+
+        let (image, jpeg) = match self.image.as_ref() {
+            ImageType::Raster(RasterImageType::Image(image)) => (image.clone(), false),
+            ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(path)) => {
+                (image::open(path)?, true)
+            }
+            ImageType::Raster(RasterImageType::Jpeg(bytes)) => (
+                image::load_from_memory_with_format(bytes, image::ImageFormat::Jpeg)?,
+                true,
+            ),
+            ImageType::Raster(RasterImageType::Spilled(spilled)) => (
+                spilled.decode(self.width as u32, self.height as u32)?,
+                spilled.jpeg,
+            ),
+            ImageType::SVG(_) => return Ok(false),
+        };
+
+        let has_alpha = image.color().has_alpha();
+        let mut pixels = image.to_rgba8();
+        for pixel in pixels.pixels_mut() {
+            let [r, g, b, a] = pixel.0;
+            let (r, g, b) = conversion.convert(Colour::new_rgb_bytes(r, g, b)).to_rgb();
+            let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            pixel.0 = [byte(r), byte(g), byte(b), a];
+        }
+        let image = if has_alpha {
+            DynamicImage::ImageRgba8(pixels)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(pixels).to_rgb8())
+        };
+
+        self.image = Arc::new(ImageType::Raster(if jpeg {
+            RasterImageType::Jpeg(encode_jpeg(&image, JPEG_QUALITY)?)
+        } else {
+            RasterImageType::Image(image)
+        }));
+        Ok(true)
+    }
+
+    /// Encode a raster image and write it to temporary files in `directory`, so that it
+    /// no longer takes up memory (unless other clones of the image share it). It is
+    /// read back when the document is written. Returns whether the image was spilled;
+    /// SVGs, JPEGs embedded from disk, and images which have already been spilled are
+    /// left as they are
+    pub fn spill<P: AsRef<Path>>(&mut self, directory: P) -> Result<bool, PDFError> {
+        static SPILLED: AtomicUsize = AtomicUsize::new(0);
+
+        match self.image.as_ref() {
+            ImageType::Raster(RasterImageType::Image(_))
+            | ImageType::Raster(RasterImageType::Jpeg(_)) => {}
+            _ => return Ok(false),
+        }
+        let encoded = self.encode_raster(Compression::default())?;
+
+        let name = format!(
+            "pdf-gen-{}-{}",
+            std::process::id(),
+            SPILLED.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = directory.as_ref().join(format!("{name}.bin"));
+        std::fs::write(&path, &encoded.bytes)?;
+        let mut spilled = SpilledImage {
+            path,
+            mask: None,
+            jpeg: encoded.filter == Some(Filter::DctDecode),
+        };
+        if let Some(mask) = &encoded.mask {
+            let path = directory.as_ref().join(format!("{name}-mask.bin"));
+            std::fs::write(&path, mask)?;
+            spilled.mask = Some(path);
+        }
+
+        self.image = Arc::new(ImageType::Raster(RasterImageType::Spilled(spilled)));
+        Ok(true)
+    }
+
+    /// Roughly how many bytes of image data the image holds in memory
+    pub(crate) fn memory_size(&self) -> usize {
+        match self.image.as_ref() {
+            ImageType::Raster(RasterImageType::Image(image)) => image.as_bytes().len(),
+            ImageType::Raster(RasterImageType::Jpeg(bytes)) => bytes.len(),
+            ImageType::SVG(data) => data.len(),
+            _ => 0,
+        }
+    }
+
+    /// Encode a raster image, compressing its pixels (and transparency mask) as given.
+    /// JPEGs are left as they are, as are the pixels of spilled images, which are
+    /// compressed when they are spilled
+    fn encode_raster(&self, compression: Compression) -> Result<EncodeOutput, PDFError> {
+        match self.image.as_ref() {
             ImageType::Raster(RasterImageType::DirectlyEmbeddableJpeg(path)) => {
                 let bytes = std::fs::read(&path)?;
                 Ok(EncodeOutput {
-                    filter: Filter::DctDecode,
+                    filter: Some(Filter::DctDecode),
                     bytes,
                     mask: None,
                 })
             }
+            ImageType::Raster(RasterImageType::Jpeg(bytes)) => Ok(EncodeOutput {
+                filter: Some(Filter::DctDecode),
+                bytes: bytes.clone(),
+                mask: None,
+            }),
+            ImageType::Raster(RasterImageType::Spilled(spilled)) => Ok(EncodeOutput {
+                filter: Some(if spilled.jpeg {
+                    Filter::DctDecode
+                } else {
+                    Filter::FlateDecode
+                }),
+                bytes: std::fs::read(&spilled.path)?,
+                mask: spilled.mask.as_ref().map(std::fs::read).transpose()?,
+            }),
             ImageType::Raster(RasterImageType::Image(image)) => {
                 use image::GenericImageView;
-                let level = CompressionLevel::DefaultLevel as u8;
 
                 let mask = image.color().has_alpha().then(|| {
                     let alphas: Vec<_> = image.pixels().map(|p| (p.2).0[3]).collect();
-                    compress_to_vec_zlib(&alphas, level)
+                    compression.compress(&alphas).0.into_owned()
                 });
 
-                let bytes = compress_to_vec_zlib(image.to_rgb8().as_raw(), level);
+                let rgb = image.to_rgb8();
+                let (bytes, filter) = compression.compress(rgb.as_raw());
 
                 Ok(EncodeOutput {
-                    filter: Filter::FlateDecode,
-                    bytes,
+                    filter,
+                    bytes: bytes.into_owned(),
                     mask,
                 })
             }
@@ -192,16 +485,19 @@ impl Image {
         &self,
         refs: &mut ObjectReferences,
         image_index: usize,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Result<(), PDFError> {
         let id = refs.gen(RefType::Image(image_index));
 
-        match &self.image {
+        match self.image.as_ref() {
             ImageType::Raster(_) => {
-                let encoded = self.encode_raster()?;
+                let encoded = self.encode_raster(compression)?;
 
                 let mut image = writer.image_xobject(id, encoded.bytes.as_slice());
-                image.filter(encoded.filter);
+                if let Some(filter) = encoded.filter {
+                    image.filter(filter);
+                }
                 image.width(self.width as i32);
                 image.height(self.height as i32);
                 image.color_space().device_rgb();
@@ -222,15 +518,21 @@ impl Image {
                     // unwrap will always be safe as the mask id is mapped from mask to start with
                     let mut s_mask =
                         writer.image_xobject(mask_id, encoded.mask.as_ref().unwrap().as_slice());
+                    // only images encoded from pixels have masks, which are compressed in
+                    // the same way as the pixels
+                    if let Some(filter) = encoded.filter {
+                        s_mask.filter(filter);
+                    }
                     s_mask.width(self.width as i32);
                     s_mask.height(self.height as i32);
                     s_mask.color_space().device_gray();
                     s_mask.bits_per_component(8);
                 }
             }
-            ImageType::SVG(tree) => {
+            ImageType::SVG(data) => {
+                let tree = parse_svg(data)?;
                 let next_id =
-                    svg2pdf::convert_tree_into(tree, svg2pdf::Options::default(), writer, id);
+                    svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), writer, id);
                 refs.set_next_id(next_id);
             }
         }
@@ -238,3 +540,22 @@ impl Image {
         Ok(())
     }
 }
+
+fn parse_svg(data: &[u8]) -> Result<Tree, PDFError> {
+    let opts = usvg::Options {
+        ..Default::default()
+    };
+    Tree::from_data(data, &opts.to_ref()).map_err(Into::into)
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, PDFError> {
+    let rgb = image.to_rgb8();
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, quality).encode(
+        rgb.as_raw(),
+        rgb.width(),
+        rgb.height(),
+        ColorType::Rgb8,
+    )?;
+    Ok(bytes)
+}
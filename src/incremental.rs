@@ -0,0 +1,470 @@
+use crate::rect::Rect;
+use crate::PDFError;
+use pdf_writer::Ref;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// An incremental update to an existing PDF which was written by this crate. The
+/// original bytes of the file are preserved exactly, and any new or changed objects
+/// are appended after them along with a new cross-reference section and trailer which
+/// points back to the original one. This is what signing workflows require, as
+/// anything which has already been signed must not change.
+///
+/// Objects are given as raw PDF syntax (i.e. `<< /Type /Annot ... >>`), without the
+/// surrounding `obj` / `endobj` keywords. Only files with classic cross-reference
+/// tables (such as those written by [crate::Document::write]) are supported.
+///
+/// Files which have already been updated can be updated again, as every earlier
+/// cross-reference section is read as well:
+///
+/// ```
+/// # use pdf_gen::{pagesize, Document, IncrementalUpdate, Page, Pt, Rect};
+/// # let mut doc = Document::default();
+/// # doc.add_page(Page::new(pagesize::LETTER, None));
+/// # let mut original = Vec::new();
+/// # doc.write(&mut original).unwrap();
+/// let position = Rect { x1: Pt(72.0), y1: Pt(72.0), x2: Pt(144.0), y2: Pt(90.0) };
+///
+/// let mut first = IncrementalUpdate::new(original).unwrap();
+/// let page = first.page_refs().unwrap()[0];
+/// let first_link = first.add_uri_link(page, position, "https://example.com").unwrap();
+/// let mut updated = Vec::new();
+/// first.write(&mut updated).unwrap();
+///
+/// // the page tree is only in the original section, and the page in the first update
+/// let mut second = IncrementalUpdate::new(updated).unwrap();
+/// let page = second.page_refs().unwrap()[0];
+/// let second_link = second.add_uri_link(page, position, "https://example.org").unwrap();
+/// let body = String::from_utf8_lossy(second.object(page).unwrap()).to_string();
+/// assert!(body.contains(&format!("{} 0 R", first_link.get())));
+/// assert!(body.contains(&format!("{} 0 R", second_link.get())));
+/// ```
+pub struct IncrementalUpdate {
+    original: Vec<u8>,
+    startxref: usize,
+    size: i32,
+    root: Ref,
+    info: Option<Ref>,
+    offsets: HashMap<i32, usize>,
+    objects: BTreeMap<i32, Vec<u8>>,
+}
+
+impl IncrementalUpdate {
+    /// Start an incremental update of the given file, reading its trailer and
+    /// cross-reference table
+    pub fn new(original: Vec<u8>) -> Result<IncrementalUpdate, PDFError> {
+        let startxref_pos =
+            rfind(&original, b"startxref").ok_or(PDFError::MalformedPdf("no startxref keyword"))?;
+        let startxref = Tokens::new(&original[startxref_pos + b"startxref".len()..])
+            .next_number()
+            .ok_or(PDFError::MalformedPdf(
+                "no cross-reference offset after startxref",
+            ))? as usize;
+
+        let trailer_pos = rfind(&original[..startxref_pos], b"trailer")
+            .ok_or(PDFError::MalformedPdf("no trailer"))?;
+        let trailer = &original[trailer_pos..startxref_pos];
+        let size = dict_number(trailer, b"/Size")
+            .ok_or(PDFError::MalformedPdf("the trailer has no /Size"))?;
+        let root = dict_ref(trailer, b"/Root")
+            .ok_or(PDFError::MalformedPdf("the trailer has no /Root"))?;
+        let info = dict_ref(trailer, b"/Info");
+
+        // read every cross-reference section, from the newest back through each
+        // trailer's /Prev, so that objects which haven't changed since an earlier update
+        // can be found. Newer entries take precedence over older ones
+        let mut entries: HashMap<i32, Option<usize>> = HashMap::new();
+        let mut visited: Vec<usize> = Vec::new();
+        let mut section = Some(startxref);
+        while let Some(offset) = section {
+            if visited.contains(&offset) {
+                return Err(PDFError::MalformedPdf(
+                    "the cross-reference sections refer back to each other",
+                ));
+            }
+            visited.push(offset);
+            let (section_entries, prev) = parse_xref(original.get(offset..).ok_or(
+                PDFError::MalformedPdf("the cross-reference offset is out of range"),
+            )?)?;
+            for (id, entry) in section_entries {
+                entries.entry(id).or_insert(entry);
+            }
+            section = prev;
+        }
+        let offsets = entries
+            .into_iter()
+            .filter_map(|(id, offset)| Some((id, offset?)))
+            .collect();
+
+        Ok(IncrementalUpdate {
+            original,
+            startxref,
+            size: size as i32,
+            root,
+            info,
+            offsets,
+            objects: BTreeMap::new(),
+        })
+    }
+
+    /// Allocate a reference for a new object. The object must then be given with
+    /// [IncrementalUpdate::set_object]
+    pub fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.size);
+        self.size += 1;
+        id
+    }
+
+    /// Add a new object or replace an existing one. Replaced objects are superseded by
+    /// the update, but their original bytes remain in the file
+    pub fn set_object(&mut self, id: Ref, body: Vec<u8>) {
+        if id.get() >= self.size {
+            self.size = id.get() + 1;
+        }
+        self.objects.insert(id.get(), body);
+    }
+
+    /// Read the current body of an object (without the `obj` / `endobj` keywords),
+    /// taking any changes made in this update into account
+    pub fn object(&self, id: Ref) -> Option<&[u8]> {
+        if let Some(body) = self.objects.get(&id.get()) {
+            return Some(body.as_slice());
+        }
+        let offset = *self.offsets.get(&id.get())?;
+        let object = self.original.get(offset..)?;
+        let start = find(object, b"obj")? + b"obj".len();
+        let end = find(object, b"endobj")?;
+        object.get(start..end).map(trim)
+    }
+
+    /// Read and decode the data of a stream object, inflating it if it is compressed
+    /// with `/FlateDecode` (the only filter this crate writes content with)
+    pub(crate) fn stream(&self, id: Ref) -> Result<Vec<u8>, PDFError> {
+        let body = self
+            .object(id)
+            .ok_or(PDFError::MalformedPdf("the stream object is missing"))?;
+        let keyword = find(body, b"stream").ok_or(PDFError::MalformedPdf("not a stream"))?;
+        let dict = &body[..keyword];
+        let length = dict_number(dict, b"/Length")
+            .ok_or(PDFError::MalformedPdf("the stream has no /Length"))?
+            as usize;
+
+        let mut start = keyword + b"stream".len();
+        if body.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if body.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let data = body
+            .get(start..start + length)
+            .ok_or(PDFError::MalformedPdf(
+                "the stream is shorter than its /Length",
+            ))?;
+
+        if find_key(dict, b"/FlateDecode").is_some() {
+            miniz_oxide::inflate::decompress_to_vec_zlib(data)
+                .map_err(|_| PDFError::MalformedPdf("the stream can't be inflated"))
+        } else {
+            Ok(data.to_vec())
+        }
+    }
+
+    /// The reference to the document's catalog
+    pub(crate) fn root(&self) -> Ref {
+        self.root
+    }
+
+    /// The references to each page in the document, in order
+    pub fn page_refs(&self) -> Result<Vec<Ref>, PDFError> {
+        let root = self
+            .object(self.root)
+            .ok_or(PDFError::MalformedPdf("the catalog is missing"))?;
+        let pages =
+            dict_ref(root, b"/Pages").ok_or(PDFError::MalformedPdf("the catalog has no /Pages"))?;
+        let pages = self
+            .object(pages)
+            .ok_or(PDFError::MalformedPdf("the page tree is missing"))?;
+        let kids =
+            find(pages, b"/Kids").ok_or(PDFError::MalformedPdf("the page tree has no /Kids"))?;
+        let kids = &pages[kids + b"/Kids".len()..];
+        let start = find(kids, b"[").ok_or(PDFError::MalformedPdf("/Kids is not an array"))?;
+        let end = find(kids, b"]").ok_or(PDFError::MalformedPdf("unterminated /Kids array"))?;
+
+        let mut tokens = Tokens::new(&kids[start + 1..end]);
+        let mut refs = Vec::new();
+        while let Some(r) = tokens.next_ref() {
+            refs.push(r);
+        }
+        Ok(refs)
+    }
+
+    /// Add an annotation to a page, returning the reference to the new annotation. The
+    /// annotation is a dictionary such as `<< /Type /Annot /Subtype /Text ... >>`, and
+    /// the page is rewritten to include it in its `/Annots` array
+    pub fn add_annotation(&mut self, page: Ref, annotation: Vec<u8>) -> Result<Ref, PDFError> {
+        let body = self
+            .object(page)
+            .ok_or(PDFError::MalformedPdf("the page to annotate is missing"))?
+            .to_vec();
+
+        let id = self.alloc();
+        let reference = format!(" {} 0 R", id.get());
+        let mut updated = Vec::with_capacity(body.len() + reference.len() + 10);
+        match find(&body, b"/Annots") {
+            Some(annots) => {
+                let open = annots
+                    + find(&body[annots..], b"[")
+                        .ok_or(PDFError::MalformedPdf("the page's /Annots is not an array"))?;
+                updated.extend_from_slice(&body[..=open]);
+                updated.extend_from_slice(reference.as_bytes());
+                updated.extend_from_slice(&body[open + 1..]);
+            }
+            None => {
+                let close = rfind(&body, b">>")
+                    .ok_or(PDFError::MalformedPdf("the page is not a dictionary"))?;
+                updated.extend_from_slice(&body[..close]);
+                writeln!(&mut updated, "/Annots [{}]", reference.trim_start())?;
+                updated.extend_from_slice(&body[close..]);
+            }
+        }
+
+        self.set_object(id, annotation);
+        self.set_object(page, updated);
+        Ok(id)
+    }
+
+    /// Add a link to a page which opens the given URL when clicked
+    pub fn add_uri_link(&mut self, page: Ref, position: Rect, uri: &str) -> Result<Ref, PDFError> {
+        let mut annotation = Vec::new();
+        write!(
+            &mut annotation,
+            "<<\n  /Type /Annot\n  /Subtype /Link\n  /Rect [{} {} {} {}]\n  /Border [0 0 0]\n  /A << /S /URI /URI (",
+            position.x1, position.y1, position.x2, position.y2
+        )?;
+        for &b in uri.as_bytes() {
+            if matches!(b, b'\\' | b'(' | b')') {
+                annotation.push(b'\\');
+            }
+            annotation.push(b);
+        }
+        annotation.extend_from_slice(b") >>\n>>");
+        self.add_annotation(page, annotation)
+    }
+
+    /// Write the original file followed by the update
+    pub fn write<W: Write>(self, mut w: W) -> Result<(), PDFError> {
+        let mut out = self.original;
+        if !out.ends_with(b"\n") {
+            out.push(b'\n');
+        }
+
+        let mut offsets: Vec<(i32, usize)> = Vec::with_capacity(self.objects.len());
+        for (id, body) in self.objects.iter() {
+            offsets.push((*id, out.len()));
+            writeln!(&mut out, "{id} 0 obj")?;
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n\n");
+        }
+
+        // the objects are sorted by id, so group them into subsections of
+        // consecutive ids
+        let xref = out.len();
+        out.extend_from_slice(b"xref\n");
+        let mut start = 0;
+        while start < offsets.len() {
+            let mut end = start + 1;
+            while end < offsets.len() && offsets[end].0 == offsets[end - 1].0 + 1 {
+                end += 1;
+            }
+            writeln!(&mut out, "{} {}", offsets[start].0, end - start)?;
+            for (_, offset) in offsets[start..end].iter() {
+                write!(&mut out, "{offset:010} 00000 n\r\n")?;
+            }
+            start = end;
+        }
+
+        write!(
+            &mut out,
+            "trailer\n<<\n  /Size {}\n  /Root {} 0 R\n",
+            self.size,
+            self.root.get()
+        )?;
+        if let Some(info) = self.info {
+            writeln!(&mut out, "  /Info {} 0 R", info.get())?;
+        }
+        write!(
+            &mut out,
+            "  /Prev {}\n>>\nstartxref\n{}\n%%EOF",
+            self.startxref, xref
+        )?;
+
+        w.write_all(out.as_slice()).map_err(Into::into)
+    }
+}
+
+/// Parse a classic cross-reference section into a map of object numbers to offsets (or
+/// `None` for objects which the section frees), along with the offset of the previous
+/// section given by its trailer's `/Prev`, if any
+fn parse_xref(xref: &[u8]) -> Result<(HashMap<i32, Option<usize>>, Option<usize>), PDFError> {
+    let mut tokens = Tokens::new(xref);
+    if tokens.next() != Some(b"xref".as_slice()) {
+        return Err(PDFError::MalformedPdf(
+            "the cross-reference offset doesn't point to a cross-reference table",
+        ));
+    }
+
+    let mut offsets = HashMap::new();
+    loop {
+        let first = match tokens.peek() {
+            Some(b"trailer") | None => break,
+            Some(_) => tokens.next_number().ok_or(PDFError::MalformedPdf(
+                "malformed cross-reference subsection",
+            ))?,
+        };
+        let count = tokens.next_number().ok_or(PDFError::MalformedPdf(
+            "malformed cross-reference subsection",
+        ))?;
+        for id in first..first + count {
+            let offset = tokens.next_number();
+            let _generation = tokens.next();
+            let kind = tokens.next();
+            match (offset, kind) {
+                (Some(offset), Some(b"n")) => {
+                    offsets.insert(id as i32, Some(offset as usize));
+                }
+                (Some(_), Some(b"f")) => {
+                    offsets.insert(id as i32, None);
+                }
+                _ => return Err(PDFError::MalformedPdf("malformed cross-reference entry")),
+            }
+        }
+    }
+
+    let prev = match tokens.next() {
+        Some(b"trailer") => {
+            let trailer = &xref[tokens.position()..];
+            let trailer = &trailer[..find(trailer, b"startxref").unwrap_or(trailer.len())];
+            dict_number(trailer, b"/Prev").map(|prev| prev as usize)
+        }
+        _ => None,
+    };
+    Ok((offsets, prev))
+}
+
+/// Find the number following a key in a dictionary
+fn dict_number(dict: &[u8], key: &[u8]) -> Option<i64> {
+    let pos = find_key(dict, key)?;
+    Tokens::new(&dict[pos + key.len()..]).next_number()
+}
+
+/// Find the indirect reference following a key in a dictionary
+pub(crate) fn dict_ref(dict: &[u8], key: &[u8]) -> Option<Ref> {
+    let pos = find_key(dict, key)?;
+    Tokens::new(&dict[pos + key.len()..]).next_ref()
+}
+
+/// Find a key in a dictionary, making sure it isn't just the start of a longer name
+pub(crate) fn find_key(dict: &[u8], key: &[u8]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(pos) = find(&dict[from..], key) {
+        let pos = from + pos;
+        match dict.get(pos + key.len()) {
+            Some(b) if b.is_ascii_alphanumeric() => from = pos + key.len(),
+            _ => return Some(pos),
+        }
+    }
+    None
+}
+
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+pub(crate) fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map(|end| end + 1)
+        .unwrap_or(start);
+    &bytes[start..end]
+}
+
+/// A very small tokenizer for the parts of PDF syntax needed to read trailers and
+/// cross-reference tables. Delimiters are returned as their own tokens.
+pub(crate) struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Tokens<'a> {
+        Tokens { bytes, pos: 0 }
+    }
+
+    /// How far into the bytes the tokenizer has read
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn peek(&mut self) -> Option<&'a [u8]> {
+        let pos = self.pos;
+        let token = self.next();
+        self.pos = pos;
+        token
+    }
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        let start = self.pos;
+        if is_delimiter(self.bytes[start]) {
+            self.pos += 1;
+            return Some(&self.bytes[start..self.pos]);
+        }
+        self.pos += 1;
+        while self.pos < self.bytes.len()
+            && !self.bytes[self.pos].is_ascii_whitespace()
+            && !is_delimiter(self.bytes[self.pos])
+        {
+            self.pos += 1;
+        }
+        Some(&self.bytes[start..self.pos])
+    }
+
+    fn next_number(&mut self) -> Option<i64> {
+        std::str::from_utf8(self.next()?).ok()?.parse().ok()
+    }
+
+    pub(crate) fn next_ref(&mut self) -> Option<Ref> {
+        let id = self.next_number()?;
+        let _generation = self.next_number()?;
+        match self.next()? {
+            b"R" => Some(Ref::new(id as i32)),
+            _ => None,
+        }
+    }
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'[' | b']' | b'<' | b'>' | b'(' | b')' | b'/' | b'{' | b'}' | b'%'
+    )
+}
@@ -1,4 +1,5 @@
 use crate::refs::{ObjectReferences, RefType};
+use chrono::prelude::*;
 use pdf_writer::{Date as PDate, PdfWriter, TextStr};
 
 /// General document metatdata such as title, author, etc
@@ -45,7 +46,37 @@ impl Info {
         self
     }
 
-    pub(crate) fn write(&self, refs: &mut ObjectReferences, writer: &mut PdfWriter) {
+    /// Set the title of the info block, consuming and returning `self` so that calls
+    /// can be chained without a trailing `.clone()`
+    pub fn with_title<S: ToString>(mut self, title: S) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Set the author of the info block, consuming and returning `self`
+    pub fn with_author<S: ToString>(mut self, author: S) -> Self {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    /// Set the subject of the info block, consuming and returning `self`
+    pub fn with_subject<S: ToString>(mut self, subject: S) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Set the keywords of the info block, consuming and returning `self`
+    pub fn with_keywords<S: ToString>(mut self, keywords: S) -> Self {
+        self.keywords = Some(keywords.to_string());
+        self
+    }
+
+    pub(crate) fn write(
+        &self,
+        refs: &mut ObjectReferences,
+        writer: &mut PdfWriter,
+        now: &DateTime<Local>,
+    ) {
         let id = refs.gen(RefType::Info);
         let mut info = writer.document_info(id);
 
@@ -61,25 +92,25 @@ impl Info {
         if let Some(keywords) = &self.keywords {
             info.keywords(TextStr(keywords.as_str()));
         }
-        info.creator(TextStr(concat!(
-            env!("CARGO_PKG_NAME"),
-            " v",
-            env!("CARGO_PKG_VERSION")
-        )));
-
-        use chrono::prelude::*;
-        let now = Local::now();
-        let offset = now.offset().fix();
-        let offset_hours = offset.local_minus_utc() / (60 * 60);
-        let offset_minutes = ((offset.local_minus_utc() - (offset_hours * (60 * 60))) / 60).abs();
-        let date = PDate::new(now.year() as u16)
-            .month(now.month() as u8)
-            .day(now.day() as u8)
-            .hour(now.hour() as u8)
-            .minute(now.minute() as u8)
-            .second(now.second() as u8)
-            .utc_offset_hour(offset_hours as i8)
-            .utc_offset_minute(offset_minutes as u8);
-        info.creation_date(date);
+        info.creator(TextStr(CREATOR));
+        info.creation_date(pdf_date(now));
     }
 }
+
+/// The application recorded as having created documents
+pub(crate) const CREATOR: &str = concat!(env!("CARGO_PKG_NAME"), " v", env!("CARGO_PKG_VERSION"));
+
+/// Convert a timestamp into a PDF date, keeping its UTC offset
+pub(crate) fn pdf_date(now: &DateTime<Local>) -> PDate {
+    let offset = now.offset().fix();
+    let offset_hours = offset.local_minus_utc() / (60 * 60);
+    let offset_minutes = ((offset.local_minus_utc() - (offset_hours * (60 * 60))) / 60).abs();
+    PDate::new(now.year() as u16)
+        .month(now.month() as u8)
+        .day(now.day() as u8)
+        .hour(now.hour() as u8)
+        .minute(now.minute() as u8)
+        .second(now.second() as u8)
+        .utc_offset_hour(offset_hours as i8)
+        .utc_offset_minute(offset_minutes as u8)
+}
@@ -0,0 +1,148 @@
+use crate::document::Document;
+use crate::page::{pagesize, Page, PageSize};
+use crate::rect::Rect;
+use crate::units::Pt;
+use id_arena::Id;
+
+/// A sheet of evenly spaced, identically sized labels, such as address labels or asset
+/// tags. Labels are numbered left-to-right, then top-to-bottom. Presets are provided for
+/// some common label sheets, i.e. [LabelSheet::AVERY_5160].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelSheet {
+    /// The size of the sheet of labels
+    pub page_size: PageSize,
+    /// The width and height of each label
+    pub label_size: (Pt, Pt),
+    /// The distance from the top edge of the sheet to the top edge of the first row
+    pub top_margin: Pt,
+    /// The distance from the left edge of the sheet to the left edge of the first column
+    pub left_margin: Pt,
+    /// The distance between the left edges of labels in adjacent columns
+    pub horizontal_pitch: Pt,
+    /// The distance between the top edges of labels in adjacent rows
+    pub vertical_pitch: Pt,
+    /// The number of labels across the sheet
+    pub columns: usize,
+    /// The number of labels down the sheet
+    pub rows: usize,
+}
+
+impl LabelSheet {
+    /// Avery 5160 / 8160: 30 address labels (1" x 2⅝") on US Letter paper
+    pub const AVERY_5160: LabelSheet = LabelSheet {
+        page_size: pagesize::LETTER,
+        label_size: (Pt(2.625 * 72.0), Pt(1.0 * 72.0)),
+        top_margin: Pt(0.5 * 72.0),
+        left_margin: Pt(0.1875 * 72.0),
+        horizontal_pitch: Pt(2.75 * 72.0),
+        vertical_pitch: Pt(1.0 * 72.0),
+        columns: 3,
+        rows: 10,
+    };
+
+    /// Avery 5163 / 8163: 10 shipping labels (2" x 4") on US Letter paper
+    pub const AVERY_5163: LabelSheet = LabelSheet {
+        page_size: pagesize::LETTER,
+        label_size: (Pt(4.0 * 72.0), Pt(2.0 * 72.0)),
+        top_margin: Pt(0.5 * 72.0),
+        left_margin: Pt(0.15625 * 72.0),
+        horizontal_pitch: Pt(4.1875 * 72.0),
+        vertical_pitch: Pt(2.0 * 72.0),
+        columns: 2,
+        rows: 5,
+    };
+
+    /// Avery 5167 / 8167: 80 return address labels (½" x 1¾") on US Letter paper
+    pub const AVERY_5167: LabelSheet = LabelSheet {
+        page_size: pagesize::LETTER,
+        label_size: (Pt(1.75 * 72.0), Pt(0.5 * 72.0)),
+        top_margin: Pt(0.5 * 72.0),
+        left_margin: Pt(0.3 * 72.0),
+        horizontal_pitch: Pt(2.05 * 72.0),
+        vertical_pitch: Pt(0.5 * 72.0),
+        columns: 4,
+        rows: 20,
+    };
+
+    /// Avery L7160: 21 address labels (63.5mm x 38.1mm) on A4 paper
+    pub const AVERY_L7160: LabelSheet = LabelSheet {
+        page_size: pagesize::A4,
+        label_size: (Pt(63.5 * 72.0 / 25.4), Pt(38.1 * 72.0 / 25.4)),
+        top_margin: Pt(15.15 * 72.0 / 25.4),
+        left_margin: Pt(7.2 * 72.0 / 25.4),
+        horizontal_pitch: Pt(66.04 * 72.0 / 25.4),
+        vertical_pitch: Pt(38.1 * 72.0 / 25.4),
+        columns: 3,
+        rows: 7,
+    };
+
+    /// The number of labels on each sheet
+    pub fn labels_per_sheet(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// Calculate the bounding box of the label with the given index on a sheet. Indices
+    /// greater than or equal to [LabelSheet::labels_per_sheet] wrap around to the start
+    /// of the sheet.
+    pub fn label_rect(&self, index: usize) -> Rect {
+        let index = index % self.labels_per_sheet().max(1);
+        let column = index % self.columns.max(1);
+        let row = index / self.columns.max(1);
+
+        let (_, page_height) = self.page_size;
+        let (width, height) = self.label_size;
+        let x1 = self.left_margin + self.horizontal_pitch * column as f32;
+        let y2 = page_height - self.top_margin - self.vertical_pitch * row as f32;
+        Rect {
+            x1,
+            y1: y2 - height,
+            x2: x1 + width,
+            y2,
+        }
+    }
+
+    /// Generate as many sheets of labels as are needed for all the items, calling `f`
+    /// with the page and bounding box of the label for each item. Returns the Ids of the
+    /// pages that were added to the document.
+    pub fn layout<T, I, F>(&self, document: &mut Document, items: I, f: F) -> Vec<Id<Page>>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&Document, &mut Page, Rect, T),
+    {
+        self.layout_skipping(document, 0, items, f)
+    }
+
+    /// Like [LabelSheet::layout], but skips the first `skip` labels on the first sheet so
+    /// that partially used sheets can be printed on
+    pub fn layout_skipping<T, I, F>(
+        &self,
+        document: &mut Document,
+        skip: usize,
+        items: I,
+        mut f: F,
+    ) -> Vec<Id<Page>>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&Document, &mut Page, Rect, T),
+    {
+        let per_sheet = self.labels_per_sheet().max(1);
+        let mut ids = Vec::new();
+        let mut page: Option<Page> = None;
+        let mut index = skip % per_sheet;
+        for item in items.into_iter() {
+            let current = page.get_or_insert_with(|| Page::new(self.page_size, None));
+            f(document, current, self.label_rect(index), item);
+
+            index += 1;
+            if index >= per_sheet {
+                // unwrap is safe, we just inserted the page above
+                ids.push(document.add_page(page.take().unwrap()));
+                index = 0;
+            }
+        }
+        if let Some(page) = page {
+            ids.push(document.add_page(page));
+        }
+        ids
+    }
+}
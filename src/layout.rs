@@ -1,10 +1,15 @@
+use crate::artifact::Artifact;
 use crate::colour::Colour;
 use crate::document::Document;
+use crate::error::{excerpt, PDFError};
 use crate::font::Font;
+use crate::form::FormXObject;
 use crate::page::*;
 use crate::rect::Rect;
+use crate::run::{TextEffects, TextRun};
+use crate::shaping::text_advances;
+use crate::style::{Alignment, OpticalMargins, TextStyle};
 use crate::units::Pt;
-use owned_ttf_parser::AsFaceRef;
 
 /// Margins are used when laying out objects on a page. There is no control
 /// preventing objects on pages to overflow the margins—the margins are there
@@ -97,20 +102,84 @@ impl Margins {
     }
 }
 
+/// A point on a rect (one of its corners, the middle of one of its edges, or its centre)
+/// that something can be positioned relative to, such as placing a logo in the top-right
+/// corner of a page's content box
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Position something of the given size (width, height) within `rect` at the anchor.
+    /// The offset (x, y) moves it inwards, away from the anchored edges (i.e. for
+    /// [Anchor::TopRight], a positive x moves it left and a positive y moves it down);
+    /// along an axis that is centred, a positive offset moves it right or up.
+    ///
+    /// ```
+    /// use pdf_gen::layout::Anchor;
+    /// use pdf_gen::{Pt, Rect};
+    ///
+    /// let page = Rect { x1: Pt(0.0), y1: Pt(0.0), x2: Pt(612.0), y2: Pt(792.0) };
+    /// let logo = Anchor::TopRight.position((Pt(100.0), Pt(50.0)), &page, (Pt(36.0), Pt(36.0)));
+    /// assert_eq!(logo, Rect { x1: Pt(476.0), y1: Pt(706.0), x2: Pt(576.0), y2: Pt(756.0) });
+    /// ```
+    pub fn position(&self, size: (Pt, Pt), rect: &Rect, offset: (Pt, Pt)) -> Rect {
+        let (width, height) = size;
+        let (dx, dy) = offset;
+        let x1 = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => rect.x1 + dx,
+            Anchor::Top | Anchor::Centre | Anchor::Bottom => {
+                rect.x1 + (rect.x2 - rect.x1 - width) / 2.0 + dx
+            }
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => rect.x2 - width - dx,
+        };
+        let y1 = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => rect.y2 - height - dy,
+            Anchor::Left | Anchor::Centre | Anchor::Right => {
+                rect.y1 + (rect.y2 - rect.y1 - height) / 2.0 + dy
+            }
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => rect.y1 + dy,
+        };
+        Rect {
+            x1,
+            y1,
+            x2: x1 + width,
+            y2: y1 + height,
+        }
+    }
+
+    /// The point on `rect` that this anchor refers to
+    pub fn point(&self, rect: &Rect) -> (Pt, Pt) {
+        let position = self.position((Pt(0.0), Pt(0.0)), rect, (Pt(0.0), Pt(0.0)));
+        (position.x1, position.y1)
+    }
+}
+
 /// Calculates the coordinates of where text can start on a page to be just within the top left
 /// margin, taking into account the ascending height of the font and the font size. Text is laid
 /// out according to the `ContentBox` of the page, which is usually derived from the page size
 /// and accompanying margins.
-pub fn baseline_start(page: &Page, font: &Font, size: Pt) -> (Pt, Pt) {
-    let scaling: Pt = size / Pt(font.face.as_face_ref().units_per_em() as f32);
-    let ascent: Pt = scaling * font.face.as_face_ref().ascender() as f32;
-    let x = page.content_box.x1;
-    let y = page.content_box.y2 - ascent;
+pub fn baseline_start<T: ContentTarget + ?Sized>(page: &T, font: &Font, size: Pt) -> (Pt, Pt) {
+    let scaling: Pt = size / Pt(font.units_per_em() as f32);
+    let ascent: Pt = scaling * font.ascender() as f32;
+    let content_box = page.content_box();
+    let x = content_box.x1;
+    let y = content_box.y2 - ascent;
     (x, y)
 }
 
-/// Lays out text in a character-by-character manner, splitting all words at the exact end
-/// and not adding anything to the left. i.e. if the input were "asdf asdf" and the page
+/// Lays out text in a character-by-character manner, wrapping lines where the document's
+/// [crate::LineBreaker] allows. By default this splits all words at the exact end and
+/// doesn't add anything to the left. i.e. if the input were "asdf asdf" and the page
 /// only fit 6 characters wide, this will split the text in: "asdf a\nsdf". Applies these
 /// spans to the page contents, keeping colours intact for all rendered text.
 ///
@@ -118,44 +187,481 @@ pub fn baseline_start(page: &Page, font: &Font, size: Pt) -> (Pt, Pt) {
 /// this function finishes is text that would have overflowed the page. Normally you would
 /// then create a new page and layout the text on that page as well.
 ///
-/// Returns the page coordinates of where the layout stopped, in case you ended up short
-pub fn layout_text(
+/// Returns the page coordinates of where the layout stopped, in case you ended up short.
+/// Returns an error if the text contains a character which the font has no glyph for
+/// and the font also lacks a replacement glyph; in that case nothing is added to the page
+/// and the text is left unchanged.
+///
+/// Characters which the font has no glyph for are drawn in a fallback font instead if a
+/// [crate::FontStack] has been registered for the font (see
+/// [Document::set_font_stack]), splitting the text into spans which switch fonts.
+pub fn layout_text<T: ContentTarget + ?Sized>(
     document: &Document,
-    page: &mut Page,
+    page: &mut T,
     start: (Pt, Pt),
     text: &mut Vec<(String, Colour, SpanFont)>,
     wrap_offset: Pt,
     bounding_box: Rect,
-) -> (Pt, Pt) {
+) -> Result<(Pt, Pt), PDFError> {
+    let mut with_metadata: Vec<(String, Colour, SpanFont, SpanMetadata)> = text
+        .drain(..)
+        .map(|(span, colour, font)| (span, colour, font, SpanMetadata::default()))
+        .collect();
+    let result = layout_text_with_metadata(
+        document,
+        page,
+        start,
+        &mut with_metadata,
+        wrap_offset,
+        bounding_box,
+    );
+    text.extend(
+        with_metadata
+            .into_iter()
+            .map(|(span, colour, font, _)| (span, colour, font)),
+    );
+    result
+}
+
+/// The same as [layout_text], except that each input span carries [SpanMetadata] which
+/// is copied onto every span generated from it. Any text left over after layout keeps
+/// its metadata.
+pub fn layout_text_with_metadata<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, Colour, SpanFont, SpanMetadata)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+) -> Result<(Pt, Pt), PDFError> {
+    layout_text_with_exclusions(document, page, start, text, wrap_offset, bounding_box, &[])
+}
+
+/// The same as [layout_text_with_metadata], except that lines are shortened to wrap
+/// around the exclusions: regions of the bounding box (such as a photo) which text
+/// shouldn't be laid out over (see [layout_runs])
+pub fn layout_text_with_exclusions<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, Colour, SpanFont, SpanMetadata)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    exclusions: &[Rect],
+) -> Result<(Pt, Pt), PDFError> {
+    let mut runs: Vec<(String, TextRun)> = text
+        .drain(..)
+        .map(|(span, colour, font, metadata)| (span, TextRun::from((colour, font, metadata))))
+        .collect();
+    let result = layout_runs(
+        document,
+        page,
+        start,
+        &mut runs,
+        wrap_offset,
+        bounding_box,
+        exclusions,
+    );
+    text.extend(
+        runs.into_iter()
+            .map(|(span, run)| (span, run.colour, run.font, run.metadata)),
+    );
+    result
+}
+
+/// A line of text laid out by one of the layout functions, as given to the `on_line`
+/// callback of [layout_runs_with_lines] and [layout_styled_text_with_lines]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaidOutLine {
+    /// The 0-based index of the line among the lines laid out by the call
+    pub index: usize,
+    /// The region covered by the line's text, from the lowest descent to the highest
+    /// ascent of its fonts
+    pub rect: Rect,
+    /// The baseline of the line
+    pub baseline: Pt,
+    /// The text on the line
+    pub text: String,
+}
+
+/// Lays out runs of text, each in its own character style (see [TextRun]), wrapping them
+/// as with [layout_text]. Any text left over after layout keeps its style.
+///
+/// With the `shaping` feature, text is measured and drawn with the glyphs that its font
+/// shapes it into (see [crate::ShapedGlyph]), so ligatures, contextual forms, kerning,
+/// and combining marks come out as the font intends.
+///
+/// Runs of different sizes on the same line share its baseline. Each line after the first
+/// is spaced from the one above it by the largest ascent (and leading) of the runs on it
+/// and the largest descent of the runs on the line above, so that larger runs don't
+/// collide with the lines around them. The first line's baseline is always `start`.
+///
+/// Lines are shortened to wrap around the exclusions: regions of the bounding box (such
+/// as a photo) which text shouldn't be laid out over. Exclusions in the right half of
+/// the bounding box shorten the lines that they overlap from the right, and those in the
+/// left half shorten them from the left. Lines which would be left with less than an em
+/// of space are skipped.
+///
+/// Runs with ruby (see [TextRun::ruby]) are moved to the next line whole rather than
+/// being broken, and make their line taller to fit the annotation above them. They are
+/// drawn in their own font, without its font stack or synthesized small capitals.
+pub fn layout_runs<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, TextRun)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    exclusions: &[Rect],
+) -> Result<(Pt, Pt), PDFError> {
+    layout_runs_with_lines(
+        document,
+        page,
+        start,
+        text,
+        wrap_offset,
+        bounding_box,
+        exclusions,
+        |_, _| {},
+    )
+}
+
+/// The same as [layout_runs], except that `on_line` is called with each line that is
+/// laid out (empty lines aside), so that things can be drawn aligned with the lines,
+/// such as zebra striping, line numbers, or change bars. The callbacks are all made
+/// before the text is added, so anything they draw is beneath the text.
+///
+/// ```
+/// # use pdf_gen::{layout, Colour, Document, Font, Page, Pt, Rect, SpanFont, TextRun};
+/// # use pdf_gen::drawing::DrawStyle;
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let font = doc.add_font(font);
+/// # let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+/// # let bounding_box = page.content_box;
+/// # let start = layout::baseline_start(&page, &doc.fonts[font], Pt(10.0));
+/// let body = TextRun::new(SpanFont { id: font, size: Pt(10.0) });
+/// let mut text = vec![body.text("One\nTwo\nThree\nFour")];
+/// layout::layout_runs_with_lines(&doc, &mut page, start, &mut text, Pt(0.0), bounding_box, &[],
+///     |page: &mut Page, line| {
+///         if line.index % 2 == 1 {
+///             let stripe = Rect { x1: bounding_box.x1, x2: bounding_box.x2, ..line.rect };
+///             page.add_shape(&stripe, &DrawStyle::filled(Colour::new_grey(0.9)));
+///         }
+///     },
+/// ).unwrap();
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn layout_runs_with_lines<T, F>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, TextRun)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    exclusions: &[Rect],
+    on_line: F,
+) -> Result<(Pt, Pt), PDFError>
+where
+    T: ContentTarget + ?Sized,
+    F: FnMut(&mut T, &LaidOutLine),
+{
+    let mut origins: Vec<Origin> = (0..text.len()).map(Origin::new).collect();
+    layout_queue(
+        document,
+        page,
+        start,
+        text,
+        &mut origins,
+        wrap_offset,
+        bounding_box,
+        exclusions,
+        on_line,
+    )
+}
+
+/// Lay out a queue of runs as [layout_runs_with_lines] does, keeping track of where each
+/// run left in the queue came from in the text that layout was given (see [Origin])
+#[allow(clippy::too_many_arguments)]
+fn layout_queue<T, F>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, TextRun)>,
+    origins: &mut Vec<Origin>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    exclusions: &[Rect],
+    mut on_line: F,
+) -> Result<(Pt, Pt), PDFError>
+where
+    T: ContentTarget + ?Sized,
+    F: FnMut(&mut T, &LaidOutLine),
+{
     if text.is_empty() {
-        return start;
+        return Ok(start);
     }
-
-    const TABSIZE: usize = 4;
+    check_glyphs(document, text)?;
 
     let mut x = start.0;
     let mut y = start.1;
 
     let mut spans: Vec<SpanLayout> = Vec::with_capacity(text.len());
+    // whether the current line has any text on it yet, and whether that text ends with
+    // whitespace (so that the line can be broken before the next input span)
+    let mut line_has_content = false;
+    let mut follows_whitespace = false;
+    // how many characters into its line the next input span starts, for tab stops
+    let mut column = 0;
+    // the space that the current line leaves above its baseline (for its ascent and
+    // leading) if it isn't the first line, and the descent below the baseline of the
+    // text on it so far
+    let mut line_top: Option<Pt> = None;
+    let mut line_descent: Option<Pt> = None;
+    // the ruby annotations of the runs laid out so far, with the spans of each run, how
+    // far its first span is moved right to centre it beneath the annotation, and the
+    // annotation positioned relative to the start of the run
+    let mut rubies: Vec<(std::ops::Range<usize>, Pt, SpanLayout)> = Vec::new();
 
     'inputspans: while !text.is_empty() {
-        let (span, colour, font) = text.remove(0);
+        let (
+            span,
+            TextRun {
+                font,
+                colour,
+                effects,
+                metadata,
+                ruby,
+            },
+        ) = text.remove(0);
+        let origin = origins.remove(0);
+
+        // switch to the fonts of the run's font stack for any characters that its font
+        // doesn't have glyphs for. Runs with ruby are kept whole, so that the annotation
+        // isn't repeated over each piece
+        if let Some(stack) = document
+            .font_stacks
+            .get(&font.id)
+            .filter(|_| ruby.is_none())
+        {
+            let pieces = stack.split(document, &span);
+            if pieces.iter().any(|&(_, id)| id != font.id) {
+                let mut offset = 0;
+                let mut piece_origins = Vec::with_capacity(pieces.len());
+                for (piece, _) in pieces.iter() {
+                    let len = piece.chars().count();
+                    piece_origins.push(origin.slice(offset, offset + len));
+                    offset += len;
+                }
+                for ((piece, id), piece_origin) in pieces.into_iter().zip(piece_origins).rev() {
+                    origins.insert(0, piece_origin);
+                    text.insert(
+                        0,
+                        (
+                            piece,
+                            TextRun {
+                                font: SpanFont { id, ..font },
+                                colour,
+                                effects,
+                                metadata: metadata.clone(),
+                                ruby: None,
+                            },
+                        ),
+                    );
+                }
+                continue 'inputspans;
+            }
+        }
+
+        // without the font's own small capitals, draw lowercase letters as smaller capitals
+        if effects.small_caps
+            && ruby.is_none()
+            && !(cfg!(feature = "shaping") && document.fonts[font.id].has_small_caps())
+        {
+            let effects = TextEffects {
+                small_caps: false,
+                ..effects
+            };
+            let mut offset = 0;
+            let pieces: Vec<(String, bool, Origin)> = small_caps_pieces(&span)
+                .into_iter()
+                .map(|(piece, lowercase, counts)| {
+                    let piece_origin = origin.slice(offset, offset + counts.len()).map(&counts);
+                    offset += counts.len();
+                    (piece, lowercase, piece_origin)
+                })
+                .collect();
+            for (piece, lowercase, piece_origin) in pieces.into_iter().rev() {
+                origins.insert(0, piece_origin);
+                let size = if lowercase {
+                    font.size * SMALL_CAPS_SCALE
+                } else {
+                    font.size
+                };
+                text.insert(
+                    0,
+                    (
+                        piece,
+                        TextRun {
+                            font: SpanFont { size, ..font },
+                            colour,
+                            effects,
+                            metadata: metadata.clone(),
+                            ruby: None,
+                        },
+                    ),
+                );
+            }
+            continue 'inputspans;
+        }
+
         let SpanFont {
             id: font_id,
             size: font_size,
         } = font;
 
-        let scaling: Pt =
-            font_size / document.fonts[font_id].face.as_face_ref().units_per_em() as f32;
-        let leading: Pt = scaling * document.fonts[font_id].face.as_face_ref().line_gap() as f32;
-        let ascent: Pt = scaling * document.fonts[font_id].face.as_face_ref().ascender() as f32;
-        let descent: Pt = scaling * document.fonts[font_id].face.as_face_ref().descender() as f32;
+        let scaling: Pt = font_size / document.fonts[font_id].units_per_em() as f32;
+        let leading: Pt = scaling * document.fonts[font_id].line_gap() as f32;
+        let ascent: Pt = scaling * document.fonts[font_id].ascender() as f32;
+        let descent: Pt = scaling * document.fonts[font_id].descender() as f32;
         let line_gap: Pt = leading + ascent - descent;
+        // ruby is set above the run, so the run needs that much more room above it
+        let ruby_size = font_size * RUBY_SCALE;
+        let ruby_height: Pt = match ruby {
+            Some(_) => (ascent - descent) * RUBY_SCALE,
+            None => Pt(0.0),
+        };
+        let run_top = leading + ascent + ruby_height;
+        // how far below the current line the next line's baseline is, if it starts in
+        // this run
+        let line_step = |line_descent: Option<Pt>| run_top - line_descent.unwrap_or(descent);
+
+        // a taller run moves the line it starts on down so that it doesn't collide with
+        // the line above, and the first text on a line sets how far down the line is
+        if let Some(top) = line_top {
+            let starts_line = span.starts_with(['\n', '\r']);
+            if !span.is_empty() && !starts_line && (run_top > top || !line_has_content) {
+                let shifted = y - (run_top - top);
+                if shifted < y && shifted < bounding_box.y1 + descent {
+                    // the run doesn't fit on the line, so leave it over
+                    origins.insert(0, origin);
+                    text.insert(
+                        0,
+                        (
+                            span,
+                            TextRun {
+                                font,
+                                colour,
+                                effects,
+                                metadata,
+                                ruby,
+                            },
+                        ),
+                    );
+                    break 'inputspans;
+                }
+                for span in spans.iter_mut().filter(|span| span.coords.1 == y) {
+                    span.coords.1 = shifted;
+                }
+                y = shifted;
+                line_top = Some(run_top);
+            }
+        }
+
+        // find where text continuing from (x, y) can go, moving down to the start of the
+        // next line until the exclusions leave enough room. Returns the position and the
+        // right edge of the line
+        let fit_line = |mut x: Pt, mut y: Pt| -> (Pt, Pt, Pt) {
+            loop {
+                let (left, right) = line_extent(&bounding_box, exclusions, y + descent, y + ascent);
+                if x < left {
+                    x = left;
+                }
+                if exclusions.is_empty()
+                    || right - x >= font_size
+                    || y - line_gap < bounding_box.y1 + descent
+                {
+                    return (x, y, right);
+                }
+                x = start.0 + wrap_offset;
+                y -= line_gap;
+            }
+        };
+        let (fitted_x, fitted_y, mut right) = fit_line(x, y);
+        x = fitted_x;
+        y = fitted_y;
+
+        // normalize newlines, and replace tabs with spaces (see [crate::Tabs])
+        let (span, origin) = normalise(document, &span, column, &origin);
+
+        // where the line may be broken, see [LineBreaker]
+        let mut opportunities = match ruby {
+            // an annotated run can only be moved to the next line as a whole
+            Some(_) => vec![0],
+            None => document.line_breaker.break_opportunities(&span),
+        };
+        if follows_whitespace && opportunities.first() != Some(&0) {
+            opportunities.insert(0, 0);
+        }
+        let trims_whitespace = document.line_breaker.trims_whitespace();
+        // where words may be hyphenated, if the font can draw the hyphen
+        let hyphenation_points = match ruby {
+            None if document.fonts[font_id].glyph_id('-').is_some() => {
+                document.line_breaker.hyphenation_points(&span)
+            }
+            _ => Vec::new(),
+        };
+        let hyphen_width = document.fonts[font_id].advance('-', font_size) + effects.letter_spacing;
 
-        // replace tabs with spaces
-        let span = span.replace('\t', &" ".repeat(TABSIZE));
-        // normalize newlines
-        let span = span.replace("\r\n", "\n").replace('\r', "\n");
+        let chars: Vec<char> = span.chars().collect();
+        let mut advances = text_advances(
+            &document.fonts[font_id],
+            &span,
+            font_size,
+            effects.letter_spacing,
+            effects.small_caps,
+        );
+
+        // spread out the shorter of the run and its ruby to the width of the longer, with
+        // half as much space at either end as between characters. The annotation is
+        // positioned relative to the start of the run's baseline once the line is done
+        let mut span_effects = effects;
+        let mut annotation: Option<(Pt, SpanLayout)> = None;
+        if let Some(ruby) = &ruby {
+            let ruby_advances =
+                text_advances(&document.fonts[font_id], ruby, ruby_size, Pt(0.0), false);
+            let width: Pt = advances.iter().copied().sum();
+            let ruby_width: Pt = ruby_advances.iter().copied().sum();
+            let mut ruby_span = SpanLayout {
+                text: ruby.clone(),
+                font: SpanFont {
+                    id: font_id,
+                    size: ruby_size,
+                },
+                colour,
+                coords: (Pt(0.0), ascent - descent * RUBY_SCALE),
+                effects: TextEffects::default(),
+                metadata: SpanMetadata {
+                    anchor: None,
+                    ..metadata.clone()
+                },
+                glyphs: Vec::new(),
+            };
+            let mut shift = Pt(0.0);
+            if ruby_width > width && !advances.is_empty() {
+                let spacing = (ruby_width - width) / advances.len() as f32;
+                for advance in advances.iter_mut() {
+                    *advance += spacing;
+                }
+                span_effects.letter_spacing += spacing;
+                shift = spacing / 2.0;
+            } else if width > ruby_width && !ruby_advances.is_empty() {
+                let spacing = (width - ruby_width) / ruby_advances.len() as f32;
+                ruby_span.effects.letter_spacing = spacing;
+                ruby_span.coords.0 = spacing / 2.0;
+            }
+            annotation = Some((shift, ruby_span));
+        }
 
         let mut current_span: SpanLayout = SpanLayout {
             text: "".into(),
@@ -165,21 +671,34 @@ pub fn layout_text(
             },
             colour,
             coords: (x, y),
+            effects: span_effects,
+            metadata: metadata.clone(),
+            glyphs: Vec::new(),
         };
+        let first_span = spans.len();
+        // the index of the first character of the current span within the input span,
+        // and whether there is anything before the current span on its line
+        let mut segment_start = 0;
+        let mut follows_content = line_has_content;
 
-        'chars: for (ci, ch) in span.chars().enumerate() {
+        'chars: for (ci, &ch) in chars.iter().enumerate() {
             if ch == '\n' {
+                column = 0;
+
                 // collect what's left and push it to the front of the queue
                 let remaining: String = span.chars().skip(ci + 1).collect();
                 if !remaining.is_empty() {
+                    origins.insert(0, origin.skip(ci + 1));
                     text.insert(
                         0,
                         (
                             remaining,
-                            colour,
-                            SpanFont {
-                                id: font_id,
-                                size: font_size,
+                            TextRun {
+                                font,
+                                colour,
+                                effects,
+                                metadata: metadata.clone(),
+                                ruby: ruby.clone(),
                             },
                         ),
                     );
@@ -187,7 +706,11 @@ pub fn layout_text(
 
                 // move to the next line
                 x = start.0;
-                y -= line_gap;
+                y -= line_step(line_descent);
+                line_top = Some(run_top);
+                line_descent = None;
+                line_has_content = false;
+                follows_whitespace = false;
 
                 // check if we would now overflow on the bottom
                 if y < bounding_box.y1 + descent {
@@ -195,14 +718,17 @@ pub fn layout_text(
                     // collect what's left of our current input span
                     let remaining: String = span.chars().skip(ci).collect();
                     if !remaining.is_empty() {
+                        origins.insert(0, origin.skip(ci));
                         text.insert(
                             0,
                             (
                                 remaining,
-                                colour,
-                                SpanFont {
-                                    id: font_id,
-                                    size: font_size,
+                                TextRun {
+                                    font,
+                                    colour,
+                                    effects,
+                                    metadata: metadata.clone(),
+                                    ruby: ruby.clone(),
                                 },
                             ),
                         );
@@ -216,100 +742,1105 @@ pub fn layout_text(
                 }
             }
 
-            let gid = document.fonts[font_id]
-                .face
-                .as_face_ref()
-                .glyph_index(ch)
-                .unwrap_or_else(|| {
-                    document.fonts[font_id]
-                        .face
-                        .as_face_ref()
-                        .glyph_index('\u{FFFD}')
-                        //.expect("Font has a replacement glyph")
-                        .unwrap_or_else(|| {
-                            document.fonts[font_id]
-                                .face
-                                .as_face_ref()
-                                .glyph_index('?')
-                                .expect("font has a question mark glyph")
-                        })
-                });
+            let hadv = advances[ci];
 
-            let hadv = scaling
-                * document.fonts[font_id]
-                    .face
-                    .as_face_ref()
-                    .glyph_hor_advance(gid)
-                    .unwrap_or_default() as f32;
+            if x + hadv >= right && trims_whitespace && ch.is_whitespace() {
+                // let whitespace hang past the end of the line
+                current_span.text.push(ch);
+                x += hadv;
+            } else if x + hadv >= right {
+                // break the line at the last opportunity on it, or before this character
+                // if there isn't one. Words may also be hyphenated where the hyphen fits
+                let (line_break, hyphenated) = hyphenation_points
+                    .iter()
+                    .map(|&b| (b, true))
+                    .filter(|&(b, _)| {
+                        b <= ci
+                            && b > segment_start
+                            && x - advances[b..ci].iter().copied().sum::<Pt>() + hyphen_width
+                                < right
+                    })
+                    .chain(opportunities.iter().map(|&b| (b, false)).filter(|&(b, _)| {
+                        b <= ci && (b > segment_start || (b == segment_start && follows_content))
+                    }))
+                    // an opportunity is preferred to hyphenating at the same place
+                    .max_by_key(|&(b, hyphenated)| (b, !hyphenated))
+                    .unwrap_or((ci, false));
+                let mut next_start = line_break;
+                if trims_whitespace {
+                    while next_start < ci && chars[next_start].is_whitespace() {
+                        next_start += 1;
+                    }
+                }
 
-            if x + hadv >= bounding_box.x2 {
-                // stop the current span
+                // stop the current span at the break
+                current_span.text = chars[segment_start..line_break].iter().collect();
+                if trims_whitespace {
+                    current_span
+                        .text
+                        .truncate(current_span.text.trim_end().len());
+                }
+                if hyphenated {
+                    current_span.text.push('-');
+                }
                 spans.push(current_span.clone());
 
                 // start a new span on the next line
-                x = start.0 + wrap_offset;
-                y -= line_gap;
+                let (fitted_x, fitted_y, fitted_right) =
+                    fit_line(start.0 + wrap_offset, y - line_step(line_descent));
+                x = fitted_x;
+                y = fitted_y;
+                right = fitted_right;
+                line_top = Some(run_top);
+                line_descent = None;
 
                 // check if we're overflowing on the bottom
                 if y < bounding_box.y1 + descent {
                     // yup, we're going to overflow. That's okay, just return our leftovers
                     // collect what's left of our current input span
-                    let remaining: String = span.chars().skip(ci).collect();
+                    let remaining: String = chars[next_start..].iter().collect();
                     if !remaining.is_empty() {
+                        origins.insert(0, origin.skip(next_start));
                         text.insert(
                             0,
                             (
                                 remaining,
-                                colour,
-                                SpanFont {
-                                    id: font_id,
-                                    size: font_size,
+                                TextRun {
+                                    font,
+                                    colour,
+                                    effects,
+                                    metadata: metadata.clone(),
+                                    ruby: ruby.clone(),
                                 },
                             ),
                         );
                     }
-
-                    spans.push(current_span.clone());
                     break 'inputspans;
                 } else {
-                    // not overflowing the bottom yet,
-                    current_span.text.clear();
-                    current_span.text.push(ch);
+                    // not overflowing the bottom yet, so carry the characters after the
+                    // break over to the new line
+                    current_span.text = chars[next_start..=ci].iter().collect();
                     current_span.coords.0 = x;
                     current_span.coords.1 = y;
-
-                    x += hadv;
+                    for &carried in advances[next_start..=ci].iter() {
+                        x += carried;
+                    }
+                    segment_start = next_start;
+                    follows_content = false;
                 }
             } else {
                 current_span.text.push(ch);
                 x += hadv;
             }
+
+            line_has_content = true;
+            line_descent = match line_descent {
+                Some(line_descent) if line_descent < descent => Some(line_descent),
+                _ => Some(descent),
+            };
+            follows_whitespace = ch.is_whitespace();
+            column += 1;
         }
 
         spans.push(current_span.clone());
+        if let Some((shift, ruby_span)) = annotation {
+            rubies.push((first_span..spans.len(), shift, ruby_span));
+        }
     }
 
-    for span in spans.into_iter() {
-        if !span.text.is_empty() {
-            page.add_span(span);
+    // set each ruby annotation above the first line of its run, now that the lines have
+    // stopped moving
+    let mut annotations: Vec<SpanLayout> = Vec::with_capacity(rubies.len());
+    for (range, shift, mut ruby_span) in rubies.into_iter() {
+        if let Some(base) = spans[range].iter_mut().find(|span| !span.text.is_empty()) {
+            ruby_span.coords.0 += base.coords.0;
+            ruby_span.coords.1 += base.coords.1;
+            base.coords.0 += shift;
+            annotations.push(ruby_span);
         }
     }
 
-    (x, y)
+    spans.retain(|span| !span.text.is_empty());
+    if document.trim_line_ends {
+        trim_line_ends(document, &mut spans);
+    }
+    #[cfg(feature = "shaping")]
+    for span in spans.iter_mut().chain(annotations.iter_mut()) {
+        span.shape(document);
+    }
+    for line in laid_out_lines(document, &spans) {
+        on_line(page, &line);
+    }
+    page.add_spans(spans.into_iter().chain(annotations).collect());
+
+    Ok((x, y))
+}
+
+/// How large capitals drawn in place of lowercase letters are, relative to the text
+/// around them, for fonts without their own small capitals
+const SMALL_CAPS_SCALE: f32 = 0.75;
+
+/// How large ruby annotations are, relative to the runs that they annotate
+const RUBY_SCALE: f32 = 0.5;
+
+/// Split text into runs of lowercase letters (which are converted to capitals) and runs
+/// of everything else, for drawing small capitals with fonts that don't have them
+fn small_caps_pieces(text: &str) -> Vec<(String, bool, Vec<usize>)> {
+    let mut pieces: Vec<(String, bool, Vec<usize>)> = Vec::new();
+    for ch in text.chars() {
+        let lowercase = ch.is_lowercase();
+        let converted: String = if lowercase {
+            ch.to_uppercase().collect()
+        } else {
+            ch.to_string()
+        };
+        let count = converted.chars().count();
+        match pieces.last_mut() {
+            Some((piece, current, counts)) if *current == lowercase => {
+                piece.push_str(&converted);
+                counts.push(count);
+            }
+            _ => pieces.push((converted, lowercase, vec![count])),
+        }
+    }
+    pieces
+}
+
+/// Where the characters of a run queued for layout came from: the index of the run in
+/// the text that layout was given, and the offset (in characters) of each character
+/// within that run's text. Layout normalises newlines, expands tabs, and capitalises
+/// small capitals before requeueing what doesn't fit, so the runs that it leaves over
+/// can't be matched up with its input by their lengths
+#[derive(Debug, Clone)]
+struct Origin {
+    run: usize,
+    /// The offset of the first character
+    start: usize,
+    /// The offset of each character, or `None` if they follow on from `start`
+    offsets: Option<Vec<usize>>,
+}
+
+impl Origin {
+    /// The origin of the whole of the given run
+    fn new(run: usize) -> Origin {
+        Origin {
+            run,
+            start: 0,
+            offsets: None,
+        }
+    }
+
+    /// The offset of the `i`th character
+    fn offset(&self, i: usize) -> usize {
+        match &self.offsets {
+            Some(offsets) => offsets.get(i).copied().unwrap_or(self.start),
+            None => self.start + i,
+        }
+    }
+
+    /// The origin of the characters from the `from`th up to the `to`th
+    fn slice(&self, from: usize, to: usize) -> Origin {
+        Origin {
+            run: self.run,
+            start: self.offset(from),
+            offsets: self
+                .offsets
+                .as_ref()
+                .map(|offsets| offsets[from.min(offsets.len())..to.min(offsets.len())].to_vec()),
+        }
+    }
+
+    /// The origin of the characters from the `from`th on
+    fn skip(&self, from: usize) -> Origin {
+        self.slice(from, usize::MAX)
+    }
+
+    /// The origin of the text made by replacing each character with however many
+    /// characters `counts` gives for it
+    fn map(&self, counts: &[usize]) -> Origin {
+        let mut offsets = Vec::with_capacity(counts.len());
+        for (i, &count) in counts.iter().enumerate() {
+            offsets.extend(std::iter::repeat(self.offset(i)).take(count));
+        }
+        Origin {
+            run: self.run,
+            start: self.offset(0),
+            offsets: Some(offsets),
+        }
+    }
+}
+
+/// Normalise the newlines of a run's text to `\n`, and expand its tabs into spaces for
+/// text starting `column` characters into its line (see [crate::Tabs::expand]), along
+/// with the origin of each character of the result
+fn normalise(document: &Document, text: &str, column: usize, origin: &Origin) -> (String, Origin) {
+    if !text.contains(['\r', '\t']) {
+        return (text.to_string(), origin.clone());
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut normalised = String::with_capacity(text.len());
+    let mut counts = Vec::with_capacity(chars.len());
+    let mut column = column;
+    for (i, &ch) in chars.iter().enumerate() {
+        let replaced = match ch {
+            '\r' if chars.get(i + 1) == Some(&'\n') => String::new(),
+            '\r' => "\n".to_string(),
+            _ => document.tabs.expand(ch.encode_utf8(&mut [0; 4]), column),
+        };
+        let count = replaced.chars().count();
+        column = if replaced == "\n" { 0 } else { column + count };
+        counts.push(count);
+        normalised.push_str(&replaced);
+    }
+    (normalised, origin.map(&counts))
+}
+
+/// Trim the whitespace from the end of each line of laid out spans (the consecutive
+/// spans which share a baseline), then drop the spans which don't have any width
+fn trim_line_ends(document: &Document, spans: &mut Vec<SpanLayout>) {
+    let mut trimming = false;
+    for i in (0..spans.len()).rev() {
+        if i + 1 == spans.len() || spans[i + 1].coords.1 != spans[i].coords.1 {
+            trimming = true;
+        }
+        if trimming {
+            let span = &mut spans[i];
+            span.text.truncate(span.text.trim_end().len());
+            trimming = span.text.is_empty();
+        }
+    }
+    spans.retain(|span| {
+        let font = &document.fonts[span.font.id];
+        span.char_advances(font)
+            .into_iter()
+            .any(|advance| advance != Pt(0.0))
+    });
+}
+
+/// Group laid out spans into lines: the consecutive spans which share a baseline
+fn laid_out_lines(document: &Document, spans: &[SpanLayout]) -> Vec<LaidOutLine> {
+    let mut lines: Vec<LaidOutLine> = Vec::new();
+    for span in spans.iter() {
+        let rect = span.bounding_box(document);
+        match lines.last_mut() {
+            Some(line) if line.baseline == span.coords.1 => {
+                line.rect = Rect::union([&line.rect, &rect]).unwrap_or(rect);
+                line.text.push_str(&span.text);
+            }
+            _ => lines.push(LaidOutLine {
+                index: lines.len(),
+                rect,
+                baseline: span.coords.1,
+                text: span.text.clone(),
+            }),
+        }
+    }
+    lines
+}
+
+/// The left and right edges that a line of text between `bottom` and `top` can occupy,
+/// once it has been shortened around any exclusions which overlap it. Without any
+/// exclusions, lines may start anywhere and end at the right of the bounding box
+fn line_extent(bounding_box: &Rect, exclusions: &[Rect], bottom: Pt, top: Pt) -> (Pt, Pt) {
+    let centre = (bounding_box.x1 + bounding_box.x2) / 2.0;
+    let mut left = Pt(f32::MIN);
+    let mut right = bounding_box.x2;
+    for exclusion in exclusions
+        .iter()
+        .filter(|exclusion| exclusion.y1 < top && exclusion.y2 > bottom)
+    {
+        if (exclusion.x1 + exclusion.x2) / 2.0 >= centre {
+            if exclusion.x1 < right {
+                right = exclusion.x1;
+            }
+        } else if exclusion.x2 > left {
+            left = exclusion.x2;
+        }
+    }
+    (left, right)
+}
+
+/// The same as [layout_text], except that each wrapped line is aligned within the
+/// bounding box (see [Alignment]). Lines are aligned as though they started at the left
+/// edge of the bounding box, so with [Alignment::Centre] or [Alignment::Right] the
+/// horizontal position of `start` and `wrap_offset` only affect where the text wraps.
+/// With [Alignment::Justified], the last line of each paragraph (i.e. before a newline,
+/// or at the end of the text) is left aligned.
+///
+/// With optical margins (see [OpticalMargins]), punctuation at the start and end of the
+/// lines hangs outside the bounding box.
+///
+/// ```
+/// # use pdf_gen::{layout, Alignment, Colour, Document, Font, Page, Pt, SpanFont};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let font = doc.add_font(font);
+/// # let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+/// let size = Pt(10.0);
+/// let start = layout::baseline_start(&page, &doc.fonts[font], size);
+/// let bounding_box = page.content_box;
+/// let mut text = vec![(
+///     "A title, centred\nabove the rest".to_string(),
+///     Colour::new_grey(0.0),
+///     SpanFont { id: font, size },
+/// )];
+/// layout::layout_text_aligned(&doc, &mut page, start, &mut text, Pt(0.0), bounding_box,
+///     Alignment::Centre, None).unwrap();
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn layout_text_aligned<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, Colour, SpanFont)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    alignment: Alignment,
+    optical_margins: Option<&OpticalMargins>,
+) -> Result<(Pt, Pt), PDFError> {
+    let mut runs: Vec<(String, TextRun)> = text
+        .drain(..)
+        .map(|(span, colour, font)| (span, TextRun::from((colour, font, SpanMetadata::default()))))
+        .collect();
+    let result = layout_runs_aligned(
+        document,
+        page,
+        start,
+        &mut runs,
+        wrap_offset,
+        bounding_box,
+        alignment,
+        optical_margins,
+    );
+    text.extend(
+        runs.into_iter()
+            .map(|(span, run)| (span, run.colour, run.font)),
+    );
+    result
+}
+
+/// The same as [layout_runs] (without exclusions), except that each wrapped line is
+/// aligned within the bounding box as with [layout_text_aligned].
+///
+/// Returns the position where layout stopped, as [layout_runs] does, moved along with
+/// the last line that it is on. Runs with ruby (see [TextRun::ruby]) can only be laid
+/// out left-aligned without optical margins, as the annotations would be aligned apart
+/// from their runs; otherwise a [PDFError::AlignedRuby] error is returned, leaving the
+/// text unchanged.
+#[allow(clippy::too_many_arguments)]
+pub fn layout_runs_aligned<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, TextRun)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    alignment: Alignment,
+    optical_margins: Option<&OpticalMargins>,
+) -> Result<(Pt, Pt), PDFError> {
+    if alignment == Alignment::Left && optical_margins.is_none() {
+        return layout_runs(document, page, start, text, wrap_offset, bounding_box, &[]);
+    }
+
+    let aligned = layout_aligned_spans(
+        document,
+        start,
+        text,
+        wrap_offset,
+        bounding_box,
+        alignment,
+        optical_margins,
+    )?;
+    page.add_spans(aligned.spans);
+    Ok(aligned.end)
+}
+
+/// Text laid out and aligned by [layout_aligned_spans]
+struct AlignedSpans {
+    /// The aligned spans, which are left for the caller to add
+    spans: Vec<SpanLayout>,
+    /// The position where layout stopped, moved along with the line that it is on
+    end: (Pt, Pt),
+    /// Where the text left over starts in the runs that were given, as the index of a
+    /// run and a character offset within it
+    leftover: Option<(usize, usize)>,
+}
+
+/// Lay out runs into a scratch form with [layout_runs] (without exclusions), then align
+/// their lines within the bounding box (see [align_lines])
+fn layout_aligned_spans(
+    document: &Document,
+    start: (Pt, Pt),
+    text: &mut Vec<(String, TextRun)>,
+    wrap_offset: Pt,
+    bounding_box: Rect,
+    alignment: Alignment,
+    optical_margins: Option<&OpticalMargins>,
+) -> Result<AlignedSpans, PDFError> {
+    if text.iter().any(|(_, run)| run.ruby.is_some()) {
+        return Err(PDFError::AlignedRuby);
+    }
+    let mut scratch = FormXObject::new(bounding_box);
+    let original: String = match alignment {
+        Alignment::Justified => text.iter().map(|(span, _)| span.as_str()).collect(),
+        _ => String::new(),
+    };
+    let mut origins: Vec<Origin> = (0..text.len()).map(Origin::new).collect();
+    let (mut x, y) = layout_queue(
+        document,
+        &mut scratch,
+        start,
+        text,
+        &mut origins,
+        wrap_offset,
+        bounding_box,
+        &[],
+        |_, _| {},
+    )?;
+    let leftover = origins.first().map(|origin| (origin.run, origin.offset(0)));
+
+    let spans: Vec<SpanLayout> = scratch
+        .contents
+        .into_iter()
+        .filter_map(|content| match content {
+            PageContents::Text(spans) | PageContents::ClippedText(spans, _) => Some(spans),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    // the start of the line that layout stopped on, so that the end position can be
+    // moved along with it
+    let line_start = |spans: &[SpanLayout]| {
+        spans
+            .iter()
+            .filter(|span| span.coords.1 == y)
+            .map(|span| span.coords.0)
+            .reduce(|a, b| if b < a { b } else { a })
+    };
+    let unaligned = line_start(&spans);
+    let spans = align_lines(
+        document,
+        spans,
+        &original,
+        alignment,
+        &bounding_box,
+        optical_margins,
+    );
+    if let (Some(unaligned), Some(aligned)) = (unaligned, line_start(&spans)) {
+        x += aligned - unaligned;
+    }
+    Ok(AlignedSpans {
+        spans,
+        end: (x, y),
+        leftover,
+    })
+}
+
+/// Lay out a paragraph of text in a named style from the document's stylesheet (see
+/// [crate::StyleSheet]), starting at the top of the bounding box (after the style's
+/// `space_before`). Lines are wrapped as with [layout_text] and then aligned within the
+/// bounding box according to the style. Each span is given the style's structure role
+/// (see [crate::StyleSheet::role]), unless the style has none.
+///
+/// As with [layout_text], the text is consumed; anything left in `text` afterwards would
+/// have overflowed the bounding box.
+///
+/// Returns the vertical position below the paragraph (after the style's `space_after`),
+/// which is where the next paragraph should start.
+pub fn layout_styled_text<T: ContentTarget + ?Sized>(
+    document: &Document,
+    page: &mut T,
+    style: &str,
+    text: &mut String,
+    bounding_box: Rect,
+) -> Result<Pt, PDFError> {
+    layout_styled_text_with_lines(document, page, style, text, bounding_box, |_, _| {})
+}
+
+/// The same as [layout_styled_text], except that `on_line` is called with each line once
+/// it has been aligned (see [layout_runs_with_lines])
+pub fn layout_styled_text_with_lines<T, F>(
+    document: &Document,
+    page: &mut T,
+    style: &str,
+    text: &mut String,
+    bounding_box: Rect,
+    mut on_line: F,
+) -> Result<Pt, PDFError>
+where
+    T: ContentTarget + ?Sized,
+    F: FnMut(&mut T, &LaidOutLine),
+{
+    let (font, colour) = document.styles.resolve(document, style)?;
+    let role = document.styles.role(style);
+    let style = document.styles.require(style)?;
+    let font_ref = &document.fonts[font.id];
+    let top = bounding_box.y2 - style.space_before;
+    let start = (bounding_box.x1, top - font_ref.ascent(font.size));
+
+    let area = Rect {
+        y2: top,
+        ..bounding_box
+    };
+    let metadata = SpanMetadata {
+        role,
+        ..SpanMetadata::default()
+    };
+    let mut runs = vec![(
+        std::mem::take(text),
+        TextRun::from((colour, font, metadata)),
+    )];
+    let aligned = layout_aligned_spans(
+        document,
+        start,
+        &mut runs,
+        Pt(0.0),
+        area,
+        style.alignment,
+        None,
+    )?;
+    *text = runs.into_iter().map(|(t, _)| t).collect();
+
+    for line in laid_out_lines(document, &aligned.spans) {
+        on_line(page, &line);
+    }
+    page.add_spans(aligned.spans);
+
+    let baseline = aligned.end.1;
+    Ok(baseline + font_ref.descent(font.size) - style.space_after)
+}
+
+/// A paragraph made up of runs of text in different styles (see [TextStyle]), such as a
+/// sentence with an emphasised phrase in it. The runs' styles give the font, size,
+/// colour, and structure role of their text, while the alignment, first line indent, and
+/// spacing belong to the paragraph.
+///
+/// ```
+/// # use pdf_gen::{layout::Paragraph, Alignment, Document, Font, Page, Pt, TextStyle};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let font = doc.add_font(font);
+/// let body = TextStyle::new(doc.fonts[font].family(), Pt(10.0));
+/// let mut emphasis = body.clone();
+/// emphasis.colour(pdf_gen::colours::RED);
+///
+/// let mut paragraph = Paragraph::new();
+/// paragraph
+///     .run("Payment is due ", &body)
+///     .run("within 30 days", &emphasis)
+///     .run(" of the invoice date.", &body)
+///     .alignment(Alignment::Justified)
+///     .indent(Pt(18.0));
+///
+/// let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+/// let (next_y, overflow) = paragraph.layout(&doc, &mut page, page.content_box).unwrap();
+/// assert!(overflow.is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Paragraph {
+    /// The runs of text, in order, with the style of each
+    pub runs: Vec<(String, TextStyle)>,
+    /// How the lines of the paragraph are aligned
+    pub alignment: Alignment,
+    /// How far the first line is indented from the left edge of the bounding box
+    pub indent: Pt,
+    /// Extra space above the paragraph
+    pub space_before: Pt,
+    /// Extra space below the paragraph
+    pub space_after: Pt,
+    /// Whether punctuation at the edges of the lines hangs outside the bounding box, and
+    /// by how much (see [OpticalMargins])
+    pub optical_margins: Option<OpticalMargins>,
+}
+
+impl Paragraph {
+    /// Create an empty, left-aligned paragraph with no indent or extra spacing
+    pub fn new() -> Paragraph {
+        Paragraph::default()
+    }
+
+    /// Add a run of text in the given style to the end of the paragraph
+    pub fn run<S: ToString>(&mut self, text: S, style: &TextStyle) -> &mut Self {
+        self.runs.push((text.to_string(), style.clone()));
+        self
+    }
+
+    /// Set how the lines of the paragraph are aligned
+    pub fn alignment(&mut self, alignment: Alignment) -> &mut Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set how far the first line is indented
+    pub fn indent(&mut self, indent: Pt) -> &mut Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the extra space above and below the paragraph
+    pub fn spacing(&mut self, before: Pt, after: Pt) -> &mut Self {
+        self.space_before = before;
+        self.space_after = after;
+        self
+    }
+
+    /// Set the optical margins of the paragraph
+    pub fn optical_margins(&mut self, optical_margins: OpticalMargins) -> &mut Self {
+        self.optical_margins = Some(optical_margins);
+        self
+    }
+
+    /// Lay out the paragraph from the top of the bounding box (after its `space_before`),
+    /// wrapping its runs as with [layout_runs] and then aligning its lines. The fonts of
+    /// the runs' styles must have been added to the document.
+    ///
+    /// Returns the vertical position below the paragraph (after its `space_after`), which
+    /// is where the next paragraph should start, along with the part of the paragraph
+    /// which overflowed the bounding box, if any. The overflow keeps the paragraph's
+    /// alignment, optical margins, and `space_after`, but not its indent or
+    /// `space_before`, as it continues the paragraph rather than starting a new one, so it
+    /// can be laid out at the top of the next page or column as it is.
+    pub fn layout<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        bounding_box: Rect,
+    ) -> Result<(Pt, Option<Paragraph>), PDFError> {
+        let top = bounding_box.y2 - self.space_before;
+        let mut runs: Vec<(String, TextRun)> = Vec::with_capacity(self.runs.len());
+        for (text, style) in self.runs.iter() {
+            let (font, colour) = style.resolve(document)?;
+            let metadata = SpanMetadata {
+                role: style.role.clone(),
+                ..SpanMetadata::default()
+            };
+            runs.push((text.clone(), TextRun::from((colour, font, metadata))));
+        }
+        let first = match runs.first() {
+            Some((_, run)) => run.font,
+            None => return Ok((top - self.space_after, None)),
+        };
+        let start = (
+            bounding_box.x1 + self.indent,
+            top - document.fonts[first.id].ascent(first.size),
+        );
+
+        let area = Rect {
+            y2: top,
+            ..bounding_box
+        };
+        let aligned = layout_aligned_spans(
+            document,
+            start,
+            &mut runs,
+            Pt(0.0) - self.indent,
+            area,
+            self.alignment,
+            self.optical_margins.as_ref(),
+        )?;
+        let bottom = laid_out_lines(document, &aligned.spans)
+            .last()
+            .map(|line| line.rect.y1)
+            .unwrap_or(top);
+        page.add_spans(aligned.spans);
+
+        // the text left over starts part way through one of the paragraph's own runs,
+        // which are continued from there as they were given
+        let (run, offset) = match aligned.leftover {
+            Some(leftover) if !runs.iter().all(|(text, _)| text.trim().is_empty()) => leftover,
+            _ => return Ok((bottom - self.space_after, None)),
+        };
+        let (text, style) = &self.runs[run];
+        let mut overflow = vec![(text.chars().skip(offset).collect(), style.clone())];
+        overflow.extend(self.runs[run + 1..].iter().cloned());
+        Ok((
+            bottom - self.space_after,
+            Some(Paragraph {
+                runs: overflow,
+                alignment: self.alignment,
+                indent: Pt(0.0),
+                space_before: Pt(0.0),
+                space_after: self.space_after,
+                optical_margins: self.optical_margins.clone(),
+            }),
+        ))
+    }
+}
+
+/// Check that every character of the runs has a glyph to be drawn with, even if it is a
+/// replacement, in the font that layout will draw it in (after switching to the fonts of
+/// any font stack). Done before layout starts, so that an error leaves the text unchanged
+fn check_glyphs(document: &Document, text: &[(String, TextRun)]) -> Result<(), PDFError> {
+    for (span, run) in text.iter() {
+        let pieces = match document
+            .font_stacks
+            .get(&run.font.id)
+            .filter(|_| run.ruby.is_none())
+        {
+            Some(stack) => stack.split(document, span),
+            None => vec![(span.clone(), run.font.id)],
+        };
+        let ruby = run.ruby.iter().map(|ruby| (ruby.clone(), run.font.id));
+        for (piece, id) in pieces.into_iter().chain(ruby) {
+            let font = &document.fonts[id];
+            // tabs are drawn as spaces, and newlines aren't drawn at all
+            let missing = piece
+                .chars()
+                .map(|ch| if ch == '\t' { ' ' } else { ch })
+                .find(|&ch| ch != '\n' && ch != '\r' && font.glyph_id_or_replacement(ch).is_none());
+            if let Some(ch) = missing {
+                return Err(PDFError::MissingGlyph {
+                    font: font.name(),
+                    ch,
+                    excerpt: excerpt(&piece),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Align the lines of laid out text within the bounding box. `original` is the text that
+/// was laid out, which is needed to tell which lines end paragraphs when justifying.
+/// With optical margins, punctuation at the edges of the lines hangs outside the
+/// bounding box
+fn align_lines(
+    document: &Document,
+    spans: Vec<SpanLayout>,
+    original: &str,
+    alignment: Alignment,
+    bounding_box: &Rect,
+    optical_margins: Option<&OpticalMargins>,
+) -> Vec<SpanLayout> {
+    if alignment == Alignment::Left && optical_margins.is_none() {
+        return spans;
+    }
+    // each line is made up of the spans on the same baseline
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    while line_start < spans.len() {
+        let y = spans[line_start].coords.1;
+        let line_end = spans[line_start..]
+            .iter()
+            .position(|span| span.coords.1 != y)
+            .map(|n| line_start + n)
+            .unwrap_or(spans.len());
+        lines.push(line_start..line_end);
+        line_start = line_end;
+    }
+    let ends_paragraph = match alignment {
+        Alignment::Justified => hard_breaks(original, &spans, &lines),
+        _ => Vec::new(),
+    };
+
+    let mut aligned = Vec::with_capacity(spans.len());
+    for (li, line) in lines.into_iter().enumerate() {
+        // measured with the span's own advances, so shaping and letter spacing are
+        // included
+        let last = &spans[line.end - 1];
+        let visible = last.text.trim_end().chars().count();
+        let right = last.coords.0 + last.prefix_width(&document.fonts[last.font.id], visible);
+        let (hang_left, hang_right) = match optical_margins {
+            Some(margins) => line_protrusion(document, &spans[line.clone()], margins),
+            None => (Pt(0.0), Pt(0.0)),
+        };
+        let slack = bounding_box.x2 - right;
+        let shift = match alignment {
+            Alignment::Centre => (slack + hang_right - hang_left) / 2.0,
+            Alignment::Right => slack + hang_right,
+            Alignment::Justified if !ends_paragraph[li] => {
+                let line: Vec<SpanLayout> = spans[line]
+                    .iter()
+                    .map(|span| {
+                        let mut span = span.clone();
+                        span.coords.0 -= hang_left;
+                        span
+                    })
+                    .collect();
+                aligned.extend(justify(document, &line, slack + hang_left + hang_right));
+                continue;
+            }
+            _ => Pt(0.0) - hang_left,
+        };
+        for span in spans[line].iter() {
+            let mut span = span.clone();
+            span.coords.0 += shift;
+            aligned.push(span);
+        }
+    }
+    aligned
+}
+
+/// How far the first and last visible characters of a line hang outside the left and
+/// right edges of the bounding box with optical margins
+fn line_protrusion(document: &Document, line: &[SpanLayout], margins: &OpticalMargins) -> (Pt, Pt) {
+    let hang = |span: &SpanLayout, ch: char, fraction: f32| -> Pt {
+        width_of_text(
+            ch.encode_utf8(&mut [0; 4]),
+            &document.fonts[span.font.id],
+            span.font.size,
+        ) * fraction
+    };
+    let left = line
+        .iter()
+        .find_map(|span| {
+            let ch = span.text.chars().find(|ch| !ch.is_whitespace())?;
+            Some(hang(span, ch, margins.protrusion(ch).0))
+        })
+        .unwrap_or(Pt(0.0));
+    let right = line
+        .iter()
+        .rev()
+        .find_map(|span| {
+            let ch = span.text.chars().rev().find(|ch| !ch.is_whitespace())?;
+            Some(hang(span, ch, margins.protrusion(ch).1))
+        })
+        .unwrap_or(Pt(0.0));
+    (left, right)
+}
+
+/// Whether each line of laid out text ends a paragraph, i.e. it is followed by a newline
+/// in the original text or is the end of the text, rather than having been wrapped
+fn hard_breaks(
+    original: &str,
+    spans: &[SpanLayout],
+    lines: &[std::ops::Range<usize>],
+) -> Vec<bool> {
+    // layout only ever drops or replaces whitespace, adds hyphens where it hyphenates
+    // words, or uppercases text for synthetic small capitals, so the characters which
+    // aren't whitespace can be matched up with the original text once both are uppercased
+    let mut original = original.chars().flat_map(char::to_uppercase).peekable();
+    lines
+        .iter()
+        .map(|line| {
+            for ch in spans[line.clone()]
+                .iter()
+                .flat_map(|span| span.text.chars())
+                .flat_map(char::to_uppercase)
+                .filter(|ch| !ch.is_whitespace())
+            {
+                while let Some(original_ch) = original.peek() {
+                    if !original_ch.is_whitespace() {
+                        break;
+                    }
+                    original.next();
+                }
+                if original.peek() == Some(&ch) {
+                    original.next();
+                }
+            }
+            while let Some(ch) = original.peek() {
+                if *ch == '\n' || *ch == '\r' || !ch.is_whitespace() {
+                    break;
+                }
+                original.next();
+            }
+            !matches!(original.peek(), Some(ch) if !ch.is_whitespace())
+        })
+        .collect()
+}
+
+/// Spread `slack` evenly between the words of a line, splitting its spans so that each
+/// word (with any whitespace after it) is its own span
+fn justify(document: &Document, line: &[SpanLayout], slack: Pt) -> Vec<SpanLayout> {
+    // the gaps between words are the runs of whitespace which have a word on either side
+    let mut gaps = 0;
+    let mut after_word = false;
+    let mut in_gap = false;
+    for ch in line.iter().flat_map(|span| span.text.chars()) {
+        if ch.is_whitespace() {
+            in_gap = after_word;
+        } else {
+            if in_gap {
+                gaps += 1;
+            }
+            in_gap = false;
+            after_word = true;
+        }
+    }
+    if gaps == 0 {
+        return line.to_vec();
+    }
+    let extra = slack / gaps as f32;
+
+    let mut words = Vec::with_capacity(line.len() + gaps);
+    let mut shift = Pt(0.0);
+    let mut after_word = false;
+    let mut in_gap = false;
+    for span in line.iter() {
+        let font = &document.fonts[span.font.id];
+        let advances = span.char_advances(font);
+        let mut x = span.coords.0;
+        let mut word = SpanLayout {
+            text: String::new(),
+            coords: (x + shift, span.coords.1),
+            ..span.clone()
+        };
+        for (ci, ch) in span.text.chars().enumerate() {
+            if ch.is_whitespace() {
+                in_gap = after_word;
+            } else {
+                if in_gap {
+                    // each word starts a share of the slack further along than the last
+                    shift += extra;
+                    if !word.text.is_empty() {
+                        let mut finished = word.clone();
+                        finished.reshape(document);
+                        words.push(finished);
+                    }
+                    word.text.clear();
+                    word.coords.0 = x + shift;
+                }
+                in_gap = false;
+                after_word = true;
+            }
+            word.text.push(ch);
+            x += advances[ci];
+        }
+        if !word.text.is_empty() {
+            word.reshape(document);
+            words.push(word);
+        }
+    }
+    words
 }
 
-/// Calculate the width of a given string of text given the font and font size
+/// Add a page number (such as "Page 2 of 5", depending on `format`) to the bottom-right
+/// corner of each of the given pages, halfway between the bottom of the page and the
+/// bottom of its content box. `format` receives the 1-based page number and the total
+/// number of pages. The page numbers are marked as artifacts (see [crate::Artifact]), so
+/// that screen readers and text extraction skip them.
+pub fn number_pages(
+    document: &Document,
+    pages: &mut [Page],
+    font: SpanFont,
+    colour: Colour,
+    format: fn(usize, usize) -> String,
+) {
+    let count = pages.len();
+    for (pi, page) in pages.iter_mut().enumerate() {
+        let text = format(pi + 1, count);
+        let width = width_of_text(&text, &document.fonts[font.id], font.size);
+        let mut metadata = SpanMetadata::default();
+        metadata.artifact(Artifact::PageNumber);
+        page.add_span(SpanLayout {
+            text,
+            font,
+            colour,
+            coords: (page.content_box.x2 - width, page.content_box.y1 / 2.0),
+            effects: Default::default(),
+            metadata,
+            glyphs: Vec::new(),
+        });
+    }
+}
+
+/// The default format for [number_pages]: "Page X of Y"
+pub fn page_x_of_y(page: usize, count: usize) -> String {
+    format!("Page {page} of {count}")
+}
+
+/// Calculate the width of a given string of text given the font and font size, including
+/// the kerning between its characters (see [Font::kerning])
 pub fn width_of_text(text: &str, font: &Font, size: Pt) -> Pt {
-    let scaling = size / font.face.as_face_ref().units_per_em() as f32;
-    text.chars()
-        .filter_map(|ch| font.glyph_id(ch))
-        .map(|gid| {
-            scaling
-                * font
-                    .face
-                    .as_face_ref()
-                    .glyph_hor_advance(owned_ttf_parser::GlyphId(gid))
-                    .unwrap_or_default() as f32
+    let scaling = size / font.units_per_em() as f32;
+    let ids: Vec<u16> = text.chars().filter_map(|ch| font.glyph_id(ch)).collect();
+    let advance: i32 = ids
+        .iter()
+        .map(|&id| font.glyph_advance(id) as i32)
+        .sum::<i32>()
+        + ids
+            .windows(2)
+            .map(|pair| font.kerning(pair[0], pair[1]) as i32)
+            .sum::<i32>();
+    scaling * advance as f32
+}
+
+/// The widest that a number formatted like `template` can be drawn in the given font and
+/// size, such as to size table columns before the numbers in them are known. Every
+/// digit in the template stands for any digit, and is measured as the font's widest
+/// digit; the other characters (such as signs, currency symbols, and separators) are
+/// measured as they are. Kerning is left out, since it depends on the actual digits.
+///
+/// ```
+/// # use pdf_gen::{layout, Font, Pt};
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// // room for amounts from -99,999.99 to 99,999.99
+/// let width = layout::max_number_width("-99,999.99", &font, Pt(10.0));
+/// assert_eq!(width.0.round(), 60.0);
+/// ```
+pub fn max_number_width(template: &str, font: &Font, size: Pt) -> Pt {
+    let scaling = size / font.units_per_em() as f32;
+    let advance = |ch: char| {
+        font.glyph_id_or_replacement(ch)
+            .map(|id| font.glyph_advance(id))
+            .unwrap_or_default()
+    };
+    let digit = ('0'..='9').map(advance).max().unwrap_or_default();
+    let units: u32 = template
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_digit() {
+                digit as u32
+            } else {
+                advance(ch) as u32
+            }
+        })
+        .sum();
+    scaling * units as f32
+}
+
+/// The offsets from the left of a column `width` wide at which to place each cell's text
+/// so that the cells are right-aligned on their decimal separators, as for columns of
+/// amounts in financial tables. Each cell is measured in its own font, so that rows in
+/// other fonts (such as totals in a bold font) line up too. The separator is found from
+/// the end of the text, so thousands separators are left alone, and cells without a
+/// separator end where the separators are. Cells too wide for the column start at its
+/// left edge.
+///
+/// ```
+/// # use pdf_gen::{layout, Document, Font, Pt, SpanFont};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let id = doc.add_font(font);
+/// let font = SpanFont { id, size: Pt(10.0) };
+/// let cells = [("1,250.00", font), ("9.5", font), ("42", font)];
+/// let offsets = layout::decimal_offsets(&doc, &cells, '.', Pt(100.0));
+/// // each character of the monospaced font is 6pt wide at 10pt
+/// let offsets: Vec<f32> = offsets.iter().map(|offset| offset.0.round()).collect();
+/// assert_eq!(offsets, vec![52.0, 76.0, 70.0]);
+/// ```
+pub fn decimal_offsets(
+    document: &Document,
+    cells: &[(&str, SpanFont)],
+    separator: char,
+    width: Pt,
+) -> Vec<Pt> {
+    // the widths of the text before and from the separator
+    let parts: Vec<(Pt, Pt)> = cells
+        .iter()
+        .map(|&(text, font)| {
+            let font_ref = &document.fonts[font.id];
+            let (whole, fraction) = text.split_at(text.rfind(separator).unwrap_or(text.len()));
+            (
+                width_of_text(whole, font_ref, font.size),
+                width_of_text(fraction, font_ref, font.size),
+            )
+        })
+        .collect();
+    let fraction = parts
+        .iter()
+        .map(|&(_, fraction)| fraction)
+        .fold(Pt(0.0), |a, b| if b > a { b } else { a });
+    parts
+        .into_iter()
+        .map(|(whole, _)| {
+            let offset = width - fraction - whole;
+            if offset < Pt(0.0) {
+                Pt(0.0)
+            } else {
+                offset
+            }
         })
-        .sum()
+        .collect()
 }
@@ -3,12 +3,38 @@
 //! Provides abstractions over [pdf-writer](https://crates.io/crates/pdf-writer) while including
 //! features and utilities such as:
 //!
-//! * Unicode font embedding
+//! * Unicode font embedding, with fallback fonts for characters a font doesn't cover
+//! * The standard 14 PDF fonts, used without embedding them
+//! * Checking fonts' licences allow them to be embedded
+//! * Text shaping for ligatures, contextual forms, and mark positioning (with the
+//!   `shaping` feature)
+//! * Loading installed fonts by family, weight, and style (with the `system-fonts` feature)
 //! * Raster and SVG image embedding
 //! * Page generation with laid out text spans, images, or raw PDF contents
+//! * Drawing helpers for shapes, arrows, and callouts
+//! * Simple maths formulas (fractions, scripts, and radicals), parsed from a subset of LaTeX
+//! * An optional top-left coordinate system for placing content
+//! * Reusable Form XObjects, drawn on any number of pages at any size
+//! * Measurement scales for technical drawings, honoured by viewers' measuring tools
 //! * Document metadata
-//! * Compressed streams where possible
-//! * Basic text layout utilities
+//! * Sidebar or breadcrumb navigation links generated from the document outline
+//! * Compressed streams, with a configurable compression level
+//! * Output size budgets, downsampling and compressing images as needed
+//! * Basic text layout utilities, with pluggable line breaking (including Unicode line
+//!   breaking with the `uax14` feature, and hyphenation with the `hyphenation` feature) and
+//!   diagnostics for loosely spaced lines
+//! * Row and column containers for laying out blocks within a region
+//! * Column grids for aligning blocks across a page
+//! * Simple tables, which can be generated from serde records or CSV (with the `records` feature)
+//! * Invoice and report builders
+//! * Mail merging of records into documents
+//! * Batch generation of many documents sharing the same fonts, images, and templates
+//! * Data-driven document templates (with the `templates` feature)
+//! * Incremental updates (such as adding annotations) to previously generated documents
+//! * Exporting links and highlights, and re-applying them to a revised document
+//! * Digital signatures, using a signer of your choice
+//! * Factur-X / ZUGFeRD e-invoices, written as PDF/A-3 with the invoice XML attached
+//! * Golden-file regression testing utilities (with the `test-support` feature)
 //!
 //! # Hello World Example
 //!
@@ -38,19 +64,19 @@
 //!     let start = layout::baseline_start(&page, &doc.fonts[fira_mono], Pt(16.0));
 //!
 //!     // add a span of text to the page
-//!     page.add_span(SpanLayout {
+//!     page.add_span(SpanLayout::new(
 //!         // that will say "Hello world!"
-//!         text: "Hello world!".to_string(),
+//!         "Hello world!",
 //!         // that will be presented in size 16pt Fira Mono font
-//!         font: SpanFont {
+//!         SpanFont {
 //!             id: fira_mono,
 //!             size: Pt(16.0),
 //!         },
 //!         // that will be black
-//!         colour: colours::BLACK,
+//!         colours::BLACK,
 //!         // and start where we calculated it should go before
-//!         coords: start,
-//!     });
+//!         start,
+//!     ));
 //!
 //!     // don't forget to add the page to the document (or it won't be rendered!)
 //!     doc.add_page(page);
@@ -69,37 +95,176 @@ pub use id_arena as id_arena_crate;
 pub use pdf_writer as pdf_writer_crate;
 pub use usvg as usvg_crate;
 
+mod annotations;
+pub use annotations::*;
+
+mod artifact;
+pub use artifact::*;
+
+mod budget;
+pub use budget::*;
+
+mod canvas;
+pub use canvas::*;
+
+mod changebars;
+
 mod colour;
 pub use colour::*;
 
+mod compression;
+pub use compression::*;
+
+mod diagnostics;
+pub use diagnostics::*;
+
 mod document;
 pub use document::*;
 
+mod einvoice;
+pub use einvoice::*;
+
+/// Utilities for drawing shapes, arrows, and callouts on pages
+pub mod drawing;
+
+mod fallback;
+pub use fallback::*;
+
+mod figure;
+pub use figure::*;
+
+mod flex;
+pub use flex::*;
+
 mod font;
 pub use font::*;
 
+mod form;
+pub use form::*;
+
+mod generator;
+pub use generator::*;
+
+mod grid;
+pub use grid::*;
+
 mod image;
 pub use self::image::*;
 
+mod dump;
+
+mod incremental;
+pub use incremental::*;
+
 mod info;
 pub use info::*;
 
+mod labels;
+pub use labels::*;
+
+mod linebreak;
+pub use linebreak::*;
+
+mod linkcheck;
+pub use linkcheck::*;
+
 /// Utility functions and structures to layout objects (most text) on pages
 pub mod layout;
 
+mod math;
+pub use math::*;
+
+mod measure;
+pub use measure::*;
+
+mod merge;
+pub use merge::*;
+
+mod navigation;
+pub use navigation::*;
+
+mod numbering;
+pub use numbering::*;
+
 mod page;
 pub use page::*;
 
+mod placed;
+pub use placed::*;
+
 mod rect;
 pub use rect::*;
 
+mod redact;
+
+/// Opinionated builders for business documents such as invoices and reports
+pub mod report;
+
+mod standard;
+pub use standard::*;
+
+mod style;
+pub use style::*;
+
+mod table;
+pub use table::*;
+
+#[cfg(feature = "test-support")]
+pub mod testing;
+
+#[cfg(feature = "templates")]
+mod template;
+#[cfg(feature = "templates")]
+pub use template::*;
+
 pub(crate) mod refs;
 
+mod resources;
+
+mod run;
+pub use run::*;
+
+mod shaping;
+pub use shaping::*;
+
+mod shared;
+
+mod signing;
+pub use signing::*;
+
+#[cfg(feature = "system-fonts")]
+mod system;
+#[cfg(feature = "system-fonts")]
+pub use system::*;
+
+mod tabs;
+pub use tabs::*;
+
+mod theme;
+pub use theme::*;
+
 mod units;
 pub use units::*;
 
+mod validate;
+
+mod embedding;
+pub use embedding::*;
+
 mod error;
 pub use error::*;
 
 mod outline;
 pub use outline::*;
+
+// fonts, images, and pages can be built on worker threads and then sent to the thread
+// which is assembling the document, and documents can be sent to other threads to be
+// written
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Document>();
+    assert_send::<Font>();
+    assert_send::<Image>();
+    assert_send::<Page>();
+    assert_send::<FormXObject>();
+};
@@ -0,0 +1,203 @@
+/// Decides where the layout functions (such as [crate::layout::layout_text]) may wrap
+/// text onto a new line. The breaker used is set with [crate::Document::line_breaker]:
+///
+/// ```
+/// # use pdf_gen::{Document, NaturalBreaker};
+/// let mut doc = Document::default();
+/// doc.line_breaker = Box::new(NaturalBreaker);
+/// ```
+///
+/// Any function which gives the break opportunities in a string is also a breaker, so
+/// domain-specific rules are easy to add, such as allowing URLs to break after slashes:
+///
+/// ```
+/// # use pdf_gen::Document;
+/// let mut doc = Document::default();
+/// doc.line_breaker = Box::new(|text: &str| -> Vec<usize> {
+///     let chars: Vec<char> = text.chars().collect();
+///     (1..chars.len())
+///         .filter(|&i| chars[i - 1] == '/' || (chars[i - 1] == ' ' && chars[i] != ' '))
+///         .collect()
+/// });
+/// ```
+///
+/// When a line overflows, it is broken at the last opportunity on it; if there isn't
+/// one (such as when a single word is wider than the line), it is broken before the
+/// character which overflowed. Breakers must be [Send], so that documents can be sent
+/// between threads.
+pub trait LineBreaker: Send {
+    /// The character (not byte) indices in `text` at which a new line may start.
+    /// Breakers are given one input span at a time; a break between two spans is
+    /// allowed if the first ends with whitespace, or if the second span starts with an
+    /// opportunity at index 0
+    fn break_opportunities(&self, text: &str) -> Vec<usize>;
+
+    /// Whether whitespace which overflows the end of a line is allowed to hang past it
+    /// and is dropped from the start of the next line, rather than being wrapped like any
+    /// other character
+    fn trims_whitespace(&self) -> bool {
+        true
+    }
+
+    /// The character (not byte) indices in `text` at which a word may be hyphenated:
+    /// broken with a hyphen drawn at the end of the line. A line is broken at whichever
+    /// of its opportunities and hyphenation points is last on it, so long as the hyphen
+    /// also fits on the line. Most breakers don't hyphenate words
+    fn hyphenation_points(&self, text: &str) -> Vec<usize> {
+        let _ = text;
+        Vec::new()
+    }
+}
+
+impl<F: Fn(&str) -> Vec<usize> + Send> LineBreaker for F {
+    fn break_opportunities(&self, text: &str) -> Vec<usize> {
+        self(text)
+    }
+}
+
+impl Default for Box<dyn LineBreaker> {
+    fn default() -> Self {
+        Box::new(NaiveBreaker)
+    }
+}
+
+/// Breaks lines before whichever character overflows them, even in the middle of a
+/// word, i.e. "asdf asdf" in a line 6 characters wide becomes "asdf a" and "sdf". This
+/// is the default, and suits text without words, such as code or hex dumps
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct NaiveBreaker;
+
+impl LineBreaker for NaiveBreaker {
+    fn break_opportunities(&self, text: &str) -> Vec<usize> {
+        (0..text.chars().count()).collect()
+    }
+
+    fn trims_whitespace(&self) -> bool {
+        false
+    }
+}
+
+/// Breaks lines between words (after runs of whitespace) and after hyphens and dashes
+/// within words, like most word processors do with western text
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct NaturalBreaker;
+
+impl LineBreaker for NaturalBreaker {
+    fn break_opportunities(&self, text: &str) -> Vec<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        (1..chars.len())
+            .filter(|&i| {
+                let (before, after) = (chars[i - 1], chars[i]);
+                if after.is_whitespace() {
+                    return false;
+                }
+                before.is_whitespace()
+                    || (matches!(before, '-' | '\u{2010}' | '\u{2013}' | '\u{2014}')
+                        && i >= 2
+                        && chars[i - 2].is_alphanumeric())
+            })
+            .collect()
+    }
+}
+
+/// Breaks lines according to the Unicode line breaking algorithm
+/// ([UAX #14](https://www.unicode.org/reports/tr14/)), which handles punctuation,
+/// numbers, and scripts which don't separate words with spaces (such as Chinese and
+/// Japanese). Only available with the `uax14` feature
+#[cfg(feature = "uax14")]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Uax14Breaker;
+
+#[cfg(feature = "uax14")]
+impl LineBreaker for Uax14Breaker {
+    fn break_opportunities(&self, text: &str) -> Vec<usize> {
+        let mut opportunities = Vec::new();
+        let mut chars = text.char_indices().enumerate().peekable();
+        for (byte, _) in unicode_linebreak::linebreaks(text) {
+            // convert byte offsets to character indices
+            while let Some(&(ci, (bi, _))) = chars.peek() {
+                if bi >= byte {
+                    if bi == byte {
+                        opportunities.push(ci);
+                    }
+                    break;
+                }
+                chars.next();
+            }
+        }
+        opportunities
+    }
+}
+
+/// Breaks lines as [NaturalBreaker] does, and also hyphenates words which would
+/// otherwise overflow the line, using Knuth–Liang hyphenation patterns for a language.
+/// Only available with the `hyphenation` feature
+///
+/// ```
+/// # use pdf_gen::{Document, HyphenatingBreaker};
+/// let mut doc = Document::default();
+/// doc.line_breaker = Box::new(HyphenatingBreaker::english_us().unwrap());
+/// ```
+#[cfg(feature = "hyphenation")]
+#[derive(Debug, Clone)]
+pub struct HyphenatingBreaker {
+    /// The hyphenation patterns for the language of the text
+    pub dictionary: hyphenation::Standard,
+}
+
+#[cfg(feature = "hyphenation")]
+impl HyphenatingBreaker {
+    /// Hyphenate words with the given patterns, such as those loaded with
+    /// [hyphenation::Load::from_path]
+    pub fn new(dictionary: hyphenation::Standard) -> HyphenatingBreaker {
+        HyphenatingBreaker { dictionary }
+    }
+
+    /// Hyphenate words with the patterns for American English, which are built in
+    pub fn english_us() -> Result<HyphenatingBreaker, crate::PDFError> {
+        use hyphenation::Load;
+        let dictionary = hyphenation::Standard::from_embedded(hyphenation::Language::EnglishUS)?;
+        Ok(HyphenatingBreaker { dictionary })
+    }
+}
+
+#[cfg(feature = "hyphenation")]
+impl LineBreaker for HyphenatingBreaker {
+    fn break_opportunities(&self, text: &str) -> Vec<usize> {
+        NaturalBreaker.break_opportunities(text)
+    }
+
+    fn hyphenation_points(&self, text: &str) -> Vec<usize> {
+        use hyphenation::Hyphenator;
+
+        let mut points = Vec::new();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut start = 0;
+        while start < chars.len() {
+            if !chars[start].1.is_alphabetic() {
+                start += 1;
+                continue;
+            }
+            let end = chars[start..]
+                .iter()
+                .position(|&(_, ch)| !ch.is_alphabetic())
+                .map(|n| start + n)
+                .unwrap_or(chars.len());
+            let (first_byte, _) = chars[start];
+            let last_byte = chars.get(end).map(|&(byte, _)| byte).unwrap_or(text.len());
+            let word = &text[first_byte..last_byte];
+
+            // the breaks are given as byte offsets into the word
+            for byte in self.dictionary.hyphenate(word).breaks.into_iter() {
+                if let Some(ci) = chars[start..end]
+                    .iter()
+                    .position(|&(b, _)| b == first_byte + byte)
+                {
+                    points.push(start + ci);
+                }
+            }
+            start = end;
+        }
+        points
+    }
+}
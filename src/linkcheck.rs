@@ -0,0 +1,171 @@
+use crate::document::Document;
+use crate::outline::OutlineId;
+use crate::page::{DestinationView, Page, PageLinkReference};
+use crate::rect::Rect;
+use crate::units::Pt;
+use id_arena::Id;
+use std::fmt;
+
+/// Something in the document which navigates to a page. See [BrokenReference]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceSource {
+    /// An entry in the document outline
+    Bookmark { title: String },
+    /// A heading registered with [Document::add_heading], which becomes a bookmark (and
+    /// typically a line in a table of contents)
+    Heading { title: String },
+    /// A link on a page, such as a line in a table of contents or a cross-reference
+    Link { page_index: usize, position: Rect },
+}
+
+/// Why a reference is broken. See [BrokenReference]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReferenceProblem {
+    /// The page that is referred to isn't in the document
+    PageMissing,
+    /// The page exists, but the position on it that is referred to is outside of its
+    /// media box
+    OutsidePage {
+        /// The 0-based index of the page that is referred to
+        page_index: usize,
+    },
+}
+
+/// A bookmark, heading, or link which refers to a page that doesn't exist, or to a
+/// position outside of the page it refers to. See [Document::broken_references]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenReference {
+    /// What refers to the page
+    pub source: ReferenceSource,
+    /// What is wrong with the reference
+    pub problem: ReferenceProblem,
+}
+
+impl fmt::Display for BrokenReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            ReferenceSource::Bookmark { title } => write!(f, "the bookmark \"{title}\"")?,
+            ReferenceSource::Heading { title } => write!(f, "the heading \"{title}\"")?,
+            ReferenceSource::Link {
+                page_index,
+                position,
+            } => write!(
+                f,
+                "the link on page {page_index} at [{} {} {} {}]",
+                *position.x1, *position.y1, *position.x2, *position.y2
+            )?,
+        }
+        match self.problem {
+            ReferenceProblem::PageMissing => write!(f, " refers to a page which doesn't exist"),
+            ReferenceProblem::OutsidePage { page_index } => {
+                write!(f, " refers to a position outside of page {page_index}")
+            }
+        }
+    }
+}
+
+impl Document {
+    /// Check that every bookmark in the outline, every registered heading, and every
+    /// link between pages of the document refers to a page which is in the document and
+    /// (where it refers to a position on the page) to a position within that page's
+    /// media box, returning every reference which doesn't.
+    ///
+    /// This is checked when the document is written if [Document::validate_references]
+    /// is enabled, so that a table of contents or outline which has fallen out of step
+    /// with the pages (i.e. after pages were removed or reordered) is reported rather
+    /// than written.
+    pub fn broken_references(&self) -> Vec<BrokenReference> {
+        let mut broken = Vec::new();
+
+        // visit the outline's entries in order, depth first
+        let mut entries: Vec<OutlineId> = self.outline.roots.iter().rev().copied().collect();
+        while let Some(id) = entries.pop() {
+            let entry = &self.outline.entries[id];
+            entries.extend(entry.children.iter().rev().copied());
+            let problem = match self.page_order.get(entry.page_index) {
+                None => Some(ReferenceProblem::PageMissing),
+                Some(&page) => entry
+                    .top
+                    .and_then(|top| self.outside(entry.page_index, page, &[], &[top])),
+            };
+            if let Some(problem) = problem {
+                broken.push(BrokenReference {
+                    source: ReferenceSource::Bookmark {
+                        title: entry.title.clone(),
+                    },
+                    problem,
+                });
+            }
+        }
+
+        for heading in self.headings.iter() {
+            let problem = match self.page_order.iter().position(|&p| p == heading.page) {
+                None => Some(ReferenceProblem::PageMissing),
+                Some(page_index) => self.outside(page_index, heading.page, &[], &[heading.y]),
+            };
+            if let Some(problem) = problem {
+                broken.push(BrokenReference {
+                    source: ReferenceSource::Heading {
+                        title: heading.title.clone(),
+                    },
+                    problem,
+                });
+            }
+        }
+
+        for (page_index, id) in self.page_order.iter().enumerate() {
+            for link in self.pages[*id].links.iter() {
+                let target = match link.page {
+                    PageLinkReference::ById(page) => self
+                        .page_order
+                        .iter()
+                        .position(|&p| p == page)
+                        .map(|index| (index, page)),
+                    PageLinkReference::ByIndex(index) => {
+                        self.page_order.get(index).map(|&page| (index, page))
+                    }
+                };
+                let problem = match target {
+                    None => Some(ReferenceProblem::PageMissing),
+                    Some((index, page)) => match link.view {
+                        DestinationView::Fit => None,
+                        DestinationView::Top(top) => self.outside(index, page, &[], &[top]),
+                        DestinationView::Rect(rect) => {
+                            self.outside(index, page, &[rect.x1, rect.x2], &[rect.y1, rect.y2])
+                        }
+                    },
+                };
+                if let Some(problem) = problem {
+                    broken.push(BrokenReference {
+                        source: ReferenceSource::Link {
+                            page_index,
+                            position: link.position,
+                        },
+                        problem,
+                    });
+                }
+            }
+        }
+
+        broken
+    }
+
+    /// The problem with a reference to positions on a page, if any of them aren't
+    /// within the page's media box
+    fn outside(
+        &self,
+        page_index: usize,
+        page: Id<Page>,
+        xs: &[Pt],
+        ys: &[Pt],
+    ) -> Option<ReferenceProblem> {
+        let media_box = self.pages[page].media_box;
+        let within = xs.iter().all(|&x| x >= media_box.x1 && x <= media_box.x2)
+            && ys.iter().all(|&y| y >= media_box.y1 && y <= media_box.y2);
+        if within {
+            None
+        } else {
+            Some(ReferenceProblem::OutsidePage { page_index })
+        }
+    }
+}
@@ -0,0 +1,654 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::drawing::{self, DrawStyle, Path};
+use crate::error::excerpt;
+use crate::layout;
+use crate::page::{ContentTarget, SpanFont, SpanLayout};
+use crate::rect::Rect;
+use crate::run::TextEffects;
+use crate::units::Pt;
+use crate::PDFError;
+use pdf_writer::Content;
+
+/// How much smaller the numerator and denominator of a fraction are than the text
+/// around them
+const FRACTION_SCALE: f32 = 0.85;
+/// How much smaller superscripts and subscripts are than their base
+const SCRIPT_SCALE: f32 = 0.7;
+/// The height of the maths axis (where fraction bars and the middles of operators sit)
+/// above the baseline, in ems
+const AXIS_HEIGHT: f32 = 0.25;
+/// The thickness of fraction bars and radical signs, in ems
+const RULE_THICKNESS: f32 = 0.05;
+
+/// A part of a mathematical formula (see [Formula])
+#[derive(Debug, Clone, PartialEq)]
+pub enum MathNode {
+    /// A variable, number, or other ordinary text. Single letters are variables, which
+    /// are slanted unless [Formula::slant_variables] is turned off
+    Text(String),
+    /// An operator, such as `+` or `=`, which is spaced from the nodes around it.
+    /// Relations (such as `=` and `<`) get more space than binary operators (such as
+    /// `+`), and binary operators at the start of a row (such as a leading minus sign)
+    /// don't get any
+    Operator(String),
+    /// A sequence of nodes, placed one after another
+    Row(Vec<MathNode>),
+    /// A fraction, with its numerator centred over its denominator
+    Fraction {
+        numerator: Box<MathNode>,
+        denominator: Box<MathNode>,
+    },
+    /// A base with a subscript, a superscript, or both
+    Scripts {
+        base: Box<MathNode>,
+        subscript: Option<Box<MathNode>>,
+        superscript: Option<Box<MathNode>>,
+    },
+    /// A square root
+    Radical(Box<MathNode>),
+}
+
+impl MathNode {
+    /// Parse a formula written in a small subset of LaTeX's maths syntax:
+    ///
+    /// * Letters, digits (including decimal numbers), and punctuation
+    /// * The operators `+`, `-`, `*`, `/`, `=`, `<`, and `>`
+    /// * Groups in braces, such as `{a + b}`
+    /// * Superscripts and subscripts, such as `x^2`, `x_i`, and `x_i^{n+1}`
+    /// * `\frac{numerator}{denominator}` and `\sqrt{radicand}`
+    /// * Greek letters (such as `\alpha` and `\Omega`), the symbols `\infty`,
+    ///   `\partial`, and `\sum`, the operators `\times`, `\cdot`, `\div`, `\pm`,
+    ///   `\leq`, `\geq`, `\neq`, `\approx`, `\equiv`, and `\to`, and the functions
+    ///   `\sin`, `\cos`, `\tan`, `\log`, `\ln`, and `\exp`
+    ///
+    /// Whitespace is ignored, as in LaTeX.
+    pub fn parse(formula: &str) -> Result<MathNode, PDFError> {
+        let mut parser = Parser {
+            formula,
+            chars: formula.chars().collect(),
+            position: 0,
+        };
+        let row = parser.row()?;
+        match parser.peek() {
+            Some('}') => Err(parser.error("there is an unmatched '}'")),
+            _ => Ok(row),
+        }
+    }
+}
+
+/// A mathematical formula, such as a fraction or an equation, laid out from text and
+/// lines rather than being rendered to an image beforehand. Formulas are built from
+/// [MathNode]s, or parsed from a subset of LaTeX (see [MathNode::parse]):
+///
+/// ```
+/// # use pdf_gen::{Document, Font, Formula, Page, Pt, SpanFont};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let id = doc.add_font(font);
+/// # let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+/// let font = SpanFont { id, size: Pt(12.0) };
+/// let formula = Formula::parse(r"x = \frac{-b \pm \sqrt{b^2 - 4ac}}{2a}", font).unwrap();
+/// let (width, ascent, descent) = formula.measure(&doc);
+/// formula.layout(&doc, &mut page, (Pt(72.0), Pt(700.0)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    /// The content of the formula
+    pub root: MathNode,
+    /// The font (and size) of the formula's text. Fractions and scripts are drawn in
+    /// smaller sizes of the same font
+    pub font: SpanFont,
+    /// The colour of the formula's text and lines
+    pub colour: Colour,
+    /// Whether single-letter variables are slanted (see
+    /// [TextEffects::synthetic_oblique]), as maths is usually typeset. Turn this off
+    /// when the font is already italic
+    pub slant_variables: bool,
+}
+
+impl Formula {
+    /// Create a black formula in the given font
+    pub fn new(root: MathNode, font: SpanFont) -> Formula {
+        Formula {
+            root,
+            font,
+            colour: crate::colours::BLACK,
+            slant_variables: true,
+        }
+    }
+
+    /// Parse a black formula in the given font (see [MathNode::parse])
+    pub fn parse(formula: &str, font: SpanFont) -> Result<Formula, PDFError> {
+        Ok(Formula::new(MathNode::parse(formula)?, font))
+    }
+
+    /// Set the colour of the formula
+    pub fn colour(&mut self, colour: Colour) -> &mut Self {
+        self.colour = colour;
+        self
+    }
+
+    /// The width of the formula, and how far it extends above its baseline (its ascent)
+    /// and below it (its descent, which is usually negative)
+    pub fn measure(&self, document: &Document) -> (Pt, Pt, Pt) {
+        let laid_out = self.layout_node(document, &self.root, self.font.size);
+        (laid_out.width, laid_out.ascent, laid_out.descent)
+    }
+
+    /// Lay out the formula with the start of its baseline at `origin`, returning its
+    /// width
+    pub fn layout<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        origin: (Pt, Pt),
+    ) -> Pt {
+        let laid_out = self.layout_node(document, &self.root, self.font.size);
+        let mut content = Content::new();
+        let mut has_lines = false;
+        for item in laid_out.items.into_iter() {
+            match item {
+                MathItem::Text {
+                    text,
+                    font,
+                    slanted,
+                    at,
+                } => {
+                    let effects = TextEffects {
+                        synthetic_oblique: slanted,
+                        ..Default::default()
+                    };
+                    page.add_span(SpanLayout {
+                        text,
+                        font,
+                        colour: self.colour,
+                        coords: (origin.0 + at.0, origin.1 + at.1),
+                        effects,
+                        metadata: Default::default(),
+                        glyphs: Vec::new(),
+                    });
+                }
+                MathItem::Rule(rect) => {
+                    let rect = Rect {
+                        x1: origin.0 + rect.x1,
+                        y1: origin.1 + rect.y1,
+                        x2: origin.0 + rect.x2,
+                        y2: origin.1 + rect.y2,
+                    };
+                    drawing::draw(&mut content, &rect, &DrawStyle::filled(self.colour));
+                    has_lines = true;
+                }
+                MathItem::Stroke(path, width) => {
+                    let path = path.translated(origin.0, origin.1);
+                    drawing::draw(&mut content, &path, &DrawStyle::stroked(self.colour, width));
+                    has_lines = true;
+                }
+            }
+        }
+        if has_lines {
+            page.add_content(content);
+        }
+        laid_out.width
+    }
+
+    /// Lay out a node at the given size, relative to the start of its baseline
+    fn layout_node(&self, document: &Document, node: &MathNode, size: Pt) -> MathBox {
+        match node {
+            MathNode::Text(text) => {
+                let slanted = self.slant_variables
+                    && text.chars().count() == 1
+                    && text.chars().all(char::is_alphabetic);
+                self.text_box(document, text.clone(), size, slanted)
+            }
+            MathNode::Operator(operator) => {
+                // use a real minus sign rather than a hyphen where the font has one
+                let operator = match operator.as_str() {
+                    "-" if document.fonts[self.font.id].glyph_id('\u{2212}').is_some() => {
+                        "\u{2212}".to_string()
+                    }
+                    _ => operator.clone(),
+                };
+                self.text_box(document, operator, size, false)
+            }
+            MathNode::Row(nodes) => {
+                let mut row = MathBox::empty();
+                for (ni, node) in nodes.iter().enumerate() {
+                    let space = match node {
+                        MathNode::Operator(operator) => {
+                            let follows_operand =
+                                ni > 0 && !matches!(nodes[ni - 1], MathNode::Operator(_));
+                            operator_space(operator, follows_operand) * size
+                        }
+                        _ => Pt(0.0),
+                    };
+                    let laid_out = self.layout_node(document, node, size);
+                    let x = row.width + space;
+                    row.width = x + laid_out.width + space;
+                    row.place(laid_out, (x, Pt(0.0)));
+                }
+                row
+            }
+            MathNode::Fraction {
+                numerator,
+                denominator,
+            } => {
+                let numerator = self.layout_node(document, numerator, size * FRACTION_SCALE);
+                let denominator = self.layout_node(document, denominator, size * FRACTION_SCALE);
+                let axis = size * AXIS_HEIGHT;
+                let thickness = size * RULE_THICKNESS;
+                let gap = size * 0.15;
+                let padding = size * 0.1;
+                let inner = if numerator.width > denominator.width {
+                    numerator.width
+                } else {
+                    denominator.width
+                };
+
+                let mut fraction = MathBox::empty();
+                fraction.width = inner + padding * 2.0;
+                let above = axis + thickness / 2.0 + gap - numerator.descent;
+                let below = axis - thickness / 2.0 - gap - denominator.ascent;
+                let numerator_x = padding + (inner - numerator.width) / 2.0;
+                let denominator_x = padding + (inner - denominator.width) / 2.0;
+                fraction.place(numerator, (numerator_x, above));
+                fraction.place(denominator, (denominator_x, below));
+                fraction.items.push(MathItem::Rule(Rect {
+                    x1: Pt(0.0),
+                    y1: axis - thickness / 2.0,
+                    x2: fraction.width,
+                    y2: axis + thickness / 2.0,
+                }));
+                fraction
+            }
+            MathNode::Scripts {
+                base,
+                subscript,
+                superscript,
+            } => {
+                let mut scripts = self.layout_node(document, base, size);
+                let base_ascent = scripts.ascent;
+                let base_descent = scripts.descent;
+                let x = scripts.width;
+                let superscript = superscript
+                    .as_ref()
+                    .map(|node| self.layout_node(document, node, size * SCRIPT_SCALE));
+                let subscript = subscript
+                    .as_ref()
+                    .map(|node| self.layout_node(document, node, size * SCRIPT_SCALE));
+
+                // raise superscripts past the top of tall bases, and lower subscripts past
+                // the bottom of deep ones
+                let mut rise = superscript
+                    .as_ref()
+                    .map(|sup| larger(size * 0.4, base_ascent - sup.ascent * 0.8))
+                    .unwrap_or_default();
+                let drop = subscript
+                    .as_ref()
+                    .map(|sub| larger(size * 0.2, Pt(0.0) - base_descent - sub.ascent * 0.5))
+                    .unwrap_or_default();
+                if let (Some(sup), Some(sub)) = (&superscript, &subscript) {
+                    // keep the scripts apart when there are both
+                    let clearance = (rise + sup.descent) - (sub.ascent - drop);
+                    if clearance < size * 0.1 {
+                        rise += size * 0.1 - clearance;
+                    }
+                }
+
+                let mut width = Pt(0.0);
+                if let Some(sup) = superscript {
+                    width = larger(width, sup.width);
+                    scripts.place(sup, (x, rise));
+                }
+                if let Some(sub) = subscript {
+                    width = larger(width, sub.width);
+                    scripts.place(sub, (x, Pt(0.0) - drop));
+                }
+                scripts.width = x + width + size * 0.05;
+                scripts
+            }
+            MathNode::Radical(radicand) => {
+                let radicand = self.layout_node(document, radicand, size);
+                let thickness = size * RULE_THICKNESS;
+                let gap = size * 0.1;
+                let sign = size * 0.5;
+                let top = radicand.ascent + gap;
+                let bottom = radicand.descent;
+                let height = top - bottom;
+
+                let mut radical = MathBox::empty();
+                radical.width = sign + radicand.width + gap * 2.0;
+                let mut path = Path::new();
+                path.move_to((Pt(0.0), bottom + height * 0.45))
+                    .line_to((sign * 0.35, bottom))
+                    .line_to((sign, top))
+                    .line_to((radical.width, top));
+                radical.items.push(MathItem::Stroke(path, thickness));
+                radical.place(radicand, (sign + gap, Pt(0.0)));
+                radical.ascent = larger(radical.ascent, top + thickness);
+                radical
+            }
+        }
+    }
+
+    /// A box holding some text at the given size
+    fn text_box(&self, document: &Document, text: String, size: Pt, slanted: bool) -> MathBox {
+        let font = SpanFont {
+            id: self.font.id,
+            size,
+        };
+        let font_ref = &document.fonts[font.id];
+        MathBox {
+            width: layout::width_of_text(&text, font_ref, size),
+            ascent: font_ref.ascent(size),
+            descent: font_ref.descent(size),
+            items: vec![MathItem::Text {
+                text,
+                font,
+                slanted,
+                at: (Pt(0.0), Pt(0.0)),
+            }],
+        }
+    }
+}
+
+/// The space on either side of an operator, in ems: more for relations than for binary
+/// operators, which only get space between two operands
+fn operator_space(operator: &str, follows_operand: bool) -> f32 {
+    match operator {
+        "=" | "<" | ">" | "\u{2264}" | "\u{2265}" | "\u{2260}" | "\u{2248}" | "\u{2261}"
+        | "\u{2192}" => 5.0 / 18.0,
+        _ if follows_operand => 4.0 / 18.0,
+        _ => 0.0,
+    }
+}
+
+fn larger(a: Pt, b: Pt) -> Pt {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+/// Something drawn as part of a formula, positioned relative to the start of the
+/// baseline of the node it belongs to
+#[derive(Debug, Clone)]
+enum MathItem {
+    Text {
+        text: String,
+        font: SpanFont,
+        slanted: bool,
+        at: (Pt, Pt),
+    },
+    /// A filled rectangle, such as a fraction bar
+    Rule(Rect),
+    /// A stroked path with the given line width, such as a radical sign
+    Stroke(Path, Pt),
+}
+
+/// A laid out node of a formula: its width, how far it extends above and below its
+/// baseline, and what it draws
+#[derive(Debug, Clone)]
+struct MathBox {
+    width: Pt,
+    ascent: Pt,
+    descent: Pt,
+    items: Vec<MathItem>,
+}
+
+impl MathBox {
+    fn empty() -> MathBox {
+        MathBox {
+            width: Pt(0.0),
+            ascent: Pt(0.0),
+            descent: Pt(0.0),
+            items: Vec::new(),
+        }
+    }
+
+    /// Add another box's items, with the start of its baseline at `at`. The width isn't
+    /// changed, since where the next item goes depends on the kind of node
+    fn place(&mut self, other: MathBox, (dx, dy): (Pt, Pt)) {
+        self.ascent = larger(self.ascent, other.ascent + dy);
+        if other.descent + dy < self.descent {
+            self.descent = other.descent + dy;
+        }
+        self.items
+            .extend(other.items.into_iter().map(|item| match item {
+                MathItem::Text {
+                    text,
+                    font,
+                    slanted,
+                    at,
+                } => MathItem::Text {
+                    text,
+                    font,
+                    slanted,
+                    at: (at.0 + dx, at.1 + dy),
+                },
+                MathItem::Rule(rect) => MathItem::Rule(Rect {
+                    x1: rect.x1 + dx,
+                    y1: rect.y1 + dy,
+                    x2: rect.x2 + dx,
+                    y2: rect.y2 + dy,
+                }),
+                MathItem::Stroke(path, width) => MathItem::Stroke(path.translated(dx, dy), width),
+            }));
+    }
+}
+
+/// Parses the LaTeX subset described at [MathNode::parse]
+struct Parser<'a> {
+    formula: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> PDFError {
+        PDFError::MalformedFormula {
+            formula: excerpt(self.formula),
+            reason,
+        }
+    }
+
+    /// The next character which isn't whitespace, without consuming it
+    fn peek(&mut self) -> Option<char> {
+        while matches!(self.chars.get(self.position), Some(ch) if ch.is_whitespace()) {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    /// Nodes up to the end of the formula or the end of the current group
+    fn row(&mut self) -> Result<MathNode, PDFError> {
+        let mut nodes = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '}' {
+                break;
+            }
+            let base = if ch == '^' || ch == '_' {
+                // scripts without a base are attached to nothing
+                MathNode::Row(Vec::new())
+            } else {
+                self.atom()?
+            };
+            nodes.push(self.scripts(base)?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => MathNode::Row(nodes),
+        })
+    }
+
+    /// Any superscript and subscript following a base
+    fn scripts(&mut self, base: MathNode) -> Result<MathNode, PDFError> {
+        let mut subscript = None;
+        let mut superscript = None;
+        while let Some(ch @ ('^' | '_')) = self.peek() {
+            self.position += 1;
+            let script = if ch == '^' {
+                &mut superscript
+            } else {
+                &mut subscript
+            };
+            if script.is_some() {
+                return Err(self.error("a node has two superscripts or two subscripts"));
+            }
+            *script = Some(Box::new(self.argument()?));
+        }
+        Ok(match (subscript, superscript) {
+            (None, None) => base,
+            (subscript, superscript) => MathNode::Scripts {
+                base: Box::new(base),
+                subscript,
+                superscript,
+            },
+        })
+    }
+
+    /// A group in braces, or a single atom
+    fn argument(&mut self) -> Result<MathNode, PDFError> {
+        match self.peek() {
+            None | Some('}') => Err(self.error("a command or script is missing its argument")),
+            Some(_) => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<MathNode, PDFError> {
+        let ch = match self.peek() {
+            Some(ch) => ch,
+            None => return Err(self.error("the formula ended unexpectedly")),
+        };
+        self.position += 1;
+        match ch {
+            '{' => {
+                let group = self.row()?;
+                if self.peek() != Some('}') {
+                    return Err(self.error("there is an unmatched '{'"));
+                }
+                self.position += 1;
+                Ok(group)
+            }
+            '\\' => self.command(),
+            '0'..='9' | '.' => {
+                let mut number = ch.to_string();
+                while let Some(&(next @ ('0'..='9' | '.'))) = self.chars.get(self.position) {
+                    number.push(next);
+                    self.position += 1;
+                }
+                Ok(MathNode::Text(number))
+            }
+            '+' | '-' | '=' | '<' | '>' => Ok(MathNode::Operator(ch.to_string())),
+            '*' => Ok(MathNode::Operator("\u{2217}".to_string())),
+            _ => Ok(MathNode::Text(ch.to_string())),
+        }
+    }
+
+    /// A command, after its backslash
+    fn command(&mut self) -> Result<MathNode, PDFError> {
+        let mut name = String::new();
+        while let Some(&ch) = self
+            .chars
+            .get(self.position)
+            .filter(|ch| ch.is_ascii_alphabetic())
+        {
+            name.push(ch);
+            self.position += 1;
+        }
+        if name.is_empty() {
+            // escaped characters, such as braces
+            return match self.chars.get(self.position) {
+                Some(&ch) => {
+                    self.position += 1;
+                    Ok(MathNode::Text(ch.to_string()))
+                }
+                None => Err(self.error("the formula ends with a backslash")),
+            };
+        }
+
+        match name.as_str() {
+            "frac" => Ok(MathNode::Fraction {
+                numerator: Box::new(self.argument()?),
+                denominator: Box::new(self.argument()?),
+            }),
+            "sqrt" => Ok(MathNode::Radical(Box::new(self.argument()?))),
+            "sin" | "cos" | "tan" | "log" | "ln" | "exp" => Ok(MathNode::Text(name)),
+            _ => {
+                if let Some(operator) = operator_symbol(&name) {
+                    Ok(MathNode::Operator(operator.to_string()))
+                } else if let Some(symbol) = symbol(&name) {
+                    Ok(MathNode::Text(symbol.to_string()))
+                } else {
+                    Err(self.error("the formula uses an unsupported command"))
+                }
+            }
+        }
+    }
+}
+
+/// The character of an operator command
+fn operator_symbol(name: &str) -> Option<char> {
+    Some(match name {
+        "times" => '\u{d7}',
+        "cdot" => '\u{22c5}',
+        "div" => '\u{f7}',
+        "pm" => '\u{b1}',
+        "leq" | "le" => '\u{2264}',
+        "geq" | "ge" => '\u{2265}',
+        "neq" | "ne" => '\u{2260}',
+        "approx" => '\u{2248}',
+        "equiv" => '\u{2261}',
+        "to" | "rightarrow" => '\u{2192}',
+        _ => return None,
+    })
+}
+
+/// The character of a symbol command, such as a Greek letter
+fn symbol(name: &str) -> Option<char> {
+    const GREEK: [(&str, char); 24] = [
+        ("alpha", 'α'),
+        ("beta", 'β'),
+        ("gamma", 'γ'),
+        ("delta", 'δ'),
+        ("epsilon", 'ε'),
+        ("zeta", 'ζ'),
+        ("eta", 'η'),
+        ("theta", 'θ'),
+        ("iota", 'ι'),
+        ("kappa", 'κ'),
+        ("lambda", 'λ'),
+        ("mu", 'μ'),
+        ("nu", 'ν'),
+        ("xi", 'ξ'),
+        ("omicron", 'ο'),
+        ("pi", 'π'),
+        ("rho", 'ρ'),
+        ("sigma", 'σ'),
+        ("tau", 'τ'),
+        ("upsilon", 'υ'),
+        ("phi", 'φ'),
+        ("chi", 'χ'),
+        ("psi", 'ψ'),
+        ("omega", 'ω'),
+    ];
+    match name {
+        "infty" => return Some('\u{221e}'),
+        "partial" => return Some('\u{2202}'),
+        "sum" => return Some('\u{2211}'),
+        _ => {}
+    }
+    GREEK.iter().find_map(|&(lower, ch)| {
+        if lower == name {
+            Some(ch)
+        } else if name.len() == lower.len()
+            && name[..1] == lower[..1].to_uppercase()
+            && name[1..] == lower[1..]
+        {
+            ch.to_uppercase().next()
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,119 @@
+use crate::rect::Rect;
+use crate::units::{Mm, Pt};
+use pdf_writer::{Name, Obj, TextStr};
+
+/// A rectilinear measurement scale, which tells the measuring tools of PDF viewers how
+/// distances on the page correspond to distances in the real world. For example, a
+/// floor plan drawn at 1:50 would use `Measure::metric(50.0)`, so that a line 20 mm
+/// long on the page measures as 1 m.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure {
+    /// A description of the scale, shown by viewers, such as `"1:50"`
+    pub ratio: String,
+    /// The label for the real-world units, such as `"m"` or `"ft"`
+    pub units: String,
+    /// How many real-world units one point on the page corresponds to
+    pub factor: f32,
+    /// The precision that measurements are displayed with, as a power of 10 (i.e. `100`
+    /// displays measurements to two decimal places)
+    pub precision: u32,
+}
+
+impl Measure {
+    /// Create a scale where one point on the page is `factor` real-world `units`
+    pub fn new<R: ToString, U: ToString>(ratio: R, units: U, factor: f32) -> Measure {
+        Measure {
+            ratio: ratio.to_string(),
+            units: units.to_string(),
+            factor,
+            precision: 100,
+        }
+    }
+
+    /// Create a scale for a drawing at 1:`scale`, measured in metres
+    pub fn metric(scale: f32) -> Measure {
+        let metres_per_pt = *Mm::from(Pt(1.0)) / 1000.0;
+        Measure::new(format!("1:{scale}"), "m", metres_per_pt * scale)
+    }
+
+    /// Create a scale where `page_inches` inches on the page is one real-world foot, such
+    /// as architectural scales like ¼" = 1'
+    pub fn imperial(page_inches: f32) -> Measure {
+        Measure::new(
+            format!("{page_inches} in = 1 ft"),
+            "ft",
+            1.0 / (page_inches * 72.0),
+        )
+    }
+
+    /// Set the precision that measurements are displayed with, as a power of 10
+    pub fn precision(&mut self, precision: u32) -> &mut Self {
+        self.precision = precision;
+        self
+    }
+
+    fn write(&self, obj: Obj<'_>) {
+        let mut measure = obj.dict();
+        measure.pair(Name(b"Type"), Name(b"Measure"));
+        measure.pair(Name(b"Subtype"), Name(b"RL"));
+        measure.pair(Name(b"R"), TextStr(&self.ratio));
+        // the same number format is used for distances along x, along y, and for areas
+        // (as areas are measured in the square of the units, they get their own format)
+        for (key, units, factor) in [
+            (&b"X"[..], self.units.clone(), self.factor),
+            (&b"D"[..], self.units.clone(), 1.0),
+            (&b"A"[..], format!("sq {}", self.units), 1.0),
+        ] {
+            let mut formats = measure.insert(Name(key)).array();
+            let mut format = formats.push().dict();
+            format.pair(Name(b"Type"), Name(b"NumberFormat"));
+            format.pair(Name(b"U"), TextStr(&units));
+            format.pair(Name(b"C"), factor);
+            format.pair(Name(b"D"), self.precision as i32);
+            format.finish();
+            formats.finish();
+        }
+        measure.finish();
+    }
+}
+
+/// A region of a page with its own measurement scale (see [crate::Page::add_viewport]).
+/// Pages may contain several viewports, such as a floor plan at 1:50 alongside a detail
+/// at 1:10.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewport {
+    /// The region of the page that the scale applies to
+    pub bbox: Rect,
+    /// An optional name for the viewport, shown by some viewers
+    pub name: Option<String>,
+    /// The scale of the region
+    pub measure: Measure,
+}
+
+impl Viewport {
+    /// Create a viewport with no name
+    pub fn new(bbox: Rect, measure: Measure) -> Viewport {
+        Viewport {
+            bbox,
+            name: None,
+            measure,
+        }
+    }
+
+    /// Set the name of the viewport
+    pub fn name<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn write(&self, obj: Obj<'_>) {
+        let mut viewport = obj.dict();
+        viewport.pair(Name(b"Type"), Name(b"Viewport"));
+        viewport.pair(Name(b"BBox"), pdf_writer::Rect::from(self.bbox));
+        if let Some(name) = &self.name {
+            viewport.pair(Name(b"Name"), TextStr(name));
+        }
+        self.measure.write(viewport.insert(Name(b"Measure")));
+        viewport.finish();
+    }
+}
@@ -0,0 +1,112 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::layout;
+use crate::page::{Page, SpanFont};
+use id_arena::Id;
+
+/// The pages generated for a single record in a [MailMerge]
+pub struct MergeRecord {
+    /// The title of the record, used for its bookmark in the document outline
+    pub title: String,
+    /// The pages for the record, in order
+    pub pages: Vec<Page>,
+}
+
+/// Generates documents from a single page-building closure and a list of data records,
+/// such as form letters, certificates, or statements. Each record may produce any number
+/// of pages; page numbers are counted separately for each record.
+#[derive(Debug, Clone)]
+pub struct MailMerge {
+    /// If set, each page is numbered (within its record) in the given font and colour
+    pub page_numbers: Option<(SpanFont, Colour)>,
+    /// How page numbers are formatted, given the 1-based page number within the record
+    /// and the number of pages in the record
+    pub page_number_format: fn(usize, usize) -> String,
+    /// Whether to add a bookmark for each record pointing at its first page
+    pub bookmarks: bool,
+}
+
+impl Default for MailMerge {
+    fn default() -> Self {
+        MailMerge {
+            page_numbers: None,
+            page_number_format: layout::page_x_of_y,
+            bookmarks: true,
+        }
+    }
+}
+
+impl MailMerge {
+    /// Create a new mail merge without page numbers, but with bookmarks
+    pub fn new() -> MailMerge {
+        MailMerge::default()
+    }
+
+    /// Number each page (within its record) using the given font and colour
+    pub fn with_page_numbers(mut self, font: SpanFont, colour: Colour) -> MailMerge {
+        self.page_numbers = Some((font, colour));
+        self
+    }
+
+    /// Generate the pages for every record, adding them all to the end of a single
+    /// document. Returns the Ids of the pages generated for each record.
+    pub fn merge<T, I, F>(
+        &self,
+        document: &mut Document,
+        records: I,
+        mut build: F,
+    ) -> Vec<Vec<Id<Page>>>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&Document, T) -> MergeRecord,
+    {
+        let mut ids = Vec::new();
+        for record in records.into_iter() {
+            let record = build(document, record);
+            ids.push(self.add_record(document, record));
+        }
+        ids
+    }
+
+    /// Generate a separate document for every record. `new_document` is called to
+    /// create each document (and should add any fonts and images needed by `build`).
+    pub fn merge_separately<T, I, F, D>(
+        &self,
+        records: I,
+        mut new_document: D,
+        mut build: F,
+    ) -> Vec<Document>
+    where
+        I: IntoIterator<Item = T>,
+        D: FnMut() -> Document,
+        F: FnMut(&Document, T) -> MergeRecord,
+    {
+        records
+            .into_iter()
+            .map(|record| {
+                let mut document = new_document();
+                let record = build(&document, record);
+                self.add_record(&mut document, record);
+                document
+            })
+            .collect()
+    }
+
+    fn add_record(&self, document: &mut Document, record: MergeRecord) -> Vec<Id<Page>> {
+        let MergeRecord { title, mut pages } = record;
+        if let Some((font, colour)) = self.page_numbers {
+            layout::number_pages(document, &mut pages, font, colour, self.page_number_format);
+        }
+
+        let ids: Vec<Id<Page>> = pages
+            .into_iter()
+            .map(|page| document.add_page(page))
+            .collect();
+        if self.bookmarks {
+            if let Some(page_index) = ids.first().and_then(|&id| document.index_of_page(id)) {
+                document.add_bookmark(None, title, page_index);
+            }
+        }
+        ids
+    }
+}
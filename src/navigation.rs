@@ -0,0 +1,274 @@
+use crate::artifact::Artifact;
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::outline::{Outline, OutlineId};
+use crate::page::{
+    DestinationView, IntraDocumentLink, PageLinkReference, SpanFont, SpanLayout, SpanMetadata,
+};
+use crate::rect::Rect;
+use crate::run::TextEffects;
+use crate::shaping::text_advances;
+use crate::units::Pt;
+
+/// How [OutlineNavigation] lays out its links
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NavigationStyle {
+    /// A table of contents, with one entry per line (indented by its depth in the
+    /// outline) from the top of the region, down to a maximum depth. The section that
+    /// the page is in is drawn in the current colour. Entries which don't fit in the
+    /// region are left out
+    #[default]
+    Sidebar,
+    /// The path through the outline to the section that the page is in, on a single
+    /// line along the top of the region, such as "Report › Results › Costs"
+    Breadcrumb,
+}
+
+/// Navigation links generated from the document's outline (including its headings, see
+/// [Document::add_heading]) when the document is written, and drawn in the same region
+/// of every page. Each entry links to the start of its section. See
+/// [Document::outline_navigation].
+///
+/// A page is in the section of the last outline entry (in outline order) which starts
+/// on or before it, so the text of the pages should leave the region empty, such as by
+/// placing it in a margin.
+#[derive(Debug, Clone)]
+pub struct OutlineNavigation {
+    /// How the links are laid out
+    pub style: NavigationStyle,
+    /// The region of each page to draw the links in
+    pub region: Rect,
+    /// The font and size of the links
+    pub font: SpanFont,
+    /// The colour of the links
+    pub colour: Colour,
+    /// The colour of the link to the section that the page is in
+    pub current_colour: Colour,
+    /// The deepest level of the outline to include, where `1` only includes its
+    /// top-level entries
+    pub max_depth: usize,
+    /// How far each level of a sidebar is indented from the level above it
+    pub indent: Pt,
+    /// The text placed between the entries of a breadcrumb
+    pub separator: String,
+    /// Whether pages before the first outline entry (such as a cover page) are left
+    /// without navigation
+    pub skip_before_first: bool,
+}
+
+impl OutlineNavigation {
+    /// Create a sidebar (see [NavigationStyle::Sidebar]) which is drawn in the given
+    /// region of every page
+    pub fn sidebar(region: Rect, font: SpanFont) -> OutlineNavigation {
+        OutlineNavigation {
+            style: NavigationStyle::Sidebar,
+            region,
+            font,
+            colour: Colour::new_grey(0.4),
+            current_colour: Colour::new_grey(0.0),
+            max_depth: 2,
+            indent: font.size,
+            separator: " \u{203A} ".to_string(),
+            skip_before_first: true,
+        }
+    }
+
+    /// Create a breadcrumb (see [NavigationStyle::Breadcrumb]) which is drawn in the
+    /// given region of every page
+    pub fn breadcrumb(region: Rect, font: SpanFont) -> OutlineNavigation {
+        OutlineNavigation {
+            style: NavigationStyle::Breadcrumb,
+            max_depth: usize::MAX,
+            ..OutlineNavigation::sidebar(region, font)
+        }
+    }
+
+    /// Set the colours of the links, and of the link to the current section
+    pub fn colours(&mut self, colour: Colour, current_colour: Colour) -> &mut Self {
+        self.colour = colour;
+        self.current_colour = current_colour;
+        self
+    }
+
+    /// Set the deepest level of the outline to include
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set how far each level of a sidebar is indented
+    pub fn indent(&mut self, indent: Pt) -> &mut Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the text placed between the entries of a breadcrumb
+    pub fn separator<S: ToString>(&mut self, separator: S) -> &mut Self {
+        self.separator = separator.to_string();
+        self
+    }
+}
+
+/// Every entry of the outline in outline order, along with its depth (starting at 1)
+fn flatten(
+    outline: &Outline,
+    entries: &[OutlineId],
+    depth: usize,
+    out: &mut Vec<(OutlineId, usize)>,
+) {
+    for &entry in entries {
+        out.push((entry, depth));
+        flatten(outline, &outline.entries[entry].children, depth + 1, out);
+    }
+}
+
+impl Document {
+    /// Draw [Document::outline_navigation] on every page, once the outline is complete
+    pub(crate) fn apply_outline_navigation(&mut self) {
+        let navigation = match self.outline_navigation.take() {
+            Some(navigation) => navigation,
+            None => return,
+        };
+
+        let mut entries = Vec::new();
+        flatten(&self.outline, &self.outline.roots, 1, &mut entries);
+        entries.retain(|&(_, depth)| depth <= navigation.max_depth);
+        if entries.is_empty() {
+            return;
+        }
+
+        let font = &self.fonts[navigation.font.id];
+        let size = navigation.font.size;
+        let width = |text: &str| -> Pt {
+            text_advances(font, text, size, Pt(0.0), false)
+                .into_iter()
+                .sum()
+        };
+        let ascent = font.ascent(size);
+        let descent = font.descent(size);
+        let line_height = font.line_height(size);
+
+        let mut pages = Vec::with_capacity(self.page_order.len());
+        for page_index in 0..self.page_order.len() {
+            // the entry of the section that the page is in
+            let current = entries
+                .iter()
+                .rposition(|&(entry, _)| self.outline.entries[entry].page_index <= page_index);
+            if current.is_none() && navigation.skip_before_first {
+                pages.push(Vec::new());
+                continue;
+            }
+            let path: Vec<OutlineId> = match current {
+                Some(current) => {
+                    let mut path = vec![entries[current].0];
+                    while let Some(parent) = self.outline.entries[path[0]].parent {
+                        path.insert(0, parent);
+                    }
+                    path
+                }
+                None => Vec::new(),
+            };
+
+            // the text of each link, where its baseline starts, its colour, and its entry
+            let mut items: Vec<(String, (Pt, Pt), Colour, OutlineId)> = Vec::new();
+            let region = navigation.region;
+            match navigation.style {
+                NavigationStyle::Sidebar => {
+                    let mut baseline = region.y2 - ascent;
+                    for &(entry, depth) in entries.iter() {
+                        if baseline + descent < region.y1 {
+                            break;
+                        }
+                        let colour = if path.last() == Some(&entry) {
+                            navigation.current_colour
+                        } else {
+                            navigation.colour
+                        };
+                        let x = region.x1 + navigation.indent * (depth - 1) as f32;
+                        items.push((
+                            self.outline.entries[entry].title.clone(),
+                            (x, baseline),
+                            colour,
+                            entry,
+                        ));
+                        baseline -= line_height;
+                    }
+                }
+                NavigationStyle::Breadcrumb => {
+                    let baseline = region.y2 - ascent;
+                    let separator_width = width(&navigation.separator);
+                    let mut x = region.x1;
+                    for (i, &entry) in path.iter().take(navigation.max_depth).enumerate() {
+                        if i > 0 {
+                            x += separator_width;
+                        }
+                        let title = self.outline.entries[entry].title.clone();
+                        let title_width = width(&title);
+                        let colour = if i + 1 == path.len().min(navigation.max_depth) {
+                            navigation.current_colour
+                        } else {
+                            navigation.colour
+                        };
+                        items.push((title, (x, baseline), colour, entry));
+                        x += title_width;
+                    }
+                }
+            }
+            pages.push(items);
+        }
+
+        for (page_index, items) in pages.into_iter().enumerate() {
+            if items.is_empty() {
+                continue;
+            }
+            let mut spans = Vec::with_capacity(items.len() * 2);
+            let mut links = Vec::with_capacity(items.len());
+            for (i, (text, coords, colour, entry)) in items.into_iter().enumerate() {
+                if navigation.style == NavigationStyle::Breadcrumb && i > 0 {
+                    spans.push(SpanLayout {
+                        text: navigation.separator.clone(),
+                        font: navigation.font,
+                        colour: navigation.colour,
+                        coords: (coords.0 - width(&navigation.separator), coords.1),
+                        effects: TextEffects::default(),
+                        metadata: SpanMetadata {
+                            artifact: Some(Artifact::Layout),
+                            ..SpanMetadata::default()
+                        },
+                        glyphs: Vec::new(),
+                    });
+                }
+                let entry = &self.outline.entries[entry];
+                links.push(IntraDocumentLink {
+                    position: Rect {
+                        x1: coords.0,
+                        y1: coords.1 + descent,
+                        x2: coords.0 + width(&text),
+                        y2: coords.1 + ascent,
+                    },
+                    page: PageLinkReference::ByIndex(entry.page_index),
+                    view: match entry.top {
+                        Some(top) => DestinationView::Top(top),
+                        None => DestinationView::Fit,
+                    },
+                    regions: Vec::new(),
+                });
+                spans.push(SpanLayout {
+                    text,
+                    font: navigation.font,
+                    colour,
+                    coords,
+                    effects: TextEffects::default(),
+                    metadata: SpanMetadata {
+                        artifact: Some(Artifact::Layout),
+                        ..SpanMetadata::default()
+                    },
+                    glyphs: Vec::new(),
+                });
+            }
+            let page = &mut self.pages[self.page_order[page_index]];
+            page.add_clipped_spans(spans, navigation.region);
+            page.links.extend(links);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::artifact::Artifact;
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::layout::{width_of_text, LaidOutLine};
+use crate::page::{ContentTarget, SpanFont, SpanLayout, SpanMetadata};
+use crate::units::Pt;
+
+/// Numbers lines of text in the margin beside them, such as for contracts and code
+/// listings. Line numbers are drawn from the `on_line` callbacks of the layout functions
+/// (see [crate::layout::layout_runs_with_lines]); the numbering carries on from one
+/// call to the next, so it continues across pages and columns:
+///
+/// ```
+/// # use pdf_gen::{layout, Document, Font, LineNumbers, Page, Pt, SpanFont, TextRun};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let font = doc.add_font(font);
+/// let font = SpanFont { id: font, size: Pt(10.0) };
+/// let mut numbers = LineNumbers::new(font);
+/// numbers.step(5);
+///
+/// let mut text = vec![TextRun::new(font).text("WHEREAS the parties...")];
+/// while !text.is_empty() {
+///     let mut page = Page::new(pdf_gen::pagesize::LETTER, None);
+///     let bounding_box = page.content_box;
+///     let start = layout::baseline_start(&page, &doc.fonts[font.id], font.size);
+///     layout::layout_runs_with_lines(&doc, &mut page, start, &mut text, Pt(0.0), bounding_box, &[],
+///         |page: &mut Page, line| numbers.number(&doc, page, line, bounding_box.x1),
+///     ).unwrap();
+///     doc.add_page(page);
+/// }
+/// ```
+///
+/// Only lines with text on them are numbered. The numbers are marked as artifacts (see
+/// [Artifact]), so that screen readers and text extraction skip them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineNumbers {
+    /// The font (and size) to draw the numbers in
+    pub font: SpanFont,
+    /// The colour of the numbers
+    pub colour: Colour,
+    /// The number that the next line will be given
+    pub next: usize,
+    /// Only lines whose numbers are multiples of this are labelled, i.e. 5 to label
+    /// every fifth line. Every line is still counted
+    pub step: usize,
+    /// The space between the numbers and the start of the text
+    pub gap: Pt,
+    /// Formats a line number
+    pub format: fn(usize) -> String,
+}
+
+impl LineNumbers {
+    /// Number every line, starting from 1, in black
+    pub fn new(font: SpanFont) -> LineNumbers {
+        LineNumbers {
+            font,
+            colour: crate::colours::BLACK,
+            next: 1,
+            step: 1,
+            gap: font.size,
+            format: |number| number.to_string(),
+        }
+    }
+
+    /// Set the number that the next line will be given
+    pub fn start(&mut self, start: usize) -> &mut Self {
+        self.next = start;
+        self
+    }
+
+    /// Only label lines whose numbers are multiples of `step`
+    pub fn step(&mut self, step: usize) -> &mut Self {
+        self.step = step;
+        self
+    }
+
+    /// Set the colour of the numbers
+    pub fn colour(&mut self, colour: Colour) -> &mut Self {
+        self.colour = colour;
+        self
+    }
+
+    /// Set the space between the numbers and the start of the text
+    pub fn gap(&mut self, gap: Pt) -> &mut Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Set how the numbers are formatted
+    pub fn format(&mut self, format: fn(usize) -> String) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Count a line, labelling it if its number is a multiple of [LineNumbers::step].
+    /// The label is drawn on the line's baseline, ending `gap` before `left` (typically
+    /// the left edge of the text's bounding box)
+    pub fn number<T: ContentTarget + ?Sized>(
+        &mut self,
+        document: &Document,
+        page: &mut T,
+        line: &LaidOutLine,
+        left: Pt,
+    ) {
+        let number = self.next;
+        self.next += 1;
+        if self.step > 1 && number % self.step != 0 {
+            return;
+        }
+
+        let text = (self.format)(number);
+        let width = width_of_text(&text, &document.fonts[self.font.id], self.font.size);
+        let mut metadata = SpanMetadata::default();
+        metadata.artifact(Artifact::LineNumber);
+        page.add_span(SpanLayout {
+            text,
+            font: self.font,
+            colour: self.colour,
+            coords: (left - self.gap - width, line.baseline),
+            effects: Default::default(),
+            metadata,
+            glyphs: Vec::new(),
+        });
+    }
+}
@@ -1,24 +1,62 @@
-use std::{cell::RefCell, rc::Rc};
-
 use pdf_writer::{types::OutlineItemFlags, Finish, PdfWriter, TextStr};
 
-use crate::refs::{ObjectReferences, RefType};
+use crate::{
+    colour::Colour,
+    refs::{ObjectReferences, RefType},
+    PDFError, Page, Pt,
+};
+use id_arena::{Arena, Id};
+
+/// A heading registered with the document through [crate::Document::add_heading]. Headings
+/// are turned into a nested document outline when the document is written, using their
+/// levels to determine nesting.
+#[derive(Debug, Clone)]
+pub struct Heading {
+    /// The level of the heading, where lower levels are more significant (i.e. `1` for a
+    /// chapter, `2` for a section within that chapter, etc)
+    pub level: usize,
+    /// The title of the heading, as it should appear in the outline
+    pub title: String,
+    /// The page the heading is on
+    pub page: Id<Page>,
+    /// The vertical position of the top of the heading on the page, measured from the
+    /// bottom of the page
+    pub y: Pt,
+}
+
+/// A handle to an entry in the document [Outline]
+pub type OutlineId = Id<OutlineEntry>;
 
-#[derive(Default, Debug)]
+/// The document outline (bookmarks). Entries are stored in an arena and referred to by
+/// their [OutlineId], so they can be edited or moved around after they are created.
+#[derive(Default, Debug, Clone)]
 pub struct Outline {
-    pub entries: Vec<Rc<RefCell<OutlineEntry>>>,
-    next_index: usize,
+    /// Every entry that has been added to the outline, including any which have since
+    /// been detached
+    pub entries: Arena<OutlineEntry>,
+    /// The top-level entries of the outline, in order
+    pub roots: Vec<OutlineId>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OutlineEntry {
-    pub index: usize,
+    /// The 0-based index of the page within the document's page order
     pub page_index: usize,
     pub title: String,
     pub italic: bool,
     pub bold: bool,
-    pub parent: Option<Rc<RefCell<OutlineEntry>>>,
-    pub children: Vec<Rc<RefCell<OutlineEntry>>>,
+    /// The colour used to display the entry's title. Viewers only support
+    /// RGB colours for outline entries, so CMYK and grey colours are converted
+    /// to RGB when written. If [None], the viewer's default colour is used.
+    pub colour: Option<Colour>,
+    /// Whether the entry's children are shown (expanded) by default when the
+    /// document is opened
+    pub open: bool,
+    /// If set, navigating to the entry scrolls to this vertical position (measured from
+    /// the bottom of the page) rather than fitting the entire page into view
+    pub top: Option<Pt>,
+    pub parent: Option<OutlineId>,
+    pub children: Vec<OutlineId>,
 }
 
 impl OutlineEntry {
@@ -31,136 +69,220 @@ impl OutlineEntry {
         self.italic = true;
         self
     }
+
+    pub fn coloured(&mut self, colour: Colour) -> &mut Self {
+        self.colour = Some(colour);
+        self
+    }
+
+    pub fn expanded(&mut self) -> &mut Self {
+        self.open = true;
+        self
+    }
 }
 
 impl Outline {
     pub fn add_bookmark(
         &mut self,
-        parent: Option<Rc<RefCell<OutlineEntry>>>,
+        parent: Option<OutlineId>,
         page_index: usize,
         title: String,
-    ) -> Rc<RefCell<OutlineEntry>> {
-        let entry = OutlineEntry {
-            index: self.next_index,
+    ) -> OutlineId {
+        let entry = self.entries.alloc(OutlineEntry {
             page_index,
             title,
             italic: false,
             bold: false,
-            parent: parent.clone(),
+            colour: None,
+            open: false,
+            top: None,
+            parent: None,
             children: Vec::default(),
+        });
+        self.attach(entry, parent);
+        entry
+    }
+
+    /// Move an entry (along with all of its children) to the end of another entry's
+    /// children, or to the end of the top level if `parent` is [None]. Returns `false`
+    /// and leaves the outline unchanged if `parent` is the entry itself or one of its
+    /// descendants.
+    pub fn move_bookmark(&mut self, entry: OutlineId, parent: Option<OutlineId>) -> bool {
+        let mut ancestor = parent;
+        while let Some(id) = ancestor {
+            if id == entry {
+                return false;
+            }
+            ancestor = self.entries[id].parent;
+        }
+
+        self.detach(entry);
+        self.attach(entry, parent);
+        true
+    }
+
+    /// Remove an entry (along with all of its children) from the outline, so that it
+    /// won't be written. The entry remains in [Outline::entries] and can be re-attached
+    /// with [Outline::move_bookmark].
+    pub fn detach(&mut self, entry: OutlineId) {
+        let siblings = match self.entries[entry].parent.take() {
+            Some(parent) => &mut self.entries[parent].children,
+            None => &mut self.roots,
         };
-        self.next_index += 1;
-        let entry = Rc::new(RefCell::new(entry));
-        if let Some(parent) = parent {
-            parent.borrow_mut().children.push(entry.clone());
-        } else {
-            self.entries.push(entry.clone());
+        siblings.retain(|&id| id != entry);
+    }
+
+    fn attach(&mut self, entry: OutlineId, parent: Option<OutlineId>) {
+        self.entries[entry].parent = parent;
+        match parent {
+            Some(parent) => self.entries[parent].children.push(entry),
+            None => self.roots.push(entry),
         }
-        entry
     }
 
-    pub fn generate_next_index(&mut self) -> usize {
-        let ret = self.next_index;
-        self.next_index += 1;
-        ret
+    /// Convert registered headings into nested bookmarks, appended after any bookmarks
+    /// that were added manually. Each heading becomes a child of the closest preceding
+    /// heading with a lower level.
+    pub(crate) fn add_headings(
+        &mut self,
+        headings: &[Heading],
+        page_order: &[Id<Page>],
+    ) -> Result<(), PDFError> {
+        let mut stack: Vec<(usize, OutlineId)> = Vec::new();
+        for heading in headings {
+            let page_index = page_order
+                .iter()
+                .position(|&p| p == heading.page)
+                .ok_or_else(|| PDFError::BookmarkPageMissing {
+                    title: heading.title.clone(),
+                })?;
+
+            while matches!(stack.last(), Some((level, _)) if *level >= heading.level) {
+                stack.pop();
+            }
+            let parent = stack.last().map(|&(_, entry)| entry);
+
+            let entry = self.add_bookmark(parent, page_index, heading.title.clone());
+            self.entries[entry].top = Some(heading.y);
+            stack.push((heading.level, entry));
+        }
+        Ok(())
     }
 
-    fn generate_entry_ids(
-        &self,
-        refs: &mut ObjectReferences,
-        entries: &[Rc<RefCell<OutlineEntry>>],
-    ) {
-        for entry in entries {
-            refs.gen(RefType::OutlineEntry(entry.borrow().index));
-            self.generate_entry_ids(refs, &entry.borrow().children.as_slice());
+    /// Count the number of descendants of an entry that would be visible if the entry
+    /// were open, taking into account the open state of each child
+    fn visible_descendants(&self, entry: OutlineId) -> i32 {
+        self.entries[entry]
+            .children
+            .iter()
+            .map(|&child| {
+                if self.entries[child].open {
+                    1 + self.visible_descendants(child)
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
+    fn generate_entry_ids(&self, refs: &mut ObjectReferences, entries: &[OutlineId]) {
+        for &entry in entries {
+            refs.gen(RefType::OutlineEntry(entry.index()));
+            self.generate_entry_ids(refs, self.entries[entry].children.as_slice());
         }
     }
 
     fn write_outline_entries(
         &self,
-        entries: &[Rc<RefCell<OutlineEntry>>],
+        entries: &[OutlineId],
+        page_order: &[Id<Page>],
         refs: &mut ObjectReferences,
         writer: &mut PdfWriter,
-    ) {
-        for (i, entry) in entries.iter().enumerate() {
-            self.write_outline_entries(entry.borrow().children.as_slice(), refs, writer);
+    ) -> Result<(), PDFError> {
+        for (i, &id) in entries.iter().enumerate() {
+            let entry = &self.entries[id];
+            self.write_outline_entries(entry.children.as_slice(), page_order, refs, writer)?;
 
-            let mut item = writer.outline_item(
-                refs.get(RefType::OutlineEntry(entry.borrow().index))
-                    .unwrap(),
-            );
+            // validate the target before we start writing the item. Entries refer to pages
+            // by their position in the page order, but pages are referenced by their Ids
+            let page_ref = page_order
+                .get(entry.page_index)
+                .and_then(|page| refs.get(RefType::Page(page.index())))
+                .ok_or_else(|| PDFError::BookmarkPageMissing {
+                    title: entry.title.clone(),
+                })?;
 
-            item.title(TextStr(entry.borrow().title.as_str()));
-            item.dest_direct()
-                .page(refs.get(RefType::Page(entry.borrow().page_index)).unwrap())
-                .fit();
+            let mut item = writer.outline_item(refs.require(RefType::OutlineEntry(id.index()))?);
+
+            item.title(TextStr(entry.title.as_str()));
+            let dest = item.dest_direct().page(page_ref);
+            match entry.top {
+                Some(top) => dest.xyz(0.0, *top, None),
+                None => dest.fit(),
+            }
 
             let mut flags: OutlineItemFlags = OutlineItemFlags::empty();
-            flags.set(OutlineItemFlags::BOLD, entry.borrow().bold);
-            flags.set(OutlineItemFlags::ITALIC, entry.borrow().italic);
+            flags.set(OutlineItemFlags::BOLD, entry.bold);
+            flags.set(OutlineItemFlags::ITALIC, entry.italic);
             item.flags(flags);
 
-            if let Some(parent) = &entry.borrow().parent {
-                item.parent(
-                    refs.get(RefType::OutlineEntry(parent.borrow().index))
-                        .unwrap(),
-                );
+            if let Some(colour) = entry.colour {
+                let (r, g, b) = colour.to_rgb();
+                item.color_rgb(r, g, b);
+            }
+
+            if let Some(parent) = entry.parent {
+                item.parent(refs.require(RefType::OutlineEntry(parent.index()))?);
             } else {
-                item.parent(refs.get(RefType::Outlines).unwrap());
+                item.parent(refs.require(RefType::Outlines)?);
             }
             if i > 0 {
-                item.prev(
-                    refs.get(RefType::OutlineEntry(entries[i - 1].borrow().index))
-                        .unwrap(),
-                );
+                item.prev(refs.require(RefType::OutlineEntry(entries[i - 1].index()))?);
             }
             if i < entries.len() - 1 {
-                item.next(
-                    refs.get(RefType::OutlineEntry(entries[i + 1].borrow().index))
-                        .unwrap(),
-                );
+                item.next(refs.require(RefType::OutlineEntry(entries[i + 1].index()))?);
             }
-            if !entry.borrow().children.is_empty() {
-                item.count(entry.borrow().children.len() as i32 * -1);
-                item.first(
-                    refs.get(RefType::OutlineEntry(
-                        entry.borrow().children.first().unwrap().borrow().index,
-                    ))
-                    .unwrap(),
-                );
-                item.last(
-                    refs.get(RefType::OutlineEntry(
-                        entry.borrow().children.last().unwrap().borrow().index,
-                    ))
-                    .unwrap(),
-                );
+            if let (Some(first), Some(last)) = (entry.children.first(), entry.children.last()) {
+                // a positive count shows the children by default, a negative count hides them
+                let count = self.visible_descendants(id);
+                item.count(if entry.open { count } else { -count });
+                item.first(refs.require(RefType::OutlineEntry(first.index()))?);
+                item.last(refs.require(RefType::OutlineEntry(last.index()))?);
             }
         }
+        Ok(())
     }
 
-    pub(crate) fn write(&self, refs: &mut ObjectReferences, writer: &mut PdfWriter) {
+    pub(crate) fn write(
+        &self,
+        page_order: &[Id<Page>],
+        refs: &mut ObjectReferences,
+        writer: &mut PdfWriter,
+    ) -> Result<(), PDFError> {
         // generate IDs for everything
         let outlines_id = refs.gen(RefType::Outlines);
-        self.generate_entry_ids(refs, self.entries.as_slice());
+        self.generate_entry_ids(refs, self.roots.as_slice());
 
         // write the root outline
         let mut outline = writer.outline(outlines_id);
-        if !self.entries.is_empty() {
-            outline.first(
-                refs.get(RefType::OutlineEntry(
-                    self.entries.first().unwrap().borrow().index,
-                ))
-                .unwrap(),
-            );
-            outline.last(
-                refs.get(RefType::OutlineEntry(
-                    self.entries.last().unwrap().borrow().index,
-                ))
-                .unwrap(),
-            );
+        if let (Some(first), Some(last)) = (self.roots.first(), self.roots.last()) {
+            let visible: i32 = self
+                .roots
+                .iter()
+                .map(|&entry| {
+                    if self.entries[entry].open {
+                        1 + self.visible_descendants(entry)
+                    } else {
+                        1
+                    }
+                })
+                .sum();
+            outline.count(visible);
+            outline.first(refs.require(RefType::OutlineEntry(first.index()))?);
+            outline.last(refs.require(RefType::OutlineEntry(last.index()))?);
         }
         outline.finish();
 
-        self.write_outline_entries(self.entries.as_slice(), refs, writer);
+        self.write_outline_entries(self.roots.as_slice(), page_order, refs, writer)
     }
 }
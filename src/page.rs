@@ -1,13 +1,24 @@
+use crate::artifact::Artifact;
 use crate::colour::Colour;
+use crate::compression::Compression;
+use crate::document::Document;
+use crate::drawing::{DrawStyle, Line, Shape, Stroke};
 use crate::font::Font;
+use crate::form::{FormLayout, FormXObject};
 use crate::image::Image;
 use crate::layout::Margins;
+use crate::measure::Viewport;
 use crate::rect::Rect;
 use crate::refs::{ObjectReferences, RefType};
-use crate::{units::*, PDFError};
+use crate::run::TextEffects;
+use crate::shaping::ShapedGlyph;
+use crate::shared::SharedContent;
+use crate::validate::validate_content;
+use crate::{error::excerpt, units::*, PDFError};
 use id_arena::{Arena, Id};
 use pdf_writer::{Content, Finish};
-use pdf_writer::{Name, PdfWriter};
+use pdf_writer::{Name, PdfWriter, Str, TextStr};
+use std::collections::HashMap;
 use std::io::Write;
 
 pub use self::pagesize::PageSize;
@@ -40,6 +51,177 @@ pub struct SpanLayout {
     /// measured from the bottom-left corner of the page to the
     /// horizontal beginning and baseline of the text
     pub coords: (Pt, Pt),
+    /// Decorations and spacing of the text
+    pub effects: TextEffects,
+    /// Extra information about the span which isn't printed, but which can be used
+    /// after layout to generate links, tags, tables of contents, etc
+    pub metadata: SpanMetadata,
+    /// The glyphs that the text was shaped into (see [ShapedGlyph]), which are drawn
+    /// instead of the text's characters. Empty if the text hasn't been shaped, in which
+    /// case each character is drawn with its own glyph from the font
+    pub(crate) glyphs: Vec<ShapedGlyph>,
+}
+
+impl SpanLayout {
+    /// Create a span of text, with no effects or metadata, which is drawn with one glyph
+    /// per character until it is shaped
+    pub fn new<S: ToString>(text: S, font: SpanFont, colour: Colour, coords: (Pt, Pt)) -> Self {
+        SpanLayout {
+            text: text.to_string(),
+            font,
+            colour,
+            coords,
+            effects: TextEffects::default(),
+            metadata: SpanMetadata::default(),
+            glyphs: Vec::new(),
+        }
+    }
+
+    /// The glyphs that the text was shaped into, or nothing if it hasn't been shaped
+    pub fn glyphs(&self) -> &[ShapedGlyph] {
+        &self.glyphs
+    }
+
+    /// The region of the page covered by the span, from the descent to the ascent of
+    /// its font
+    pub fn bounding_box(&self, document: &Document) -> Rect {
+        let font = &document.fonts[self.font.id];
+        let (x, y) = self.coords;
+        Rect {
+            x1: x,
+            y1: y + font.descent(self.font.size),
+            x2: x + self.width(font),
+            y2: y + font.ascent(self.font.size),
+        }
+    }
+
+    /// Split the span into one span per character, with colours blended from `start`
+    /// for the first character to `end` for the last (see [Colour::mix]), such as for
+    /// decorative headings or to shade the cells of a severity ramp. The characters stay
+    /// where they were; add the result to a page with [Page::add_spans].
+    ///
+    /// Any [SpanMetadata::actual_text] is dropped from the pieces, as it describes the
+    /// span as a whole
+    pub fn gradient(&self, document: &Document, start: Colour, end: Colour) -> Vec<SpanLayout> {
+        let font = &document.fonts[self.font.id];
+        let count = self.text.chars().count();
+        let mut x = self.coords.0;
+        self.text
+            .chars()
+            .enumerate()
+            .map(|(i, ch)| {
+                let t = if count > 1 {
+                    i as f32 / (count - 1) as f32
+                } else {
+                    0.0
+                };
+                let mut piece = SpanLayout {
+                    text: ch.to_string(),
+                    colour: start.mix(end, t),
+                    coords: (x, self.coords.1),
+                    ..self.clone()
+                };
+                piece.metadata.actual_text = None;
+                piece.reshape(document);
+                x += piece.width(font);
+                piece
+            })
+            .collect()
+    }
+
+    /// The width of the span's text, including any letter spacing
+    fn width(&self, font: &Font) -> Pt {
+        self.prefix_width(font, self.text.chars().count())
+    }
+}
+
+/// Arbitrary information attached to a [SpanLayout]. Layout functions copy the metadata
+/// of their input text onto every span they generate from it, so once layout has
+/// decided where the text ends up (including where it was split across lines), the
+/// metadata can be used to find it again (see [Page::spans_with_metadata]).
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SpanMetadata {
+    /// A name which can be used as the target of links within the document
+    pub anchor: Option<String>,
+    /// The semantic role of the text, such as `"H1"` or `"Caption"`
+    pub role: Option<String>,
+    /// A URL that the text should link to
+    pub url: Option<String>,
+    /// The text that the span stands for, when it differs from the characters that are
+    /// drawn (such as when the span is drawn with ligatures). Text extraction, search,
+    /// and screen readers use this instead of the drawn characters. Spans containing
+    /// ligature characters or characters missing from their font are given this
+    /// automatically
+    pub actual_text: Option<String>,
+    /// Marks the span as an artifact (such as a running header or a page number) rather
+    /// than part of the document's content, so that screen readers and text extraction
+    /// skip it
+    pub artifact: Option<Artifact>,
+    /// Marks the span as having changed since a previous revision of the document, so
+    /// that a change bar can be drawn beside it (see [Page::add_change_bars])
+    pub changed: bool,
+    /// Any other key / value pairs
+    pub attributes: HashMap<String, String>,
+}
+
+impl SpanMetadata {
+    /// Create empty metadata
+    pub fn new() -> SpanMetadata {
+        SpanMetadata::default()
+    }
+
+    /// Set the anchor name of the span, modifying `self`
+    pub fn anchor<S: ToString>(&mut self, anchor: S) -> &mut Self {
+        self.anchor = Some(anchor.to_string());
+        self
+    }
+
+    /// Set the semantic role of the span, modifying `self`
+    pub fn role<S: ToString>(&mut self, role: S) -> &mut Self {
+        self.role = Some(role.to_string());
+        self
+    }
+
+    /// Set the URL of the span, modifying `self`
+    pub fn url<S: ToString>(&mut self, url: S) -> &mut Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    /// Set the text that the span stands for, modifying `self`
+    pub fn actual_text<S: ToString>(&mut self, actual_text: S) -> &mut Self {
+        self.actual_text = Some(actual_text.to_string());
+        self
+    }
+
+    /// Mark the span as an artifact, modifying `self`
+    pub fn artifact(&mut self, artifact: Artifact) -> &mut Self {
+        self.artifact = Some(artifact);
+        self
+    }
+
+    /// Mark the span as changed, modifying `self`
+    pub fn changed(&mut self) -> &mut Self {
+        self.changed = true;
+        self
+    }
+
+    /// Set an arbitrary attribute of the span, modifying `self`
+    pub fn attribute<K: ToString, V: ToString>(&mut self, key: K, value: V) -> &mut Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Whether no metadata has been set at all
+    pub fn is_empty(&self) -> bool {
+        self.anchor.is_none()
+            && self.role.is_none()
+            && self.url.is_none()
+            && self.actual_text.is_none()
+            && self.artifact.is_none()
+            && !self.changed
+            && self.attributes.is_empty()
+    }
 }
 
 /// An image to be laid out onto a page
@@ -50,20 +232,47 @@ pub struct ImageLayout {
     /// Where the image should be laid out on the page, relative to
     /// the bottom-left corner of the page
     pub position: Rect,
+    /// Marks the image as an artifact (such as a background decoration) rather than
+    /// part of the document's content
+    pub artifact: Option<Artifact>,
 }
 
 /// The types of content that can be rendered on the page
 pub enum PageContents {
     /// A block of text (broken into spans)
     Text(Vec<SpanLayout>),
+    /// A block of text which is clipped to a region, so that nothing outside of the
+    /// region is drawn even if the text overflows it
+    ClippedText(Vec<SpanLayout>, Rect),
     /// An image
     Image(ImageLayout),
+    /// A form, drawn at a given position
+    Form(FormLayout),
     /// Raw content, typically rendered by [pdf_writer::Content]. The
     /// content **MUST** be **UNCOMPRESSED**.
     RawContent(Vec<u8>),
+    /// Content which is generated when the document is written (see
+    /// [Page::add_deferred])
+    Deferred(DeferredContent),
+}
+
+/// A closure which generates page content when the document is written
+pub type DeferredContent =
+    Box<dyn Fn(&DeferredContext<'_>, &mut Page) -> Result<(), PDFError> + Send>;
+
+/// Information about the final state of the document, given to [DeferredContent] when the
+/// document is written
+pub struct DeferredContext<'a> {
+    /// The 0-based index of the page within the document
+    pub page_index: usize,
+    /// The total number of pages in the document
+    pub page_count: usize,
+    /// The document being written, for looking up fonts, images, etc
+    pub document: &'a Document,
 }
 
 /// A reference to page via its Id or 0-based page index
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PageLinkReference {
     /// Refer to a page by it's Id (resilient to page re-ordering)
     ById(Id<Page>),
@@ -73,6 +282,54 @@ pub enum PageLinkReference {
     ByIndex(usize),
 }
 
+/// How the target page of a [Destination] is shown
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DestinationView {
+    /// Fit the entire page into view
+    Fit,
+    /// Scroll so that the given vertical position (measured from the bottom of the
+    /// page) is at the top of the view, keeping the current zoom level
+    Top(Pt),
+    /// Zoom and scroll so that the given region of the page fills the view
+    Rect(Rect),
+}
+
+/// A location within the document, such as the exact paragraph that an index entry
+/// refers to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Destination {
+    /// The page to navigate to
+    pub page: PageLinkReference,
+    /// Where on the page to navigate to
+    pub view: DestinationView,
+}
+
+impl Destination {
+    /// A destination that fits the entire page into view
+    pub fn page(page: PageLinkReference) -> Destination {
+        Destination {
+            page,
+            view: DestinationView::Fit,
+        }
+    }
+
+    /// A destination that scrolls to the given vertical position on the page
+    pub fn top(page: PageLinkReference, top: Pt) -> Destination {
+        Destination {
+            page,
+            view: DestinationView::Top(top),
+        }
+    }
+
+    /// A destination that zooms to fit the given region of the page into view
+    pub fn rect(page: PageLinkReference, rect: Rect) -> Destination {
+        Destination {
+            page,
+            view: DestinationView::Rect(rect),
+        }
+    }
+}
+
 /// An annotated region on the page that when clicked on, will navigate to the
 /// given page index
 pub struct IntraDocumentLink {
@@ -81,6 +338,169 @@ pub struct IntraDocumentLink {
 
     /// The page to navigate to when clicked
     pub page: PageLinkReference,
+
+    /// Where on the page to navigate to
+    pub view: DestinationView,
+
+    /// The regions within `position` that are actually clickable, such as each line of
+    /// a link over text that wraps across lines. If empty, all of `position` is
+    pub regions: Vec<Rect>,
+}
+
+/// What an [ExternalLink] does when it is clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalLinkTarget {
+    /// Open another PDF document (usually given by a path relative to this document)
+    /// and navigate to the given 0-based page index within it
+    RemoteDocument { file: String, page: usize },
+    /// Launch an application, or open a file with the application associated with it
+    Launch { file: String },
+    /// Open a URI, such as a web page
+    Uri { uri: String },
+}
+
+/// An annotated region on the page that when clicked on, will open another document
+/// or application
+pub struct ExternalLink {
+    /// The bounding box for the link
+    pub position: Rect,
+
+    /// What to open when clicked
+    pub target: ExternalLinkTarget,
+
+    /// The regions within `position` that are actually clickable, such as each line of
+    /// a link over text that wraps across lines. If empty, all of `position` is
+    pub regions: Vec<Rect>,
+}
+
+/// A highlighted region of text, which viewers show as a markup annotation
+pub struct Highlight {
+    /// The regions to highlight, typically one per line of text
+    pub regions: Vec<Rect>,
+    /// The colour of the highlight. Viewers only support RGB colours for annotations,
+    /// so CMYK and grey colours are converted to RGB when written
+    pub colour: Colour,
+    /// An optional note attached to the highlight
+    pub note: Option<String>,
+}
+
+/// An annotation drawn with one of the document's forms, such as an "Approved" or
+/// "Draft" stamp (see [Page::add_stamp])
+pub struct Stamp {
+    /// Which form within the document the stamp is drawn with
+    pub form_index: usize,
+    /// Where the stamp is on the page. The form's bounding box is scaled to fill this
+    pub position: Rect,
+    /// An optional note attached to the stamp
+    pub note: Option<String>,
+}
+
+/// How the spaces between words are written into a page's content stream
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum WordSpacing {
+    /// Spaces are written as space glyphs, with each span written as a single string.
+    /// This produces the smallest content streams
+    #[default]
+    Glyphs,
+    /// Each word is written as a separate string, with the gaps between words written
+    /// as positioning adjustments (using the `TJ` operator) the width of a space. This
+    /// doesn't change how the text looks, but some text extractors rely on these gaps
+    /// to find word boundaries, improving copy / paste and search quality
+    PositionedWords,
+}
+
+/// Graphics state which is set at the start of a page's content stream, before any of
+/// its contents. Each item of content starts from this state, so raw content and shapes
+/// don't need to set it themselves
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct PageDefaults {
+    /// The colour used by fill operations (including text, unless a span sets its own)
+    pub fill: Option<Colour>,
+    /// The colour, width, and dash pattern used by stroke operations
+    pub stroke: Option<Stroke>,
+    /// The font used by text operations in raw content
+    pub font: Option<SpanFont>,
+}
+
+impl PageDefaults {
+    pub(crate) fn render(&self) -> Vec<u8> {
+        let mut content = Content::new();
+        if let Some(fill) = self.fill {
+            crate::drawing::set_fill_colour(&mut content, fill);
+        }
+        if let Some(stroke) = &self.stroke {
+            crate::drawing::set_stroke(&mut content, stroke);
+        }
+        if let Some(font) = self.font {
+            content.set_font(
+                Name(format!("F{}", font.font_index()).as_bytes()),
+                *font.size,
+            );
+        }
+        content.finish()
+    }
+}
+
+/// Something which content can be laid out into, so that the layout utilities (such as
+/// [crate::layout::layout_text] and [crate::Table::layout]) can fill either a [Page] or
+/// a [FormXObject]
+pub trait ContentTarget {
+    /// The region that content should be laid out within: a page's content box, or a
+    /// form's bounding box
+    fn content_box(&self) -> Rect;
+    /// Add a span of text, in the layering order that it was added
+    fn add_span(&mut self, span: SpanLayout);
+    /// Add several spans of text as a single block, in the layering order that they were
+    /// added
+    fn add_spans(&mut self, spans: Vec<SpanLayout>);
+    /// Add an image, in the layering order that it was added
+    fn add_image(&mut self, image: ImageLayout);
+    /// Add arbitrary `pdf_writer::Content`, in the layering order that it was added
+    fn add_content(&mut self, content: Content);
+}
+
+impl ContentTarget for Page {
+    fn content_box(&self) -> Rect {
+        self.content_box
+    }
+
+    fn add_span(&mut self, span: SpanLayout) {
+        Page::add_span(self, span)
+    }
+
+    fn add_spans(&mut self, spans: Vec<SpanLayout>) {
+        Page::add_spans(self, spans)
+    }
+
+    fn add_image(&mut self, image: ImageLayout) {
+        Page::add_image(self, image)
+    }
+
+    fn add_content(&mut self, content: Content) {
+        Page::add_content(self, content)
+    }
+}
+
+impl ContentTarget for FormXObject {
+    fn content_box(&self) -> Rect {
+        self.bbox
+    }
+
+    fn add_span(&mut self, span: SpanLayout) {
+        FormXObject::add_span(self, span)
+    }
+
+    fn add_spans(&mut self, spans: Vec<SpanLayout>) {
+        FormXObject::add_spans(self, spans)
+    }
+
+    fn add_image(&mut self, image: ImageLayout) {
+        FormXObject::add_image(self, image)
+    }
+
+    fn add_content(&mut self, content: Content) {
+        FormXObject::add_content(self, content)
+    }
 }
 
 /// A page in the document
@@ -93,6 +513,18 @@ pub struct Page {
     pub contents: Vec<PageContents>,
     /// Any links that are on the page
     pub links: Vec<IntraDocumentLink>,
+    /// Any links to other documents or applications that are on the page
+    pub external_links: Vec<ExternalLink>,
+    /// Any highlighted regions on the page
+    pub highlights: Vec<Highlight>,
+    /// Any stamps on the page
+    pub stamps: Vec<Stamp>,
+    /// Regions of the page with measurement scales
+    pub viewports: Vec<Viewport>,
+    /// How the spaces between words in text spans are written
+    pub word_spacing: WordSpacing,
+    /// Graphics state set at the start of the page, before any of its contents
+    pub defaults: PageDefaults,
 }
 
 impl Page {
@@ -118,6 +550,12 @@ impl Page {
             },
             contents: Vec::default(),
             links: Vec::default(),
+            external_links: Vec::default(),
+            highlights: Vec::default(),
+            stamps: Vec::default(),
+            viewports: Vec::default(),
+            word_spacing: WordSpacing::default(),
+            defaults: PageDefaults::default(),
         }
     }
 
@@ -126,6 +564,36 @@ impl Page {
         self.contents.push(PageContents::Text(vec![span]));
     }
 
+    /// Iterate over every span of text on the page which has metadata attached to it,
+    /// in the layering order that they were added
+    pub fn spans_with_metadata(&self) -> impl Iterator<Item = &SpanLayout> {
+        self.contents
+            .iter()
+            .filter_map(|content| match content {
+                PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+                    Some(spans.iter())
+                }
+                _ => None,
+            })
+            .flatten()
+            .filter(|span| !span.metadata.is_empty())
+    }
+
+    /// Add a block of text to the page, in the layering order that it was added, clipped
+    /// to the given region. Layout functions only wrap text within their bounding boxes
+    /// as well as they can (a single word may be wider than a column, and manually
+    /// placed text isn't checked at all), so clipping guarantees that the text never
+    /// draws outside of a table cell or column
+    pub fn add_clipped_spans(&mut self, spans: Vec<SpanLayout>, clip: Rect) {
+        self.contents.push(PageContents::ClippedText(spans, clip));
+    }
+
+    /// Add several spans of text to the page as a single block, in the layering order
+    /// that they were added
+    pub fn add_spans(&mut self, spans: Vec<SpanLayout>) {
+        self.contents.push(PageContents::Text(spans));
+    }
+
     /// Add an image to the page, in the layering order that it was added
     pub fn add_image(&mut self, image: ImageLayout) {
         self.contents.push(PageContents::Image(image));
@@ -141,6 +609,41 @@ impl Page {
             .push(PageContents::RawContent(content.finish()));
     }
 
+    /// Draw a form on the page, in the layering order that it was added
+    pub fn add_form(&mut self, form: FormLayout) {
+        self.contents.push(PageContents::Form(form));
+    }
+
+    /// Add arbitrary `pdf_writer::Content` to the page as with [Page::add_content], marked
+    /// as an artifact (such as a rule beneath a running header, or a background
+    /// decoration) so that screen readers and text extraction skip it
+    pub fn add_artifact_content(&mut self, artifact: Artifact, content: Content) {
+        let mut marked = artifact.begin().as_bytes().to_vec();
+        marked.extend(content.finish());
+        marked.extend_from_slice(b"\nEMC");
+        self.contents.push(PageContents::RawContent(marked));
+    }
+
+    /// Draw a shape on the page, in the layering order that it was added (see
+    /// [crate::drawing])
+    pub fn add_shape<S: Shape + ?Sized>(&mut self, shape: &S, style: &DrawStyle) {
+        let mut content = Content::new();
+        crate::drawing::draw(&mut content, shape, style);
+        self.add_content(content);
+    }
+
+    /// Draw a straight line between two points on the page, such as a separator between
+    /// sections, in the layering order that it was added
+    pub fn add_rule(&mut self, from: (Pt, Pt), to: (Pt, Pt), stroke: Stroke) {
+        self.add_shape(
+            &Line { from, to },
+            &DrawStyle {
+                fill: None,
+                stroke: Some(stroke),
+            },
+        );
+    }
+
     /// Add content, rendering it yourself. Refer to the pdf specifications (pdf_reference_1-7)
     /// for full information about how to render this.
     ///
@@ -154,11 +657,31 @@ impl Page {
             .push(PageContents::RawContent(content.into_iter().collect()));
     }
 
+    /// Add content which is generated when the document is written, in the layering
+    /// order that it was added. The closure is given the final index of the page, the
+    /// total number of pages, and the document, along with a blank page (with the same
+    /// size and margins as this page) to add content to. This makes it possible to add
+    /// things like "Page X of Y" without knowing how many pages there will be ahead of
+    /// time.
+    ///
+    /// Only the contents added to the blank page are used; any links added to it are
+    /// ignored. The closure may be called more than once if the document is written
+    /// more than once (i.e. by [Document::write_with_budget]).
+    pub fn add_deferred<F>(&mut self, content: F)
+    where
+        F: Fn(&DeferredContext<'_>, &mut Page) -> Result<(), PDFError> + Send + 'static,
+    {
+        self.contents
+            .push(PageContents::Deferred(Box::new(content)));
+    }
+
     /// Add a link on the page that when clicked will navigate to the given page index
     pub fn add_intradocument_link_by_id(&mut self, position: Rect, page: Id<Page>) {
         self.links.push(IntraDocumentLink {
             position,
             page: PageLinkReference::ById(page),
+            view: DestinationView::Fit,
+            regions: Vec::default(),
         });
     }
 
@@ -167,108 +690,155 @@ impl Page {
         self.links.push(IntraDocumentLink {
             position,
             page: PageLinkReference::ByIndex(page),
+            view: DestinationView::Fit,
+            regions: Vec::default(),
         });
     }
 
-    #[allow(clippy::write_with_newline)]
-    fn render(&self, fonts: &Arena<Font>) -> Result<Vec<u8>, std::io::Error> {
-        if self.contents.is_empty() {
-            return Ok(Vec::default());
+    /// Add a link on the page that when clicked will navigate to the given destination,
+    /// such as a specific position on a page
+    pub fn add_intradocument_link(&mut self, position: Rect, destination: Destination) {
+        self.links.push(IntraDocumentLink {
+            position,
+            page: destination.page,
+            view: destination.view,
+            regions: Vec::default(),
+        });
+    }
+
+    /// Add a link made up of several regions (such as each line of some text that wraps
+    /// across lines) that when clicked will navigate to the given destination. Nothing
+    /// is added if there are no regions
+    pub fn add_intradocument_link_regions(&mut self, regions: Vec<Rect>, destination: Destination) {
+        if let Some(position) = Rect::union(&regions) {
+            self.links.push(IntraDocumentLink {
+                position,
+                page: destination.page,
+                view: destination.view,
+                // a single region is the same as the link's position
+                regions: if regions.len() > 1 {
+                    regions
+                } else {
+                    Vec::default()
+                },
+            });
         }
-        let mut content: Vec<u8> = Vec::default();
+    }
 
-        'contents: for page_content in self.contents.iter() {
-            match page_content {
-                PageContents::Text(spans) => {
-                    if spans.is_empty() {
-                        continue 'contents;
-                    }
+    /// Add a link on the page that when clicked will open the given URI
+    pub fn add_uri_link<S: ToString>(&mut self, position: Rect, uri: S) {
+        self.add_uri_link_regions(vec![position], uri);
+    }
 
-                    write!(&mut content, "q\n")?;
-                    // unwrap is safe, as we know spans isn't empty
-                    let mut current_font: SpanFont = spans.first().unwrap().font;
-                    let mut current_colour: Colour = spans.first().unwrap().colour;
+    /// Add a link made up of several regions (such as each line of some text that wraps
+    /// across lines) that when clicked will open the given URI. Nothing is added if there
+    /// are no regions
+    pub fn add_uri_link_regions<S: ToString>(&mut self, regions: Vec<Rect>, uri: S) {
+        if let Some(position) = Rect::union(&regions) {
+            self.external_links.push(ExternalLink {
+                position,
+                target: ExternalLinkTarget::Uri {
+                    uri: uri.to_string(),
+                },
+                // a single region is the same as the link's position
+                regions: if regions.len() > 1 {
+                    regions
+                } else {
+                    Vec::default()
+                },
+            });
+        }
+    }
 
-                    write!(
-                        &mut content,
-                        "/F{} {} Tf\n",
-                        current_font.font_index(),
-                        current_font.size
-                    )?;
-                    match current_colour {
-                        Colour::RGB { r, g, b } => write!(&mut content, "{r} {g} {b} rg\n")?,
-                        Colour::CMYK { c, m, y, k } => write!(&mut content, "{c} {m} {y} {k} k\n")?,
-                        Colour::Grey { g } => write!(&mut content, "{g} g\n")?,
-                    }
+    /// Highlight regions of the page (typically, each line of some text)
+    pub fn add_highlight(&mut self, regions: Vec<Rect>, colour: Colour, note: Option<String>) {
+        self.highlights.push(Highlight {
+            regions,
+            colour,
+            note,
+        });
+    }
 
-                    for span in spans.iter() {
-                        if span.font != current_font {
-                            current_font = span.font;
-                            write!(
-                                &mut content,
-                                "/F{} {} Tf\n",
-                                current_font.font_index(),
-                                current_font.size
-                            )?;
-                        }
-                        if span.colour != current_colour {
-                            current_colour = span.colour;
-                            match current_colour {
-                                Colour::RGB { r, g, b } => {
-                                    write!(&mut content, "{r} {g} {b} rg\n")?
-                                }
-                                Colour::CMYK { c, m, y, k } => {
-                                    write!(&mut content, "{c} {m} {y} {k} k\n")?
-                                }
-                                Colour::Grey { g } => write!(&mut content, "{g} g\n")?,
-                            }
-                        }
+    /// Stamp the page with a form from the document (see [Document::add_form]), scaled to
+    /// fill `position`. The form is the stamp's appearance, and is written once no matter
+    /// how many stamps use it, so the same stamp on every page only adds a small
+    /// annotation to each. Unlike forms drawn with [Page::add_form], stamps are
+    /// annotations, which viewers let readers select, comment on, or delete
+    pub fn add_stamp(&mut self, form: Id<FormXObject>, position: Rect, note: Option<String>) {
+        self.stamps.push(Stamp {
+            form_index: form.index(),
+            position,
+            note,
+        });
+    }
 
-                        write!(&mut content, "BT\n")?;
-                        write!(&mut content, "{} {} Td\n", span.coords.0, span.coords.1)?;
-                        write!(&mut content, "<")?;
-                        for ch in span.text.chars() {
-                            write!(
-                                &mut content,
-                                "{:04x}",
-                                fonts[current_font.id].glyph_id(ch).unwrap_or_else(|| fonts
-                                    [current_font.id]
-                                    .replacement_glyph_id()
-                                    //.expect("Font has replacement glyph")
-                                    .unwrap_or_else(|| fonts[current_font.id]
-                                        .glyph_id('?')
-                                        .expect("Font has '?' glyph!")))
-                            )?;
-                        }
-                        write!(&mut content, "> Tj\n")?;
-                        write!(&mut content, "ET\n")?;
-                    }
-                    write!(&mut content, "Q\n")?;
-                }
-                PageContents::Image(image) => {
-                    write!(&mut content, "q\n")?;
-                    write!(
-                        &mut content,
-                        "{} 0 0 {} {} {} cm\n",
-                        image.position.x2 - image.position.x1,
-                        image.position.y2 - image.position.y1,
-                        image.position.x1,
-                        image.position.y1
-                    )?;
-                    write!(&mut content, "/I{} Do\n", image.image_index)?;
-                    write!(&mut content, "Q\n")?;
-                }
-                PageContents::RawContent(c) => {
-                    write!(&mut content, "q\n")?;
-                    content.write_all(c.as_slice())?;
-                    write!(&mut content, "\nQ\n")?;
+    /// Give a region of the page a measurement scale, which the measuring tools of PDF
+    /// viewers will honour. Where viewports overlap, the last one added takes precedence
+    pub fn add_viewport(&mut self, viewport: Viewport) {
+        self.viewports.push(viewport);
+    }
+
+    /// Add links for all of the spans on the page which have a URL in their metadata.
+    /// Consecutive spans with the same URL (such as the lines of a link that was wrapped
+    /// by the layout functions) are combined into a single link with a region per span.
+    pub fn link_span_urls(&mut self, document: &Document) {
+        let mut links: Vec<(String, Vec<Rect>)> = Vec::new();
+        for span in self.spans_with_metadata() {
+            if let Some(url) = &span.metadata.url {
+                let bounds = span.bounding_box(document);
+                match links.last_mut() {
+                    Some((last, regions)) if last == url => regions.push(bounds),
+                    _ => links.push((url.clone(), vec![bounds])),
                 }
             }
         }
+        for (url, regions) in links {
+            self.add_uri_link_regions(regions, url);
+        }
+    }
 
-        Ok(content)
+    /// Add a link on the page that when clicked will open another PDF document at the
+    /// given 0-based page index
+    pub fn add_remote_document_link<S: ToString>(&mut self, position: Rect, file: S, page: usize) {
+        self.external_links.push(ExternalLink {
+            position,
+            target: ExternalLinkTarget::RemoteDocument {
+                file: file.to_string(),
+                page,
+            },
+            regions: Vec::default(),
+        });
     }
 
+    /// Add a link on the page that when clicked will launch an application or open a
+    /// file with its associated application
+    pub fn add_launch_link<S: ToString>(&mut self, position: Rect, file: S) {
+        self.external_links.push(ExternalLink {
+            position,
+            target: ExternalLinkTarget::Launch {
+                file: file.to_string(),
+            },
+            regions: Vec::default(),
+        });
+    }
+
+    /// Render each of the page's contents into its own chunk of content stream, so that
+    /// identical chunks can be shared between pages
+    pub(crate) fn render_items(
+        &self,
+        document: &Document,
+        page_index: usize,
+    ) -> Result<Vec<Vec<u8>>, PDFError> {
+        render_contents(
+            &self.contents,
+            (self.media_box, self.content_box),
+            self.word_spacing,
+            document,
+            page_index,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn write(
         &self,
         refs: &mut ObjectReferences,
@@ -276,38 +846,134 @@ impl Page {
         page_order: &Vec<Id<Page>>,
         fonts: &Arena<Font>,
         images: &Arena<Image>,
+        document_forms: &Arena<FormXObject>,
+        rendered: &[Vec<u8>],
+        shared: &SharedContent,
+        compression: Compression,
         writer: &mut PdfWriter,
     ) -> Result<(), PDFError> {
-        // unwrap is ok, because we SHOULD panic if this page index doesn't already exist
-        // as the references are managed by the library (specifically, Document::write)
-        let id = refs.get(RefType::Page(page_index)).unwrap();
+        // the references are managed by the library (specifically, Document::write), so
+        // this should only fail if there is a bug in the library
+        let id = refs.require(RefType::Page(page_index))?;
         let mut page = writer.page(id);
         page.media_box(self.media_box.into());
         page.art_box(self.content_box.into());
-        page.parent(refs.get(RefType::PageTree).unwrap());
+        page.parent(refs.require(RefType::PageTree)?);
 
-        if !self.links.is_empty() {
+        if !self.viewports.is_empty() {
+            // viewers use the last viewport in the array which contains the point
+            // being measured
+            let mut viewports = page.insert(Name(b"VP")).array();
+            for viewport in self.viewports.iter() {
+                viewport.write(viewports.push());
+            }
+            viewports.finish();
+        }
+
+        if !self.links.is_empty()
+            || !self.external_links.is_empty()
+            || !self.highlights.is_empty()
+            || !self.stamps.is_empty()
+        {
             let mut annotations = page.annotations();
             for link in self.links.iter() {
                 let page_ref = match link.page {
                     PageLinkReference::ById(id) => id.index(),
-                    PageLinkReference::ByIndex(idx) => {
-                        page_order.get(idx).ok_or(PDFError::PageMissing)?.index()
-                    }
+                    PageLinkReference::ByIndex(idx) => page_order
+                        .get(idx)
+                        .ok_or(PDFError::LinkPageMissing { page_index: idx })?
+                        .index(),
                 };
+                let page_ref = refs
+                    .get(RefType::Page(page_ref))
+                    .ok_or(PDFError::PageMissing)?;
 
                 let mut annotation = annotations.push();
                 annotation.subtype(pdf_writer::types::AnnotationType::Link);
                 annotation.rect(link.position.into());
+                if !link.regions.is_empty() {
+                    annotation.quad_points(quad_points(&link.regions));
+                }
                 annotation.flags(pdf_writer::types::AnnotationFlags::INVISIBLE);
                 annotation.border(0.0, 0.0, 0.0, None);
                 annotation.color_transparent();
-                annotation
+                let destination = annotation
                     .action()
                     .action_type(pdf_writer::types::ActionType::GoTo)
                     .destination_direct()
-                    .page(refs.get(RefType::Page(page_ref)).unwrap())
-                    .fit();
+                    .page(page_ref);
+                match link.view {
+                    DestinationView::Fit => destination.fit(),
+                    DestinationView::Top(top) => destination.xyz(0.0, *top, None),
+                    DestinationView::Rect(rect) => destination.fit_rect(rect.into()),
+                }
+            }
+            for link in self.external_links.iter() {
+                let mut annotation = annotations.push();
+                annotation.subtype(pdf_writer::types::AnnotationType::Link);
+                annotation.rect(link.position.into());
+                if !link.regions.is_empty() {
+                    annotation.quad_points(quad_points(&link.regions));
+                }
+                annotation.flags(pdf_writer::types::AnnotationFlags::INVISIBLE);
+                annotation.border(0.0, 0.0, 0.0, None);
+                annotation.color_transparent();
+
+                let mut action = annotation.action();
+                match &link.target {
+                    ExternalLinkTarget::RemoteDocument { file, page } => {
+                        action.action_type(pdf_writer::types::ActionType::RemoteGoTo);
+                        action.pair(Name(b"F"), Str(file.as_bytes()));
+                        // remote destinations refer to pages by number rather than reference
+                        action
+                            .insert(Name(b"D"))
+                            .array()
+                            .item(*page as i32)
+                            .item(Name(b"Fit"));
+                    }
+                    ExternalLinkTarget::Launch { file } => {
+                        action.action_type(pdf_writer::types::ActionType::Launch);
+                        action.pair(Name(b"F"), Str(file.as_bytes()));
+                    }
+                    ExternalLinkTarget::Uri { uri } => {
+                        action.action_type(pdf_writer::types::ActionType::Uri);
+                        action.uri(Str(uri.as_bytes()));
+                    }
+                }
+            }
+            for highlight in self.highlights.iter() {
+                let position = match Rect::union(&highlight.regions) {
+                    Some(position) => position,
+                    None => continue,
+                };
+                let mut annotation = annotations.push();
+                annotation.subtype(pdf_writer::types::AnnotationType::Highlight);
+                annotation.rect(position.into());
+                annotation.quad_points(quad_points(&highlight.regions));
+                let (r, g, b) = highlight.colour.to_rgb();
+                annotation.color_rgb(r, g, b);
+                if let Some(note) = &highlight.note {
+                    annotation.contents(TextStr(note.as_str()));
+                }
+            }
+            for stamp in self.stamps.iter() {
+                let appearance =
+                    refs.get(RefType::Form(stamp.form_index))
+                        .ok_or(PDFError::FormMissing {
+                            form_index: stamp.form_index,
+                        })?;
+                let mut annotation = annotations.push();
+                annotation.pair(Name(b"Subtype"), Name(b"Stamp"));
+                annotation.rect(stamp.position.into());
+                annotation.flags(pdf_writer::types::AnnotationFlags::PRINT);
+                if let Some(note) = &stamp.note {
+                    annotation.contents(TextStr(note.as_str()));
+                }
+                // every stamp drawn with the form shares it as its appearance
+                annotation
+                    .insert(Name(b"AP"))
+                    .dict()
+                    .pair(Name(b"N"), appearance);
             }
         }
 
@@ -316,7 +982,7 @@ impl Page {
         for (i, _) in fonts.iter().enumerate() {
             resource_fonts.pair(
                 Name(format!("F{i}").as_bytes()),
-                refs.get(RefType::Font(i)).unwrap(),
+                refs.require(RefType::Font(i))?,
             );
         }
         resource_fonts.finish();
@@ -324,7 +990,25 @@ impl Page {
         for (i, _) in images.iter().enumerate() {
             resource_xobjects.pair(
                 Name(format!("I{i}").as_bytes()),
-                refs.get(RefType::Image(i)).unwrap(),
+                refs.require(RefType::Image(i))?,
+            );
+        }
+        let mut used_shared: Vec<usize> = rendered
+            .iter()
+            .filter_map(|item| shared.index_of(item))
+            .collect();
+        used_shared.sort_unstable();
+        used_shared.dedup();
+        for (i, _) in document_forms.iter().enumerate() {
+            resource_xobjects.pair(
+                Name(format!("X{i}").as_bytes()),
+                refs.require(RefType::Form(i))?,
+            );
+        }
+        for i in used_shared {
+            resource_xobjects.pair(
+                Name(format!("S{i}").as_bytes()),
+                refs.require(RefType::SharedContent(i))?,
             );
         }
         resource_xobjects.finish();
@@ -334,19 +1018,536 @@ impl Page {
         page.contents(content_id);
         page.finish();
 
-        let rendered = self.render(fonts)?;
-        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(
-            &rendered,
-            miniz_oxide::deflate::CompressionLevel::DefaultCompression as u8,
-        );
-        writer
-            .stream(content_id, compressed.as_slice())
-            .filter(pdf_writer::Filter::FlateDecode);
+        let mut content: Vec<u8> = Vec::with_capacity(rendered.iter().map(Vec::len).sum());
+        let defaults = self.defaults.render();
+        if !defaults.is_empty() {
+            content.extend_from_slice(&defaults);
+            content.push(b'\n');
+        }
+        for item in rendered.iter() {
+            match shared.index_of(item) {
+                Some(i) => writeln!(&mut content, "/S{i} Do")?,
+                None => content.extend_from_slice(item),
+            }
+        }
+        let (data, filter) = compression.compress(&content);
+        let mut stream = writer.stream(content_id, &data);
+        if let Some(filter) = filter {
+            stream.filter(filter);
+        }
+
+        Ok(())
+    }
+}
+
+/// Render each of some contents into its own chunk of content stream. `area` is the media
+/// box and content box of the page (or form) that the contents are rendered into, which
+/// is given to any deferred contents.
+pub(crate) fn render_contents(
+    contents: &[PageContents],
+    area: (Rect, Rect),
+    word_spacing: WordSpacing,
+    document: &Document,
+    page_index: usize,
+) -> Result<Vec<Vec<u8>>, PDFError> {
+    contents
+        .iter()
+        .map(|page_content| render_item(page_content, area, word_spacing, document, page_index))
+        .filter(|item| !matches!(item, Ok(item) if item.is_empty()))
+        .collect()
+}
+
+/// The width of the outline drawn around synthetic bold glyphs, in ems (see
+/// [TextEffects::synthetic_bold])
+const SYNTHETIC_BOLD_STROKE: f32 = 0.03;
+
+/// How far synthetic oblique text is slanted: the tangent of 12 degrees (see
+/// [TextEffects::synthetic_oblique])
+const SYNTHETIC_OBLIQUE_SKEW: f32 = 0.2126;
+
+#[allow(clippy::write_with_newline)]
+fn render_item(
+    page_content: &PageContents,
+    (media_box, content_box): (Rect, Rect),
+    word_spacing: WordSpacing,
+    document: &Document,
+    page_index: usize,
+) -> Result<Vec<u8>, PDFError> {
+    let fonts = &document.fonts;
+    let mut content: Vec<u8> = Vec::default();
+    match page_content {
+        PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+            if spans.is_empty() {
+                return Ok(content);
+            }
+
+            write!(&mut content, "q\n")?;
+            if let PageContents::ClippedText(_, clip) = page_content {
+                write!(
+                    &mut content,
+                    "{} {} {} {} re W n\n",
+                    clip.x1,
+                    clip.y1,
+                    clip.x2 - clip.x1,
+                    clip.y2 - clip.y1
+                )?;
+            }
+            // unwrap is safe, as we know spans isn't empty
+            let mut current_font: SpanFont = spans.first().unwrap().font;
+            let mut current_colour: Colour = spans.first().unwrap().colour;
+
+            write!(
+                &mut content,
+                "/F{} {} Tf\n",
+                current_font.font_index(),
+                current_font.size
+            )?;
+            match current_colour {
+                Colour::RGB { r, g, b } => write!(&mut content, "{r} {g} {b} rg\n")?,
+                Colour::CMYK { c, m, y, k } => write!(&mut content, "{c} {m} {y} {k} k\n")?,
+                Colour::Grey { g } => write!(&mut content, "{g} g\n")?,
+            }
+
+            // consecutive spans without marked content or effects share a text object
+            let mut text_line: Option<TextLine> = None;
+            for span in spans.iter() {
+                if span.font != current_font {
+                    current_font = span.font;
+                    write!(
+                        &mut content,
+                        "/F{} {} Tf\n",
+                        current_font.font_index(),
+                        current_font.size
+                    )?;
+                }
+                if span.colour != current_colour {
+                    current_colour = span.colour;
+                    match current_colour {
+                        Colour::RGB { r, g, b } => write!(&mut content, "{r} {g} {b} rg\n")?,
+                        Colour::CMYK { c, m, y, k } => write!(&mut content, "{c} {m} {y} {k} k\n")?,
+                        Colour::Grey { g } => write!(&mut content, "{g} g\n")?,
+                    }
+                }
+
+                let font = &fonts[current_font.id];
+                // artifacts are skipped by text extraction, so they don't need actual text
+                let artifact = span.metadata.artifact;
+                let actual_text = match artifact {
+                    Some(_) => None,
+                    None => actual_text(font, span),
+                };
+                if artifact.is_none() && actual_text.is_none() && span.effects.is_empty() {
+                    match &mut text_line {
+                        Some(line) => line.move_to(&mut content, span.coords)?,
+                        None => {
+                            write!(&mut content, "BT\n")?;
+                            write!(&mut content, "{} {} Td\n", span.coords.0, span.coords.1)?;
+                            text_line = Some(TextLine {
+                                start: span.coords,
+                                leading: None,
+                            });
+                        }
+                    }
+                    if span.glyphs.is_empty() {
+                        write_glyphs(&mut content, font, span, word_spacing)?;
+                    } else {
+                        write_shaped(&mut content, font, span, word_spacing)?;
+                    }
+                    continue;
+                }
+                if text_line.take().is_some() {
+                    write!(&mut content, "ET\n")?;
+                }
+
+                if let Some(artifact) = artifact {
+                    write!(&mut content, "{}", artifact.begin())?;
+                }
+                if let Some(actual_text) = &actual_text {
+                    // text strings are written as UTF-16BE with a byte order mark
+                    write!(&mut content, "/Span <</ActualText <FEFF")?;
+                    for unit in actual_text.encode_utf16() {
+                        write!(&mut content, "{unit:04X}")?;
+                    }
+                    write!(&mut content, ">>> BDC\n")?;
+                }
+                let effects = span.effects;
+                if effects.synthetic_bold {
+                    // outline the glyphs in the text's colour as well as filling them
+                    match current_colour {
+                        Colour::RGB { r, g, b } => write!(&mut content, "{r} {g} {b} RG\n")?,
+                        Colour::CMYK { c, m, y, k } => write!(&mut content, "{c} {m} {y} {k} K\n")?,
+                        Colour::Grey { g } => write!(&mut content, "{g} G\n")?,
+                    }
+                    write!(
+                        &mut content,
+                        "{} w\n",
+                        *span.font.size * SYNTHETIC_BOLD_STROKE
+                    )?;
+                }
+                write!(&mut content, "BT\n")?;
+                if effects.synthetic_oblique {
+                    // skew the text space rather than moving to the span
+                    write!(
+                        &mut content,
+                        "1 0 {} 1 {} {} Tm\n",
+                        SYNTHETIC_OBLIQUE_SKEW, span.coords.0, span.coords.1
+                    )?;
+                } else {
+                    write!(&mut content, "{} {} Td\n", span.coords.0, span.coords.1)?;
+                }
+                if effects.synthetic_bold {
+                    write!(&mut content, "2 Tr\n")?;
+                }
+                if *effects.letter_spacing != 0.0 {
+                    write!(&mut content, "{} Tc\n", effects.letter_spacing)?;
+                }
+                if *effects.rise != 0.0 {
+                    write!(&mut content, "{} Ts\n", effects.rise)?;
+                }
+                if span.glyphs.is_empty() {
+                    write_glyphs(&mut content, font, span, word_spacing)?;
+                } else {
+                    write_shaped(&mut content, font, span, word_spacing)?;
+                }
+                // text state outlasts the text object, so put it back for the next span
+                if *effects.letter_spacing != 0.0 {
+                    write!(&mut content, "0 Tc\n")?;
+                }
+                if *effects.rise != 0.0 {
+                    write!(&mut content, "0 Ts\n")?;
+                }
+                if effects.synthetic_bold {
+                    write!(&mut content, "0 Tr\n")?;
+                }
+                write!(&mut content, "ET\n")?;
+                let scaling = *span.font.size / font.units_per_em() as f32;
+                for (enabled, metrics, fallback) in [
+                    (effects.underline, font.underline_metrics(), (-0.1, 0.05)),
+                    (
+                        effects.strikethrough,
+                        font.strikeout_metrics(),
+                        (0.25, 0.05),
+                    ),
+                ] {
+                    if !enabled {
+                        continue;
+                    }
+                    // without metrics from the font, fall back to a position and
+                    // thickness in ems
+                    let (position, thickness) = metrics
+                        .map(|m| (m.position as f32 * scaling, m.thickness as f32 * scaling))
+                        .unwrap_or((fallback.0 * *span.font.size, fallback.1 * *span.font.size));
+                    write!(
+                        &mut content,
+                        "{} {} {} {} re f\n",
+                        span.coords.0,
+                        *span.coords.1 + *effects.rise + position - thickness / 2.0,
+                        span.width(font),
+                        thickness
+                    )?;
+                }
+                if actual_text.is_some() || artifact.is_some() {
+                    write!(&mut content, "EMC\n")?;
+                }
+            }
+            if text_line.is_some() {
+                write!(&mut content, "ET\n")?;
+            }
+            write!(&mut content, "Q\n")?;
+        }
+        PageContents::Image(image) => {
+            if let Some(artifact) = image.artifact {
+                write!(&mut content, "{}", artifact.begin())?;
+            }
+            write!(&mut content, "q\n")?;
+            write!(
+                &mut content,
+                "{} 0 0 {} {} {} cm\n",
+                image.position.x2 - image.position.x1,
+                image.position.y2 - image.position.y1,
+                image.position.x1,
+                image.position.y1
+            )?;
+            write!(&mut content, "/I{} Do\n", image.image_index)?;
+            write!(&mut content, "Q\n")?;
+            if image.artifact.is_some() {
+                write!(&mut content, "EMC\n")?;
+            }
+        }
+        PageContents::Form(form) => {
+            let bbox = document
+                .forms
+                .iter()
+                .nth(form.form_index)
+                .map(|(_, f)| f.bbox)
+                .ok_or(PDFError::FormMissing {
+                    form_index: form.form_index,
+                })?;
+            // scale and translate the form's bounding box onto its position
+            let sx = *(form.position.x2 - form.position.x1) / *(bbox.x2 - bbox.x1);
+            let sy = *(form.position.y2 - form.position.y1) / *(bbox.y2 - bbox.y1);
+            let tx = *form.position.x1 - *bbox.x1 * sx;
+            let ty = *form.position.y1 - *bbox.y1 * sy;
+            if let Some(artifact) = form.artifact {
+                write!(&mut content, "{}", artifact.begin())?;
+            }
+            write!(&mut content, "q\n")?;
+            write!(&mut content, "{sx} 0 0 {sy} {tx} {ty} cm\n")?;
+            write!(&mut content, "/X{} Do\n", form.form_index)?;
+            write!(&mut content, "Q\n")?;
+            if form.artifact.is_some() {
+                write!(&mut content, "EMC\n")?;
+            }
+        }
+        PageContents::RawContent(c) => {
+            if document.validate_content {
+                validate_content(c)?;
+            }
+            write!(&mut content, "q\n")?;
+            content.write_all(c.as_slice())?;
+            write!(&mut content, "\nQ\n")?;
+        }
+        PageContents::Deferred(generate) => {
+            let mut page = Page {
+                media_box,
+                content_box,
+                contents: Vec::default(),
+                links: Vec::default(),
+                external_links: Vec::default(),
+                highlights: Vec::default(),
+                stamps: Vec::default(),
+                viewports: Vec::default(),
+                word_spacing,
+                defaults: PageDefaults::default(),
+            };
+            let context = DeferredContext {
+                page_index,
+                page_count: document.page_order.len(),
+                document,
+            };
+            generate(&context, &mut page)?;
+            for item in page.render_items(document, page_index)? {
+                content.extend_from_slice(&item);
+            }
+        }
+    }
+
+    Ok(content)
+}
 
+/// Convert regions into the coordinates of quadrilaterals for an annotation's
+/// `QuadPoints`, in the order that viewers expect: top-left, top-right, bottom-left,
+/// then bottom-right
+fn quad_points(regions: &[Rect]) -> Vec<f32> {
+    regions
+        .iter()
+        .flat_map(|r| [*r.x1, *r.y2, *r.x2, *r.y2, *r.x1, *r.y1, *r.x2, *r.y1])
+        .collect()
+}
+
+/// The start of the current line of an open text object, and the text leading (the
+/// distance between the baselines of lines) that it was last given
+struct TextLine {
+    start: (Pt, Pt),
+    leading: Option<Pt>,
+}
+
+impl TextLine {
+    /// Move to the start of a span in the text object. Spans which start a line below
+    /// the current one, at the same horizontal position, move with `T*` and the text
+    /// leading, which is only given again when it changes. As lines of a paragraph are
+    /// evenly spaced, this keeps multi-line text compact
+    fn move_to(&mut self, content: &mut Vec<u8>, (x, y): (Pt, Pt)) -> Result<(), PDFError> {
+        let (dx, dy) = (x - self.start.0, y - self.start.1);
+        if *dx == 0.0 && *dy < 0.0 {
+            let leading = self.start.1 - y;
+            match self.leading {
+                // allow for rounding in the positions of the lines
+                Some(current) if (*current - *leading).abs() < 0.001 => {
+                    write!(content, "T*\n")?;
+                    self.start.1 -= current;
+                }
+                _ => {
+                    write!(content, "{leading} TL\nT*\n")?;
+                    self.leading = Some(leading);
+                    self.start.1 = y;
+                }
+            }
+        } else {
+            write!(content, "{dx} {dy} Td\n")?;
+            self.start = (x, y);
+        }
         Ok(())
     }
 }
 
+/// Write the characters of an unshaped span, each with its own glyph. Glyphs which
+/// are kerned against the glyph before them (see [Font::kerning]), and the words after
+/// positioned word gaps (see [WordSpacing]), are moved with a `TJ` array; text that
+/// needs neither is written with a plain `Tj`
+fn write_glyphs(
+    content: &mut Vec<u8>,
+    font: &Font,
+    span: &SpanLayout,
+    word_spacing: WordSpacing,
+) -> Result<(), PDFError> {
+    // each glyph with how far it is moved from where drawing the last one left it, in
+    // font units
+    let mut glyphs: Vec<(f32, u16)> = Vec::with_capacity(span.text.len());
+    // the letter spacing which a positioned word gap would have been drawn with
+    let letter_spacing =
+        *span.effects.letter_spacing * font.units_per_em() as f32 / *span.font.size;
+    let mut offset: f32 = 0.0;
+    let mut previous: Option<u16> = None;
+    for ch in span.text.chars() {
+        let id = glyph_id(font, ch, span)?;
+        if let Some(previous) = previous {
+            offset += font.kerning(previous, id) as f32;
+        }
+        previous = Some(id);
+        if ch == ' ' && word_spacing == WordSpacing::PositionedWords {
+            offset += font.space_advance() + letter_spacing;
+            continue;
+        }
+        glyphs.push((offset, id));
+        offset = 0.0;
+    }
+
+    if glyphs.iter().all(|&(offset, _)| offset == 0.0) {
+        write!(content, "<")?;
+        for (_, id) in glyphs.iter() {
+            font.write_glyph(content, *id)?;
+        }
+        write!(content, "> Tj\n")?;
+        return Ok(());
+    }
+
+    // adjustments are in thousandths of a unit of text space, and positive values move
+    // the next glyph to the left
+    let scaling = 1000.0 / font.units_per_em() as f32;
+    write!(content, "[<")?;
+    for (offset, id) in glyphs.iter() {
+        if *offset != 0.0 {
+            write!(content, "> {} <", -offset * scaling)?;
+        }
+        font.write_glyph(content, *id)?;
+    }
+    write!(content, ">] TJ\n")?;
+    Ok(())
+}
+
+/// Write the glyphs of a shaped span as a `TJ` array. Glyphs are advanced by their
+/// widths in the font, so the array adjusts the position of each glyph by the
+/// difference between that and where shaping placed it (such as for kerning or for the
+/// offsets of combining marks). Vertical offsets change the text rise, splitting the
+/// array where they do
+fn write_shaped(
+    content: &mut Vec<u8>,
+    font: &Font,
+    span: &SpanLayout,
+    word_spacing: WordSpacing,
+) -> Result<(), PDFError> {
+    // adjustments are in thousandths of a unit of text space, and positive values move
+    // the next glyph to the left
+    let units_per_em = font.units_per_em() as f32;
+    let scaling = 1000.0 / units_per_em;
+    let rise_scaling = *span.font.size / units_per_em;
+    let chars: Vec<char> = span.text.chars().collect();
+
+    // how far the next glyph is from where drawing the last one left it, in font units
+    let mut offset: f32 = 0.0;
+    let mut y_offset: i32 = 0;
+    write!(content, "[")?;
+    for glyph in span.glyphs.iter() {
+        if word_spacing == WordSpacing::PositionedWords && chars.get(glyph.cluster) == Some(&' ') {
+            // word gaps are positioned rather than drawn, along with their letter spacing
+            offset += glyph.x_advance as f32 + *span.effects.letter_spacing / rise_scaling;
+            continue;
+        }
+        if glyph.y_offset != y_offset {
+            y_offset = glyph.y_offset;
+            let rise = *span.effects.rise + y_offset as f32 * rise_scaling;
+            write!(content, "] TJ\n{rise} Ts\n[")?;
+        }
+        offset += glyph.x_offset as f32;
+        if offset != 0.0 {
+            write!(content, " {} ", -offset * scaling)?;
+        }
+        write!(content, "<")?;
+        font.write_glyph(content, glyph.id)?;
+        write!(content, ">")?;
+        offset = (glyph.x_advance - glyph.x_offset - font.glyph_advance(glyph.id) as i32) as f32;
+    }
+    write!(content, "] TJ\n")?;
+    if y_offset != 0 && *span.effects.rise == 0.0 {
+        // spans with a rise of their own have it put back after the text object
+        write!(content, "0 Ts\n")?;
+    }
+    Ok(())
+}
+
+/// Look up the glyph to print for a character in a span, falling back to the font's
+/// replacement glyph
+fn glyph_id(font: &Font, ch: char, span: &SpanLayout) -> Result<u16, PDFError> {
+    font.glyph_id_or_replacement(ch)
+        .ok_or_else(|| PDFError::MissingGlyph {
+            font: font.name(),
+            ch,
+            excerpt: excerpt(&span.text),
+        })
+}
+
+/// The text that a span should be extracted as, if it differs from the characters that
+/// are drawn: either as given in the span's metadata, or with ligature characters
+/// expanded and characters which the font has no glyph for (and so are drawn with a
+/// replacement glyph) kept. Shaped spans drawn with anything other than one glyph per
+/// character, each the character's own, are extracted as their text. [None] if the
+/// drawn characters can be extracted as they are
+fn actual_text(font: &Font, span: &SpanLayout) -> Option<String> {
+    if let Some(actual_text) = &span.metadata.actual_text {
+        return Some(actual_text.clone());
+    }
+
+    if !span.glyphs.is_empty() {
+        // ligatures and contextual forms aren't in the font's character map, so they
+        // can't be mapped back to the characters they were shaped from
+        let chars: Vec<char> = span.text.chars().collect();
+        let nominal =
+            span.glyphs.len() == chars.len()
+                && span.glyphs.iter().enumerate().all(|(i, glyph)| {
+                    glyph.cluster == i && font.glyph_id(chars[i]) == Some(glyph.id)
+                });
+        if !nominal {
+            return Some(span.text.clone());
+        }
+    }
+
+    let mut needed = false;
+    let mut text = String::with_capacity(span.text.len());
+    for ch in span.text.chars() {
+        let expanded = match ch {
+            '\u{FB00}' => "ff",
+            '\u{FB01}' => "fi",
+            '\u{FB02}' => "fl",
+            '\u{FB03}' => "ffi",
+            '\u{FB04}' => "ffl",
+            '\u{FB05}' | '\u{FB06}' => "st",
+            _ => {
+                needed |= font.glyph_id(ch).is_none();
+                text.push(ch);
+                continue;
+            }
+        };
+        needed = true;
+        text.push_str(expanded);
+    }
+    if needed {
+        Some(text)
+    } else {
+        None
+    }
+}
+
 /// Pre-defined page sizes for common usage
 pub mod pagesize {
     use crate::units::*;
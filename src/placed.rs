@@ -0,0 +1,241 @@
+use crate::document::Document;
+use crate::page::{Page, PageContents, SpanLayout};
+use crate::rect::Rect;
+use crate::units::Pt;
+use std::ops::Range;
+
+/// A span of text where it was placed on a page. Layout functions split text into one
+/// span per line (and per change of style), so each placed span is a fragment of a
+/// single line. See [Page::placed_spans]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedSpan<'a> {
+    /// The span itself, including any metadata that was attached to its text
+    pub span: &'a SpanLayout,
+    /// The region of the page that the span covers, from the descent to the ascent of
+    /// its font
+    pub rect: Rect,
+    /// The line that the span is on, counting lines (spans which share a baseline) from
+    /// the top of the page starting at 0
+    pub line: usize,
+}
+
+impl<'a> PlacedSpan<'a> {
+    /// The horizontal position of the start of the character at the given index (in
+    /// characters, not bytes) of the span's text. Indices past the end of the text give
+    /// the position of the end of the span
+    pub fn x_at(&self, document: &Document, index: usize) -> Pt {
+        let font = &document.fonts[self.span.font.id];
+        self.span.coords.0 + self.span.prefix_width(font, index)
+    }
+
+    /// The region covered by a range of characters (not bytes) of the span's text
+    pub fn range_rect(&self, document: &Document, range: Range<usize>) -> Rect {
+        Rect {
+            x1: self.x_at(document, range.start),
+            x2: self.x_at(document, range.end),
+            ..self.rect
+        }
+    }
+
+    /// Each word of the span (separated by whitespace) with the region it covers, so
+    /// that links, highlights, or tags can be attached to individual words
+    pub fn words(&self, document: &Document) -> Vec<(&'a str, Rect)> {
+        let text: &'a str = &self.span.text;
+        let mut words = Vec::new();
+        let mut start: Option<(usize, usize)> = None;
+        for (ci, (byte, ch)) in text.char_indices().enumerate() {
+            match (start, ch.is_whitespace()) {
+                (None, false) => start = Some((ci, byte)),
+                (Some((start_ci, start_byte)), true) => {
+                    words.push((
+                        &text[start_byte..byte],
+                        self.range_rect(document, start_ci..ci),
+                    ));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some((start_ci, start_byte)) = start {
+            let end = text.chars().count();
+            words.push((
+                &text[start_byte..],
+                self.range_rect(document, start_ci..end),
+            ));
+        }
+        words
+    }
+}
+
+impl Page {
+    /// Iterate over every span of text on the page where it was placed, in the layering
+    /// order that they were added. After text has been laid out, this can be used to find
+    /// where particular text (or text with particular metadata) ended up
+    pub fn placed_spans<'a>(&'a self, document: &Document) -> impl Iterator<Item = PlacedSpan<'a>> {
+        let spans: Vec<&SpanLayout> = self
+            .contents
+            .iter()
+            .filter_map(|content| match content {
+                PageContents::Text(spans) | PageContents::ClippedText(spans, _) => {
+                    Some(spans.iter())
+                }
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        // number the lines from the top of the page down
+        let mut baselines: Vec<Pt> = spans.iter().map(|span| span.coords.1).collect();
+        baselines.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        baselines.dedup();
+
+        spans
+            .into_iter()
+            .map(|span| PlacedSpan {
+                span,
+                rect: span.bounding_box(document),
+                line: baselines
+                    .iter()
+                    .position(|baseline| *baseline == span.coords.1)
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+/// A character of the text on a page, or the break between two lines
+#[derive(Clone, Copy)]
+enum TextUnit {
+    Char { ch: char, span: usize, index: usize },
+    LineBreak,
+}
+
+impl TextUnit {
+    /// Whether this separates words
+    fn is_gap(&self) -> bool {
+        match self {
+            TextUnit::Char { ch, .. } => ch.is_whitespace(),
+            TextUnit::LineBreak => true,
+        }
+    }
+}
+
+impl Page {
+    /// Find every occurrence of `pattern` in the text on the page, giving the regions
+    /// that each occurrence covers. An occurrence which was split across lines by layout
+    /// (or which spans differently styled spans) covers one region per fragment. Any
+    /// whitespace in the pattern matches any amount of whitespace or a line break, and
+    /// lines broken in the middle of a word are joined back together.
+    pub fn find_text_occurrences(&self, document: &Document, pattern: &str) -> Vec<Vec<Rect>> {
+        let spans: Vec<PlacedSpan> = self.placed_spans(document).collect();
+        let pattern: Vec<char> = pattern.trim().chars().collect();
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+
+        let mut text: Vec<TextUnit> = Vec::new();
+        for (si, placed) in spans.iter().enumerate() {
+            if si > 0 && spans[si - 1].line != placed.line {
+                text.push(TextUnit::LineBreak);
+            }
+            text.extend(
+                placed
+                    .span
+                    .text
+                    .chars()
+                    .enumerate()
+                    .map(|(index, ch)| TextUnit::Char {
+                        ch,
+                        span: si,
+                        index,
+                    }),
+            );
+        }
+
+        let mut occurrences = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let end = match match_at(&text, start, &pattern) {
+                Some(end) => end,
+                None => {
+                    start += 1;
+                    continue;
+                }
+            };
+
+            // one region per span that the occurrence touches
+            let mut regions: Vec<Rect> = Vec::new();
+            let mut fragment: Option<(usize, usize, usize)> = None;
+            for unit in text[start..end].iter() {
+                if let TextUnit::Char { span, index, .. } = *unit {
+                    fragment = match fragment {
+                        Some((fragment_span, first, _)) if fragment_span == span => {
+                            Some((span, first, index))
+                        }
+                        Some((fragment_span, first, last)) => {
+                            regions
+                                .push(spans[fragment_span].range_rect(document, first..last + 1));
+                            Some((span, index, index))
+                        }
+                        None => Some((span, index, index)),
+                    };
+                }
+            }
+            if let Some((fragment_span, first, last)) = fragment {
+                regions.push(spans[fragment_span].range_rect(document, first..last + 1));
+            }
+            occurrences.push(regions);
+            start = end;
+        }
+        occurrences
+    }
+
+    /// The regions covered by every occurrence of `pattern` in the text on the page (see
+    /// [Page::find_text_occurrences]), such as to highlight, redact, or link them
+    pub fn find_text(&self, document: &Document, pattern: &str) -> Vec<Rect> {
+        self.find_text_occurrences(document, pattern)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// If the pattern occurs at `start` in the text, the index just past the end of it
+fn match_at(text: &[TextUnit], start: usize, pattern: &[char]) -> Option<usize> {
+    if !matches!(text[start], TextUnit::Char { ch, .. } if ch == pattern[0]) {
+        return None;
+    }
+
+    let mut i = start;
+    let mut p = 0;
+    while p < pattern.len() {
+        if pattern[p].is_whitespace() {
+            // whitespace matches any run of whitespace and line breaks
+            let gap_start = i;
+            while i < text.len() && text[i].is_gap() {
+                i += 1;
+            }
+            if i == gap_start {
+                return None;
+            }
+            while p < pattern.len() && pattern[p].is_whitespace() {
+                p += 1;
+            }
+            continue;
+        }
+
+        // words may have been broken across lines without any whitespace
+        if i > start && matches!(text.get(i), Some(TextUnit::LineBreak)) {
+            i += 1;
+        }
+        match text.get(i) {
+            Some(TextUnit::Char { ch, .. }) if *ch == pattern[p] => {
+                i += 1;
+                p += 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(i)
+}
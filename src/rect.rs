@@ -13,6 +13,40 @@ pub struct Rect {
     pub y2: Pt,
 }
 
+impl Rect {
+    /// The smallest rectangle which contains all of the given rectangles, or [None] if
+    /// there are no rectangles
+    pub fn union<'a, I: IntoIterator<Item = &'a Rect>>(rects: I) -> Option<Rect> {
+        rects.into_iter().fold(None, |acc: Option<Rect>, r| {
+            let (x1, x2) = if r.x1 <= r.x2 {
+                (r.x1, r.x2)
+            } else {
+                (r.x2, r.x1)
+            };
+            let (y1, y2) = if r.y1 <= r.y2 {
+                (r.y1, r.y2)
+            } else {
+                (r.y2, r.y1)
+            };
+            Some(match acc {
+                None => Rect { x1, y1, x2, y2 },
+                Some(acc) => Rect {
+                    x1: if x1 < acc.x1 { x1 } else { acc.x1 },
+                    y1: if y1 < acc.y1 { y1 } else { acc.y1 },
+                    x2: if x2 > acc.x2 { x2 } else { acc.x2 },
+                    y2: if y2 > acc.y2 { y2 } else { acc.y2 },
+                },
+            })
+        })
+    }
+
+    /// Whether the two rectangles overlap by more than just their edges. Both rectangles
+    /// must be specified by their lower-left and upper-right corners
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x1 < other.x2 && other.x1 < self.x2 && self.y1 < other.y2 && other.y1 < self.y2
+    }
+}
+
 impl From<Rect> for pdf_writer::Rect {
     fn from(r: Rect) -> Self {
         pdf_writer::Rect {
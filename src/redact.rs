@@ -0,0 +1,72 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::drawing::DrawStyle;
+use crate::page::{Page, PageContents, SpanLayout};
+use crate::placed::PlacedSpan;
+use crate::rect::Rect;
+
+impl Page {
+    /// Redact regions of the page: every character of text on the page which overlaps
+    /// one of the regions is removed from the page (so it isn't written to the PDF at all,
+    /// and can't be recovered by copying or extracting the text), then the regions are
+    /// covered with boxes of the given colour. The text around the redacted characters
+    /// stays where it was.
+    ///
+    /// Only text added directly to the page is removed; images, forms, raw content, and
+    /// deferred content beneath the regions are only covered. Regions can be found with
+    /// [Page::find_text], such as to redact every occurrence of a name.
+    pub fn redact(&mut self, document: &Document, regions: &[Rect], colour: Colour) {
+        for content in self.contents.iter_mut() {
+            if let PageContents::Text(spans) | PageContents::ClippedText(spans, _) = content {
+                *spans = spans
+                    .iter()
+                    .flat_map(|span| redact_span(document, span, regions))
+                    .collect();
+            }
+        }
+        for region in regions.iter() {
+            self.add_shape(region, &DrawStyle::filled(colour));
+        }
+    }
+}
+
+/// Split a span into the pieces which are left once any characters overlapping the
+/// regions are removed
+fn redact_span(document: &Document, span: &SpanLayout, regions: &[Rect]) -> Vec<SpanLayout> {
+    let placed = PlacedSpan {
+        span,
+        rect: span.bounding_box(document),
+        line: 0,
+    };
+    if !regions.iter().any(|region| placed.rect.intersects(region)) {
+        return vec![span.clone()];
+    }
+
+    let mut pieces: Vec<SpanLayout> = Vec::new();
+    let mut piece: Option<SpanLayout> = None;
+    for (ci, ch) in span.text.chars().enumerate() {
+        let rect = placed.range_rect(document, ci..ci + 1);
+        if regions.iter().any(|region| rect.intersects(region)) {
+            pieces.extend(piece.take());
+            continue;
+        }
+        piece
+            .get_or_insert_with(|| {
+                let mut piece = SpanLayout {
+                    text: String::new(),
+                    coords: (rect.x1, span.coords.1),
+                    ..span.clone()
+                };
+                // the replacement text would describe the redacted text too
+                piece.metadata.actual_text = None;
+                piece
+            })
+            .text
+            .push(ch);
+    }
+    pieces.extend(piece);
+    for piece in pieces.iter_mut() {
+        piece.reshape(document);
+    }
+    pieces
+}
@@ -1,3 +1,4 @@
+use crate::PDFError;
 use pdf_writer::Ref;
 use std::collections::HashMap;
 
@@ -17,6 +18,12 @@ pub(crate) enum RefType {
     ImageMask(usize),
     Outlines,
     OutlineEntry(usize),
+    SharedContent(usize),
+    Form(usize),
+    EmbeddedFile,
+    FileSpec,
+    OutputProfile,
+    Metadata,
 }
 
 pub(crate) struct ObjectReferences {
@@ -47,6 +54,13 @@ impl ObjectReferences {
         self.refs.get(&ref_type).map(Clone::clone)
     }
 
+    /// Like [ObjectReferences::get], but returns an error if the reference hasn't been
+    /// generated yet
+    pub(crate) fn require(&self, ref_type: RefType) -> Result<Ref, PDFError> {
+        self.get(ref_type)
+            .ok_or_else(|| PDFError::ReferenceMissing(format!("{ref_type:?}")))
+    }
+
     pub(crate) fn gen(&mut self, ref_type: RefType) -> Ref {
         let id = self.new_id();
         self.refs.insert(ref_type, id);
@@ -0,0 +1,743 @@
+use crate::artifact::Artifact;
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::figure::{Figure, FigureFloat};
+use crate::form::FormXObject;
+use crate::layout::{self, Margins};
+use crate::page::{Page, PageSize, SpanFont, SpanLayout, SpanMetadata};
+use crate::rect::Rect;
+use crate::table::Table;
+use crate::units::Pt;
+use crate::PDFError;
+use id_arena::Id;
+
+/// A block of content within a [Report]. Blocks are laid out one after another down
+/// the page, starting new pages as needed.
+#[derive(Debug, Clone)]
+pub enum ReportBlock {
+    /// A title (in the report's heading font) with an optional subtitle beneath it
+    Header {
+        title: String,
+        subtitle: Option<String>,
+    },
+    /// A section heading (in the report's heading font), which may wrap onto several
+    /// lines. A heading is never left at the bottom of a page: if it wouldn't fit on the
+    /// current page along with the first `keep_with_next` lines (or table rows) of the
+    /// block after it, it is moved to the next page
+    Heading { text: String, keep_with_next: usize },
+    /// Text (in the report's font) which is wrapped to the width of the page and
+    /// continued onto the next page if it doesn't fit
+    Paragraph(String),
+    /// A list of labelled values, such as `("Invoice Date", "2022-06-01")`. The labels
+    /// are aligned in a column on the left, with the values to their right
+    KeyValues(Vec<(String, String)>),
+    /// A table, with an optional title above it and optional totals rows beneath it.
+    /// When the table doesn't fit on a page, it is continued on the next page with its
+    /// title (marked as continued) and header row repeated
+    Table {
+        title: Option<String>,
+        table: Table,
+        totals: Vec<Vec<String>>,
+    },
+    /// An image with an optional caption, kept together on one page. A figure which
+    /// doesn't fit on the current page may float to the next page (see [FigureFloat])
+    Figure(Figure),
+    /// Empty vertical space
+    Spacer(Pt),
+}
+
+/// The text of the markers which a [Report] adds where a paragraph or table is split
+/// across pages (see [Report::continuation_markers])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContinuationMarkers {
+    /// Added to the footer of the page that the block is split at
+    pub continued: String,
+    /// Added to the header of the page that the block continues on
+    pub continued_from: String,
+}
+
+impl Default for ContinuationMarkers {
+    fn default() -> Self {
+        ContinuationMarkers {
+            continued: "continued\u{2026}".to_string(),
+            continued_from: "\u{2026}continued from previous page".to_string(),
+        }
+    }
+}
+
+/// An opinionated builder for business documents such as invoices, statements, and
+/// reports. A report is a sequence of [ReportBlock]s which are laid out from the top
+/// of the first page, flowing onto as many pages as are needed.
+///
+/// Any fonts used in the report must already be added to the document that the report
+/// is rendered into.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The size of each page in the report
+    pub page_size: PageSize,
+    /// The margins of each page in the report
+    pub margins: Margins,
+    /// The font used for most text in the report
+    pub font: SpanFont,
+    /// The font used for titles. The same font (at the body font's size) is used
+    /// for labels, table titles, and totals
+    pub heading_font: SpanFont,
+    /// The colour of the text in the report
+    pub colour: Colour,
+    /// The vertical space between blocks
+    pub block_spacing: Pt,
+    /// Whether to add "Page X of Y" to the bottom-right of each page
+    pub page_numbers: bool,
+    /// Markers to add when a paragraph or table is split across pages: one at the
+    /// bottom-left of the page it is split at (level with any page numbers), and one at
+    /// the top-left of the page it continues on, in the page's top margin. None are added
+    /// by default
+    pub continuation_markers: Option<ContinuationMarkers>,
+    /// The content of the report
+    pub blocks: Vec<ReportBlock>,
+}
+
+impl Report {
+    /// Create an empty report
+    pub fn new(
+        page_size: PageSize,
+        margins: Margins,
+        font: SpanFont,
+        heading_font: SpanFont,
+    ) -> Report {
+        Report {
+            page_size,
+            margins,
+            font,
+            heading_font,
+            colour: crate::colours::BLACK,
+            block_spacing: font.size,
+            page_numbers: true,
+            continuation_markers: None,
+            blocks: Vec::default(),
+        }
+    }
+
+    /// Add a header block to the end of the report
+    pub fn header<S: ToString>(&mut self, title: S, subtitle: Option<S>) -> &mut Self {
+        self.blocks.push(ReportBlock::Header {
+            title: title.to_string(),
+            subtitle: subtitle.map(|s| s.to_string()),
+        });
+        self
+    }
+
+    /// Add a section heading to the end of the report, which is kept on the same page as
+    /// at least `keep_with_next` lines of the block after it
+    pub fn heading<S: ToString>(&mut self, text: S, keep_with_next: usize) -> &mut Self {
+        self.blocks.push(ReportBlock::Heading {
+            text: text.to_string(),
+            keep_with_next,
+        });
+        self
+    }
+
+    /// Add a paragraph of text to the end of the report
+    pub fn paragraph<S: ToString>(&mut self, text: S) -> &mut Self {
+        self.blocks.push(ReportBlock::Paragraph(text.to_string()));
+        self
+    }
+
+    /// Add a block of labelled values to the end of the report
+    pub fn key_values<K: ToString, V: ToString>(
+        &mut self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self {
+        self.blocks.push(ReportBlock::KeyValues(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ));
+        self
+    }
+
+    /// Add a table to the end of the report, with an optional title and any number of
+    /// totals rows (which are printed in the heading font after the last row)
+    pub fn table<S: ToString>(
+        &mut self,
+        title: Option<S>,
+        table: Table,
+        totals: Vec<Vec<String>>,
+    ) -> &mut Self {
+        self.blocks.push(ReportBlock::Table {
+            title: title.map(|t| t.to_string()),
+            table,
+            totals,
+        });
+        self
+    }
+
+    /// Add a figure to the end of the report
+    pub fn figure(&mut self, figure: Figure) -> &mut Self {
+        self.blocks.push(ReportBlock::Figure(figure));
+        self
+    }
+
+    /// Add empty vertical space to the end of the report
+    pub fn spacer(&mut self, height: Pt) -> &mut Self {
+        self.blocks.push(ReportBlock::Spacer(height));
+        self
+    }
+
+    /// Lay out the report, adding its pages to the end of the document. Returns the Ids
+    /// of the pages that were added
+    pub fn render(&self, document: &mut Document) -> Result<Vec<Id<Page>>, PDFError> {
+        let mut flow = Flow {
+            pages: Vec::new(),
+            y: Pt(0.0),
+            bottom: Pt(0.0),
+            floats: Vec::new(),
+            splits: Vec::new(),
+        };
+        self.start_page(document, &mut flow)?;
+
+        for (bi, block) in self.blocks.iter().enumerate() {
+            if bi > 0 {
+                flow.y -= self.block_spacing;
+            }
+            match block {
+                ReportBlock::Header { title, subtitle } => {
+                    let height = self.header_height(document, subtitle.is_some());
+                    self.ensure_space(document, &mut flow, height)?;
+                    self.line(document, &mut flow, title, self.heading_font, None);
+                    if let Some(subtitle) = subtitle {
+                        self.line(document, &mut flow, subtitle, self.font, None);
+                    }
+                }
+                ReportBlock::Heading {
+                    text,
+                    keep_with_next,
+                } => {
+                    let content_box = flow.page().content_box;
+                    let width = content_box.x2 - content_box.x1;
+                    let mut height =
+                        self.text_height(document, text, self.heading_font, width, None)?;
+                    if *keep_with_next > 0 {
+                        if let Some(next) = self.blocks.get(bi + 1) {
+                            height += self.block_spacing
+                                + self.lead_height(document, next, *keep_with_next, width)?;
+                        }
+                    }
+                    self.ensure_space(document, &mut flow, height)?;
+                    self.paragraph_block(document, &mut flow, text, self.heading_font)?;
+                }
+                ReportBlock::Paragraph(text) => {
+                    self.paragraph_block(document, &mut flow, text, self.font)?;
+                }
+                ReportBlock::KeyValues(pairs) => {
+                    self.key_values_block(document, &mut flow, pairs)?;
+                }
+                ReportBlock::Table {
+                    title,
+                    table,
+                    totals,
+                } => {
+                    self.table_block(document, &mut flow, title.as_deref(), table, totals)?;
+                }
+                ReportBlock::Figure(figure) => {
+                    self.figure_block(document, &mut flow, figure, bi > 0)?;
+                }
+                ReportBlock::Spacer(height) => {
+                    flow.y -= *height;
+                }
+            }
+        }
+
+        // figures still waiting to float onto the next page need a page of their own
+        if !flow.floats.is_empty() {
+            self.start_page(document, &mut flow)?;
+        }
+
+        let mut pages = flow.pages;
+        if self.page_numbers {
+            layout::number_pages(
+                document,
+                &mut pages,
+                self.font,
+                self.colour,
+                layout::page_x_of_y,
+            );
+        }
+        if let Some(markers) = &self.continuation_markers {
+            self.mark_continuations(document, &mut pages, &flow.splits, markers);
+        }
+
+        Ok(pages
+            .into_iter()
+            .map(|page| document.add_page(page))
+            .collect())
+    }
+
+    fn emphasis_font(&self) -> SpanFont {
+        SpanFont {
+            id: self.heading_font.id,
+            size: self.font.size,
+        }
+    }
+
+    fn new_page(&self) -> Page {
+        Page::new(self.page_size, Some(self.margins.clone()))
+    }
+
+    /// Start a new page, placing any figures which were floated onto it at its top and
+    /// bottom
+    fn start_page(&self, document: &Document, flow: &mut Flow) -> Result<(), PDFError> {
+        flow.pages.push(self.new_page());
+        let content_box = flow.page().content_box;
+        flow.y = content_box.y2;
+        flow.bottom = content_box.y1;
+
+        for figure in std::mem::take(&mut flow.floats) {
+            match figure.float {
+                FigureFloat::Bottom => {
+                    let width = content_box.x2 - content_box.x1;
+                    let height = figure.height(document, self.font, width)?;
+                    figure.layout(
+                        document,
+                        flow.page_mut(),
+                        self.font,
+                        self.colour,
+                        Rect {
+                            y2: flow.bottom + height,
+                            ..content_box
+                        },
+                    )?;
+                    flow.bottom += height + self.block_spacing;
+                }
+                _ => {
+                    flow.y = figure.layout(
+                        document,
+                        flow.page_mut(),
+                        self.font,
+                        self.colour,
+                        Rect {
+                            y2: flow.y,
+                            ..content_box
+                        },
+                    )? - self.block_spacing;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a new page to continue a block which didn't fit on the current one
+    fn continue_page(&self, document: &Document, flow: &mut Flow) -> Result<(), PDFError> {
+        let split = flow.pages.len() - 1;
+        if flow.splits.last() != Some(&split) {
+            flow.splits.push(split);
+        }
+        self.start_page(document, flow)
+    }
+
+    /// Add the continuation markers to the pages that blocks were split at, and to the
+    /// pages after them
+    fn mark_continuations(
+        &self,
+        document: &Document,
+        pages: &mut [Page],
+        splits: &[usize],
+        markers: &ContinuationMarkers,
+    ) {
+        let ascent = document.fonts[self.font.id].ascent(self.font.size);
+        for &pi in splits.iter() {
+            if let Some(page) = pages.get_mut(pi) {
+                let coords = (page.content_box.x1, page.content_box.y1 / 2.0);
+                self.marker(page, &markers.continued, coords, Artifact::Footer);
+            }
+            if let Some(page) = pages.get_mut(pi + 1) {
+                let top = (page.media_box.y2 + page.content_box.y2) / 2.0;
+                let coords = (page.content_box.x1, top - ascent / 2.0);
+                self.marker(page, &markers.continued_from, coords, Artifact::Header);
+            }
+        }
+    }
+
+    /// Add a continuation marker to a page, marked as an artifact
+    fn marker(&self, page: &mut Page, text: &str, coords: (Pt, Pt), artifact: Artifact) {
+        let mut metadata = SpanMetadata::default();
+        metadata.artifact(artifact);
+        page.add_span(SpanLayout {
+            text: text.to_string(),
+            font: self.font,
+            colour: self.colour,
+            coords,
+            effects: Default::default(),
+            metadata,
+            glyphs: Vec::new(),
+        });
+    }
+
+    /// Start a new page if there isn't at least `height` left on the current page. If
+    /// the current page is empty, the content is placed regardless so that content
+    /// taller than a page doesn't result in infinite empty pages.
+    fn ensure_space(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        height: Pt,
+    ) -> Result<bool, PDFError> {
+        if flow.y - height < flow.bottom && !flow.page_is_empty() {
+            self.start_page(document, flow)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// The height of a header block
+    fn header_height(&self, document: &Document, has_subtitle: bool) -> Pt {
+        let mut height = document.fonts[self.heading_font.id].line_height(self.heading_font.size);
+        if has_subtitle {
+            height += document.fonts[self.font.id].line_height(self.font.size);
+        }
+        height
+    }
+
+    /// The height of text when wrapped to the given width, or of only its first
+    /// `max_lines` lines
+    fn text_height(
+        &self,
+        document: &Document,
+        text: &str,
+        font: SpanFont,
+        width: Pt,
+        max_lines: Option<usize>,
+    ) -> Result<Pt, PDFError> {
+        let font_ref = &document.fonts[font.id];
+        let ascent = font_ref.ascent(font.size);
+        let line_height = font_ref.line_height(font.size);
+        let area = Rect {
+            x1: Pt(0.0),
+            y1: Pt(f32::MIN),
+            x2: width,
+            y2: Pt(0.0),
+        };
+        let mut scratch = FormXObject::new(area);
+        let mut text = vec![(text.to_string(), self.colour, font)];
+        let start = Pt(0.0) - ascent;
+        let (_, baseline) = layout::layout_text(
+            document,
+            &mut scratch,
+            (Pt(0.0), start),
+            &mut text,
+            Pt(0.0),
+            area,
+        )?;
+        let mut lines = (*((start - baseline) / line_height)).round() as usize + 1;
+        if let Some(max_lines) = max_lines {
+            lines = lines.min(max_lines);
+        }
+        Ok(line_height * lines as f32)
+    }
+
+    /// The height of the start of a block (its first `lines` lines or table rows, or
+    /// all of it if it can't be split), which a heading before it is kept with
+    fn lead_height(
+        &self,
+        document: &Document,
+        block: &ReportBlock,
+        lines: usize,
+        width: Pt,
+    ) -> Result<Pt, PDFError> {
+        let line_height = document.fonts[self.font.id].line_height(self.font.size);
+        Ok(match block {
+            ReportBlock::Header { subtitle, .. } => {
+                self.header_height(document, subtitle.is_some())
+            }
+            ReportBlock::Heading { text, .. } => {
+                self.text_height(document, text, self.heading_font, width, Some(lines))?
+            }
+            ReportBlock::Paragraph(text) => {
+                self.text_height(document, text, self.font, width, Some(lines))?
+            }
+            ReportBlock::KeyValues(pairs) => line_height * pairs.len().min(lines) as f32,
+            ReportBlock::Table { title, table, .. } => {
+                let font = self.emphasis_font();
+                let title_height = match title {
+                    Some(_) => document.fonts[font.id].line_height(font.size),
+                    None => Pt(0.0),
+                };
+                title_height + table.row_height(document) * (table.rows.len().min(lines) + 1) as f32
+            }
+            ReportBlock::Figure(figure) => figure.height(document, self.font, width)?,
+            ReportBlock::Spacer(height) => *height,
+        })
+    }
+
+    /// Print text at the current position, wrapping it to the width of the page and
+    /// continuing it on new pages as needed, then moving the position down below it
+    fn paragraph_block(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        text: &str,
+        font: SpanFont,
+    ) -> Result<(), PDFError> {
+        let font_ref = &document.fonts[font.id];
+        let ascent = font_ref.ascent(font.size);
+        let line_height = font_ref.line_height(font.size);
+
+        let mut text = vec![(text.to_string(), self.colour, font)];
+        loop {
+            self.ensure_space(document, flow, line_height)?;
+            let page_was_empty = flow.page_is_empty();
+            let remaining: usize = text.iter().map(|(t, _, _)| t.len()).sum();
+
+            let content_box = flow.page().content_box;
+            let top = flow.y;
+            let (_, baseline) = layout::layout_text(
+                document,
+                flow.page_mut(),
+                (content_box.x1, top - ascent),
+                &mut text,
+                Pt(0.0),
+                Rect {
+                    y1: flow.bottom,
+                    y2: top,
+                    ..content_box
+                },
+            )?;
+            flow.y = baseline + ascent - line_height;
+
+            // if not even a line fits on an empty page, it never will
+            let left: usize = text.iter().map(|(t, _, _)| t.len()).sum();
+            if left == 0 || (page_was_empty && left == remaining) {
+                break;
+            }
+            self.continue_page(document, flow)?;
+        }
+        Ok(())
+    }
+
+    /// Print a single line of text at the current position, moving the position down
+    fn line(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        text: &str,
+        font: SpanFont,
+        x: Option<Pt>,
+    ) {
+        let font_ref = &document.fonts[font.id];
+        let x = x.unwrap_or(flow.page().content_box.x1);
+        let y = flow.y - font_ref.ascent(font.size);
+        flow.page_mut().add_span(SpanLayout {
+            text: text.to_string(),
+            font,
+            colour: self.colour,
+            coords: (x, y),
+            effects: Default::default(),
+            metadata: Default::default(),
+            glyphs: Vec::new(),
+        });
+        flow.y -= font_ref.line_height(font.size);
+    }
+
+    fn key_values_block(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        pairs: &[(String, String)],
+    ) -> Result<(), PDFError> {
+        let key_font = self.emphasis_font();
+        let key_width = pairs
+            .iter()
+            .map(|(k, _)| layout::width_of_text(k, &document.fonts[key_font.id], key_font.size))
+            .fold(Pt(0.0), |a, b| if b > a { b } else { a });
+        let gap = self.font.size;
+        let ascent = document.fonts[self.font.id].ascent(self.font.size);
+        let line_height = document.fonts[self.font.id].line_height(self.font.size);
+
+        for (key, value) in pairs.iter() {
+            self.ensure_space(document, flow, line_height)?;
+            let mut top = flow.y;
+            self.line(document, flow, key, key_font, None);
+
+            // values may wrap onto multiple lines, and continue on new pages
+            let mut text = vec![(value.clone(), self.colour, self.font)];
+            let mut continued = false;
+            loop {
+                let remaining: usize = text.iter().map(|(t, _, _)| t.len()).sum();
+                let content_box = flow.page().content_box;
+                let value_x = content_box.x1 + key_width + gap;
+                let (_, baseline) = layout::layout_text(
+                    document,
+                    flow.page_mut(),
+                    (value_x, top - ascent),
+                    &mut text,
+                    Pt(0.0),
+                    Rect {
+                        x1: value_x,
+                        y1: flow.bottom,
+                        y2: top,
+                        ..content_box
+                    },
+                )?;
+                flow.y = baseline + ascent - line_height;
+
+                // if not even a line fits on a new page, it never will
+                let left: usize = text.iter().map(|(t, _, _)| t.len()).sum();
+                if left == 0 || (continued && left == remaining) {
+                    break;
+                }
+                self.continue_page(document, flow)?;
+                top = flow.y;
+                continued = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn table_block(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        title: Option<&str>,
+        table: &Table,
+        totals: &[Vec<String>],
+    ) -> Result<(), PDFError> {
+        let title_font = self.emphasis_font();
+        let title_height = document.fonts[title_font.id].line_height(title_font.size);
+        let row_height = table.row_height(document);
+
+        // keep the title with the header and at least one row
+        let mut needed = row_height * 2.0;
+        if title.is_some() {
+            needed += title_height;
+        }
+        self.ensure_space(document, flow, needed)?;
+        if let Some(title) = title {
+            self.line(document, flow, title, title_font, None);
+        }
+
+        let mut next_row = 0;
+        let mut continued = false;
+        loop {
+            let content_box = flow.page().content_box;
+            let (laid_out_to, bottom) = table.layout(
+                document,
+                flow.page_mut(),
+                Rect {
+                    y1: flow.bottom,
+                    y2: flow.y,
+                    ..content_box
+                },
+                next_row,
+            );
+            flow.y = bottom;
+            // if we couldn't fit a single row on a continuation page, we never will
+            if laid_out_to >= table.rows.len() || (laid_out_to == next_row && continued) {
+                break;
+            }
+            next_row = laid_out_to;
+            continued = true;
+
+            self.continue_page(document, flow)?;
+            if let Some(title) = title {
+                self.line(
+                    document,
+                    flow,
+                    &format!("{} (continued)", title),
+                    title_font,
+                    None,
+                );
+            }
+        }
+
+        let mut remaining = totals;
+        while !remaining.is_empty() {
+            let content_box = flow.page().content_box;
+            let (laid_out, bottom) = table.layout_rows(
+                document,
+                flow.page_mut(),
+                Rect {
+                    y1: flow.bottom,
+                    y2: flow.y,
+                    ..content_box
+                },
+                remaining,
+                title_font,
+            );
+            flow.y = bottom;
+            remaining = &remaining[laid_out..];
+            if remaining.is_empty() {
+                break;
+            }
+            let split = flow.pages.len() - 1;
+            if !self.ensure_space(document, flow, row_height)? {
+                break;
+            }
+            flow.splits.push(split);
+        }
+        Ok(())
+    }
+
+    fn figure_block(
+        &self,
+        document: &Document,
+        flow: &mut Flow,
+        figure: &Figure,
+        spaced: bool,
+    ) -> Result<(), PDFError> {
+        let content_box = flow.page().content_box;
+        let height = figure.height(document, self.font, content_box.x2 - content_box.x1)?;
+        if flow.y - height < flow.bottom
+            && !flow.page_is_empty()
+            && figure.float != FigureFloat::Inline
+        {
+            // the blocks after the figure carry on where it would have been
+            flow.floats.push(figure.clone());
+            if spaced {
+                flow.y += self.block_spacing;
+            }
+            return Ok(());
+        }
+
+        self.ensure_space(document, flow, height)?;
+        let content_box = flow.page().content_box;
+        flow.y = figure.layout(
+            document,
+            flow.page_mut(),
+            self.font,
+            self.colour,
+            Rect {
+                y2: flow.y,
+                ..content_box
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// The state of laying out a report: the pages generated so far, the current vertical
+/// position on the last page, how far down the last page content can go, any figures
+/// waiting to float onto the next page, and the pages which blocks were split at
+struct Flow {
+    pages: Vec<Page>,
+    y: Pt,
+    bottom: Pt,
+    floats: Vec<Figure>,
+    splits: Vec<usize>,
+}
+
+impl Flow {
+    /// Whether nothing has been placed at the top of the last page yet
+    fn page_is_empty(&self) -> bool {
+        self.y >= self.page().content_box.y2
+    }
+
+    fn page(&self) -> &Page {
+        // there is always at least one page
+        self.pages.last().unwrap()
+    }
+
+    fn page_mut(&mut self) -> &mut Page {
+        self.pages.last_mut().unwrap()
+    }
+}
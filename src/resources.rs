@@ -0,0 +1,183 @@
+use crate::document::Document;
+use crate::PDFError;
+use std::collections::BTreeSet;
+
+/// The fonts, images, and forms which a content stream refers to by name (`/Fi`, `/Ii`,
+/// and `/Xi` respectively)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct UsedResources {
+    pub(crate) fonts: BTreeSet<usize>,
+    pub(crate) images: BTreeSet<usize>,
+    pub(crate) forms: BTreeSet<usize>,
+}
+
+impl UsedResources {
+    /// Find the resources used by a content stream. As raw content can refer to anything,
+    /// this looks at every name in the stream which could refer to a resource, skipping
+    /// over strings, comments, and the data of inline images. Names which look like
+    /// resources but don't refer to anything in the document are ignored.
+    pub(crate) fn scan(content: &[u8], document: &Document) -> UsedResources {
+        let mut used = UsedResources::default();
+        let mut i = 0;
+        while i < content.len() {
+            match content[i] {
+                // comments run to the end of the line
+                b'%' => {
+                    while i < content.len() && !matches!(content[i], b'\n' | b'\r') {
+                        i += 1;
+                    }
+                }
+                // literal strings, which may contain balanced or escaped parentheses
+                b'(' => {
+                    let mut depth = 0;
+                    while i < content.len() {
+                        match content[i] {
+                            b'\\' => i += 1,
+                            b'(' => depth += 1,
+                            b')' => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                // the start of a dictionary, rather than a hex string
+                b'<' if content.get(i + 1) == Some(&b'<') => i += 2,
+                // hex strings
+                b'<' => {
+                    while i < content.len() && content[i] != b'>' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                b'/' => {
+                    let end = token_end(content, i + 1);
+                    used.insert_name(&content[i + 1..end], document);
+                    i = end;
+                }
+                b if is_regular(b) => {
+                    let end = token_end(content, i);
+                    // the data of an inline image is binary, and runs until `EI`
+                    i = if &content[i..end] == b"ID" {
+                        inline_image_end(content, end)
+                    } else {
+                        end
+                    };
+                }
+                _ => i += 1,
+            }
+        }
+        used
+    }
+
+    /// Record the resource that a name (without its leading `/`) refers to, if any. The
+    /// name must be a kind followed only by digits, so i.e. `/F1a` isn't mistaken for
+    /// `/F1`
+    fn insert_name(&mut self, name: &[u8], document: &Document) {
+        let (kind, digits) = match name.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return;
+        }
+        let index: usize = match std::str::from_utf8(digits)
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            Some(index) => index,
+            None => return,
+        };
+        match kind {
+            b'F' if index < document.fonts.len() => {
+                self.fonts.insert(index);
+            }
+            b'I' if index < document.images.len() => {
+                self.images.insert(index);
+            }
+            b'X' if index < document.forms.len() => {
+                self.forms.insert(index);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Where the run of regular bytes (see [is_regular]) starting at `start` ends
+fn token_end(content: &[u8], start: usize) -> usize {
+    start
+        + content[start..]
+            .iter()
+            .take_while(|b| is_regular(**b))
+            .count()
+}
+
+/// Where the `EI` operator which ends an inline image's data is, given where the data
+/// starts, or the end of the content if it isn't there
+fn inline_image_end(content: &[u8], start: usize) -> usize {
+    (start..content.len().saturating_sub(1))
+        .find(|&i| {
+            &content[i..i + 2] == b"EI"
+                && content[i - 1].is_ascii_whitespace()
+                && content.get(i + 2).map(|b| !is_regular(*b)).unwrap_or(true)
+        })
+        .map(|i| i + 2)
+        .unwrap_or(content.len())
+}
+
+/// Whether the byte can be part of a name (i.e. isn't whitespace or a delimiter)
+pub(crate) fn is_regular(b: u8) -> bool {
+    !b.is_ascii_whitespace()
+        && b != 0
+        && !matches!(
+            b,
+            b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+        )
+}
+
+/// Make sure that no form draws itself, either directly or through other forms, as
+/// viewers would never finish drawing it. `used` is the resources used by each form, in
+/// order.
+pub(crate) fn check_form_cycles(used: &[UsedResources]) -> Result<(), PDFError> {
+    #[derive(Copy, Clone, PartialEq, Eq)]
+    enum State {
+        Unvisited,
+        Visiting,
+        Done,
+    }
+
+    let mut states = vec![State::Unvisited; used.len()];
+    for root in 0..used.len() {
+        if states[root] != State::Unvisited {
+            continue;
+        }
+        // depth-first search, keeping each form's remaining children on the stack
+        let mut stack: Vec<(usize, Vec<usize>)> =
+            vec![(root, used[root].forms.iter().copied().collect())];
+        states[root] = State::Visiting;
+        while let Some((form, children)) = stack.last_mut() {
+            match children.pop() {
+                Some(child) => match states[child] {
+                    State::Visiting => {
+                        return Err(PDFError::FormCycle { form_index: child });
+                    }
+                    State::Unvisited => {
+                        states[child] = State::Visiting;
+                        stack.push((child, used[child].forms.iter().copied().collect()));
+                    }
+                    State::Done => {}
+                },
+                None => {
+                    states[*form] = State::Done;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use crate::colour::Colour;
+use crate::page::{SpanFont, SpanLayout, SpanMetadata};
+use crate::units::Pt;
+
+/// How a span of text is drawn beyond its font and colour
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TextEffects {
+    /// Draw a line beneath the text
+    pub underline: bool,
+    /// Draw a line through the middle of the text
+    pub strikethrough: bool,
+    /// Extra space added after each character (which may be negative, to tighten text)
+    pub letter_spacing: Pt,
+    /// How far the text is raised above its baseline (or lowered, if negative), such as
+    /// for superscripts and subscripts
+    pub rise: Pt,
+    /// Embolden the text by outlining its glyphs as well as filling them, for fonts
+    /// without a bold face. The outline doesn't change how far the glyphs advance, so
+    /// synthetic bold text is set as tightly as the regular text
+    pub synthetic_bold: bool,
+    /// Slant the text to the right, for fonts without an italic or oblique face
+    pub synthetic_oblique: bool,
+    /// Draw lowercase letters as small capitals. The layout functions use the font's own
+    /// small capitals if it has them (see [crate::Font::has_small_caps]) and the
+    /// `shaping` feature is enabled, and otherwise draw lowercase letters as capitals in
+    /// a smaller size
+    pub small_caps: bool,
+}
+
+impl TextEffects {
+    /// Whether the effects change how text is drawn at all
+    pub fn is_empty(&self) -> bool {
+        *self == TextEffects::default()
+    }
+}
+
+/// A reusable character style (font, colour, effects, and metadata) which can be
+/// applied to any number of strings to turn them into input for the layout functions
+/// (see [crate::layout::layout_runs]) or into spans:
+///
+/// ```
+/// # use pdf_gen::{colours, Document, Font, TextRun, SpanFont, Pt};
+/// # let mut doc = Document::default();
+/// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+/// # let font = doc.add_font(font);
+/// let body = SpanFont { id: font, size: Pt(10.0) };
+/// let mut link = TextRun::new(body);
+/// link.colour(colours::BLUE).underline();
+/// link.metadata.url("https://example.com");
+///
+/// let text = vec![
+///     TextRun::new(body).text("Read more at "),
+///     link.text("example.com"),
+/// ];
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextRun {
+    /// The font (and size) to draw the text in
+    pub font: SpanFont,
+    /// The colour of the text
+    pub colour: Colour,
+    /// Decorations and spacing
+    pub effects: TextEffects,
+    /// Metadata copied onto every span generated from the run
+    pub metadata: SpanMetadata,
+    /// Ruby text annotating the run, such as furigana giving the reading of kanji. The
+    /// layout functions keep an annotated run together on one line and set the
+    /// annotation above it at half its size, spreading out whichever of the two is
+    /// shorter to the width of the other
+    pub ruby: Option<String>,
+}
+
+impl TextRun {
+    /// Create a style for black text in the given font, with no effects or metadata
+    pub fn new(font: SpanFont) -> TextRun {
+        TextRun {
+            font,
+            colour: crate::colours::BLACK,
+            effects: TextEffects::default(),
+            metadata: SpanMetadata::default(),
+            ruby: None,
+        }
+    }
+
+    /// Set the colour of the text
+    pub fn colour(&mut self, colour: Colour) -> &mut Self {
+        self.colour = colour;
+        self
+    }
+
+    /// Underline the text
+    pub fn underline(&mut self) -> &mut Self {
+        self.effects.underline = true;
+        self
+    }
+
+    /// Strike through the text
+    pub fn strikethrough(&mut self) -> &mut Self {
+        self.effects.strikethrough = true;
+        self
+    }
+
+    /// Set the extra space added after each character
+    pub fn letter_spacing(&mut self, letter_spacing: Pt) -> &mut Self {
+        self.effects.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Set how far the text is raised above its baseline
+    pub fn rise(&mut self, rise: Pt) -> &mut Self {
+        self.effects.rise = rise;
+        self
+    }
+
+    /// Embolden the text, for fonts without a bold face
+    pub fn synthetic_bold(&mut self) -> &mut Self {
+        self.effects.synthetic_bold = true;
+        self
+    }
+
+    /// Slant the text, for fonts without an italic or oblique face
+    pub fn synthetic_oblique(&mut self) -> &mut Self {
+        self.effects.synthetic_oblique = true;
+        self
+    }
+
+    /// Draw lowercase letters as small capitals
+    pub fn small_caps(&mut self) -> &mut Self {
+        self.effects.small_caps = true;
+        self
+    }
+
+    /// Annotate the text with ruby, such as furigana:
+    ///
+    /// ```
+    /// # use pdf_gen::{Document, Font, TextRun, SpanFont, Pt};
+    /// # let mut doc = Document::default();
+    /// # let font = Font::load(include_bytes!("../assets/FiraMono-Regular.ttf").to_vec()).unwrap();
+    /// # let font = doc.add_font(font);
+    /// let body = SpanFont { id: font, size: Pt(10.0) };
+    /// let text = vec![
+    ///     TextRun::new(body).ruby("\u{304b}\u{3093}\u{3058}").text("\u{6f22}\u{5b57}"),
+    ///     TextRun::new(body).text("\u{3092}\u{8aad}\u{3080}"),
+    /// ];
+    /// ```
+    pub fn ruby<S: ToString>(&mut self, ruby: S) -> &mut Self {
+        self.ruby = Some(ruby.to_string());
+        self
+    }
+
+    /// Apply the style to some text, giving input for [crate::layout::layout_runs]
+    pub fn text<S: ToString>(&self, text: S) -> (String, TextRun) {
+        (text.to_string(), self.clone())
+    }
+
+    /// Apply the style to some text, positioned with its baseline starting at `coords`
+    pub fn span<S: ToString>(&self, text: S, coords: (Pt, Pt)) -> SpanLayout {
+        SpanLayout {
+            text: text.to_string(),
+            font: self.font,
+            colour: self.colour,
+            coords,
+            effects: self.effects,
+            metadata: self.metadata.clone(),
+            glyphs: Vec::new(),
+        }
+    }
+}
+
+impl From<(Colour, SpanFont, SpanMetadata)> for TextRun {
+    fn from((colour, font, metadata): (Colour, SpanFont, SpanMetadata)) -> TextRun {
+        TextRun {
+            font,
+            colour,
+            effects: TextEffects::default(),
+            metadata,
+            ruby: None,
+        }
+    }
+}
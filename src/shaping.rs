@@ -0,0 +1,238 @@
+use crate::document::Document;
+use crate::font::Font;
+use crate::page::SpanLayout;
+use crate::units::Pt;
+
+/// A glyph chosen by shaping text, and where it is placed relative to the glyphs
+/// around it. Distances are in font units.
+///
+/// Shaping (with the `shaping` feature, see [Font::shape]) applies the font's own rules
+/// for which glyphs to draw: ligatures, contextual forms, kerning, and the positioning
+/// of combining marks. Shaped glyphs don't have to correspond one-to-one with the
+/// characters of the text, so each glyph records the character it was shaped from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    /// The glyph in the font
+    pub id: u16,
+    /// The index (in characters, not bytes) of the first character of the text that
+    /// the glyph was shaped from. A ligature covers every character from its cluster up
+    /// to the next glyph's cluster
+    pub cluster: usize,
+    /// How far to move along the line after drawing the glyph
+    pub x_advance: i32,
+    /// How far to move the glyph horizontally when drawing it, without affecting the
+    /// glyphs after it
+    pub x_offset: i32,
+    /// How far to move the glyph vertically when drawing it, such as to place a mark
+    /// above its base
+    pub y_offset: i32,
+}
+
+impl Font {
+    /// Shape text with the font's layout tables, giving the glyphs to draw it with in
+    /// the order that they are drawn. The script, language, and direction of the text
+    /// are guessed from its characters. Characters which the font has no glyph for are
+    /// drawn with its replacement glyph (see [Font::glyph_id_or_replacement])
+    #[cfg(feature = "shaping")]
+    pub fn shape(&self, text: &str) -> Vec<ShapedGlyph> {
+        self.shape_with_features(text, &[])
+    }
+
+    /// Shape text as [Font::shape] does, but with the font's small capitals (see
+    /// [Font::has_small_caps]) in place of its lowercase letters
+    #[cfg(feature = "shaping")]
+    pub fn shape_small_caps(&self, text: &str) -> Vec<ShapedGlyph> {
+        let smcp = rustybuzz::Feature::new(rustybuzz::Tag::from_bytes(b"smcp"), 1, ..);
+        self.shape_with_features(text, &[smcp])
+    }
+
+    #[cfg(feature = "shaping")]
+    fn shape_with_features(&self, text: &str, features: &[rustybuzz::Feature]) -> Vec<ShapedGlyph> {
+        let face = match self
+            .face()
+            .and_then(|face| rustybuzz::Face::from_slice(face.as_slice(), 0))
+        {
+            Some(face) => face,
+            // the standard fonts don't have layout tables, so each character is drawn
+            // with its own glyph
+            None => {
+                return text
+                    .chars()
+                    .enumerate()
+                    .filter_map(|(cluster, ch)| {
+                        let id = self.glyph_id_or_replacement(ch)?;
+                        Some(ShapedGlyph {
+                            id,
+                            cluster,
+                            x_advance: self.glyph_advance(id) as i32,
+                            x_offset: 0,
+                            y_offset: 0,
+                        })
+                    })
+                    .collect()
+            }
+        };
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        let shaped = rustybuzz::shape(&face, features, buffer);
+
+        // clusters are given as byte offsets into the text
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions().iter())
+            .map(|(info, position)| {
+                let cluster = chars
+                    .iter()
+                    .position(|&(byte, _)| byte == info.cluster as usize)
+                    .unwrap_or_default();
+                let mut glyph = ShapedGlyph {
+                    id: info.glyph_id as u16,
+                    cluster,
+                    x_advance: position.x_advance,
+                    x_offset: position.x_offset,
+                    y_offset: position.y_offset,
+                };
+                if glyph.id == 0 {
+                    if let Some(id) = chars
+                        .get(cluster)
+                        .and_then(|&(_, ch)| self.glyph_id_or_replacement(ch))
+                    {
+                        glyph.id = id;
+                        glyph.x_advance = self.glyph_advance(id) as i32;
+                    }
+                }
+                glyph
+            })
+            .collect()
+    }
+}
+
+impl SpanLayout {
+    /// Shape the span's text (see [Font::shape]), so that it is drawn with the glyphs
+    /// the font chooses for it rather than one glyph per character. The layout
+    /// functions shape the spans they generate, so this is only needed for spans which
+    /// are made by hand
+    #[cfg(feature = "shaping")]
+    pub fn shape(&mut self, document: &Document) {
+        let font = &document.fonts[self.font.id];
+        self.glyphs = if self.effects.small_caps {
+            font.shape_small_caps(&self.text)
+        } else {
+            font.shape(&self.text)
+        };
+    }
+
+    /// Shape the span's text again after it was changed, if it had been shaped
+    pub(crate) fn reshape(&mut self, document: &Document) {
+        if self.glyphs.is_empty() {
+            return;
+        }
+        #[cfg(feature = "shaping")]
+        self.shape(document);
+        #[cfg(not(feature = "shaping"))]
+        {
+            let _ = document;
+            self.glyphs.clear();
+        }
+    }
+
+    /// How far each character of the span advances along the line, including letter
+    /// spacing (see [char_advances])
+    pub(crate) fn char_advances(&self, font: &Font) -> Vec<Pt> {
+        if self.glyphs.is_empty() {
+            nominal_advances(
+                font,
+                &self.text,
+                self.font.size,
+                self.effects.letter_spacing,
+            )
+        } else {
+            char_advances(
+                font,
+                &self.glyphs,
+                self.text.chars().count(),
+                self.font.size,
+                self.effects.letter_spacing,
+            )
+        }
+    }
+
+    /// The width of the first `count` characters of the span, including any letter
+    /// spacing
+    pub(crate) fn prefix_width(&self, font: &Font, count: usize) -> Pt {
+        self.char_advances(font).into_iter().take(count).sum()
+    }
+}
+
+/// How far each character of shaped text advances along the line, including letter
+/// spacing. Every glyph's advance is given to the character that its cluster starts
+/// at, so the other characters of a ligature don't advance at all
+pub(crate) fn char_advances(
+    font: &Font,
+    glyphs: &[ShapedGlyph],
+    count: usize,
+    size: Pt,
+    letter_spacing: Pt,
+) -> Vec<Pt> {
+    let scaling = size / font.units_per_em() as f32;
+    let mut advances = vec![Pt(0.0); count];
+    for glyph in glyphs.iter() {
+        if let Some(advance) = advances.get_mut(glyph.cluster) {
+            *advance += scaling * glyph.x_advance as f32 + letter_spacing;
+        }
+    }
+    advances
+}
+
+/// How far each character of the text advances along the line when drawn in the font,
+/// including letter spacing. With the `shaping` feature the text is shaped (see
+/// [char_advances]), with small capitals if `small_caps` is set; without it, each
+/// character advances by the width of its own glyph
+pub(crate) fn text_advances(
+    font: &Font,
+    text: &str,
+    size: Pt,
+    letter_spacing: Pt,
+    small_caps: bool,
+) -> Vec<Pt> {
+    #[cfg(feature = "shaping")]
+    {
+        let glyphs = if small_caps {
+            font.shape_small_caps(text)
+        } else {
+            font.shape(text)
+        };
+        char_advances(font, &glyphs, text.chars().count(), size, letter_spacing)
+    }
+    #[cfg(not(feature = "shaping"))]
+    {
+        let _ = small_caps;
+        nominal_advances(font, text, size, letter_spacing)
+    }
+}
+
+/// How far each character of unshaped text advances along the line: by the width of its
+/// own glyph (or the replacement glyph), the kerning between that and the next
+/// character's glyph (see [Font::kerning]), and letter spacing
+fn nominal_advances(font: &Font, text: &str, size: Pt, letter_spacing: Pt) -> Vec<Pt> {
+    let scaling = size / font.units_per_em() as f32;
+    let ids: Vec<Option<u16>> = text
+        .chars()
+        .map(|ch| font.glyph_id_or_replacement(ch))
+        .collect();
+    ids.iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let mut advance = id
+                .map(|id| font.glyph_advance(id) as i32)
+                .unwrap_or_default();
+            if let (Some(left), Some(Some(right))) = (id, ids.get(i + 1)) {
+                advance += font.kerning(*left, *right) as i32;
+            }
+            scaling * advance as f32 + letter_spacing
+        })
+        .collect()
+}
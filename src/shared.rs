@@ -0,0 +1,127 @@
+use crate::compression::Compression;
+use crate::font::Font;
+use crate::form::FormXObject;
+use crate::image::Image;
+use crate::page::Page;
+use crate::rect::Rect;
+use crate::refs::{ObjectReferences, RefType};
+use crate::units::Pt;
+use crate::PDFError;
+use id_arena::Arena;
+use pdf_writer::{Finish, Name, PdfWriter};
+use std::collections::HashMap;
+
+/// Rendered content smaller than this isn't worth hoisting into a form, as drawing a
+/// form and the form object itself have some overhead of their own
+const MIN_SHARED_SIZE: usize = 256;
+
+/// Content which is repeated across pages (such as page decorations and headers), which
+/// is written once as a Form XObject that each page then draws by name (`/Si` where `i`
+/// is the index of the shared content)
+#[derive(Default)]
+pub(crate) struct SharedContent {
+    forms: HashMap<Vec<u8>, usize>,
+    bboxes: Vec<Rect>,
+}
+
+impl SharedContent {
+    /// Find all the rendered content which appears more than once, given each page
+    /// along with its rendered content items
+    pub(crate) fn find(pages: &[(&Page, &Vec<Vec<u8>>)]) -> SharedContent {
+        let mut counts: HashMap<&[u8], usize> = HashMap::new();
+        for (_, rendered) in pages.iter() {
+            for item in rendered.iter().filter(|item| item.len() >= MIN_SHARED_SIZE) {
+                *counts.entry(item.as_slice()).or_insert(0) += 1;
+            }
+        }
+
+        // assign indices in the order the content first appears so that output is
+        // deterministic
+        let mut shared = SharedContent::default();
+        for (page, rendered) in pages.iter() {
+            for item in rendered.iter() {
+                if counts.get(item.as_slice()).copied().unwrap_or_default() < 2 {
+                    continue;
+                }
+                match shared.forms.get(item) {
+                    Some(&i) => {
+                        // content is drawn in page space, so the form needs to cover
+                        // every page it is drawn on
+                        let bbox = &mut shared.bboxes[i];
+                        *bbox = Rect {
+                            x1: Pt(bbox.x1.min(*page.media_box.x1)),
+                            y1: Pt(bbox.y1.min(*page.media_box.y1)),
+                            x2: Pt(bbox.x2.max(*page.media_box.x2)),
+                            y2: Pt(bbox.y2.max(*page.media_box.y2)),
+                        };
+                    }
+                    None => {
+                        shared.forms.insert(item.clone(), shared.bboxes.len());
+                        shared.bboxes.push(page.media_box);
+                    }
+                }
+            }
+        }
+        shared
+    }
+
+    /// The index of the shared content matching the rendered content, if it is shared
+    pub(crate) fn index_of(&self, rendered: &[u8]) -> Option<usize> {
+        self.forms.get(rendered).copied()
+    }
+
+    pub(crate) fn write(
+        &self,
+        refs: &mut ObjectReferences,
+        fonts: &Arena<Font>,
+        images: &Arena<Image>,
+        forms: &Arena<FormXObject>,
+        compression: Compression,
+        writer: &mut PdfWriter,
+    ) -> Result<(), PDFError> {
+        let mut shared: Vec<(&Vec<u8>, usize)> = self
+            .forms
+            .iter()
+            .map(|(content, &i)| (content, i))
+            .collect();
+        shared.sort_by_key(|&(_, i)| i);
+
+        for (content, i) in shared {
+            let id = refs.gen(RefType::SharedContent(i));
+            let (data, filter) = compression.compress(content);
+            let mut form = writer.form_xobject(id, &data);
+            if let Some(filter) = filter {
+                form.filter(filter);
+            }
+            form.bbox(self.bboxes[i].into());
+
+            let mut resources = form.resources();
+            let mut resource_fonts = resources.fonts();
+            for (fi, _) in fonts.iter().enumerate() {
+                resource_fonts.pair(
+                    Name(format!("F{fi}").as_bytes()),
+                    refs.require(RefType::Font(fi))?,
+                );
+            }
+            resource_fonts.finish();
+            let mut resource_xobjects = resources.x_objects();
+            for (ii, _) in images.iter().enumerate() {
+                resource_xobjects.pair(
+                    Name(format!("I{ii}").as_bytes()),
+                    refs.require(RefType::Image(ii))?,
+                );
+            }
+            for (fi, _) in forms.iter().enumerate() {
+                resource_xobjects.pair(
+                    Name(format!("X{fi}").as_bytes()),
+                    refs.require(RefType::Form(fi))?,
+                );
+            }
+            resource_xobjects.finish();
+            resources.finish();
+            form.finish();
+        }
+
+        Ok(())
+    }
+}
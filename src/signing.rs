@@ -0,0 +1,465 @@
+use crate::incremental::{find, find_key, rfind, IncrementalUpdate, Tokens};
+use crate::rect::Rect;
+use crate::units::Pt;
+use crate::PDFError;
+use pdf_writer::Ref;
+use std::io::Write;
+
+/// The `/ByteRange` of a signature before it is known. Each number is padded to the
+/// same width as the placeholder, so filling it in doesn't move anything
+const BYTE_RANGE_PLACEHOLDER: &[u8] = b"/ByteRange [0 0000000000 0000000000 0000000000]";
+
+/// Data needed to validate the signatures in a document long after they were made,
+/// stored in the document security store (see [IncrementalUpdate::add_validation_data]).
+/// Each item is DER-encoded
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationData {
+    /// Certificates in the signers' certificate chains
+    pub certs: Vec<Vec<u8>>,
+    /// OCSP responses showing that the certificates weren't revoked
+    pub ocsps: Vec<Vec<u8>>,
+    /// Certificate revocation lists showing that the certificates weren't revoked
+    pub crls: Vec<Vec<u8>>,
+    /// Which of the certificates, OCSP responses, and CRLs are needed to validate
+    /// individual signatures
+    pub vri: Vec<SignatureValidation>,
+}
+
+/// The validation data needed for one signature (a VRI entry in the document security
+/// store), given as indices into the items of the [ValidationData]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SignatureValidation {
+    /// The SHA-1 hash of the signature (the bytes returned by the signer), as hex
+    pub signature_hash: String,
+    /// Indices into [ValidationData::certs]
+    pub certs: Vec<usize>,
+    /// Indices into [ValidationData::ocsps]
+    pub ocsps: Vec<usize>,
+    /// Indices into [ValidationData::crls]
+    pub crls: Vec<usize>,
+}
+
+/// How the signature returned by a signer is encoded
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SignatureFormat {
+    /// A detached PKCS #7 / CMS signature (`adbe.pkcs7.detached`), which all viewers
+    /// support
+    #[default]
+    Pkcs7Detached,
+    /// A detached CAdES signature (`ETSI.CAdES.detached`), as required by PAdES
+    CadesDetached,
+}
+
+/// The details recorded with a digital signature (see [IncrementalUpdate::sign])
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The name of the signature field
+    pub field_name: String,
+    /// The name of the person or authority signing
+    pub name: Option<String>,
+    /// Why the document is being signed
+    pub reason: Option<String>,
+    /// Where the document is being signed
+    pub location: Option<String>,
+    /// How to contact the signer
+    pub contact_info: Option<String>,
+    /// Where the signature field is on the page. The field has no appearance, so this
+    /// is only used by viewers to show where the signature is; the default empty rect
+    /// makes the signature invisible
+    pub position: Rect,
+    /// The number of bytes reserved in the file for the signature returned by the
+    /// signer (the CMS structure, including any certificates and timestamps)
+    pub reserved: usize,
+    /// How the signature returned by the signer is encoded
+    pub format: SignatureFormat,
+}
+
+impl Default for Signature {
+    fn default() -> Self {
+        Signature {
+            field_name: "Signature1".to_string(),
+            name: None,
+            reason: None,
+            location: None,
+            contact_info: None,
+            position: Rect {
+                x1: Pt(0.0),
+                y1: Pt(0.0),
+                x2: Pt(0.0),
+                y2: Pt(0.0),
+            },
+            reserved: 8192,
+            format: SignatureFormat::default(),
+        }
+    }
+}
+
+impl Signature {
+    /// An invisible signature with no details, reserving 8 KiB for the signature
+    pub fn new() -> Signature {
+        Signature::default()
+    }
+
+    /// Set the name of the person or authority signing
+    pub fn name<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set why the document is being signed
+    pub fn reason<S: ToString>(&mut self, reason: S) -> &mut Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    /// Set where the document is being signed
+    pub fn location<S: ToString>(&mut self, location: S) -> &mut Self {
+        self.location = Some(location.to_string());
+        self
+    }
+
+    /// Set how to contact the signer
+    pub fn contact_info<S: ToString>(&mut self, contact_info: S) -> &mut Self {
+        self.contact_info = Some(contact_info.to_string());
+        self
+    }
+
+    /// Set where the signature field is on the page
+    pub fn position(&mut self, position: Rect) -> &mut Self {
+        self.position = position;
+        self
+    }
+
+    /// Set the number of bytes reserved for the signature
+    pub fn reserve(&mut self, bytes: usize) -> &mut Self {
+        self.reserved = bytes;
+        self
+    }
+
+    /// Set how the signature returned by the signer is encoded
+    pub fn format(&mut self, format: SignatureFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+}
+
+impl IncrementalUpdate {
+    /// Finish the update by digitally signing the document, returning the signed file.
+    /// A signature field is added to the given page, then the file is written with space
+    /// reserved for the signature. Everything in the file except that space is given to
+    /// the `signer`, which must return a DER-encoded, detached CMS signature of it
+    /// (`adbe.pkcs7.detached`), such as one produced by a hardware token or a CMS
+    /// library. The signature is then embedded in the file.
+    ///
+    /// ```
+    /// # use pdf_gen::{pagesize, Document, IncrementalUpdate, Page, Signature};
+    /// # let mut doc = Document::default();
+    /// # doc.add_page(Page::new(pagesize::LETTER, None));
+    /// # let mut original = Vec::new();
+    /// # doc.write(&mut original).unwrap();
+    /// let update = IncrementalUpdate::new(original).unwrap();
+    /// let page = update.page_refs().unwrap()[0];
+    /// let mut signature = Signature::new();
+    /// signature.name("Jane Doe").reason("Approved");
+    /// let signed = update
+    ///     .sign(page, &signature, |bytes: &[u8]| -> Result<Vec<u8>, std::io::Error> {
+    ///         // a real signer would hash `bytes` and build a CMS structure here
+    ///         Ok(vec![0x30, 0x00])
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn sign<F, E>(
+        self,
+        page: Ref,
+        signature: &Signature,
+        signer: F,
+    ) -> Result<Vec<u8>, PDFError>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let sub_filter = match signature.format {
+            SignatureFormat::Pkcs7Detached => "adbe.pkcs7.detached",
+            SignatureFormat::CadesDetached => "ETSI.CAdES.detached",
+        };
+        let mut entries = format!(
+            "  /Type /Sig\n  /Filter /Adobe.PPKLite\n  /SubFilter /{sub_filter}\n  /M ({})\n",
+            chrono::Utc::now().format("D:%Y%m%d%H%M%SZ")
+        );
+        for (key, text) in [
+            ("Name", &signature.name),
+            ("Reason", &signature.reason),
+            ("Location", &signature.location),
+            ("ContactInfo", &signature.contact_info),
+        ] {
+            if let Some(text) = text {
+                entries.push_str(&format!("  /{key} {}\n", text_string(text)));
+            }
+        }
+        self.sign_field(page, signature, &entries, signer)
+    }
+
+    /// Finish the update by adding a document timestamp (`ETSI.RFC3161`), which proves
+    /// that the document, including any signatures and validation data already in it,
+    /// existed at a point in time. This is used for long-term validation (PAdES-LTV),
+    /// so that signatures can still be verified after their certificates expire.
+    ///
+    /// This works like [IncrementalUpdate::sign], except that the `timestamper` must
+    /// return an RFC 3161 timestamp token (as DER) for the bytes it is given, usually
+    /// obtained from a timestamping authority. Only the field name, position, and
+    /// reserved size of the `signature` are used.
+    pub fn timestamp<F, E>(
+        self,
+        page: Ref,
+        signature: &Signature,
+        timestamper: F,
+    ) -> Result<Vec<u8>, PDFError>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let entries =
+            "  /Type /DocTimeStamp\n  /Filter /Adobe.PPKLite\n  /SubFilter /ETSI.RFC3161\n";
+        self.sign_field(page, signature, entries, timestamper)
+    }
+
+    /// Add validation data (certificates, OCSP responses, and CRLs) to the document
+    /// security store, so that signatures in the document can be validated later
+    /// without fetching it (PAdES-LTV). This is usually added in an update after the
+    /// document is signed, followed by a document timestamp (see
+    /// [IncrementalUpdate::timestamp]). Any validation data already in the document is
+    /// replaced.
+    pub fn add_validation_data(&mut self, data: &ValidationData) -> Result<(), PDFError> {
+        let mut streams = |items: &[Vec<u8>]| -> Vec<Ref> {
+            items
+                .iter()
+                .map(|item| {
+                    let id = self.alloc();
+                    let mut body = format!("<< /Length {} >>\nstream\n", item.len()).into_bytes();
+                    body.extend_from_slice(item);
+                    body.extend_from_slice(b"\nendstream");
+                    self.set_object(id, body);
+                    id
+                })
+                .collect()
+        };
+        let certs = streams(&data.certs);
+        let ocsps = streams(&data.ocsps);
+        let crls = streams(&data.crls);
+
+        let array = |refs: &[Ref], indices: &mut dyn Iterator<Item = usize>| -> String {
+            let items: Vec<String> = indices
+                .filter_map(|i| refs.get(i))
+                .map(|r| format!("{} 0 R", r.get()))
+                .collect();
+            format!("[{}]", items.join(" "))
+        };
+        let mut dss = String::from("<<\n");
+        for (key, refs) in [("Certs", &certs), ("OCSPs", &ocsps), ("CRLs", &crls)] {
+            if !refs.is_empty() {
+                dss.push_str(&format!("  /{key} {}\n", array(refs, &mut (0..refs.len()))));
+            }
+        }
+        if !data.vri.is_empty() {
+            dss.push_str("  /VRI <<\n");
+            for vri in data.vri.iter() {
+                dss.push_str(&format!(
+                    "    /{} << /Cert {} /OCSP {} /CRL {} >>\n",
+                    vri.signature_hash.to_uppercase(),
+                    array(&certs, &mut vri.certs.iter().copied()),
+                    array(&ocsps, &mut vri.ocsps.iter().copied()),
+                    array(&crls, &mut vri.crls.iter().copied()),
+                ));
+            }
+            dss.push_str("  >>\n");
+        }
+        dss.push_str(">>");
+        let id = self.alloc();
+        self.set_object(id, dss.into_bytes());
+
+        let root = self.root();
+        let catalog = self
+            .object(root)
+            .ok_or(PDFError::MalformedPdf("the catalog is missing"))?;
+        let catalog = set_entry(catalog, b"/DSS", format!("{} 0 R", id.get()).as_bytes())?;
+        self.set_object(root, catalog);
+        Ok(())
+    }
+
+    /// Add a signature field to the page whose value has the given entries, then write
+    /// the file and sign it
+    fn sign_field<F, E>(
+        mut self,
+        page: Ref,
+        signature: &Signature,
+        entries: &str,
+        signer: F,
+    ) -> Result<Vec<u8>, PDFError>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        // the signature value, with placeholders for the byte range and signature
+        let value = self.alloc();
+        let mut dict = Vec::new();
+        dict.extend_from_slice(b"<<\n");
+        dict.extend_from_slice(entries.as_bytes());
+        dict.extend_from_slice(b"  ");
+        dict.extend_from_slice(BYTE_RANGE_PLACEHOLDER);
+        write!(
+            &mut dict,
+            "\n  /Contents <{}>\n>>",
+            "0".repeat(signature.reserved * 2)
+        )?;
+        self.set_object(value, dict);
+
+        // the field which holds the signature, as a widget on the page
+        let position = signature.position;
+        let widget = self.add_annotation(
+            page,
+            format!(
+                "<<\n  /Type /Annot\n  /Subtype /Widget\n  /FT /Sig\n  /T {}\n  /V {} 0 R\n  /F 132\n  /Rect [{} {} {} {}]\n  /P {} 0 R\n>>",
+                text_string(&signature.field_name),
+                value.get(),
+                position.x1,
+                position.y1,
+                position.x2,
+                position.y2,
+                page.get()
+            )
+            .into_bytes(),
+        )?;
+
+        // and the form which the field belongs to
+        let root = self.root();
+        let catalog = self
+            .object(root)
+            .ok_or(PDFError::MalformedPdf("the catalog is missing"))?
+            .to_vec();
+        let reference = format!(" {} 0 R", widget.get());
+        let updated = match find_key(&catalog, b"/AcroForm") {
+            // a form added by an earlier signature
+            Some(form) => {
+                let value = form + b"/AcroForm".len();
+                let inline = catalog[value..]
+                    .iter()
+                    .find(|b| !b.is_ascii_whitespace())
+                    .copied()
+                    == Some(b'<');
+                let fields = find(&catalog[value..], b"/Fields")
+                    .and_then(|fields| {
+                        find(&catalog[value + fields..], b"[").map(|open| value + fields + open)
+                    })
+                    .filter(|_| inline)
+                    .ok_or(PDFError::MalformedPdf(
+                        "the document's form doesn't have a list of fields which can be extended",
+                    ))?;
+                let mut updated = catalog[..=fields].to_vec();
+                updated.extend_from_slice(reference.as_bytes());
+                updated.extend_from_slice(&catalog[fields + 1..]);
+                updated
+            }
+            None => set_entry(
+                &catalog,
+                b"/AcroForm",
+                format!("<< /Fields [{}] /SigFlags 3 >>", reference.trim_start()).as_bytes(),
+            )?,
+        };
+        self.set_object(root, updated);
+
+        let mut out = Vec::new();
+        self.write(&mut out)?;
+
+        // the signature covers everything except its own value
+        let range_start = rfind(&out, BYTE_RANGE_PLACEHOLDER).ok_or(PDFError::ReferenceMissing(
+            "the signature's byte range".to_string(),
+        ))?;
+        let contents_start = range_start
+            + find(&out[range_start..], b"/Contents <").ok_or(PDFError::ReferenceMissing(
+                "the signature's contents".to_string(),
+            ))?
+            + b"/Contents ".len();
+        let contents_end = contents_start + signature.reserved * 2 + 2;
+        let byte_range = format!(
+            "/ByteRange [0 {:<10} {:<10} {:<10}]",
+            contents_start,
+            contents_end,
+            out.len() - contents_end
+        );
+        out[range_start..range_start + BYTE_RANGE_PLACEHOLDER.len()]
+            .copy_from_slice(byte_range.as_bytes());
+
+        let mut signed = Vec::with_capacity(out.len() - (contents_end - contents_start));
+        signed.extend_from_slice(&out[..contents_start]);
+        signed.extend_from_slice(&out[contents_end..]);
+        let cms = signer(&signed).map_err(|e| PDFError::Signer(e.into()))?;
+        if cms.len() > signature.reserved {
+            return Err(PDFError::SignatureTooLarge {
+                size: cms.len(),
+                reserved: signature.reserved,
+            });
+        }
+
+        let hex: String = cms.iter().map(|b| format!("{b:02X}")).collect();
+        out[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+        Ok(out)
+    }
+}
+
+/// Add an entry to a dictionary, or replace the value of the entry if it already has
+/// one. Replacing only works for values which are a single token or indirect reference
+fn set_entry(dict: &[u8], key: &[u8], value: &[u8]) -> Result<Vec<u8>, PDFError> {
+    let mut updated = Vec::with_capacity(dict.len() + key.len() + value.len() + 2);
+    match find_key(dict, key) {
+        Some(start) => {
+            let rest = &dict[start + key.len()..];
+            let mut tokens = Tokens::new(rest);
+            if tokens.next_ref().is_none() {
+                return Err(PDFError::MalformedPdf(
+                    "an existing entry in the catalog can't be replaced",
+                ));
+            }
+            let end = start + key.len() + tokens.position();
+            updated.extend_from_slice(&dict[..start]);
+            updated.extend_from_slice(key);
+            updated.push(b' ');
+            updated.extend_from_slice(value);
+            updated.extend_from_slice(&dict[end..]);
+        }
+        None => {
+            let close = rfind(dict, b">>")
+                .ok_or(PDFError::MalformedPdf("the catalog is not a dictionary"))?;
+            updated.extend_from_slice(&dict[..close]);
+            updated.extend_from_slice(key);
+            updated.push(b' ');
+            updated.extend_from_slice(value);
+            updated.push(b'\n');
+            updated.extend_from_slice(&dict[close..]);
+        }
+    }
+    Ok(updated)
+}
+
+/// Write text as a PDF text string: a literal string if it is ASCII, otherwise
+/// UTF-16BE with a byte order mark
+fn text_string(text: &str) -> String {
+    if text.is_ascii() {
+        let mut escaped = String::with_capacity(text.len() + 2);
+        escaped.push('(');
+        for ch in text.chars() {
+            if matches!(ch, '\\' | '(' | ')') {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        escaped.push(')');
+        escaped
+    } else {
+        let mut hex = String::from("<FEFF");
+        for unit in text.encode_utf16() {
+            hex.push_str(&format!("{unit:04X}"));
+        }
+        hex.push('>');
+        hex
+    }
+}
@@ -0,0 +1,567 @@
+use crate::refs::{ObjectReferences, RefType};
+use owned_ttf_parser::LineMetrics;
+use pdf_writer::{Finish, Name, PdfWriter};
+
+/// The fourteen fonts which every PDF reader provides, which can be used without
+/// embedding them (see [crate::Font::standard]). This keeps documents small, at the cost
+/// of only covering the characters of `WinAnsiEncoding` (or, for [Standard14::Symbol]
+/// and [Standard14::ZapfDingbats], the characters of their own encodings), and of the
+/// text looking slightly different depending on the reader's substitutes for the fonts.
+///
+/// Standard fonts are measured with the metrics of Adobe's AFM files for the fonts,
+/// without kerning
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Standard14 {
+    Helvetica,
+    HelveticaBold,
+    HelveticaOblique,
+    HelveticaBoldOblique,
+    TimesRoman,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+    Courier,
+    CourierBold,
+    CourierOblique,
+    CourierBoldOblique,
+    Symbol,
+    ZapfDingbats,
+}
+
+/// Metrics of a standard font from its AFM file, in thousandths of an em
+struct Metrics {
+    ascender: i16,
+    descender: i16,
+    widths: &'static [u16; 224],
+    chars: &'static [char; 224],
+}
+
+impl Standard14 {
+    /// The PostScript name of the font, which it is referred to by in the PDF
+    pub fn name(&self) -> &'static str {
+        match self {
+            Standard14::Helvetica => "Helvetica",
+            Standard14::HelveticaBold => "Helvetica-Bold",
+            Standard14::HelveticaOblique => "Helvetica-Oblique",
+            Standard14::HelveticaBoldOblique => "Helvetica-BoldOblique",
+            Standard14::TimesRoman => "Times-Roman",
+            Standard14::TimesBold => "Times-Bold",
+            Standard14::TimesItalic => "Times-Italic",
+            Standard14::TimesBoldItalic => "Times-BoldItalic",
+            Standard14::Courier => "Courier",
+            Standard14::CourierBold => "Courier-Bold",
+            Standard14::CourierOblique => "Courier-Oblique",
+            Standard14::CourierBoldOblique => "Courier-BoldOblique",
+            Standard14::Symbol => "Symbol",
+            Standard14::ZapfDingbats => "ZapfDingbats",
+        }
+    }
+
+    /// The family that the font belongs to
+    pub fn family(&self) -> &'static str {
+        match self {
+            Standard14::Helvetica
+            | Standard14::HelveticaBold
+            | Standard14::HelveticaOblique
+            | Standard14::HelveticaBoldOblique => "Helvetica",
+            Standard14::TimesRoman
+            | Standard14::TimesBold
+            | Standard14::TimesItalic
+            | Standard14::TimesBoldItalic => "Times",
+            Standard14::Courier
+            | Standard14::CourierBold
+            | Standard14::CourierOblique
+            | Standard14::CourierBoldOblique => "Courier",
+            Standard14::Symbol => "Symbol",
+            Standard14::ZapfDingbats => "ZapfDingbats",
+        }
+    }
+
+    /// The weight of the font (see [crate::Font::weight])
+    pub fn weight(&self) -> u16 {
+        match self {
+            Standard14::HelveticaBold
+            | Standard14::HelveticaBoldOblique
+            | Standard14::TimesBold
+            | Standard14::TimesBoldItalic
+            | Standard14::CourierBold
+            | Standard14::CourierBoldOblique => 700,
+            _ => 400,
+        }
+    }
+
+    /// Whether the font uses its own encoding rather than `WinAnsiEncoding`
+    fn is_symbolic(&self) -> bool {
+        matches!(self, Standard14::Symbol | Standard14::ZapfDingbats)
+    }
+
+    fn metrics(&self) -> Metrics {
+        let (ascender, descender, widths) = match self {
+            Standard14::Helvetica => (718, -207, &HELVETICA_WIDTHS),
+            Standard14::HelveticaBold => (718, -207, &HELVETICA_BOLD_WIDTHS),
+            Standard14::HelveticaOblique => (718, -207, &HELVETICA_OBLIQUE_WIDTHS),
+            Standard14::HelveticaBoldOblique => (718, -207, &HELVETICA_BOLD_OBLIQUE_WIDTHS),
+            Standard14::TimesRoman => (683, -217, &TIMES_ROMAN_WIDTHS),
+            Standard14::TimesBold => (683, -217, &TIMES_BOLD_WIDTHS),
+            Standard14::TimesItalic => (683, -217, &TIMES_ITALIC_WIDTHS),
+            Standard14::TimesBoldItalic => (683, -217, &TIMES_BOLD_ITALIC_WIDTHS),
+            Standard14::Courier => (629, -157, &COURIER_WIDTHS),
+            Standard14::CourierBold => (629, -157, &COURIER_BOLD_WIDTHS),
+            Standard14::CourierOblique => (629, -157, &COURIER_OBLIQUE_WIDTHS),
+            Standard14::CourierBoldOblique => (629, -157, &COURIER_BOLD_OBLIQUE_WIDTHS),
+            // the symbolic fonts' AFM files don't give an ascender or descender, so their
+            // bounding boxes are used instead
+            Standard14::Symbol => (1010, -293, &SYMBOL_WIDTHS),
+            Standard14::ZapfDingbats => (820, -143, &ZAPF_DINGBATS_WIDTHS),
+        };
+        let chars = match self {
+            Standard14::Symbol => &SYMBOL_CHARS,
+            Standard14::ZapfDingbats => &ZAPF_DINGBATS_CHARS,
+            _ => &WIN_ANSI,
+        };
+        Metrics {
+            ascender,
+            descender,
+            widths,
+            chars,
+        }
+    }
+
+    pub(crate) fn ascender(&self) -> i16 {
+        self.metrics().ascender
+    }
+
+    pub(crate) fn descender(&self) -> i16 {
+        self.metrics().descender
+    }
+
+    /// The height of capital letters, which the symbolic fonts' AFM files don't give
+    pub(crate) fn cap_height(&self) -> Option<i16> {
+        Some(match self {
+            Standard14::Helvetica
+            | Standard14::HelveticaBold
+            | Standard14::HelveticaOblique
+            | Standard14::HelveticaBoldOblique => 718,
+            Standard14::TimesRoman => 662,
+            Standard14::TimesBold => 676,
+            Standard14::TimesItalic => 653,
+            Standard14::TimesBoldItalic => 669,
+            Standard14::Courier
+            | Standard14::CourierBold
+            | Standard14::CourierOblique
+            | Standard14::CourierBoldOblique => 562,
+            Standard14::Symbol | Standard14::ZapfDingbats => return None,
+        })
+    }
+
+    /// The height of lowercase letters such as "x", which the symbolic fonts' AFM files
+    /// don't give
+    pub(crate) fn x_height(&self) -> Option<i16> {
+        Some(match self {
+            Standard14::Helvetica | Standard14::HelveticaOblique => 523,
+            Standard14::HelveticaBold | Standard14::HelveticaBoldOblique => 532,
+            Standard14::TimesRoman => 450,
+            Standard14::TimesBold => 461,
+            Standard14::TimesItalic => 441,
+            Standard14::TimesBoldItalic => 462,
+            Standard14::Courier | Standard14::CourierOblique => 426,
+            Standard14::CourierBold | Standard14::CourierBoldOblique => 439,
+            Standard14::Symbol | Standard14::ZapfDingbats => return None,
+        })
+    }
+
+    /// The AFM files don't give a line gap, so one is chosen to space lines 1.15 ems
+    /// apart, as the metric-compatible TrueType fonts do
+    pub(crate) fn line_gap(&self) -> i16 {
+        let metrics = self.metrics();
+        let gap = 1150 - (metrics.ascender - metrics.descender);
+        if gap > 0 {
+            gap
+        } else {
+            0
+        }
+    }
+
+    /// Every standard font is underlined 100 units below the baseline, 50 units thick
+    pub(crate) fn underline_metrics(&self) -> LineMetrics {
+        LineMetrics {
+            position: -100,
+            thickness: 50,
+        }
+    }
+
+    /// The character code of a character in the font's encoding, if the font has it
+    pub(crate) fn code(&self, ch: char) -> Option<u8> {
+        if ch == '\0' {
+            return None;
+        }
+        let metrics = self.metrics();
+        metrics
+            .chars
+            .iter()
+            .zip(metrics.widths.iter())
+            .position(|(&c, &width)| c == ch && width > 0)
+            .map(|index| index as u8 + 32)
+    }
+
+    /// The width of the character with the given code, in thousandths of an em
+    pub(crate) fn width(&self, code: u16) -> u16 {
+        match code.checked_sub(32) {
+            Some(index) => self
+                .metrics()
+                .widths
+                .get(index as usize)
+                .copied()
+                .unwrap_or_default(),
+            None => 0,
+        }
+    }
+
+    /// Write the font dictionary. Standard fonts aren't embedded, so this is all there
+    /// is to them
+    pub(crate) fn write(
+        &self,
+        refs: &mut ObjectReferences,
+        font_index: usize,
+        writer: &mut PdfWriter,
+    ) {
+        let id = refs.gen(RefType::Font(font_index));
+        let mut font = writer.indirect(id).dict();
+        font.pair(Name(b"Type"), Name(b"Font"));
+        font.pair(Name(b"Subtype"), Name(b"Type1"));
+        font.pair(Name(b"BaseFont"), Name(self.name().as_bytes()));
+        if !self.is_symbolic() {
+            font.pair(Name(b"Encoding"), Name(b"WinAnsiEncoding"));
+        }
+        font.pair(Name(b"FirstChar"), 32);
+        font.pair(Name(b"LastChar"), 255);
+        let mut widths = font.insert(Name(b"Widths")).array();
+        for &width in self.metrics().widths.iter() {
+            widths.item(width as i32);
+        }
+        widths.finish();
+        font.finish();
+    }
+}
+
+/// The characters of `WinAnsiEncoding` for codes 32 to 255, with `'\0'` for codes
+/// which aren't used
+const WIN_ANSI: [char; 224] = [
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', '0', '1', '2',
+    '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', '@', 'A', 'B', 'C', 'D', 'E',
+    'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X',
+    'Y', 'Z', '[', '\\', ']', '^', '_', '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k',
+    'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~',
+    '\0', '\u{20AC}', '\0', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\0', '\u{17D}', '\0', '\0',
+    '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}', '\u{2DC}',
+    '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\0', '\u{17E}', '\u{178}', '\u{A0}', '\u{A1}',
+    '\u{A2}', '\u{A3}', '\u{A4}', '\u{A5}', '\u{A6}', '\u{A7}', '\u{A8}', '\u{A9}', '\u{AA}',
+    '\u{AB}', '\u{AC}', '\u{AD}', '\u{AE}', '\u{AF}', '\u{B0}', '\u{B1}', '\u{B2}', '\u{B3}',
+    '\u{B4}', '\u{B5}', '\u{B6}', '\u{B7}', '\u{B8}', '\u{B9}', '\u{BA}', '\u{BB}', '\u{BC}',
+    '\u{BD}', '\u{BE}', '\u{BF}', '\u{C0}', '\u{C1}', '\u{C2}', '\u{C3}', '\u{C4}', '\u{C5}',
+    '\u{C6}', '\u{C7}', '\u{C8}', '\u{C9}', '\u{CA}', '\u{CB}', '\u{CC}', '\u{CD}', '\u{CE}',
+    '\u{CF}', '\u{D0}', '\u{D1}', '\u{D2}', '\u{D3}', '\u{D4}', '\u{D5}', '\u{D6}', '\u{D7}',
+    '\u{D8}', '\u{D9}', '\u{DA}', '\u{DB}', '\u{DC}', '\u{DD}', '\u{DE}', '\u{DF}', '\u{E0}',
+    '\u{E1}', '\u{E2}', '\u{E3}', '\u{E4}', '\u{E5}', '\u{E6}', '\u{E7}', '\u{E8}', '\u{E9}',
+    '\u{EA}', '\u{EB}', '\u{EC}', '\u{ED}', '\u{EE}', '\u{EF}', '\u{F0}', '\u{F1}', '\u{F2}',
+    '\u{F3}', '\u{F4}', '\u{F5}', '\u{F6}', '\u{F7}', '\u{F8}', '\u{F9}', '\u{FA}', '\u{FB}',
+    '\u{FC}', '\u{FD}', '\u{FE}', '\u{FF}',
+];
+
+/// The widths of the Helvetica font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const HELVETICA_WIDTHS: [u16; 224] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+    350, 556, 0, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333, 1000, 0, 611, 0, 0, 222, 222,
+    333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944, 0, 500, 667, 278, 333, 556, 556, 556, 556,
+    260, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 556, 537, 278, 333,
+    333, 365, 556, 834, 834, 834, 611, 667, 667, 667, 667, 667, 667, 1000, 722, 667, 667, 667, 667,
+    278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667,
+    611, 556, 556, 556, 556, 556, 556, 889, 500, 556, 556, 556, 556, 278, 278, 278, 278, 556, 556,
+    556, 556, 556, 556, 556, 584, 611, 556, 556, 556, 556, 500, 556, 500,
+];
+
+/// The widths of the Helvetica-Bold font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const HELVETICA_BOLD_WIDTHS: [u16; 224] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+    350, 556, 0, 278, 556, 500, 1000, 556, 556, 333, 1000, 667, 333, 1000, 0, 611, 0, 0, 278, 278,
+    500, 500, 350, 556, 1000, 333, 1000, 556, 333, 944, 0, 500, 667, 278, 333, 556, 556, 556, 556,
+    280, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 611, 556, 278, 333,
+    333, 365, 556, 834, 834, 834, 611, 722, 722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667,
+    278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667,
+    611, 556, 556, 556, 556, 556, 556, 889, 556, 556, 556, 556, 556, 278, 278, 278, 278, 611, 611,
+    611, 611, 611, 611, 611, 584, 611, 611, 611, 611, 611, 556, 611, 556,
+];
+
+/// The widths of the Helvetica-Oblique font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const HELVETICA_OBLIQUE_WIDTHS: [u16; 224] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+    350, 556, 0, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333, 1000, 0, 611, 0, 0, 222, 222,
+    333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944, 0, 500, 667, 278, 333, 556, 556, 556, 556,
+    260, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 556, 537, 278, 333,
+    333, 365, 556, 834, 834, 834, 611, 667, 667, 667, 667, 667, 667, 1000, 722, 667, 667, 667, 667,
+    278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667,
+    611, 556, 556, 556, 556, 556, 556, 889, 500, 556, 556, 556, 556, 278, 278, 278, 278, 556, 556,
+    556, 556, 556, 556, 556, 584, 611, 556, 556, 556, 556, 500, 556, 500,
+];
+
+/// The widths of the Helvetica-BoldOblique font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const HELVETICA_BOLD_OBLIQUE_WIDTHS: [u16; 224] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+    350, 556, 0, 278, 556, 500, 1000, 556, 556, 333, 1000, 667, 333, 1000, 0, 611, 0, 0, 278, 278,
+    500, 500, 350, 556, 1000, 333, 1000, 556, 333, 944, 0, 500, 667, 278, 333, 556, 556, 556, 556,
+    280, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 611, 556, 278, 333,
+    333, 365, 556, 834, 834, 834, 611, 722, 722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667,
+    278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667,
+    611, 556, 556, 556, 556, 556, 556, 889, 556, 556, 556, 556, 556, 278, 278, 278, 278, 611, 611,
+    611, 611, 611, 611, 611, 584, 611, 611, 611, 611, 611, 556, 611, 556,
+];
+
+/// The widths of the Times-Roman font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const TIMES_ROMAN_WIDTHS: [u16; 224] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, 921, 722, 667, 667, 722, 611,
+    556, 722, 722, 333, 389, 722, 611, 889, 722, 722, 556, 722, 667, 556, 611, 722, 722, 944, 722,
+    722, 611, 333, 278, 333, 469, 500, 333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500,
+    278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541,
+    350, 500, 0, 333, 500, 444, 1000, 500, 500, 333, 1000, 556, 333, 889, 0, 611, 0, 0, 333, 333,
+    444, 444, 350, 500, 1000, 333, 980, 389, 333, 722, 0, 444, 722, 250, 333, 500, 500, 500, 500,
+    200, 500, 333, 760, 276, 500, 564, 333, 760, 333, 400, 564, 300, 300, 333, 500, 453, 250, 333,
+    300, 310, 500, 750, 750, 750, 444, 722, 722, 722, 722, 722, 722, 889, 667, 611, 611, 611, 611,
+    333, 333, 333, 333, 722, 722, 722, 722, 722, 722, 722, 564, 722, 722, 722, 722, 722, 722, 556,
+    500, 444, 444, 444, 444, 444, 444, 667, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 500,
+    500, 500, 500, 500, 500, 564, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+/// The widths of the Times-Bold font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const TIMES_BOLD_WIDTHS: [u16; 224] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 930, 722, 667, 722, 722, 667,
+    611, 778, 778, 389, 500, 778, 667, 944, 722, 778, 611, 778, 722, 556, 667, 722, 722, 1000, 722,
+    722, 667, 333, 278, 333, 581, 500, 333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556,
+    278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520,
+    350, 500, 0, 333, 500, 500, 1000, 500, 500, 333, 1000, 556, 333, 1000, 0, 667, 0, 0, 333, 333,
+    500, 500, 350, 500, 1000, 333, 1000, 389, 333, 722, 0, 444, 722, 250, 333, 500, 500, 500, 500,
+    220, 500, 333, 747, 300, 500, 570, 333, 747, 333, 400, 570, 300, 300, 333, 556, 540, 250, 333,
+    300, 330, 500, 750, 750, 750, 500, 722, 722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667,
+    389, 389, 389, 389, 722, 722, 778, 778, 778, 778, 778, 570, 778, 722, 722, 722, 722, 722, 611,
+    556, 500, 500, 500, 500, 500, 500, 722, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 556,
+    500, 500, 500, 500, 500, 570, 500, 556, 556, 556, 556, 500, 556, 500,
+];
+
+/// The widths of the Times-Italic font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const TIMES_ITALIC_WIDTHS: [u16; 224] = [
+    250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500, 920, 611, 611, 667, 722, 611,
+    611, 722, 722, 333, 444, 667, 556, 833, 667, 722, 611, 722, 611, 500, 556, 722, 611, 833, 611,
+    556, 556, 389, 278, 389, 422, 500, 333, 500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444,
+    278, 722, 500, 500, 500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389, 400, 275, 400, 541,
+    350, 500, 0, 333, 500, 556, 889, 500, 500, 333, 1000, 500, 333, 944, 0, 556, 0, 0, 333, 333,
+    556, 556, 350, 500, 889, 333, 980, 389, 333, 667, 0, 389, 556, 250, 389, 500, 500, 500, 500,
+    275, 500, 333, 760, 276, 500, 675, 333, 760, 333, 400, 675, 300, 300, 333, 500, 523, 250, 333,
+    300, 310, 500, 750, 750, 750, 500, 611, 611, 611, 611, 611, 611, 889, 667, 611, 611, 611, 611,
+    333, 333, 333, 333, 722, 667, 722, 722, 722, 722, 722, 675, 722, 722, 722, 722, 722, 556, 611,
+    500, 500, 500, 500, 500, 500, 500, 667, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 500,
+    500, 500, 500, 500, 500, 675, 500, 500, 500, 500, 500, 444, 500, 444,
+];
+
+/// The widths of the Times-BoldItalic font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const TIMES_BOLD_ITALIC_WIDTHS: [u16; 224] = [
+    250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 832, 667, 667, 667, 722, 667,
+    667, 722, 778, 389, 500, 667, 611, 889, 722, 722, 611, 722, 667, 556, 611, 722, 667, 889, 667,
+    611, 611, 333, 278, 333, 570, 500, 333, 500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500,
+    278, 778, 556, 500, 500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389, 348, 220, 348, 570,
+    350, 500, 0, 333, 500, 500, 1000, 500, 500, 333, 1000, 556, 333, 944, 0, 611, 0, 0, 333, 333,
+    500, 500, 350, 500, 1000, 333, 1000, 389, 333, 722, 0, 389, 611, 250, 389, 500, 500, 500, 500,
+    220, 500, 333, 747, 266, 500, 606, 333, 747, 333, 400, 570, 300, 300, 333, 576, 500, 250, 333,
+    300, 300, 500, 750, 750, 750, 500, 667, 667, 667, 667, 667, 667, 944, 667, 667, 667, 667, 667,
+    389, 389, 389, 389, 722, 722, 722, 722, 722, 722, 722, 570, 722, 722, 722, 722, 722, 611, 611,
+    500, 500, 500, 500, 500, 500, 500, 722, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 556,
+    500, 500, 500, 500, 500, 570, 500, 556, 556, 556, 556, 444, 500, 444,
+];
+
+/// The widths of the Courier font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const COURIER_WIDTHS: [u16; 224] = [
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 0, 0, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+];
+
+/// The widths of the Courier-Bold font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const COURIER_BOLD_WIDTHS: [u16; 224] = [
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 0, 0, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+];
+
+/// The widths of the Courier-Oblique font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const COURIER_OBLIQUE_WIDTHS: [u16; 224] = [
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 0, 0, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+];
+
+/// The widths of the Courier-BoldOblique font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const COURIER_BOLD_OBLIQUE_WIDTHS: [u16; 224] = [
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 0, 0, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 0, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+    600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+];
+
+/// The widths of the Symbol font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const SYMBOL_WIDTHS: [u16; 224] = [
+    250, 333, 713, 500, 549, 833, 778, 439, 333, 333, 500, 549, 250, 549, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 278, 278, 549, 549, 549, 444, 549, 722, 667, 722, 612, 611,
+    763, 603, 722, 333, 631, 722, 686, 889, 722, 722, 768, 741, 556, 592, 611, 690, 439, 768, 645,
+    795, 611, 333, 863, 333, 658, 500, 500, 631, 549, 549, 494, 439, 521, 411, 603, 329, 603, 549,
+    549, 576, 521, 549, 549, 521, 549, 603, 439, 576, 713, 686, 493, 686, 494, 480, 200, 480, 549,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 750, 620, 247, 549, 167, 713, 500, 753, 753, 753, 753, 1042, 987, 603, 987, 603, 400, 549,
+    411, 549, 549, 713, 494, 460, 549, 549, 549, 549, 1000, 603, 1000, 658, 823, 686, 795, 987,
+    768, 768, 823, 768, 768, 713, 713, 713, 713, 713, 713, 713, 768, 713, 790, 790, 890, 823, 549,
+    250, 713, 603, 603, 1042, 987, 603, 987, 603, 494, 329, 790, 790, 786, 713, 384, 384, 384, 384,
+    384, 384, 494, 494, 494, 494, 0, 329, 274, 686, 686, 686, 384, 384, 384, 384, 384, 384, 494,
+    494, 494, 0,
+];
+
+/// The characters of the Symbol font's built-in encoding for codes 32 to 255
+const SYMBOL_CHARS: [char; 224] = [
+    ' ', '!', '\u{2200}', '#', '\u{2203}', '%', '&', '\u{220B}', '(', ')', '\u{2217}', '+', ',',
+    '\u{2212}', '.', '/', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=',
+    '>', '?', '\u{2245}', '\u{391}', '\u{392}', '\u{3A7}', '\u{2206}', '\u{395}', '\u{3A6}',
+    '\u{393}', '\u{397}', '\u{399}', '\u{3D1}', '\u{39A}', '\u{39B}', '\u{39C}', '\u{39D}',
+    '\u{39F}', '\u{3A0}', '\u{398}', '\u{3A1}', '\u{3A3}', '\u{3A4}', '\u{3A5}', '\u{3C2}',
+    '\u{2126}', '\u{39E}', '\u{3A8}', '\u{396}', '[', '\u{2234}', ']', '\u{22A5}', '_', '\u{F8E5}',
+    '\u{3B1}', '\u{3B2}', '\u{3C7}', '\u{3B4}', '\u{3B5}', '\u{3C6}', '\u{3B3}', '\u{3B7}',
+    '\u{3B9}', '\u{3D5}', '\u{3BA}', '\u{3BB}', '\u{B5}', '\u{3BD}', '\u{3BF}', '\u{3C0}',
+    '\u{3B8}', '\u{3C1}', '\u{3C3}', '\u{3C4}', '\u{3C5}', '\u{3D6}', '\u{3C9}', '\u{3BE}',
+    '\u{3C8}', '\u{3B6}', '{', '|', '}', '\u{223C}', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\u{20AC}', '\u{3D2}', '\u{2032}',
+    '\u{2264}', '\u{2044}', '\u{221E}', '\u{192}', '\u{2663}', '\u{2666}', '\u{2665}', '\u{2660}',
+    '\u{2194}', '\u{2190}', '\u{2191}', '\u{2192}', '\u{2193}', '\u{B0}', '\u{B1}', '\u{2033}',
+    '\u{2265}', '\u{D7}', '\u{221D}', '\u{2202}', '\u{2022}', '\u{F7}', '\u{2260}', '\u{2261}',
+    '\u{2248}', '\u{2026}', '\u{F8E6}', '\u{F8E7}', '\u{21B5}', '\u{2135}', '\u{2111}', '\u{211C}',
+    '\u{2118}', '\u{2297}', '\u{2295}', '\u{2205}', '\u{2229}', '\u{222A}', '\u{2283}', '\u{2287}',
+    '\u{2284}', '\u{2282}', '\u{2286}', '\u{2208}', '\u{2209}', '\u{2220}', '\u{2207}', '\u{F6DA}',
+    '\u{F6D9}', '\u{F6DB}', '\u{220F}', '\u{221A}', '\u{22C5}', '\u{AC}', '\u{2227}', '\u{2228}',
+    '\u{21D4}', '\u{21D0}', '\u{21D1}', '\u{21D2}', '\u{21D3}', '\u{25CA}', '\u{2329}', '\u{F8E8}',
+    '\u{F8E9}', '\u{F8EA}', '\u{2211}', '\u{F8EB}', '\u{F8EC}', '\u{F8ED}', '\u{F8EE}', '\u{F8EF}',
+    '\u{F8F0}', '\u{F8F1}', '\u{F8F2}', '\u{F8F3}', '\u{F8F4}', '\0', '\u{232A}', '\u{222B}',
+    '\u{2320}', '\u{F8F5}', '\u{2321}', '\u{F8F6}', '\u{F8F7}', '\u{F8F8}', '\u{F8F9}', '\u{F8FA}',
+    '\u{F8FB}', '\u{F8FC}', '\u{F8FD}', '\u{F8FE}', '\0',
+];
+
+/// The widths of the ZapfDingbats font's characters for codes 32 to 255, in thousandths of
+/// an em, with 0 for codes which the font has no character for
+const ZAPF_DINGBATS_WIDTHS: [u16; 224] = [
+    278, 974, 961, 974, 980, 719, 789, 790, 791, 690, 960, 939, 549, 855, 911, 933, 911, 945, 974,
+    755, 846, 762, 761, 571, 677, 763, 760, 759, 754, 494, 552, 537, 577, 692, 786, 788, 788, 790,
+    793, 794, 816, 823, 789, 841, 823, 833, 816, 831, 923, 744, 723, 749, 790, 792, 695, 776, 768,
+    792, 759, 707, 708, 682, 701, 826, 815, 789, 789, 707, 687, 696, 689, 786, 787, 713, 791, 785,
+    791, 873, 761, 762, 762, 759, 759, 892, 892, 788, 784, 438, 138, 277, 415, 392, 392, 668, 668,
+    0, 390, 390, 317, 317, 276, 276, 509, 509, 410, 410, 234, 234, 334, 334, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 732, 544, 544, 910, 667, 760, 760, 776, 595, 694, 626, 788,
+    788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+    788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+    788, 894, 838, 1016, 458, 748, 924, 748, 918, 927, 928, 928, 834, 873, 828, 924, 924, 917, 930,
+    931, 463, 883, 836, 836, 867, 867, 696, 696, 874, 0, 874, 760, 946, 771, 865, 771, 888, 967,
+    888, 831, 873, 927, 970, 918, 0,
+];
+
+/// The characters of the ZapfDingbats font's built-in encoding for codes 32 to 255
+const ZAPF_DINGBATS_CHARS: [char; 224] = [
+    ' ', '\u{2701}', '\u{2702}', '\u{2703}', '\u{2704}', '\u{260E}', '\u{2706}', '\u{2707}',
+    '\u{2708}', '\u{2709}', '\u{261B}', '\u{261E}', '\u{270C}', '\u{270D}', '\u{270E}', '\u{270F}',
+    '\u{2710}', '\u{2711}', '\u{2712}', '\u{2713}', '\u{2714}', '\u{2715}', '\u{2716}', '\u{2717}',
+    '\u{2718}', '\u{2719}', '\u{271A}', '\u{271B}', '\u{271C}', '\u{271D}', '\u{271E}', '\u{271F}',
+    '\u{2720}', '\u{2721}', '\u{2722}', '\u{2723}', '\u{2724}', '\u{2725}', '\u{2726}', '\u{2727}',
+    '\u{2605}', '\u{2729}', '\u{272A}', '\u{272B}', '\u{272C}', '\u{272D}', '\u{272E}', '\u{272F}',
+    '\u{2730}', '\u{2731}', '\u{2732}', '\u{2733}', '\u{2734}', '\u{2735}', '\u{2736}', '\u{2737}',
+    '\u{2738}', '\u{2739}', '\u{273A}', '\u{273B}', '\u{273C}', '\u{273D}', '\u{273E}', '\u{273F}',
+    '\u{2740}', '\u{2741}', '\u{2742}', '\u{2743}', '\u{2744}', '\u{2745}', '\u{2746}', '\u{2747}',
+    '\u{2748}', '\u{2749}', '\u{274A}', '\u{274B}', '\u{25CF}', '\u{274D}', '\u{25A0}', '\u{274F}',
+    '\u{2750}', '\u{2751}', '\u{2752}', '\u{25B2}', '\u{25BC}', '\u{25C6}', '\u{2756}', '\u{25D7}',
+    '\u{2758}', '\u{2759}', '\u{275A}', '\u{275B}', '\u{275C}', '\u{275D}', '\u{275E}', '\0',
+    '\u{2768}', '\u{2769}', '\u{276A}', '\u{276B}', '\u{276C}', '\u{276D}', '\u{276E}', '\u{276F}',
+    '\u{2770}', '\u{2771}', '\u{2772}', '\u{2773}', '\u{2774}', '\u{2775}', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\u{2761}', '\u{2762}', '\u{2763}', '\u{2764}', '\u{2765}', '\u{2766}', '\u{2767}', '\u{2663}',
+    '\u{2666}', '\u{2665}', '\u{2660}', '\u{2460}', '\u{2461}', '\u{2462}', '\u{2463}', '\u{2464}',
+    '\u{2465}', '\u{2466}', '\u{2467}', '\u{2468}', '\u{2469}', '\u{2776}', '\u{2777}', '\u{2778}',
+    '\u{2779}', '\u{277A}', '\u{277B}', '\u{277C}', '\u{277D}', '\u{277E}', '\u{277F}', '\u{2780}',
+    '\u{2781}', '\u{2782}', '\u{2783}', '\u{2784}', '\u{2785}', '\u{2786}', '\u{2787}', '\u{2788}',
+    '\u{2789}', '\u{278A}', '\u{278B}', '\u{278C}', '\u{278D}', '\u{278E}', '\u{278F}', '\u{2790}',
+    '\u{2791}', '\u{2792}', '\u{2793}', '\u{2794}', '\u{2192}', '\u{2194}', '\u{2195}', '\u{2798}',
+    '\u{2799}', '\u{279A}', '\u{279B}', '\u{279C}', '\u{279D}', '\u{279E}', '\u{279F}', '\u{27A0}',
+    '\u{27A1}', '\u{27A2}', '\u{27A3}', '\u{27A4}', '\u{27A5}', '\u{27A6}', '\u{27A7}', '\u{27A8}',
+    '\u{27A9}', '\u{27AA}', '\u{27AB}', '\u{27AC}', '\u{27AD}', '\u{27AE}', '\u{27AF}', '\0',
+    '\u{27B1}', '\u{27B2}', '\u{27B3}', '\u{27B4}', '\u{27B5}', '\u{27B6}', '\u{27B7}', '\u{27B8}',
+    '\u{27B9}', '\u{27BA}', '\u{27BB}', '\u{27BC}', '\u{27BD}', '\u{27BE}', '\0',
+];
@@ -0,0 +1,246 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::font::Font;
+use crate::page::SpanFont;
+use crate::units::Pt;
+use crate::PDFError;
+use id_arena::Id;
+use std::collections::HashMap;
+
+/// How lines of text are positioned horizontally within their bounding box
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Alignment {
+    /// Lines start at the left edge of the bounding box
+    #[default]
+    Left,
+    /// Lines are centred within the bounding box
+    Centre,
+    /// Lines end at the right edge of the bounding box
+    Right,
+    /// Lines are stretched to fill the bounding box by widening the spaces between their
+    /// words, except for the last line of each paragraph, which starts at the left edge
+    Justified,
+}
+
+/// Optical margin alignment: punctuation at the start or end of a line (such as quotes,
+/// hyphens, and full stops) hangs partly outside the bounding box, so that the edges of
+/// a column look straight rather than the text edges being dented by the light marks.
+/// This is most noticeable with justified text.
+///
+/// Each character hangs by a fraction of its own advance, so the protrusion scales with
+/// the size of the text it is in. Alignment which doesn't touch an edge (i.e. a
+/// left-aligned line's right edge) ignores the protrusion for that edge. The default
+/// protrusions cover common punctuation, similar to those of LaTeX's microtype package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpticalMargins {
+    /// The fraction of each character's advance which hangs outside the left and right
+    /// edges of the bounding box, when it starts or ends a line
+    pub protrusions: Vec<(char, f32, f32)>,
+}
+
+impl Default for OpticalMargins {
+    fn default() -> Self {
+        OpticalMargins {
+            protrusions: vec![
+                ('"', 0.5, 0.5),
+                ('\'', 0.7, 0.7),
+                ('\u{201C}', 0.5, 0.3),
+                ('\u{201D}', 0.3, 0.5),
+                ('\u{2018}', 0.7, 0.5),
+                ('\u{2019}', 0.5, 0.7),
+                ('\u{00AB}', 0.5, 0.5),
+                ('\u{00BB}', 0.5, 0.5),
+                ('-', 0.0, 0.7),
+                ('\u{2010}', 0.0, 0.7),
+                ('\u{00AD}', 0.0, 0.7),
+                ('\u{2013}', 0.0, 0.5),
+                ('\u{2014}', 0.0, 0.3),
+                ('.', 0.0, 0.7),
+                (',', 0.0, 0.7),
+                (':', 0.0, 0.5),
+                (';', 0.0, 0.5),
+                ('!', 0.0, 0.2),
+                ('?', 0.0, 0.2),
+                ('(', 0.1, 0.0),
+                (')', 0.0, 0.1),
+            ],
+        }
+    }
+}
+
+impl OpticalMargins {
+    /// Create optical margins with no protrusions, to add characters to with
+    /// [OpticalMargins::protrude]
+    pub fn new() -> OpticalMargins {
+        OpticalMargins {
+            protrusions: Vec::new(),
+        }
+    }
+
+    /// Set the fractions of a character's advance which hang outside the left and right
+    /// edges of the bounding box, replacing any previously set for the character
+    pub fn protrude(&mut self, ch: char, left: f32, right: f32) -> &mut Self {
+        self.protrusions.retain(|&(c, _, _)| c != ch);
+        self.protrusions.push((ch, left, right));
+        self
+    }
+
+    /// The fractions of the character's advance which hang outside the left and right
+    /// edges of the bounding box
+    pub fn protrusion(&self, ch: char) -> (f32, f32) {
+        self.protrusions
+            .iter()
+            .find(|&&(c, _, _)| c == ch)
+            .map(|&(_, left, right)| (left, right))
+            .unwrap_or((0.0, 0.0))
+    }
+}
+
+/// A named text style within a [StyleSheet]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    /// The full name or family name of a font which has been added to the document
+    pub font: String,
+    /// The size of the text
+    pub size: Pt,
+    /// The colour of the text
+    pub colour: Colour,
+    /// Extra space above paragraphs in this style
+    pub space_before: Pt,
+    /// Extra space below paragraphs in this style
+    pub space_after: Pt,
+    /// How lines of text in this style are aligned
+    pub alignment: Alignment,
+    /// The structure role (such as `"H1"` or `"Caption"`) given to text in this style.
+    /// If not set, the role is chosen from the style's name (see [StyleSheet::role])
+    pub role: Option<String>,
+}
+
+impl TextStyle {
+    /// Create a left-aligned black style with no extra spacing
+    pub fn new<S: ToString>(font: S, size: Pt) -> TextStyle {
+        TextStyle {
+            font: font.to_string(),
+            size,
+            colour: crate::colours::BLACK,
+            space_before: Pt(0.0),
+            space_after: Pt(0.0),
+            alignment: Alignment::Left,
+            role: None,
+        }
+    }
+
+    /// Set the colour of the text
+    pub fn colour(&mut self, colour: Colour) -> &mut Self {
+        self.colour = colour;
+        self
+    }
+
+    /// Set the extra space above and below paragraphs
+    pub fn spacing(&mut self, before: Pt, after: Pt) -> &mut Self {
+        self.space_before = before;
+        self.space_after = after;
+        self
+    }
+
+    /// Set how lines of text are aligned
+    pub fn alignment(&mut self, alignment: Alignment) -> &mut Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the structure role given to text in this style
+    pub fn role<S: ToString>(&mut self, role: S) -> &mut Self {
+        self.role = Some(role.to_string());
+        self
+    }
+
+    /// Find the font and colour for the style. The style's font must have been added to
+    /// the document
+    pub fn resolve(&self, document: &Document) -> Result<(SpanFont, Colour), PDFError> {
+        let id: Id<Font> = document
+            .fonts
+            .iter()
+            .find(|(_, font)| font.name() == self.font || font.family() == self.font)
+            .map(|(id, _)| id)
+            .ok_or_else(|| PDFError::StyleFontMissing(self.font.clone()))?;
+        Ok((
+            SpanFont {
+                id,
+                size: self.size,
+            },
+            self.colour,
+        ))
+    }
+}
+
+/// A set of named text styles (such as "h1", "body", and "caption"), so that typography
+/// can be decided in one place rather than wherever text is laid out. Each document has
+/// a stylesheet ([Document::styles]), which [crate::layout::layout_styled_text] uses to
+/// look styles up by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleSheet {
+    /// The styles, by name
+    pub styles: HashMap<String, TextStyle>,
+}
+
+impl StyleSheet {
+    /// Create an empty stylesheet
+    pub fn new() -> StyleSheet {
+        StyleSheet::default()
+    }
+
+    /// Add (or replace) a named style
+    pub fn style<S: ToString>(&mut self, name: S, style: TextStyle) -> &mut Self {
+        self.styles.insert(name.to_string(), style);
+        self
+    }
+
+    /// Look up a style by name
+    pub fn get(&self, name: &str) -> Option<&TextStyle> {
+        self.styles.get(name)
+    }
+
+    /// Look up a style by name, returning an error if there is no such style
+    pub fn require(&self, name: &str) -> Result<&TextStyle, PDFError> {
+        self.get(name)
+            .ok_or_else(|| PDFError::StyleMissing(name.to_string()))
+    }
+
+    /// The structure role of text in a named style: the style's own role if it has one,
+    /// otherwise the standard role matching a conventional style name (i.e. `"h2"` is
+    /// `"H2"`, `"body"` is `"P"`, and `"caption"` is `"Caption"`, ignoring case). Styles
+    /// with other names have no role.
+    ///
+    /// [crate::layout::layout_styled_text] attaches the role to the metadata of each span
+    /// it lays out ([crate::SpanMetadata::role]), so text is labelled by what it is
+    /// without annotating each paragraph by hand.
+    pub fn role(&self, name: &str) -> Option<String> {
+        if let Some(role) = self.get(name).and_then(|style| style.role.clone()) {
+            return Some(role);
+        }
+        let role = match name.to_lowercase().as_str() {
+            "title" => "Title",
+            "h" | "heading" => "H",
+            "h1" => "H1",
+            "h2" => "H2",
+            "h3" => "H3",
+            "h4" => "H4",
+            "h5" => "H5",
+            "h6" => "H6",
+            "p" | "body" | "paragraph" => "P",
+            "caption" => "Caption",
+            "quote" | "blockquote" => "BlockQuote",
+            "code" => "Code",
+            "note" | "footnote" => "Note",
+            _ => return None,
+        };
+        Some(role.to_string())
+    }
+
+    /// Find the font and colour for a named style. The style's font must have been added
+    /// to the document
+    pub fn resolve(&self, document: &Document, name: &str) -> Result<(SpanFont, Colour), PDFError> {
+        self.require(name)?.resolve(document)
+    }
+}
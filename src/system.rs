@@ -0,0 +1,130 @@
+use crate::font::Font;
+use crate::PDFError;
+
+/// The weight of an installed font to look for (see [SystemFonts::load])
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Weight {
+    Thin,
+    ExtraLight,
+    Light,
+    #[default]
+    Normal,
+    Medium,
+    SemiBold,
+    Bold,
+    ExtraBold,
+    Black,
+}
+
+impl Weight {
+    /// The numeric weight, from 100 (thin) to 900 (black), as used in the `OS/2` table
+    /// of fonts
+    pub fn value(&self) -> u16 {
+        match self {
+            Weight::Thin => 100,
+            Weight::ExtraLight => 200,
+            Weight::Light => 300,
+            Weight::Normal => 400,
+            Weight::Medium => 500,
+            Weight::SemiBold => 600,
+            Weight::Bold => 700,
+            Weight::ExtraBold => 800,
+            Weight::Black => 900,
+        }
+    }
+}
+
+/// The style of an installed font to look for (see [SystemFonts::load])
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Style {
+    /// Upright
+    #[default]
+    Normal,
+    /// A cursive form, such as "Italic" faces
+    Italic,
+    /// A slanted form of the upright face, such as "Oblique" faces
+    Oblique,
+}
+
+/// The fonts installed on the system, which can be loaded by family, weight, and style
+/// rather than by reading font files directly. Finding the installed fonts means
+/// scanning the system's font directories, so keep the [SystemFonts] around when
+/// loading several fonts:
+///
+/// ```no_run
+/// # use pdf_gen::{Document, Style, SystemFonts, Weight};
+/// let mut doc = Document::default();
+/// let fonts = SystemFonts::new();
+/// let regular = doc.add_font(fonts.load("Noto Sans", Weight::Normal, Style::Normal).unwrap());
+/// let bold = doc.add_font(fonts.load("Noto Sans", Weight::Bold, Style::Normal).unwrap());
+/// ```
+pub struct SystemFonts {
+    database: fontdb::Database,
+}
+
+impl Default for SystemFonts {
+    fn default() -> Self {
+        SystemFonts::new()
+    }
+}
+
+impl SystemFonts {
+    /// Find the fonts installed on the system
+    pub fn new() -> SystemFonts {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+        SystemFonts { database }
+    }
+
+    /// Also look for fonts in a directory (and its subdirectories), such as fonts which
+    /// are shipped alongside an application
+    pub fn load_dir<P: AsRef<std::path::Path>>(&mut self, dir: P) -> &mut Self {
+        self.database.load_fonts_dir(dir);
+        self
+    }
+
+    /// The families of all the fonts that were found, sorted and without duplicates
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self
+            .database
+            .faces()
+            .iter()
+            .map(|face| face.family.clone())
+            .collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Load the installed font in `family` which best matches the weight and style.
+    /// Family names are matched exactly, but if the family doesn't have a font with the
+    /// exact weight or style, the closest one is used instead (as in CSS)
+    pub fn load(&self, family: &str, weight: Weight, style: Style) -> Result<Font, PDFError> {
+        let query = fontdb::Query {
+            families: &[fontdb::Family::Name(family)],
+            weight: fontdb::Weight(weight.value()),
+            stretch: fontdb::Stretch::Normal,
+            style: match style {
+                Style::Normal => fontdb::Style::Normal,
+                Style::Italic => fontdb::Style::Italic,
+                Style::Oblique => fontdb::Style::Oblique,
+            },
+        };
+        let id = self
+            .database
+            .query(&query)
+            .ok_or_else(|| PDFError::SystemFontMissing(family.to_string()))?;
+        self.database
+            .with_face_data(id, |data, index| Font::load_face(data.to_vec(), index))
+            .ok_or_else(|| PDFError::SystemFontMissing(family.to_string()))?
+    }
+}
+
+impl Font {
+    /// Load the installed font in `family` which best matches the weight and style (see
+    /// [SystemFonts::load]). This finds the installed fonts every time it is called; use
+    /// [SystemFonts] directly to load several fonts
+    pub fn from_system(family: &str, weight: Weight, style: Style) -> Result<Font, PDFError> {
+        SystemFonts::new().load(family, weight, style)
+    }
+}
@@ -0,0 +1,435 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::layout;
+use crate::page::{ContentTarget, SpanFont, SpanLayout};
+use crate::rect::Rect;
+use crate::units::Pt;
+
+/// A column within a [Table]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    /// The text displayed in the header row of the column
+    pub header: String,
+    /// The width of the column, including the cell padding
+    pub width: Pt,
+    /// If set, the column's cells (other than its header) are right-aligned on this
+    /// decimal separator, as for columns of amounts (see [layout::decimal_offsets])
+    pub decimal_separator: Option<char>,
+}
+
+/// A simple table of single-line text cells, laid out row-by-row from the top of a
+/// bounding box. Cells whose text is wider than their column are truncated to fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// The columns of the table, from left to right
+    pub columns: Vec<TableColumn>,
+    /// The rows of the table. Each row should have one cell per column; any extra
+    /// cells are ignored and any missing cells are left blank
+    pub rows: Vec<Vec<String>>,
+    /// The font used for the body of the table
+    pub font: SpanFont,
+    /// The font used for the header row. If [None], `font` is used
+    pub header_font: Option<SpanFont>,
+    /// The colour of the text in the table
+    pub colour: Colour,
+    /// Space between the edges of each cell and its text
+    pub cell_padding: Pt,
+}
+
+impl Table {
+    /// Create an empty table with no columns or rows, using black text in the given font
+    pub fn new(font: SpanFont) -> Table {
+        Table {
+            columns: Vec::default(),
+            rows: Vec::default(),
+            font,
+            header_font: None,
+            colour: crate::colours::BLACK,
+            cell_padding: Pt(2.0),
+        }
+    }
+
+    /// Add a column to the right side of the table
+    pub fn column<S: ToString>(&mut self, header: S, width: Pt) -> &mut Self {
+        self.columns.push(TableColumn {
+            header: header.to_string(),
+            width,
+            decimal_separator: None,
+        });
+        self
+    }
+
+    /// Add a column of numbers to the right side of the table, whose cells are
+    /// right-aligned on the decimal separator
+    pub fn decimal_column<S: ToString>(
+        &mut self,
+        header: S,
+        width: Pt,
+        separator: char,
+    ) -> &mut Self {
+        self.columns.push(TableColumn {
+            header: header.to_string(),
+            width,
+            decimal_separator: Some(separator),
+        });
+        self
+    }
+
+    /// Add a row to the bottom of the table
+    pub fn row<S: ToString>(&mut self, cells: impl IntoIterator<Item = S>) -> &mut Self {
+        self.rows
+            .push(cells.into_iter().map(|cell| cell.to_string()).collect());
+        self
+    }
+
+    /// Resize every column so that it is just wide enough to fit its widest cell
+    /// (including the header), plus the cell padding
+    pub fn fit_columns_to_contents(&mut self, document: &Document) {
+        let header_font = self.header_font.unwrap_or(self.font);
+        for (ci, column) in self.columns.iter_mut().enumerate() {
+            let mut width = layout::width_of_text(
+                &column.header,
+                &document.fonts[header_font.id],
+                header_font.size,
+            );
+            for cell in self.rows.iter().filter_map(|row| row.get(ci)) {
+                let cell_width =
+                    layout::width_of_text(cell, &document.fonts[self.font.id], self.font.size);
+                if cell_width > width {
+                    width = cell_width;
+                }
+            }
+            column.width = width + self.cell_padding * 2.0;
+        }
+    }
+
+    /// Resize a column so that it is just wide enough to fit its header and any number
+    /// formatted like `template` (see [layout::max_number_width]), plus the cell padding,
+    /// so that it can be sized before the numbers in it are known
+    pub fn fit_column_to_numbers(&mut self, document: &Document, column: usize, template: &str) {
+        let header_font = self.header_font.unwrap_or(self.font);
+        let font = self.font;
+        let padding = self.cell_padding * 2.0;
+        if let Some(column) = self.columns.get_mut(column) {
+            let header = layout::width_of_text(
+                &column.header,
+                &document.fonts[header_font.id],
+                header_font.size,
+            );
+            let numbers = layout::max_number_width(template, &document.fonts[font.id], font.size);
+            let width = if header > numbers { header } else { numbers };
+            column.width = width + padding;
+        }
+    }
+
+    /// Shrink all columns proportionally so that the table is no wider than `width`.
+    /// Tables which are already narrow enough are left untouched.
+    pub fn fit_columns_to_width(&mut self, width: Pt) {
+        let total = self.width();
+        if total <= width || *total <= 0.0 {
+            return;
+        }
+        let scale = *width / *total;
+        for column in self.columns.iter_mut() {
+            column.width *= scale;
+        }
+    }
+
+    /// The total width of all the columns in the table
+    pub fn width(&self) -> Pt {
+        self.columns.iter().map(|column| column.width).sum()
+    }
+
+    /// Calculate the height of each row in the table, which is the line height of the
+    /// largest font used in the table plus the padding above and below the text
+    pub fn row_height(&self, document: &Document) -> Pt {
+        let body = document.fonts[self.font.id].line_height(self.font.size);
+        let header = self
+            .header_font
+            .map(|font| document.fonts[font.id].line_height(font.size))
+            .unwrap_or(body);
+        let line_height = if header > body { header } else { body };
+        line_height + self.cell_padding * 2.0
+    }
+
+    /// Lay out the header row followed by as many rows as will fit within the bounding
+    /// box, starting with the row at index `start_row`. The header is repeated every
+    /// time this is called, so that tables split across several pages have headers on
+    /// each page.
+    ///
+    /// Returns the index of the first row that did not fit, which will be equal to
+    /// `self.rows.len()` if the entire table was laid out, and the vertical position of
+    /// the bottom of the last row that was laid out
+    pub fn layout<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        bounding_box: Rect,
+        start_row: usize,
+    ) -> (usize, Pt) {
+        let row_height = self.row_height(document);
+        let mut top = bounding_box.y2;
+
+        if top - row_height < bounding_box.y1 {
+            return (start_row, top);
+        }
+        let header: Vec<&str> = self.columns.iter().map(|c| c.header.as_str()).collect();
+        self.layout_row(
+            document,
+            page,
+            bounding_box.x1,
+            top,
+            &header,
+            self.header_font.unwrap_or(self.font),
+            &[],
+        );
+        top -= row_height;
+
+        let (laid_out, bottom) = self.layout_rows(
+            document,
+            page,
+            Rect {
+                y2: top,
+                ..bounding_box
+            },
+            &self.rows[start_row.min(self.rows.len())..],
+            self.font,
+        );
+        (start_row + laid_out, bottom)
+    }
+
+    /// Lay out arbitrary rows (such as totals or other summary rows) using the columns
+    /// of this table, but without a header row. As many rows as will fit within the
+    /// bounding box are laid out.
+    ///
+    /// Returns how many of the rows were laid out, and the vertical position of the
+    /// bottom of the last row that was laid out
+    pub fn layout_rows<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        bounding_box: Rect,
+        rows: &[Vec<String>],
+        font: SpanFont,
+    ) -> (usize, Pt) {
+        let row_height = self.row_height(document);
+        let offsets = self.decimal_offsets(document, rows, font);
+        let mut top = bounding_box.y2;
+        let mut laid_out = 0;
+        for (ri, row) in rows.iter().enumerate() {
+            if top - row_height < bounding_box.y1 {
+                break;
+            }
+            let row_offsets: Vec<Option<Pt>> = offsets
+                .iter()
+                .map(|column| column.as_ref().map(|offsets| offsets[ri]))
+                .collect();
+            let row: Vec<&str> = row.iter().map(String::as_str).collect();
+            self.layout_row(
+                document,
+                page,
+                bounding_box.x1,
+                top,
+                &row,
+                font,
+                &row_offsets,
+            );
+            top -= row_height;
+            laid_out += 1;
+        }
+        (laid_out, top)
+    }
+
+    /// For each column aligned on a decimal separator, the offsets of the cells of `rows`
+    /// (in `font`) within the column. The cells are aligned with every row of the table,
+    /// so that the column lines up across pages and with any summary rows
+    fn decimal_offsets(
+        &self,
+        document: &Document,
+        rows: &[Vec<String>],
+        font: SpanFont,
+    ) -> Vec<Option<Vec<Pt>>> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(ci, column)| {
+                let separator = column.decimal_separator?;
+                let cell = |row: &Vec<String>| row.get(ci).map(String::as_str).unwrap_or("");
+                let cells: Vec<(&str, SpanFont)> = self
+                    .rows
+                    .iter()
+                    .map(|row| (cell(row), self.font))
+                    .chain(rows.iter().map(|row| (cell(row), font)))
+                    .collect();
+                let available = column.width - self.cell_padding * 2.0;
+                let offsets = layout::decimal_offsets(document, &cells, separator, available);
+                Some(offsets[self.rows.len()..].to_vec())
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn layout_row<T: ContentTarget + ?Sized>(
+        &self,
+        document: &Document,
+        page: &mut T,
+        left: Pt,
+        top: Pt,
+        cells: &[&str],
+        font: SpanFont,
+        offsets: &[Option<Pt>],
+    ) {
+        let ascent = document.fonts[font.id].ascent(font.size);
+        let mut x = left;
+        for (ci, (column, cell)) in self.columns.iter().zip(cells.iter()).enumerate() {
+            let available = column.width - self.cell_padding * 2.0;
+            let offset = offsets.get(ci).copied().flatten().unwrap_or(Pt(0.0));
+            let text = truncate_to_width(document, cell, font, available - offset);
+            if !text.is_empty() {
+                page.add_span(SpanLayout {
+                    text,
+                    font,
+                    colour: self.colour,
+                    coords: (
+                        x + self.cell_padding + offset,
+                        top - self.cell_padding - ascent,
+                    ),
+                    effects: Default::default(),
+                    metadata: Default::default(),
+                    glyphs: Vec::new(),
+                });
+            }
+            x += column.width;
+        }
+    }
+}
+
+/// Drop characters from the end of the text until it fits within the given width
+fn truncate_to_width(document: &Document, text: &str, font: SpanFont, width: Pt) -> String {
+    let font_ref = &document.fonts[font.id];
+    let mut text = text.to_string();
+    while !text.is_empty() && layout::width_of_text(&text, font_ref, font.size) > width {
+        text.pop();
+    }
+    text
+}
+
+#[cfg(feature = "records")]
+impl Table {
+    /// Create a table from an iterator of serializable records, such as a list of structs
+    /// deriving [serde::Serialize]. The columns are generated from the first record: for
+    /// structs and maps each field becomes a column with a header generated from the
+    /// field name (i.e. `unit_price` becomes "Unit Price"), for tuples and sequences each
+    /// element becomes a numbered column. Columns are then sized to fit their contents.
+    ///
+    /// Strings are printed as-is, `None` / `null` values are left blank, and any other
+    /// values are printed as they would appear in JSON.
+    pub fn from_records<T, I>(
+        document: &Document,
+        font: SpanFont,
+        records: I,
+    ) -> Result<Table, crate::PDFError>
+    where
+        T: serde::Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        use serde_json::Value;
+
+        let mut table = Table::new(font);
+        let mut keys: Vec<String> = Vec::new();
+        for (ri, record) in records.into_iter().enumerate() {
+            let value = serde_json::to_value(record)?;
+            if ri == 0 {
+                match &value {
+                    Value::Object(map) => {
+                        keys = map.keys().cloned().collect();
+                        for key in keys.iter() {
+                            table.column(header_from_field_name(key), Pt(0.0));
+                        }
+                    }
+                    Value::Array(items) => {
+                        for i in 0..items.len() {
+                            table.column(format!("Column {}", i + 1), Pt(0.0));
+                        }
+                    }
+                    _ => {
+                        table.column("Value", Pt(0.0));
+                    }
+                }
+            }
+
+            let row: Vec<String> = match &value {
+                Value::Object(map) => keys
+                    .iter()
+                    .map(|key| map.get(key).map(cell_text).unwrap_or_default())
+                    .collect(),
+                Value::Array(items) => items.iter().map(cell_text).collect(),
+                other => vec![cell_text(other)],
+            };
+            table.rows.push(row);
+        }
+
+        table.fit_columns_to_contents(document);
+        Ok(table)
+    }
+
+    /// Create a table from CSV data, using the first record of the CSV as the headers of
+    /// the table. Columns are sized to fit their contents.
+    pub fn from_csv<R: std::io::Read>(
+        document: &Document,
+        font: SpanFont,
+        reader: R,
+    ) -> Result<Table, crate::PDFError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let mut table = Table::new(font);
+        for header in reader.headers()?.iter() {
+            table.column(header, Pt(0.0));
+        }
+        for record in reader.records() {
+            table.row(record?.iter());
+        }
+
+        table.fit_columns_to_contents(document);
+        Ok(table)
+    }
+}
+
+/// Convert a field name such as `unit_price` or `unitPrice` into a header such as
+/// "Unit Price"
+#[cfg(feature = "records")]
+fn header_from_field_name(name: &str) -> String {
+    let mut header = String::with_capacity(name.len());
+    let mut start_of_word = true;
+    let mut previous_lowercase = false;
+    for ch in name.chars() {
+        if ch == '_' || ch == '-' || ch == ' ' {
+            if !header.is_empty() && !start_of_word {
+                header.push(' ');
+            }
+            start_of_word = true;
+            previous_lowercase = false;
+            continue;
+        }
+        if ch.is_uppercase() && previous_lowercase {
+            header.push(' ');
+            start_of_word = true;
+        }
+        if start_of_word {
+            header.extend(ch.to_uppercase());
+        } else {
+            header.push(ch);
+        }
+        start_of_word = false;
+        previous_lowercase = ch.is_lowercase();
+    }
+    header
+}
+
+#[cfg(feature = "records")]
+fn cell_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
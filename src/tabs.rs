@@ -0,0 +1,57 @@
+/// How the layout functions (such as [crate::layout::layout_text]) expand tab characters
+/// into spaces. The expansion used is set with [crate::Document::tabs], and can be
+/// changed between layout calls, such as to lay out a code listing with tab stops:
+///
+/// ```
+/// # use pdf_gen::{Document, Tabs};
+/// let mut doc = Document::default();
+/// doc.tabs = Tabs::Columns(8);
+/// assert_eq!(doc.tabs.expand("a\tbc\td", 0), "a       bc      d");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tabs {
+    /// Replace each tab with this many spaces
+    Spaces(usize),
+    /// Replace each tab with enough spaces to reach the next multiple of this many
+    /// columns (characters) from the start of the line, as terminals and code editors
+    /// do. Tab stops only line up in monospaced fonts
+    Columns(usize),
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Tabs::Spaces(4)
+    }
+}
+
+impl Tabs {
+    /// Replace the tabs in `text` with spaces, where the text starts `column` characters
+    /// into its line. Columns are counted from the start of the line that the text was
+    /// given on, not from where layout later wraps it
+    pub fn expand(&self, text: &str, column: usize) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut column = column;
+        for ch in text.chars() {
+            match ch {
+                '\t' => {
+                    let spaces = match *self {
+                        Tabs::Spaces(spaces) => spaces,
+                        Tabs::Columns(0) => 0,
+                        Tabs::Columns(stop) => stop - column % stop,
+                    };
+                    expanded.extend(std::iter::repeat(' ').take(spaces));
+                    column += spaces;
+                }
+                '\n' => {
+                    expanded.push(ch);
+                    column = 0;
+                }
+                _ => {
+                    expanded.push(ch);
+                    column += 1;
+                }
+            }
+        }
+        expanded
+    }
+}
@@ -0,0 +1,324 @@
+use crate::colour::Colour;
+use crate::document::Document;
+use crate::font::Font;
+use crate::image::Image;
+use crate::layout::{self, Margins};
+use crate::page::{ImageLayout, Page, SpanFont};
+use crate::rect::Rect;
+use crate::table::Table;
+use crate::units::Pt;
+use crate::PDFError;
+use id_arena::Id;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A declarative description of a document, usually loaded from a structured file
+/// (see [Template::from_json]). Templates consist of named text styles and a list of
+/// pages, each of which has a number of frames which are filled in with text, images,
+/// or tables. Text may contain `{{placeholders}}` which are substituted with strings
+/// from [TemplateData] when the template is rendered.
+///
+/// All dimensions in a template are in points, and all coordinates are measured from
+/// the bottom-left corner of the page.
+///
+/// # Example template
+///
+/// ```json
+/// {
+///     "styles": {
+///         "body": { "font": "Fira Mono", "size": 10.0 }
+///     },
+///     "pages": [{
+///         "size": [612.0, 792.0],
+///         "frames": [{
+///             "rect": [36.0, 36.0, 576.0, 756.0],
+///             "type": "text",
+///             "text": "Invoice for {{customer}}",
+///             "style": "body"
+///         }]
+///     }]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Template {
+    /// Text styles which frames can refer to by name
+    #[serde(default)]
+    pub styles: HashMap<String, TemplateStyle>,
+    /// The pages to generate, in order
+    pub pages: Vec<TemplatePage>,
+}
+
+/// A named text style within a [Template]
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateStyle {
+    /// The full name or family name of a font which has been added to the document
+    pub font: String,
+    /// The size of the text
+    pub size: f32,
+    /// The colour of the text as RGB components ranging from 0.0 to 1.0. Defaults to
+    /// black if not specified
+    #[serde(default)]
+    pub colour: Option<[f32; 3]>,
+}
+
+/// A page within a [Template]
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplatePage {
+    /// The width and height of the page
+    pub size: [f32; 2],
+    /// The top, right, bottom, and left margins of the page
+    #[serde(default)]
+    pub margins: Option<[f32; 4]>,
+    /// The frames to fill in on the page
+    #[serde(default)]
+    pub frames: Vec<TemplateFrame>,
+}
+
+/// A rectangular region of a [TemplatePage] which is filled with content
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateFrame {
+    /// The `x1`, `y1`, `x2`, and `y2` coordinates of the frame
+    pub rect: [f32; 4],
+    /// What to fill the frame with
+    #[serde(flatten)]
+    pub content: TemplateFrameContent,
+}
+
+/// The contents of a [TemplateFrame]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TemplateFrameContent {
+    /// Text which will be wrapped within the frame. Any text which overflows the frame
+    /// is discarded
+    Text { text: String, style: String },
+    /// An image from [TemplateData::images], scaled to fit within the frame while
+    /// maintaining its aspect ratio
+    Image { image: String },
+    /// A table with rows from [TemplateData::tables]. Any rows which overflow the
+    /// frame are discarded
+    Table {
+        table: String,
+        style: String,
+        #[serde(default)]
+        header_style: Option<String>,
+        columns: Vec<TemplateColumn>,
+    },
+}
+
+/// A column of a table frame within a [Template]
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateColumn {
+    /// The text in the header row of the column
+    pub header: String,
+    /// The width of the column
+    pub width: f32,
+}
+
+/// Runtime data used to fill in a [Template]
+#[derive(Debug, Clone, Default)]
+pub struct TemplateData {
+    /// Strings to substitute for `{{placeholders}}` in text frames
+    pub strings: HashMap<String, String>,
+    /// Images (which must already be added to the document) to place in image frames
+    pub images: HashMap<String, Id<Image>>,
+    /// Rows of cells to place in table frames
+    pub tables: HashMap<String, Vec<Vec<String>>>,
+}
+
+impl TemplateData {
+    /// Create a new set of template data, with nothing in it
+    pub fn new() -> TemplateData {
+        TemplateData::default()
+    }
+
+    /// Set the string to substitute for the placeholder `name`
+    pub fn string<K: ToString, V: ToString>(&mut self, name: K, value: V) -> &mut Self {
+        self.strings.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the image to place in image frames referring to `name`
+    pub fn image<K: ToString>(&mut self, name: K, image: Id<Image>) -> &mut Self {
+        self.images.insert(name.to_string(), image);
+        self
+    }
+
+    /// Set the rows to place in table frames referring to `name`
+    pub fn table<K: ToString>(&mut self, name: K, rows: Vec<Vec<String>>) -> &mut Self {
+        self.tables.insert(name.to_string(), rows);
+        self
+    }
+}
+
+impl Template {
+    /// Parse a template from a JSON string
+    pub fn from_json(json: &str) -> Result<Template, PDFError> {
+        serde_json::from_str(json).map_err(Into::into)
+    }
+
+    /// Fill the template in with the given data, adding the generated pages to the end of
+    /// the document. Fonts referred to by the template's styles must already have been
+    /// added to the document. Returns the Ids of the pages that were added.
+    pub fn render(
+        &self,
+        document: &mut Document,
+        data: &TemplateData,
+    ) -> Result<Vec<Id<Page>>, PDFError> {
+        let mut page_ids = Vec::with_capacity(self.pages.len());
+        for template_page in self.pages.iter() {
+            let margins = template_page.margins.map(|[top, right, bottom, left]| {
+                Margins::trbl(Pt(top), Pt(right), Pt(bottom), Pt(left))
+            });
+            let [width, height] = template_page.size;
+            let mut page = Page::new((Pt(width), Pt(height)), margins);
+
+            for frame in template_page.frames.iter() {
+                let [x1, y1, x2, y2] = frame.rect;
+                let rect = Rect {
+                    x1: Pt(x1),
+                    y1: Pt(y1),
+                    x2: Pt(x2),
+                    y2: Pt(y2),
+                };
+                self.render_frame(document, &mut page, rect, &frame.content, data)?;
+            }
+
+            page_ids.push(document.add_page(page));
+        }
+        Ok(page_ids)
+    }
+
+    fn render_frame(
+        &self,
+        document: &Document,
+        page: &mut Page,
+        rect: Rect,
+        content: &TemplateFrameContent,
+        data: &TemplateData,
+    ) -> Result<(), PDFError> {
+        match content {
+            TemplateFrameContent::Text { text, style } => {
+                let (font, colour) = self.resolve_style(document, style)?;
+                let text = substitute_placeholders(text, &data.strings)?;
+                let ascent = document.fonts[font.id].ascent(font.size);
+                let mut text = vec![(text, colour, font)];
+                layout::layout_text(
+                    document,
+                    page,
+                    (rect.x1, rect.y2 - ascent),
+                    &mut text,
+                    Pt(0.0),
+                    rect,
+                )?;
+            }
+            TemplateFrameContent::Image { image } => {
+                let id = *data
+                    .images
+                    .get(image)
+                    .ok_or_else(|| PDFError::TemplateDataMissing(image.clone()))?;
+                let aspect_ratio = document.images[id].aspect_ratio();
+                let frame_width = rect.x2 - rect.x1;
+                let frame_height = rect.y2 - rect.y1;
+                let (width, height) = if *frame_width / *frame_height > aspect_ratio {
+                    (frame_height * aspect_ratio, frame_height)
+                } else {
+                    (frame_width, frame_width / aspect_ratio)
+                };
+                let x = rect.x1 + (frame_width - width) / 2.0;
+                let y = rect.y1 + (frame_height - height) / 2.0;
+                page.add_image(ImageLayout {
+                    image_index: id.index(),
+                    position: Rect {
+                        x1: x,
+                        y1: y,
+                        x2: x + width,
+                        y2: y + height,
+                    },
+                    artifact: None,
+                });
+            }
+            TemplateFrameContent::Table {
+                table,
+                style,
+                header_style,
+                columns,
+            } => {
+                let rows = data
+                    .tables
+                    .get(table)
+                    .ok_or_else(|| PDFError::TemplateDataMissing(table.clone()))?;
+                let (font, colour) = self.resolve_style(document, style)?;
+                let mut t = Table::new(font);
+                t.colour = colour;
+                if let Some(header_style) = header_style {
+                    t.header_font = Some(self.resolve_style(document, header_style)?.0);
+                }
+                for column in columns.iter() {
+                    t.column(&column.header, Pt(column.width));
+                }
+                t.rows = rows.clone();
+                t.layout(document, page, rect, 0);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_style(
+        &self,
+        document: &Document,
+        style: &str,
+    ) -> Result<(SpanFont, Colour), PDFError> {
+        let style = self
+            .styles
+            .get(style)
+            .ok_or_else(|| PDFError::TemplateStyleMissing(style.to_string()))?;
+        let id: Id<Font> = document
+            .fonts
+            .iter()
+            .find(|(_, font)| font.name() == style.font || font.family() == style.font)
+            .map(|(id, _)| id)
+            .ok_or_else(|| PDFError::TemplateFontMissing(style.font.clone()))?;
+        let colour = style
+            .colour
+            .map(|[r, g, b]| Colour::new_rgb(r, g, b))
+            .unwrap_or(crate::colours::BLACK);
+        Ok((
+            SpanFont {
+                id,
+                size: Pt(style.size),
+            },
+            colour,
+        ))
+    }
+}
+
+/// Replace every `{{name}}` in the text with the corresponding string. Whitespace
+/// surrounding the name within the braces is ignored.
+fn substitute_placeholders(
+    text: &str,
+    strings: &HashMap<String, String>,
+) -> Result<String, PDFError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                let value = strings
+                    .get(name)
+                    .ok_or_else(|| PDFError::TemplateDataMissing(name.to_string()))?;
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                // unterminated placeholder, leave it as-is
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
@@ -0,0 +1,130 @@
+//! Utilities for writing regression tests against generated PDFs (with the
+//! `test-support` feature).
+//!
+//! Written documents aren't byte-for-byte reproducible as they record when they were
+//! created; [render] writes a document with those volatile values blanked out, so it
+//! can be compared against a "golden" file with [assert_golden]. As whole files are
+//! brittle to compare (any change to an embedded font changes them), [page_contents]
+//! extracts and decompresses each page's content stream so that tests can compare just
+//! what is drawn on each page instead.
+//!
+//! ```no_run
+//! use pdf_gen::testing;
+//! # let document = pdf_gen::Document::default();
+//!
+//! let pdf = testing::render(document).unwrap();
+//! for (i, content) in testing::page_contents(&pdf).unwrap().iter().enumerate() {
+//!     testing::assert_golden(format!("tests/golden/page-{i}.txt"), content.as_bytes());
+//! }
+//! ```
+
+use crate::document::Document;
+use crate::incremental::{dict_ref, IncrementalUpdate};
+use crate::PDFError;
+use std::path::Path;
+
+/// The environment variable which, when set, makes [assert_golden] overwrite golden
+/// files with the actual output instead of comparing against them
+pub const UPDATE_GOLDEN_VAR: &str = "PDF_GEN_UPDATE_GOLDEN";
+
+/// Write the document and [normalise] the result, so that writing the same document
+/// twice gives exactly the same bytes
+pub fn render(document: Document) -> Result<Vec<u8>, PDFError> {
+    let mut pdf = Vec::new();
+    document.write(&mut pdf)?;
+    Ok(normalise(&pdf))
+}
+
+/// Blank out the values in a written PDF which change every time it is written: the
+/// creation and modification dates (including those in uncompressed XMP metadata), and
+/// the file identifier. Digits are replaced with
+/// zeros rather than being removed, so the cross-reference table remains valid and the
+/// normalised file can still be opened.
+pub fn normalise(pdf: &[u8]) -> Vec<u8> {
+    let mut pdf = pdf.to_vec();
+    for key in [&b"/CreationDate"[..], &b"/ModDate"[..]] {
+        blank_after(&mut pdf, key, b'(', b')', |b| b.is_ascii_digit(), b'0');
+    }
+    for key in [&b"<xmp:CreateDate"[..], &b"<xmp:MetadataDate"[..]] {
+        blank_after(&mut pdf, key, b'>', b'<', |b| b.is_ascii_digit(), b'0');
+    }
+    blank_after(
+        &mut pdf,
+        b"/ID",
+        b'[',
+        b']',
+        |b| b.is_ascii_hexdigit(),
+        b'0',
+    );
+    pdf
+}
+
+/// Replace the bytes matching `blank` with `with`, between `open` and `close` following
+/// each occurrence of `key`
+fn blank_after(pdf: &mut [u8], key: &[u8], open: u8, close: u8, blank: fn(&u8) -> bool, with: u8) {
+    let mut from = 0;
+    while let Some(pos) = pdf[from..]
+        .windows(key.len())
+        .position(|window| window == key)
+    {
+        let pos = from + pos + key.len();
+        from = pos;
+        // make sure this is the key itself and not the start of a longer name
+        let start = match pdf[pos..].iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(start) if pdf[pos + start] == open => pos + start + 1,
+            _ => continue,
+        };
+        let end = match pdf[start..].iter().position(|&b| b == close) {
+            Some(end) => start + end,
+            None => return,
+        };
+        for b in pdf[start..end].iter_mut().filter(|b| blank(b)) {
+            *b = with;
+        }
+        from = end;
+    }
+}
+
+/// Extract the decompressed content stream of each page of a PDF written by this
+/// crate, in page order. Content streams are text apart from any raw content which
+/// contains binary data (which is replaced with `U+FFFD`)
+pub fn page_contents(pdf: &[u8]) -> Result<Vec<String>, PDFError> {
+    let file = IncrementalUpdate::new(pdf.to_vec())?;
+    let mut contents = Vec::new();
+    for page in file.page_refs()? {
+        let dict = file
+            .object(page)
+            .ok_or(PDFError::MalformedPdf("a page is missing"))?;
+        let content = dict_ref(dict, b"/Contents")
+            .ok_or(PDFError::MalformedPdf("a page has no /Contents"))?;
+        let content = file.stream(content)?;
+        contents.push(String::from_utf8_lossy(&content).into_owned());
+    }
+    Ok(contents)
+}
+
+/// Compare `actual` against the contents of the golden file at `path`, panicking with
+/// both versions if they differ. If the golden file doesn't exist yet, or if the
+/// [UPDATE_GOLDEN_VAR] environment variable is set, the golden file is written with
+/// `actual` instead.
+pub fn assert_golden<P: AsRef<Path>>(path: P, actual: &[u8]) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("can create golden file directory");
+        }
+        std::fs::write(path, actual).expect("can write golden file");
+        return;
+    }
+
+    let expected = std::fs::read(path).expect("can read golden file");
+    if expected != actual {
+        panic!(
+            "output doesn't match golden file {} (set {UPDATE_GOLDEN_VAR} to update it)\n\
+             --- expected\n{}\n+++ actual\n{}",
+            path.display(),
+            String::from_utf8_lossy(&expected),
+            String::from_utf8_lossy(actual)
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::colour::Colour;
+use crate::resources::is_regular;
+
+/// An alternate set of colours for a document, such as a printer-friendly edition of a
+/// document designed for screens. Themes are added to a document with
+/// [crate::Document::add_theme] and one can be selected with
+/// [crate::Document::set_theme] before writing; every colour in the document which
+/// matches one of the theme's colours is then replaced when the document is written.
+///
+/// Colours are replaced everywhere in the document's content, including text, shapes,
+/// and raw content, so the same document model can produce both editions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    /// Pairs of colours to replace, and what to replace them with
+    pub colours: Vec<(Colour, Colour)>,
+}
+
+impl Theme {
+    /// Create a theme which doesn't replace any colours
+    pub fn new() -> Theme {
+        Theme::default()
+    }
+
+    /// Replace `from` with `to` wherever it is used
+    pub fn colour(&mut self, from: Colour, to: Colour) -> &mut Self {
+        self.colours.push((from, to));
+        self
+    }
+
+    /// Find the colour to use in place of `colour`
+    pub fn map(&self, colour: Colour) -> Colour {
+        self.colours
+            .iter()
+            .find(|(from, _)| colours_match(*from, colour))
+            .map(|(_, to)| *to)
+            .unwrap_or(colour)
+    }
+}
+
+/// Whether two colours are the same, allowing for the rounding of colour components when
+/// they are written into content streams
+fn colours_match(a: Colour, b: Colour) -> bool {
+    const TOLERANCE: f32 = 0.001;
+    let close = |x: f32, y: f32| (x - y).abs() < TOLERANCE;
+    match (a, b) {
+        (
+            Colour::RGB { r, g, b },
+            Colour::RGB {
+                r: r2,
+                g: g2,
+                b: b2,
+            },
+        ) => close(r, r2) && close(g, g2) && close(b, b2),
+        (
+            Colour::CMYK { c, m, y, k },
+            Colour::CMYK {
+                c: c2,
+                m: m2,
+                y: y2,
+                k: k2,
+            },
+        ) => close(c, c2) && close(m, m2) && close(y, y2) && close(k, k2),
+        (Colour::Grey { g }, Colour::Grey { g: g2 }) => close(g, g2),
+        _ => false,
+    }
+}
+
+/// Replace the operands of every colour operator (`rg`, `RG`, `k`, `K`, `g`, and `G`)
+/// in a content stream with the colour returned by `f`. Everything else in the content
+/// stream is copied as-is. Content streams must not contain inline images.
+pub(crate) fn map_colours<F: Fn(Colour) -> Colour>(content: &[u8], f: F) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(content.len());
+    let mut copied_to = 0;
+    // the start, end, and value of each number since the last non-number token
+    let mut operands: Vec<(usize, f32)> = Vec::new();
+
+    let mut i = 0;
+    while i < content.len() {
+        let b = content[i];
+        if b.is_ascii_whitespace() || b == 0 {
+            i += 1;
+            continue;
+        }
+        match b {
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+                operands.clear();
+            }
+            b'(' => {
+                // skip literal strings, which may contain nested parentheses
+                let mut depth = 0;
+                while i < content.len() {
+                    match content[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                operands.clear();
+            }
+            b'<' if content.get(i + 1) != Some(&b'<') => {
+                // skip hex strings
+                while i < content.len() && content[i] != b'>' {
+                    i += 1;
+                }
+                i += 1;
+                operands.clear();
+            }
+            b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b')' => {
+                i += 1;
+                operands.clear();
+            }
+            _ => {
+                let start = i;
+                i += 1;
+                while i < content.len() && is_regular(content[i]) {
+                    i += 1;
+                }
+                let token = &content[start..i];
+                if let Some(value) = std::str::from_utf8(token)
+                    .ok()
+                    .filter(|t| !t.starts_with('/'))
+                    .and_then(|t| t.parse::<f32>().ok())
+                {
+                    operands.push((start, value));
+                    continue;
+                }
+
+                let (count, stroke) = match token {
+                    b"rg" => (3, false),
+                    b"RG" => (3, true),
+                    b"k" => (4, false),
+                    b"K" => (4, true),
+                    b"g" => (1, false),
+                    b"G" => (1, true),
+                    _ => (0, false),
+                };
+                if count > 0 && operands.len() >= count {
+                    let operands = &operands[operands.len() - count..];
+                    let v: Vec<f32> = operands.iter().map(|(_, v)| *v).collect();
+                    let colour = match count {
+                        3 => Colour::new_rgb(v[0], v[1], v[2]),
+                        4 => Colour::new_cmyk(v[0], v[1], v[2], v[3]),
+                        _ => Colour::new_grey(v[0]),
+                    };
+                    out.extend_from_slice(&content[copied_to..operands[0].0]);
+                    out.extend_from_slice(colour_operator(f(colour), stroke).as_bytes());
+                    copied_to = i;
+                }
+                operands.clear();
+            }
+        }
+    }
+    out.extend_from_slice(&content[copied_to.min(content.len())..]);
+    out
+}
+
+/// The content stream operator which sets the fill (or stroke) colour
+fn colour_operator(colour: Colour, stroke: bool) -> String {
+    let (rgb, cmyk, grey) = if stroke {
+        ("RG", "K", "G")
+    } else {
+        ("rg", "k", "g")
+    };
+    match colour {
+        Colour::RGB { r, g, b } => format!("{r} {g} {b} {rgb}"),
+        Colour::CMYK { c, m, y, k } => format!("{c} {m} {y} {k} {cmyk}"),
+        Colour::Grey { g } => format!("{g} {grey}"),
+    }
+}
@@ -0,0 +1,222 @@
+use crate::resources::is_regular;
+use crate::PDFError;
+
+/// Every operator which may appear in a content stream
+#[rustfmt::skip]
+const OPERATORS: &[&[u8]] = &[
+    // graphics state
+    b"w", b"J", b"j", b"M", b"d", b"ri", b"i", b"gs", b"q", b"Q", b"cm",
+    // paths
+    b"m", b"l", b"c", b"v", b"y", b"h", b"re",
+    b"S", b"s", b"f", b"F", b"f*", b"B", b"B*", b"b", b"b*", b"n", b"W", b"W*",
+    // text
+    b"BT", b"ET", b"Tc", b"Tw", b"Tz", b"TL", b"Tf", b"Tr", b"Ts",
+    b"Td", b"TD", b"Tm", b"T*", b"Tj", b"TJ", b"'", b"\"", b"d0", b"d1",
+    // colour
+    b"CS", b"cs", b"SC", b"SCN", b"sc", b"scn", b"G", b"g", b"RG", b"rg", b"K", b"k",
+    // shadings, images, and XObjects
+    b"sh", b"BI", b"ID", b"EI", b"Do",
+    // marked content and compatibility
+    b"MP", b"DP", b"BMC", b"BDC", b"EMC", b"BX", b"EX",
+];
+
+/// Sanity check a content stream, making sure that it only uses known operators, that
+/// its names, strings, arrays, and dictionaries are well formed, and that its `q` / `Q`,
+/// `BT` / `ET`, and marked content operators are balanced. This doesn't check that
+/// operators are given the right operands.
+pub(crate) fn validate_content(content: &[u8]) -> Result<(), PDFError> {
+    let invalid = |offset: usize, reason: String| PDFError::InvalidContent { offset, reason };
+
+    let mut saved_states: Vec<usize> = Vec::new();
+    let mut text_object: Option<usize> = None;
+    let mut marked_content: Vec<usize> = Vec::new();
+    // the offsets of the arrays and dictionaries that are open
+    let mut containers: Vec<(usize, u8)> = Vec::new();
+
+    let mut i = 0;
+    while i < content.len() {
+        let b = content[i];
+        if b.is_ascii_whitespace() || b == 0 {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        match b {
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'(' => {
+                let mut depth = 0;
+                loop {
+                    match content.get(i) {
+                        None => return Err(invalid(start, "unterminated string".to_string())),
+                        Some(b'\\') => i += 1,
+                        Some(b'(') => depth += 1,
+                        Some(b')') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            b'<' if content.get(i + 1) == Some(&b'<') => {
+                containers.push((start, b'>'));
+                i += 2;
+            }
+            b'<' => {
+                i += 1;
+                loop {
+                    match content.get(i) {
+                        None => return Err(invalid(start, "unterminated hex string".to_string())),
+                        Some(b'>') => break,
+                        Some(b) if b.is_ascii_hexdigit() || b.is_ascii_whitespace() => i += 1,
+                        Some(b) => {
+                            return Err(invalid(i, format!("{:?} in a hex string", char::from(*b))))
+                        }
+                    }
+                }
+                i += 1;
+            }
+            b'[' => {
+                containers.push((start, b']'));
+                i += 1;
+            }
+            b'>' | b']' => {
+                let width = if b == b'>' { 2 } else { 1 };
+                if b == b'>' && content.get(i + 1) != Some(&b'>') {
+                    return Err(invalid(start, "unexpected '>'".to_string()));
+                }
+                match containers.pop() {
+                    Some((_, close)) if close == b => i += width,
+                    _ => {
+                        return Err(invalid(
+                            start,
+                            format!("unexpected '{}'", if b == b'>' { ">>" } else { "]" }),
+                        ))
+                    }
+                }
+            }
+            b')' | b'{' | b'}' => {
+                return Err(invalid(start, format!("unexpected '{}'", char::from(b))));
+            }
+            b'/' => {
+                i += 1;
+                while i < content.len() && is_regular(content[i]) {
+                    if content[i] == b'#' {
+                        let escaped = content.get(i + 1..i + 3);
+                        if !matches!(escaped, Some(e) if e.iter().all(u8::is_ascii_hexdigit)) {
+                            return Err(invalid(
+                                i,
+                                "'#' in a name must be followed by two hex digits".to_string(),
+                            ));
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            _ => {
+                i += 1;
+                while i < content.len() && is_regular(content[i]) {
+                    i += 1;
+                }
+                let token = &content[start..i];
+                if is_number(token) || token == b"true" || token == b"false" || token == b"null" {
+                    continue;
+                }
+                if !OPERATORS.contains(&token) {
+                    return Err(invalid(
+                        start,
+                        format!("unknown operator {:?}", String::from_utf8_lossy(token)),
+                    ));
+                }
+                if let Some(&(offset, _)) = containers.last() {
+                    return Err(invalid(
+                        offset,
+                        "unterminated array or dictionary".to_string(),
+                    ));
+                }
+
+                match token {
+                    b"q" => saved_states.push(start),
+                    b"Q" => {
+                        if saved_states.pop().is_none() {
+                            return Err(invalid(start, "'Q' without a matching 'q'".to_string()));
+                        }
+                    }
+                    b"BT" => {
+                        if text_object.is_some() {
+                            return Err(invalid(start, "'BT' within a text object".to_string()));
+                        }
+                        text_object = Some(start);
+                    }
+                    b"ET" => {
+                        if text_object.take().is_none() {
+                            return Err(invalid(start, "'ET' without a matching 'BT'".to_string()));
+                        }
+                    }
+                    b"BMC" | b"BDC" => marked_content.push(start),
+                    b"EMC" => {
+                        if marked_content.pop().is_none() {
+                            return Err(invalid(
+                                start,
+                                "'EMC' without a matching 'BMC' or 'BDC'".to_string(),
+                            ));
+                        }
+                    }
+                    b"ID" => {
+                        // inline image data is binary, and ends with whitespace then `EI`
+                        i = (i..content.len().saturating_sub(2))
+                            .find(|&p| {
+                                content[p].is_ascii_whitespace()
+                                    && content[p + 1..p + 3] == *b"EI"
+                                    && !matches!(content.get(p + 3), Some(&b) if is_regular(b))
+                            })
+                            .map(|end| end + 3)
+                            .ok_or_else(|| {
+                                invalid(start, "inline image without 'EI'".to_string())
+                            })?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if let Some(&(offset, _)) = containers.last() {
+        return Err(invalid(
+            offset,
+            "unterminated array or dictionary".to_string(),
+        ));
+    }
+    if let Some(offset) = saved_states.pop() {
+        return Err(invalid(offset, "'q' without a matching 'Q'".to_string()));
+    }
+    if let Some(offset) = text_object {
+        return Err(invalid(offset, "'BT' without a matching 'ET'".to_string()));
+    }
+    if let Some(offset) = marked_content.pop() {
+        return Err(invalid(
+            offset,
+            "'BMC' or 'BDC' without a matching 'EMC'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn is_number(token: &[u8]) -> bool {
+    let digits = token
+        .strip_prefix(b"-")
+        .or_else(|| token.strip_prefix(b"+"))
+        .unwrap_or(token);
+    !digits.is_empty()
+        && digits.iter().filter(|&&b| b == b'.').count() <= 1
+        && digits.iter().any(u8::is_ascii_digit)
+        && digits.iter().all(|&b| b.is_ascii_digit() || b == b'.')
+}